@@ -1,5 +1,5 @@
-use futures_util::{SinkExt, StreamExt};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use futures_util::StreamExt;
+use tokio_tungstenite::connect_async;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -25,12 +25,12 @@ async fn handle_connection(peer_map: PeerMap, raw_stream: TcpStream, addr: Socke
         match msg {
             Ok(msg) => {
                 println!("Received a message from {}: {}", addr, msg);
-                let peers = peer_map.lock().await;
+                let mut peers = peer_map.lock().await;
                 
                 // 广播消息给所有其他客户端
-                for (peer_addr, tx) in peers.iter() {
+                for (peer_addr, tx) in peers.iter_mut() {
                     if *peer_addr != addr {
-                        if let Err(e) = tx.clone().send(msg.clone()).await {
+                        if let Err(e) = tx.send(msg.clone()).await {
                             println!("Error sending message to {}: {}", peer_addr, e);
                         }
                     }
@@ -0,0 +1,200 @@
+use crate::address_book::AddressBook;
+use crate::models::TradeEvent;
+use crate::wallet_monitor::SOL_DECIMALS;
+use std::{collections::{HashMap, VecDeque}, time::Duration};
+
+const BUY_SIZE_HISTORY: usize = 50; // 每个钱包保留最近多少笔买入用于计算中位数
+const MIN_BUY_SIZE_SAMPLES: usize = 10; // 样本不足时不判断异常
+
+/// 目标钱包的交易活跃度变化
+#[derive(Debug, Clone)]
+pub enum ActivityEvent {
+    Dormant { wallet: String, idle_secs: i64 },
+    Resumed { wallet: String, idle_secs: i64 },
+    Burst { wallet: String, buys: usize, window_secs: i64 },
+    OversizedBuy { wallet: String, mint: String, sol_amount: u64, median: u64 },
+}
+
+impl ActivityEvent {
+    pub fn message(&self, book: &AddressBook) -> String {
+        match self {
+            ActivityEvent::Dormant { wallet, idle_secs } => format!(
+                "💤 Wallet dormant\n\nWallet: {}\nNo trades for {}",
+                book.display(wallet),
+                format_duration(*idle_secs)
+            ),
+            ActivityEvent::Resumed { wallet, idle_secs } => format!(
+                "🔔 Wallet active again\n\nWallet: {}\nFirst trade after {} of inactivity",
+                book.display(wallet),
+                format_duration(*idle_secs)
+            ),
+            ActivityEvent::Burst { wallet, buys, window_secs } => format!(
+                "🔥 Buying spree\n\nWallet: {}\n{} buys within {}",
+                book.display(wallet),
+                buys,
+                format_duration(*window_secs)
+            ),
+            ActivityEvent::OversizedBuy { wallet, mint, sol_amount, median } => format!(
+                "🎯 Unusually large buy\n\n\
+                Token: <a href=\"https://gmgn.ai/sol/token/{mint}\">{mint}</a>\n\
+                Wallet: {}\n\
+                Size: {:.3} SOL ({:.1}x the median of {:.3} SOL)",
+                book.display(wallet),
+                to_sol(*sol_amount),
+                *sol_amount as f64 / *median as f64,
+                to_sol(*median),
+                mint = mint
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct WalletActivity {
+    last_trade_at: i64,
+    dormant: bool,
+    recent_buys: VecDeque<i64>,  // 突发窗口内的买入时间
+    buy_sizes: VecDeque<u64>,  // 最近的买入金额（lamports）
+}
+
+impl WalletActivity {
+    fn new(now: i64) -> Self {
+        Self {
+            last_trade_at: now,
+            dormant: false,
+            recent_buys: VecDeque::new(),
+            buy_sizes: VecDeque::with_capacity(BUY_SIZE_HISTORY),
+        }
+    }
+
+    fn median_buy_size(&self) -> Option<u64> {
+        if self.buy_sizes.len() < MIN_BUY_SIZE_SAMPLES {
+            return None;
+        }
+        let mut sizes: Vec<u64> = self.buy_sizes.iter().copied().collect();
+        sizes.sort_unstable();
+        Some(sizes[sizes.len() / 2])
+    }
+}
+
+/// 短时间内连续买入的提醒条件
+#[derive(Debug, Clone, Copy)]
+pub struct BurstRule {
+    pub buys: usize,
+    pub window: Duration,
+}
+
+/// 按钱包记录最近的交易时间，检测长时间不活跃和恢复交易
+pub struct ActivityTracker {
+    inactivity: Option<Duration>,  // None 表示关闭不活跃提醒
+    burst: Option<BurstRule>,
+    size_multiplier: Option<f64>,  // 买入金额超过中位数的多少倍视为异常
+    wallets: HashMap<String, WalletActivity>,
+}
+
+impl ActivityTracker {
+    pub fn new(inactivity: Option<Duration>, burst: Option<BurstRule>, size_multiplier: Option<f64>) -> Self {
+        Self {
+            inactivity,
+            burst,
+            size_multiplier,
+            wallets: HashMap::new(),
+        }
+    }
+
+    /// 开始跟踪钱包，启动前的交易无从得知，所以从当前时间开始计时
+    pub fn track_wallet(&mut self, wallet: &str, now: i64) {
+        self.wallets.entry(wallet.to_string()).or_insert_with(|| WalletActivity::new(now));
+    }
+
+    pub fn untrack_wallet(&mut self, wallet: &str) {
+        self.wallets.remove(wallet);
+    }
+
+    /// 记录一笔交易，返回恢复交易、连续买入或异常仓位事件
+    pub fn record_trade(&mut self, trade: &TradeEvent, now: i64) -> Vec<ActivityEvent> {
+        let wallet = trade.user.as_str();
+        let is_buy = trade.is_buy;
+        let mut events = Vec::new();
+        let activity = self.wallets.entry(wallet.to_string()).or_insert_with(|| WalletActivity::new(now));
+
+        if activity.dormant {
+            events.push(ActivityEvent::Resumed {
+                wallet: wallet.to_string(),
+                idle_secs: now - activity.last_trade_at,
+            });
+            activity.dormant = false;
+        }
+        activity.last_trade_at = now;
+
+        if let (true, Some(burst)) = (is_buy, self.burst) {
+            let window_secs = burst.window.as_secs() as i64;
+            activity.recent_buys.push_back(now);
+            while activity.recent_buys.front().is_some_and(|&at| now - at > window_secs) {
+                activity.recent_buys.pop_front();
+            }
+            if activity.recent_buys.len() >= burst.buys {
+                events.push(ActivityEvent::Burst {
+                    wallet: wallet.to_string(),
+                    buys: activity.recent_buys.len(),
+                    window_secs,
+                });
+                // 清空后需要再次满足条件才会重复提醒
+                activity.recent_buys.clear();
+            }
+        }
+
+        if is_buy && trade.sol_amount > 0 {
+            if let (Some(multiplier), Some(median)) = (self.size_multiplier, activity.median_buy_size()) {
+                if median > 0 && trade.sol_amount as f64 >= median as f64 * multiplier {
+                    events.push(ActivityEvent::OversizedBuy {
+                        wallet: wallet.to_string(),
+                        mint: trade.mint.clone(),
+                        sol_amount: trade.sol_amount,
+                        median,
+                    });
+                }
+            }
+            if activity.buy_sizes.len() >= BUY_SIZE_HISTORY {
+                activity.buy_sizes.pop_front();
+            }
+            activity.buy_sizes.push_back(trade.sol_amount);
+        }
+        events
+    }
+
+    /// 找出超过不活跃时长且尚未提醒过的钱包
+    pub fn check_inactive(&mut self, now: i64) -> Vec<ActivityEvent> {
+        let Some(inactivity) = self.inactivity else {
+            return Vec::new();
+        };
+
+        let mut events = Vec::new();
+        for (wallet, activity) in self.wallets.iter_mut() {
+            let idle_secs = now - activity.last_trade_at;
+            if !activity.dormant && idle_secs >= inactivity.as_secs() as i64 {
+                activity.dormant = true;
+                events.push(ActivityEvent::Dormant {
+                    wallet: wallet.clone(),
+                    idle_secs,
+                });
+            }
+        }
+        events
+    }
+}
+
+pub(crate) fn to_sol(lamports: u64) -> f64 {
+    lamports as f64 / 10f64.powi(SOL_DECIMALS as i32)
+}
+
+pub(crate) fn format_duration(secs: i64) -> String {
+    let hours = secs / 3600;
+    if hours >= 24 {
+        format!("{}d {}h", hours / 24, hours % 24)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, secs % 3600 / 60)
+    } else {
+        format!("{}m", secs / 60)
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{enrichment::escape_html, wallet_monitor::truncate_address};
+use std::collections::HashMap;
+
+/// 随程序发布的已知地址：pump.fun 的手续费和迁移账户、主要交易所的热钱包。
+/// 做市商和知名交易者的地址变化较快，由配置文件的 `wallet_labels` 补充
+const KNOWN_ENTITIES: &[(&str, &str)] = &[
+    ("CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM", "pump.fun fees"),
+    ("39azUYFWPz3VHgKCf3VChUwbpURdCHRxjWVowf5jUJjg", "pump.fun migration"),
+    ("5tzFkiKscXHK5ZXCGbXZxdw7gTjjD1mBwuoFbhUvuAi9", "Binance"),
+    ("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM", "Binance"),
+    ("H8sMJSCQxfKiFTCfDR3DUMLPwcRbM61LGFJ8N4dK3WjS", "Coinbase"),
+    ("GJRs4FwHtemZ5ZE9x3FNvJ8TMwitKTh21yxdRPqn7npE", "Coinbase"),
+    ("5VCwKtCXgCJ6kit5FybXjvriW3xELsFDhYrPSqtJNmcD", "OKX"),
+    ("AC5RDfQFmDS1deWZos921JfqscXdByf8BKHs5ACWjtW2", "Bybit"),
+    ("FWznbcNXWQuHTawe9RxvQ2LdCENssh12dsznf4RiouN5", "Kraken"),
+    ("BmFdpraQhkiDQE6SnfG5omcA1VwzqfXrwtNYBwWTymy6", "KuCoin"),
+];
+
+/// 钱包地址簿：发布的已知地址加上配置文件中的 `wallet_labels`，同一地址以配置文件为准
+#[derive(Debug, Default)]
+pub struct AddressBook {
+    labels: HashMap<String, String>,
+}
+
+impl AddressBook {
+    pub fn new(wallet_labels: &HashMap<String, String>) -> Self {
+        let mut labels: HashMap<String, String> = KNOWN_ENTITIES
+            .iter()
+            .map(|(address, label)| (address.to_string(), label.to_string()))
+            .collect();
+        labels.extend(wallet_labels.iter().map(|(address, label)| (address.clone(), label.clone())));
+        Self { labels }
+    }
+
+    pub fn label(&self, address: &str) -> Option<&str> {
+        self.labels.get(address).map(String::as_str)
+    }
+
+    /// 提醒中显示的钱包（HTML）：有名称时显示名称和截断的地址，否则显示完整地址
+    pub fn display(&self, address: &str) -> String {
+        match self.label(address) {
+            Some(label) => format!("<b>{}</b> (<code>{}</code>)", escape_html(label), truncate_address(address, 12)),
+            None => format!("<code>{}</code>", address),
+        }
+    }
+
+    /// 纯文本中的钱包，有名称时只显示名称
+    pub fn short(&self, address: &str, length: usize) -> String {
+        self.label(address).map_or_else(|| truncate_address(address, length), str::to_string)
+    }
+}
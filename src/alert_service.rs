@@ -1,111 +1,812 @@
-use crate::models::{Alert, AlertType, TradeSignal};
-use anyhow::Result;
+use crate::{
+    annotations::TradeAnnotations,
+    channels::{AlertChannel, FileChannel, TelegramChannel, WebSocketChannel},
+    circuit::{CircuitBreaker, Permit},
+    config::ProfileConfig,
+    confirm::TradeConfirmations,
+    discord::DiscordChannel,
+    email::{EmailChannel, EmailConfig},
+    enrichment::{escape_html, EnrichContext, EnrichmentPipeline},
+    error::MonitorError,
+    escalation::Escalations,
+    metadata::MetadataResolver,
+    models::{Alert, AlertSeverity, AlertSink, AlertType, HoldingSnapshot, SignalProvenance, TradeRef},
+    outbox::{Outbox, Pushed, QueuedAlert, DELIVERED_KEY_RETENTION_SECS},
+    sound,
+    store::{self, StateStore, StoreConfig},
+};
+use anyhow::{anyhow, Result};
 use chrono::Utc;
-use futures_util::SinkExt;
-use log::{error, info, warn};
-use std::env;
-use teloxide::{
-    prelude::*,
-    types::{ChatId, MessageId, ParseMode},
+use futures_util::future::join_all;
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
 };
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-use url::Url;
-use serde_json;
+use tokio::{sync::Notify, time::{sleep, timeout}};
+
+pub(crate) const UNDELIVERED_FILE: &str = "undelivered.jsonl";
+pub(crate) const HISTORY_FILE: &str = "alert_history.jsonl";
+const DEFAULT_CHAIN: &[&str] = &["telegram", "file"];
+const GLOBAL_TEMPLATE: &str = "default";  // `alert_templates` 中所有通道共用的模板
+/// 没有配置模板时大多数通道使用的格式：类型、提醒内容和时间
+pub(crate) const DEFAULT_ALERT_TEMPLATE: &str = "<b>{type}</b>\n{message}\nTimestamp: {timestamp}";
+const RETRY_DELAY: Duration = Duration::from_secs(30); // 整条投递链失败后的重试间隔
+
+/// 一次投递尝试的结果
+#[derive(Serialize)]
+struct DeliveryAttempt {
+    sink: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// 提醒历史，记录每条提醒的投递情况
+#[derive(Serialize)]
+struct AlertHistoryEntry<'a> {
+    timestamp: i64,
+    alert_type: &'a AlertType,
+    severity: AlertSeverity,
+    message: &'a str,
+    delivered_by: Option<String>,
+    attempts: Vec<DeliveryAttempt>,
+}
+
+/// 提醒队列的状态，用于导出指标
+pub struct QueueStats {
+    pub depth: usize,
+    pub dropped: u64,
+    pub coalesced: u64,
+}
+
+/// 一个通道的健康检查结果，`GET /channels` 返回
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelHealth {
+    pub name: String,
+    pub mirror: bool,
+    pub healthy: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 注册的通道和它的熔断器
+struct Registered {
+    channel: Arc<dyn AlertChannel>,
+    circuit: Mutex<CircuitBreaker>,
+    mirror: bool,  // 投递链成功后也发送，投递链中已有这个通道时不重复
+}
 
 pub struct AlertService {
-    bot: Bot,
-    chat_id: i64,
-    topic_id: Option<i32>,
-    ws_url: Option<String>,
+    channels: Vec<Registered>,
+    undelivered: Arc<FileChannel>,  // 通道不可用时的备用输出，也是 `file` 通道
+    confirmations: Option<Arc<TradeConfirmations>>,  // 交易确认，开启时代替 WebSocket 通道接收信号
+    escalations: Option<Arc<Escalations>>,  // critical 提醒的确认和重发
+    annotations: Option<Arc<TradeAnnotations>>,  // 交易提醒的标注按钮
+    store: Arc<dyn StateStore>,  // 持仓状态、交易日志和提醒历史
+    chains: HashMap<AlertSeverity, Vec<String>>,  // 按严重程度配置的投递链
+    routes: HashMap<AlertType, Vec<String>>,  // 按提醒类型配置的投递链，优先于 `chains`
+    templates: HashMap<String, String>,  // 通道名或 `default` -> 提醒模板
+    outbox: Mutex<Outbox>,  // 持久化的待投递队列
+    outbox_changed: Notify,
+    send_timeout: Duration,  // 单个通道发送一条消息的超时
+    muted: RwLock<HashSet<String>>,  // 静音的代币，这些代币的提醒除 critical 外都不发送
 }
 
 impl AlertService {
-    pub fn new(bot_token: &str, chat_id: i64, topic_id: Option<i32>, ws_url: Option<String>) -> Self {
-        Self {
-            bot: Bot::new(bot_token),
-            chat_id,
-            topic_id,
-            ws_url,
+    /// 按配置构造，与不加自定义通道的 `AlertServiceBuilder::from_config` 相同
+    pub async fn new(profile: &ProfileConfig) -> Result<Self, MonitorError> {
+        AlertServiceBuilder::from_config(profile.clone()).build().await
+    }
+
+    /// 按配置注册内置通道（Telegram、`file`，配置了地址时的 Discord、email 和 WebSocket，开启交易确认时的 `confirm`），
+    /// 再加上调用方的通道 `(通道, 是否镜像)`，并检查投递链引用的通道都已注册
+    async fn with_channels(profile: &ProfileConfig, extra: Vec<(Arc<dyn AlertChannel>, bool)>) -> Result<Self, MonitorError> {
+        let undelivered = Arc::new(FileChannel::new(profile.data_dir.join(UNDELIVERED_FILE)));
+        let mut channels: Vec<(Arc<dyn AlertChannel>, bool)> = Vec::new();
+        let escalations = match &profile.escalation {
+            Some(_) if profile.telegram_bot_token.is_empty() => {
+                return Err(MonitorError::config("A Telegram bot token is required for escalation"));
+            }
+            Some(config) => Some(Arc::new(Escalations::new(
+                &profile.name,
+                &profile.telegram_bot_token,
+                profile.telegram_chat_id,
+                profile.telegram_topic_id,
+                config.clone(),
+            ))),
+            None => None,
+        };
+        let annotations = (!profile.trade_annotations.is_empty() && !profile.telegram_bot_token.is_empty()).then(|| {
+            Arc::new(TradeAnnotations::new(
+                &profile.name,
+                &profile.telegram_bot_token,
+                profile.telegram_chat_id,
+                profile.telegram_topic_id,
+                profile.trade_annotations.clone(),
+                &profile.data_dir,
+            ))
+        });
+        if !profile.telegram_bot_token.is_empty() {
+            channels.push((
+                Arc::new(TelegramChannel::new(
+                    &profile.telegram_bot_token,
+                    profile.telegram_chat_id,
+                    profile.telegram_topic_id,
+                    escalations.clone(),
+                    annotations.clone(),
+                )),
+                false,
+            ));
+        }
+        channels.push((undelivered.clone(), false));
+        if let Some(url) = &profile.discord_webhook_url {
+            let roles = profile.escalation.as_ref().map_or_else(Vec::new, |config| config.discord_roles.clone());
+            channels.push((Arc::new(DiscordChannel::new(url, roles)), true));
+        }
+        if let Some(config) = &profile.email {
+            channels.push((Arc::new(EmailChannel::new(config).map_err(MonitorError::config)?), false));
+        }
+        let websocket = profile
+            .ws_alert_url
+            .as_ref()
+            .map(|url| Arc::new(WebSocketChannel::new(url, profile.signal_ttl)) as Arc<dyn AlertChannel>);
+        let confirmations = profile.trade_confirm.map(|rule| {
+            Arc::new(TradeConfirmations::new(
+                &profile.name,
+                &profile.telegram_bot_token,
+                profile.telegram_chat_id,
+                profile.telegram_topic_id,
+                rule,
+                profile.telegram_admins.clone(),
+                websocket.clone(),
+            ))
+        });
+        if let Some(confirmations) = &confirmations {
+            channels.push((confirmations.clone(), true));
+        }
+        if let Some(websocket) = websocket {
+            // 需要确认时信号只在按下 Buy 后发送
+            channels.push((websocket, confirmations.is_none()));
         }
+        channels.extend(extra);
+
+        let registered = |name: &str| channels.iter().any(|(channel, _)| channel.name() == name);
+        for (index, (channel, _)) in channels.iter().enumerate() {
+            if channels[..index].iter().any(|(other, _)| other.name() == channel.name()) {
+                return Err(MonitorError::config(format!("Alert channel '{}' is registered twice", channel.name())));
+            }
+        }
+        let used = [AlertSeverity::Info, AlertSeverity::Notice, AlertSeverity::Critical]
+            .iter()
+            .flat_map(|severity| profile.alert_chains.get(severity).map_or_else(
+                || DEFAULT_CHAIN.iter().map(|name| name.to_string()).collect(),
+                |chain| chain.clone(),
+            ))
+            .chain(profile.alert_routes.values().flatten().cloned());
+        for name in used {
+            if registered(&name) {
+                continue;
+            }
+            return Err(MonitorError::config(match name.as_str() {
+                "telegram" => "A Telegram bot token is required when a delivery chain uses telegram".to_string(),
+                "discord" => format!("DISCORD_WEBHOOK_URL must be set for profile '{}' when a delivery chain uses discord", profile.name),
+                "websocket" => format!("WS_ALERT_URL must be set for profile '{}' when a delivery chain uses websocket", profile.name),
+                "email" => format!("SMTP_URL must be set for profile '{}' when a delivery chain uses email", profile.name),
+                _ => format!("Unknown alert channel '{}' in a delivery chain of profile '{}'", name, profile.name),
+            }));
+        }
+        if let Some(name) = profile.alert_templates.keys().find(|name| *name != GLOBAL_TEMPLATE && !registered(name)) {
+            return Err(MonitorError::config(format!(
+                "alert_templates of profile '{}' has a template for '{}', which is not a registered alert channel",
+                profile.name, name
+            )));
+        }
+
+        let store = store::open(&profile.name, profile.instance_id.as_deref(), &profile.state_store, &profile.data_dir, profile.replica)
+            .await
+            .map_err(MonitorError::config)?;
+        let mut outbox = Outbox::open(&profile.data_dir, profile.alert_queue_capacity).map_err(MonitorError::config)?;
+        // 中断的提醒可能已经送达，不再发送，写入 undelivered 供查看
+        for alert in outbox.take_interrupted() {
+            warn!("[{}] Delivery of a {:?} alert was interrupted by the last shutdown, not sending it again", profile.name, alert.alert_type);
+            undelivered.write("interrupted", &alert.message).map_err(MonitorError::config)?;
+        }
+        if outbox.len() > 0 {
+            info!("[{}] {} pending alert(s) restored from outbox", profile.name, outbox.len());
+        }
+        Ok(Self {
+            channels: channels
+                .into_iter()
+                .map(|(channel, mirror)| Registered {
+                    channel,
+                    circuit: Mutex::new(CircuitBreaker::new(profile.circuit_failures, profile.circuit_cooldown)),
+                    mirror,
+                })
+                .collect(),
+            undelivered,
+            confirmations,
+            escalations,
+            annotations,
+            store,
+            chains: profile.alert_chains.clone(),
+            routes: profile.alert_routes.clone(),
+            templates: profile.alert_templates.clone(),
+            outbox: Mutex::new(outbox),
+            outbox_changed: Notify::new(),
+            send_timeout: profile.alert_send_timeout,
+            muted: RwLock::new(HashSet::new()),
+        })
+    }
+
+    pub fn mute(&self, mint: &str) {
+        self.muted.write().unwrap().insert(mint.to_string());
+    }
+
+    /// 取消静音，代币没有被静音时返回 false
+    pub fn unmute(&self, mint: &str) -> bool {
+        self.muted.write().unwrap().remove(mint)
+    }
+
+    pub fn muted_count(&self) -> usize {
+        self.muted.read().unwrap().len()
+    }
+
+    /// 将提醒放入持久化队列，由 `run_queue` 投递。价格提醒投递后还会发送 WebSocket 信号
+    pub async fn send_alert(&self, message: &str, alert_type: AlertType, mint: Option<String>) -> Result<(), MonitorError> {
+        let signal = (alert_type == AlertType::PriceAlert).then(|| "sniper_pump1".to_string());
+        self.enqueue(QueuedAlert::new(alert_type, message, mint, signal))
+            .await
+            .map_err(MonitorError::sink)
+    }
+
+    /// 与 `send_alert` 相同，附带持仓，Discord embed 中显示价格和盈亏。`provenance` 随价格提醒的信号发送，
+    /// `event` 标识提醒对应的事件，共享状态的多个实例发现同一事件时只发送一次
+    pub async fn send_holding_alert(
+        &self,
+        message: &str,
+        alert_type: AlertType,
+        holding: HoldingSnapshot,
+        provenance: Option<SignalProvenance>,
+        event: Option<&str>,
+    ) -> Result<(), MonitorError> {
+        let signal = (alert_type == AlertType::PriceAlert).then(|| "sniper_pump1".to_string());
+        let mut alert = QueuedAlert::new(alert_type, message, Some(holding.mint.clone()), signal)
+            .with_holding(holding)
+            .with_provenance(provenance);
+        if let Some(event) = event {
+            alert = alert.with_event(event);
+        }
+        self.enqueue(alert).await.map_err(MonitorError::sink)
+    }
+
+    /// 目标钱包交易的提醒（新建仓、卖出、清仓），开启了 `TRADE_ANNOTATIONS` 时 Telegram 消息带标注按钮
+    pub async fn send_trade_alert(&self, message: &str, alert_type: AlertType, trade: TradeRef) -> Result<(), MonitorError> {
+        let alert = QueuedAlert::new(alert_type, message, Some(trade.mint.clone()), None).with_trade(trade);
+        self.enqueue(alert).await.map_err(MonitorError::sink)
     }
 
-    pub async fn send_alert(&self, message: &str, alert_type: AlertType, mint: Option<String>) -> Result<()> {
-        let alert_type_clone = alert_type.clone();
-        let alert = Alert {
-            message: message.to_string(),
-            alert_type,
-            timestamp: Utc::now().timestamp(),
+    /// 按规则指定的目标发送提醒，WebSocket 信号名使用规则名
+    pub async fn send_rule_alert(
+        &self,
+        message: &str,
+        alert_type: AlertType,
+        mint: &str,
+        sink: AlertSink,
+        provenance: SignalProvenance,
+    ) -> Result<(), MonitorError> {
+        let result = match sink {
+            AlertSink::All => self.enqueue(
+                QueuedAlert::new(alert_type, message, Some(mint.to_string()), Some(provenance.rule.clone()))
+                    .with_provenance(Some(provenance)),
+            )
+            .await,
+            AlertSink::Telegram => self.enqueue(QueuedAlert::new(alert_type, message, Some(mint.to_string()), None)).await,
+            AlertSink::Websocket => {
+                // 只发送信号，不经过队列
+                let alert = Alert {
+                    message: message.to_string(),
+                    alert_type,
+                    timestamp: Utc::now().timestamp(),
+                    mint: Some(mint.to_string()),
+                    holding: None,
+                    signal: Some(provenance.rule.clone()),
+                    provenance: Some(provenance),
+                    token: None,
+                    trade: None,
+                };
+                let target = if self.confirmations.is_some() { "confirm" } else { "websocket" };
+                match self.channel(target) {
+                    Some(channel) => self.send_mirror(channel, &alert).await.unwrap_or(Ok(())),
+                    None => Ok(()),
+                }
+            }
         };
+        result.map_err(MonitorError::sink)
+    }
 
-        // 发送到 Telegram
-        match self.send_to_telegram(&self.format_alert_message(&alert)).await {
-            Ok(_) => {
-                info!("Successfully sent alert to Telegram");
-                
-                // 如果是价格提醒，发送信号到 WebSocket
-                if alert_type_clone == AlertType::PriceAlert {
-                    if let Some(mint_address) = mint {
-                        let signal = TradeSignal {
-                            signal: "sniper_pump1".to_string(),
-                            mint: mint_address,
-                            timestamp: Utc::now().timestamp(),
-                        };
-                        
-                        if let Err(e) = self.send_to_ws(&signal).await {
-                            error!("Failed to send signal to WebSocket: {}", e);
-                        }
+    pub fn queue_stats(&self) -> QueueStats {
+        let outbox = self.outbox.lock().unwrap();
+        QueueStats {
+            depth: outbox.len(),
+            dropped: outbox.dropped(),
+            coalesced: outbox.coalesced(),
+        }
+    }
+
+    /// 交易确认，没有开启 `TRADE_CONFIRM` 时为 None
+    pub fn trade_confirmations(&self) -> Option<Arc<TradeConfirmations>> {
+        self.confirmations.clone()
+    }
+
+    /// critical 提醒的确认和重发，没有配置 `escalation` 时为 None
+    pub fn escalations(&self) -> Option<Arc<Escalations>> {
+        self.escalations.clone()
+    }
+
+    /// 交易提醒的标注按钮，没有开启 `TRADE_ANNOTATIONS` 时为 None
+    pub fn trade_annotations(&self) -> Option<Arc<TradeAnnotations>> {
+        self.annotations.clone()
+    }
+
+    /// 按 `STATE_STORE` 打开的状态存储，监控也使用它保存持仓和交易日志
+    pub fn store(&self) -> Arc<dyn StateStore> {
+        self.store.clone()
+    }
+
+    /// 同时检查所有通道，不经过熔断器
+    pub async fn channel_health(&self) -> Vec<ChannelHealth> {
+        join_all(self.channels.iter().map(|registered| async move {
+            let result = timeout(self.send_timeout, registered.channel.health_check())
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("Health check timed out after {}s", self.send_timeout.as_secs())));
+            ChannelHealth {
+                name: registered.channel.name().to_string(),
+                mirror: registered.mirror,
+                healthy: result.is_ok(),
+                error: result.err().map(|e| e.to_string()),
+            }
+        }))
+        .await
+    }
+
+    fn channel(&self, name: &str) -> Option<&Registered> {
+        self.channels.iter().find(|registered| registered.channel.name() == name)
+    }
+
+    /// 按通道的模板格式化提醒：通道自己的模板优先，其次是 `default`，都没有配置时使用通道的默认格式
+    fn format_for(&self, channel: &dyn AlertChannel, alert: &Alert) -> Option<String> {
+        let template = self
+            .templates
+            .get(channel.name())
+            .or_else(|| self.templates.get(GLOBAL_TEMPLATE))
+            .map_or_else(|| channel.default_template(), String::as_str);
+        channel.format(alert, template)
+    }
+
+    async fn enqueue(&self, alert: QueuedAlert) -> Result<()> {
+        // 所有提醒都经过这里，静音只在这里检查。critical 提醒不受静音影响
+        let critical = alert.alert_type.severity() == AlertSeverity::Critical;
+        if let Some(mint) = alert.mint.as_deref().filter(|mint| !critical && self.muted.read().unwrap().contains(*mint)) {
+            debug!("Alerts muted for {}", mint);
+            return Ok(());
+        }
+        let pushed = self.outbox.lock().unwrap().push(alert)?;
+        match pushed {
+            Pushed::Duplicate => {
+                warn!("Skipping duplicate alert");
+                return Ok(());
+            }
+            Pushed::Coalesced => debug!("Replaced pending price alert with a newer one"),
+            Pushed::Dropped(dropped) => {
+                warn!("Alert queue is full, dropped oldest {:?} alert", dropped.alert_type);
+                self.undelivered.write("dropped", &dropped.message)?;
+            }
+            Pushed::Queued => {}
+        }
+        if critical {
+            sound::play();
+        }
+        self.outbox_changed.notify_one();
+        Ok(())
+    }
+
+    /// 共享状态时在存储中领取提醒的幂等键，其他实例已经领取时返回 false。同一实例可以重复领取。
+    /// 存储不可用时照常发送，宁可重复也不漏发。在投递队列中领取，产生提醒的交易处理不等待存储
+    async fn claim(&self, key: &str) -> bool {
+        let now = Utc::now().timestamp();
+        match self.store.claim_alert(key, now, now - DELIVERED_KEY_RETENTION_SECS).await {
+            Ok(claimed) => claimed,
+            Err(e) => {
+                error!("Failed to claim alert key in {}: {:?}", self.store.describe(), e);
+                true
+            }
+        }
+    }
+
+    /// 按顺序投递队列中的提醒，整条投递链失败时稍后重试。
+    /// 补充信息超出预算时先投递已有的内容，其余的完成后作为后续消息排队
+    pub async fn run_queue(self: Arc<Self>, enrichment: Arc<EnrichmentPipeline>, metadata: Arc<MetadataResolver>) {
+        // 重试时复用已补充的内容，(幂等键, 消息)
+        let mut enriched: Option<(String, String)> = None;
+        loop {
+            let next = self.outbox.lock().unwrap().front().cloned();
+            let Some(queued) = next else {
+                self.outbox_changed.notified().await;
+                continue;
+            };
+
+            if enriched.as_ref().is_none_or(|(key, _)| *key != queued.key) {
+                if !self.claim(&queued.key).await {
+                    debug!("Skipping alert already sent by another instance");
+                    if let Err(e) = self.outbox.lock().unwrap().complete(&queued.key) {
+                        error!("Failed to update outbox: {:?}", e);
                     }
+                    continue;
                 }
-                
-                Ok(())
+                let mut message = queued.message.clone();
+                if let Some(mint) = &queued.mint {
+                    let result = enrichment
+                        .run(EnrichContext {
+                            mint: mint.clone(),
+                            alert_type: queued.alert_type.clone(),
+                        })
+                        .await;
+                    for section in result.sections {
+                        message.push('\n');
+                        message.push_str(&section);
+                    }
+                    if let Some(late) = result.late {
+                        info!(
+                            "Enrichment of {} exceeded the {}ms budget, sending the alert now and details as a follow-up",
+                            mint,
+                            enrichment.budget().as_millis()
+                        );
+                        let service = self.clone();
+                        let alert_type = queued.alert_type.clone();
+                        let mint = mint.clone();
+                        tokio::spawn(async move {
+                            let sections = late.finish().await;
+                            if sections.is_empty() {
+                                return;
+                            }
+                            // 后续消息不带代币和信号，不会再次补充信息或触发 WebSocket 信号
+                            let message = format!("ℹ️ More on <code>{}</code>\n{}", mint, sections.join("\n"));
+                            if let Err(e) = service.enqueue(QueuedAlert::new(alert_type, &message, None, None)).await {
+                                error!("Failed to queue enrichment follow-up: {:?}", e);
+                            }
+                        });
+                    }
+                }
+                enriched = Some((queued.key.clone(), message));
             }
-            Err(e) => {
-                let err = "Failed to send alert to Telegram";
-                error!("{}: {:?}", err, e);
-                Err(anyhow::anyhow!(err))
+            let alert = Alert {
+                message: enriched.as_ref().map(|(_, message)| message.clone()).unwrap_or_default(),
+                alert_type: queued.alert_type.clone(),
+                timestamp: queued.timestamp,
+                mint: queued.mint.clone(),
+                holding: queued.holding.clone(),
+                signal: queued.signal.clone(),
+                provenance: queued.provenance.as_deref().cloned(),
+                token: queued.mint.as_deref().and_then(|mint| metadata.cached(mint)),
+                trade: queued.trade.as_deref().cloned(),
+            };
+            // 先记录开始投递，投递中途退出时重启后不会再发一次
+            if let Err(e) = self.outbox.lock().unwrap().start(&queued.key) {
+                error!("Failed to update outbox: {:?}", e);
+            }
+            if let Err(e) = self.deliver(&alert).await {
+                error!("Failed to deliver alert, retrying in {}s: {:?}", RETRY_DELAY.as_secs(), e);
+                sleep(RETRY_DELAY).await;
+                continue;
+            }
+            if let Err(e) = self.outbox.lock().unwrap().complete(&queued.key) {
+                error!("Failed to update outbox: {:?}", e);
             }
         }
     }
 
-    async fn send_to_ws(&self, signal: &TradeSignal) -> Result<()> {
-        if let Some(ref ws_url) = self.ws_url {
-            let url = Url::parse(ws_url)?;
-            let (mut ws_stream, _) = connect_async(url).await?;
-            let message = serde_json::to_string(signal)?;
-            ws_stream.send(Message::Text(message.clone())).await?;
-            info!("Signal sent to WebSocket: {}", message);
+    /// 提醒类型的路由优先，否则按严重程度选择投递链
+    fn chain_for(&self, alert_type: &AlertType) -> Vec<&str> {
+        match self.routes.get(alert_type).or_else(|| self.chains.get(&alert_type.severity())) {
+            Some(chain) => chain.iter().map(String::as_str).collect(),
+            None => DEFAULT_CHAIN.to_vec(),
         }
-        Ok(())
     }
 
-    async fn send_to_telegram(&self, message: &str) -> Result<()> {
-        let chat_id = ChatId(self.chat_id);
-        
-        match self.bot.send_message(chat_id, message)
-            .message_thread_id(self.topic_id.unwrap_or(0))  
-            .parse_mode(ParseMode::Html)
-            .await {
-            Ok(sent_message) => {
-                info!("Successfully sent message to Telegram. Message ID: {}", sent_message.id);
-                info!("Chat ID used: {}", chat_id.0);
-                if let Some(topic_id) = self.topic_id {
-                    info!("Topic ID used: {}", topic_id);
+    /// 按投递链依次尝试，直到有一个通道成功，并记录到提醒历史。
+    /// 投递成功后再发送到不在投递链中的镜像通道（Discord、WebSocket 信号等），镜像通道失败不影响投递结果
+    async fn deliver(&self, alert: &Alert) -> Result<()> {
+        let severity = alert.alert_type.severity();
+        let chain = self.chain_for(&alert.alert_type);
+
+        let mut attempts = Vec::new();
+        let mut delivered_by = None;
+        for name in &chain {
+            let Some(registered) = self.channel(name) else {
+                continue;
+            };
+            let Some(result) = self.send_via(registered, alert).await else {
+                continue;  // 这个通道不接收这类提醒
+            };
+            match result {
+                Ok(_) => {
+                    attempts.push(DeliveryAttempt { sink: name.to_string(), ok: true, error: None });
+                    delivered_by = Some(name.to_string());
+                    break;
+                }
+                Err(e) => {
+                    warn!("Alert delivery via {} failed: {}", name, e);
+                    attempts.push(DeliveryAttempt { sink: name.to_string(), ok: false, error: Some(e.to_string()) });
                 }
+            }
+        }
+        if delivered_by.is_some() {
+            for registered in self.channels.iter().filter(|registered| registered.mirror) {
+                let name = registered.channel.name();
+                if chain.contains(&name) {
+                    continue;
+                }
+                if let Some(result) = self.send_mirror(registered, alert).await {
+                    attempts.push(DeliveryAttempt {
+                        sink: name.to_string(),
+                        ok: result.is_ok(),
+                        error: result.err().map(|e| e.to_string()),
+                    });
+                }
+            }
+        }
+
+        let entry = AlertHistoryEntry {
+            timestamp: alert.timestamp,
+            alert_type: &alert.alert_type,
+            severity,
+            message: &alert.message,
+            delivered_by: delivered_by.clone(),
+            attempts,
+        };
+        let recorded = match serde_json::to_string(&entry) {
+            Ok(entry) => self.store.record_alert(alert.timestamp, &entry).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = recorded {
+            error!("Failed to write alert history: {:?}", e);
+        }
+
+        match delivered_by {
+            Some(name) => {
+                info!("Alert delivered via {}", name);
                 Ok(())
-            },
-            Err(e) => {
-                error!("Failed to send telegram message: {}", e);
-                Err(anyhow::anyhow!("Failed to send telegram message: {}", e))
+            }
+            None => Err(anyhow!("All channels in the {:?} delivery chain failed", severity)),
+        }
+    }
+
+    /// 发送到投递链之外的通道，失败时把内容写入备用文件，不重试
+    async fn send_mirror(&self, registered: &Registered, alert: &Alert) -> Option<Result<()>> {
+        let result = self.send_via(registered, alert).await?;
+        if let Err(e) = &result {
+            let name = registered.channel.name();
+            warn!("Alert delivery via {} failed: {}", name, e);
+            if let Some(message) = self.format_for(registered.channel.as_ref(), alert) {
+                if let Err(e) = self.undelivered.write(name, &message) {
+                    error!("Failed to write undelivered alert: {:?}", e);
+                }
             }
         }
+        Some(result)
     }
 
-    fn format_alert_message(&self, alert: &Alert) -> String {
-        format!(
-            "<b>{}</b>\n{}\nTimestamp: {}",
-            format!("{:?}", alert.alert_type),
-            alert.message,
-            alert.timestamp
+    /// 经过熔断器和超时发送到一个通道，熔断时直接失败，由投递链转到下一个通道。
+    /// 通道不接收这条提醒时返回 None
+    async fn send_via(&self, registered: &Registered, alert: &Alert) -> Option<Result<()>> {
+        let channel = &registered.channel;
+        let message = self.format_for(channel.as_ref(), alert)?;
+        let permit = registered.circuit.lock().unwrap().acquire();
+        if permit == Permit::Rejected {
+            return Some(Err(anyhow!("{} circuit is open", channel.name())));
+        }
+        if permit == Permit::Probe {
+            info!("Probing {} alert channel", channel.name());
+        }
+
+        let result = timeout(self.send_timeout, channel.send(&message, alert))
+            .await
+            .unwrap_or_else(|_| Err(anyhow!("{} send timed out after {}s", channel.name(), self.send_timeout.as_secs())));
+        match &result {
+            Ok(_) => {
+                let recovered = registered.circuit.lock().unwrap().on_success();
+                if let Some(diverted) = recovered {
+                    info!("{} alert channel recovered, {} alert(s) were diverted", channel.name(), diverted);
+                    let notice = Alert {
+                        message: format!(
+                            "✅ Alerts via {} recovered. {} alert(s) were routed to fallback channels while it was unavailable.",
+                            channel.name(),
+                            diverted
+                        ),
+                        alert_type: AlertType::Report,
+                        timestamp: Utc::now().timestamp(),
+                        mint: None,
+                        holding: None,
+                        signal: None,
+                        provenance: None,
+                        token: None,
+                        trade: None,
+                    };
+                    if let Some(text) = self.format_for(channel.as_ref(), &notice) {
+                        if let Err(e) = channel.send(&text, &notice).await {
+                            error!("Failed to send recovery notification: {:?}", e);
+                        }
+                    }
+                }
+            }
+            Err(_) => {
+                if registered.circuit.lock().unwrap().on_failure() {
+                    warn!("{} alert channel failed repeatedly, circuit opened", channel.name());
+                }
+            }
+        }
+        Some(result)
+    }
+}
+
+/// 以代码方式构造 AlertService，未设置的项使用与环境变量相同的默认值。`AlertService::new` 也通过它构造
+pub struct AlertServiceBuilder {
+    config: ProfileConfig,
+    channels: Vec<(Arc<dyn AlertChannel>, bool)>,
+}
+
+#[allow(dead_code)]  // 设置各项的方法供嵌入监控的调用方使用，命令行程序通过环境变量构造
+impl AlertServiceBuilder {
+    pub fn new(profile: &str) -> Self {
+        Self::from_config(ProfileConfig::with_defaults(profile))
+    }
+
+    /// 在已有配置（例如 `ProfileConfig::from_env`）的基础上修改
+    pub fn from_config(config: ProfileConfig) -> Self {
+        Self {
+            config,
+            channels: Vec::new(),
+        }
+    }
+
+    pub fn telegram(mut self, bot_token: &str, chat_id: i64) -> Self {
+        self.config.telegram_bot_token = bot_token.to_string();
+        self.config.telegram_chat_id = chat_id;
+        self
+    }
+
+    pub fn telegram_topic(mut self, topic_id: i32) -> Self {
+        self.config.telegram_topic_id = Some(topic_id);
+        self
+    }
+
+    /// 提醒同时发到这个 Discord webhook，也可以作为投递链中的 `discord` 通道
+    pub fn discord(mut self, webhook_url: &str) -> Self {
+        self.config.discord_webhook_url = Some(webhook_url.to_string());
+        self
+    }
+
+    /// 可以作为投递链中的 `email` 通道
+    pub fn email(mut self, email: EmailConfig) -> Self {
+        self.config.email = Some(email);
+        self
+    }
+
+    /// 价格提醒投递后发送交易信号的 WebSocket 端点
+    pub fn ws_signals(mut self, url: &str) -> Self {
+        self.config.ws_alert_url = Some(url.to_string());
+        self
+    }
+
+    /// 自定义通道，在投递链和路由中按 `name` 使用
+    pub fn channel(mut self, channel: Arc<dyn AlertChannel>) -> Self {
+        self.channels.push((channel, false));
+        self
+    }
+
+    /// 自定义通道，每条投递成功的提醒都会另外发到这个通道
+    pub fn mirror_channel(mut self, channel: Arc<dyn AlertChannel>) -> Self {
+        self.channels.push((channel, true));
+        self
+    }
+
+    /// 队列、提醒历史和备用文件所在的目录
+    pub fn data_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.data_dir = dir.into();
+        self
+    }
+
+    /// 持仓状态、交易日志和提醒历史的存储，默认是数据目录中的 JSON 文件
+    pub fn state_store(mut self, store: StoreConfig) -> Self {
+        self.config.state_store = store;
+        self
+    }
+
+    /// 与其他实例共享数据库中的状态时本实例的名称，需要数据库存储
+    pub fn instance_id(mut self, instance: &str) -> Self {
+        self.config.instance_id = Some(instance.to_string());
+        self
+    }
+
+    pub fn chain(mut self, severity: AlertSeverity, channels: &[&str]) -> Self {
+        self.config.alert_chains.insert(severity, channels.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    /// 通道使用的提醒模板，通道名为 `default` 时作为所有通道的模板
+    pub fn template(mut self, channel: &str, template: &str) -> Self {
+        self.config.alert_templates.insert(channel.to_string(), template.to_string());
+        self
+    }
+
+    /// 指定提醒类型使用的投递链，优先于按严重程度的投递链
+    pub fn route(mut self, alert_type: AlertType, channels: &[&str]) -> Self {
+        self.config.alert_routes.insert(alert_type, channels.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.config.alert_queue_capacity = capacity.max(1);
+        self
+    }
+
+    pub fn circuit_breaker(mut self, failures: u32, cooldown: Duration) -> Self {
+        self.config.circuit_failures = failures;
+        self.config.circuit_cooldown = cooldown;
+        self
+    }
+
+    pub fn send_timeout(mut self, timeout: Duration) -> Self {
+        self.config.alert_send_timeout = timeout;
+        self
+    }
+
+    pub async fn build(self) -> Result<AlertService, MonitorError> {
+        tokio::fs::create_dir_all(&self.config.data_dir).await.map_err(MonitorError::config)?;
+        AlertService::with_channels(&self.config, self.channels).await
+    }
+}
+
+/// 用提醒填充模板中的 `{placeholder}`。提醒内容最后替换，内容中的花括号不会被当作占位符
+pub fn render_alert(template: &str, alert: &Alert) -> String {
+    let token = match (&alert.token, &alert.mint) {
+        (Some(token), Some(mint)) => escape_html(&token.label(mint)),
+        (None, Some(mint)) => mint.clone(),
+        _ => String::new(),
+    };
+    template
+        .replace("{type}", &format!("{:?}", alert.alert_type))
+        .replace("{severity}", &format!("{:?}", alert.alert_type.severity()).to_lowercase())
+        .replace("{timestamp}", &alert.timestamp.to_string())
+        .replace(
+            "{time}",
+            &chrono::DateTime::from_timestamp(alert.timestamp, 0).map_or_else(String::new, |time| time.to_rfc3339()),
         )
+        .replace("{mint}", alert.mint.as_deref().unwrap_or_default())
+        .replace("{token}", &token)
+        .replace("{text}", &plain_text(&alert.message))
+        .replace("{message}", &alert.message)
+}
+
+/// 去掉提醒中的 HTML 标签，供纯文本的通道使用
+pub(crate) fn plain_text(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => output.push(c),
+            _ => {}
+        }
     }
+    output
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
 }
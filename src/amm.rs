@@ -0,0 +1,326 @@
+use crate::{
+    endpoints::RpcEndpoint,
+    oracle::{fetch_bonding_curve, SOL_MINT},
+    wallet_monitor::PUMP_PROGRAM_ID,
+};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use log::debug;
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+pub(crate) const PUMP_AMM_PROGRAM_ID: &str = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA"; // PumpSwap，2025 年起毕业的代币迁移到这里
+pub(crate) const RAYDIUM_AMM_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8"; // Raydium AMM v4，更早毕业的代币
+
+// PumpSwap 事件标识符（Anchor: sha256("event:<Name>") 的前 8 字节）
+pub(crate) const BUY_EVENT_DISCRIMINATOR: [u8; 8] = [103, 244, 82, 31, 44, 245, 119, 119];
+pub(crate) const SELL_EVENT_DISCRIMINATOR: [u8; 8] = [62, 47, 55, 10, 165, 3, 220, 42];
+// BuyEvent 和 SellEvent 的前部布局相同：标识符(8) + timestamp 和 13 个 u64，之后是 pool 和 user
+const BASE_AMOUNT_OFFSET: usize = 16;  // base_amount_out / base_amount_in
+const QUOTE_AMOUNT_OFFSET: usize = 64;  // quote_amount_in / quote_amount_out，不含手续费
+pub(crate) const SWAP_POOL_OFFSET: usize = 120;
+const SWAP_USER_OFFSET: usize = 152;
+
+// PumpSwap Pool 账户：discriminator(8) + pool_bump(1) + index(2) + creator(32)，之后是 base_mint 和 quote_mint
+const POOL_BASE_MINT_OFFSET: usize = 43;
+const POOL_QUOTE_MINT_OFFSET: usize = 75;
+const CANONICAL_POOL_INDEX: u16 = 0;  // pump 迁移时创建的池
+
+// Raydium AMM v4 的池账户（LIQUIDITY_STATE_LAYOUT_V4）
+const RAYDIUM_POOL_LEN: u64 = 752;
+const RAYDIUM_COIN_MINT_OFFSET: usize = 400;
+const RAYDIUM_PC_MINT_OFFSET: usize = 432;
+// ray_log 的类型和交换方向
+const RAY_LOG_SWAP_BASE_IN: u8 = 3;
+const RAY_LOG_SWAP_BASE_OUT: u8 = 4;
+const RAY_LOG_COIN_TO_PC: u64 = 2;
+
+const LOOKUP_RETRY: Duration = Duration::from_secs(10 * 60);  // 查找 Raydium 池失败的代币在这段时间内不再查找
+
+/// PumpSwap 池中的一笔买卖，base 是代币，quote 是 SOL
+#[derive(Debug, Clone)]
+pub struct AmmSwap {
+    pub pool: String,
+    pub user: String,
+    pub is_buy: bool,
+    pub base_amount: u64,
+    pub quote_amount: u64,  // lamports
+}
+
+/// 解码 PumpSwap 的 Program data（含标识符）。不是买卖的事件返回 None
+pub fn decode_swap_event(data: &[u8]) -> Result<Option<AmmSwap>> {
+    let is_buy = match data.get(..8) {
+        Some(discriminator) if discriminator == BUY_EVENT_DISCRIMINATOR => true,
+        Some(discriminator) if discriminator == SELL_EVENT_DISCRIMINATOR => false,
+        _ => return Ok(None),
+    };
+    if data.len() < SWAP_USER_OFFSET + 32 {
+        return Err(anyhow!("PumpSwap {} event too short: {} bytes", if is_buy { "buy" } else { "sell" }, data.len()));
+    }
+    let read_u64 = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    Ok(Some(AmmSwap {
+        pool: bs58::encode(&data[SWAP_POOL_OFFSET..SWAP_POOL_OFFSET + 32]).into_string(),
+        user: bs58::encode(&data[SWAP_USER_OFFSET..SWAP_USER_OFFSET + 32]).into_string(),
+        is_buy,
+        base_amount: read_u64(BASE_AMOUNT_OFFSET),
+        quote_amount: read_u64(QUOTE_AMOUNT_OFFSET),
+    }))
+}
+
+/// Raydium 池中的一笔交换，coin 和 pc 是池的两种代币
+#[derive(Debug, Clone, Copy)]
+pub struct RaySwap {
+    pub coin_to_pc: bool,  // 用 coin 换 pc
+    pub coin_amount: u64,
+    pub pc_amount: u64,
+}
+
+/// 解码 `Program log: ray_log: ` 之后的 base64，不是交换的日志返回 None。
+/// 交换日志：log_type(1) 之后是 7 个 u64，方向在第 3 个
+pub fn decode_ray_log(data_str: &str) -> Option<RaySwap> {
+    let data = general_purpose::STANDARD.decode(data_str.trim()).ok()?;
+    if data.len() < 57 {
+        return None;
+    }
+    let read_u64 = |index: usize| u64::from_le_bytes(data[1 + index * 8..9 + index * 8].try_into().unwrap());
+    let coin_to_pc = read_u64(2) == RAY_LOG_COIN_TO_PC;
+    // SwapBaseIn: amount_in, minimum_out, direction, user_source, pool_coin, pool_pc, out_amount
+    // SwapBaseOut: max_in, amount_out, direction, user_source, pool_coin, pool_pc, deduct_in
+    let (amount_in, amount_out) = match data[0] {
+        RAY_LOG_SWAP_BASE_IN => (read_u64(0), read_u64(6)),
+        RAY_LOG_SWAP_BASE_OUT => (read_u64(6), read_u64(1)),
+        _ => return None,
+    };
+    let (coin_amount, pc_amount) = if coin_to_pc { (amount_in, amount_out) } else { (amount_out, amount_in) };
+    Some(RaySwap { coin_to_pc, coin_amount, pc_amount })
+}
+
+/// 代币以 SOL 计价的 Raydium AMM v4 池
+#[derive(Debug, Clone)]
+pub struct RaydiumPool {
+    pub address: String,
+    pub mint: String,
+    pub token_is_coin: bool,  // 代币是池的 coin（另一边是 SOL）
+}
+
+impl RaydiumPool {
+    /// 按池的方向换算为 (是否买入代币, 代币数量, SOL 数量)
+    pub fn trade_of(&self, swap: RaySwap) -> (bool, u64, u64) {
+        if self.token_is_coin {
+            (!swap.coin_to_pc, swap.coin_amount, swap.pc_amount)
+        } else {
+            (swap.coin_to_pc, swap.pc_amount, swap.coin_amount)
+        }
+    }
+}
+
+/// 毕业代币的 AMM 池。PumpSwap 的事件只有池地址：持仓代币的迁移池按 PDA 推导，
+/// 其他池（目标钱包新买的代币）通过 RPC 读取池账户后缓存。Raydium 的池通过 getProgramAccounts 查找
+pub struct AmmPools {
+    rpc: Option<RpcEndpoint>,
+    pump_pools: RwLock<HashMap<String, Option<String>>>,  // PumpSwap 池 -> 代币，None 表示不是以 SOL 计价的池
+    derived: RwLock<HashSet<String>>,  // 已推导迁移池的代币
+    raydium: RwLock<HashMap<String, Option<RaydiumPool>>>,  // 代币 -> Raydium 池，None 表示没有 Raydium 池
+    raydium_failed: RwLock<HashMap<String, Instant>>,  // 查找 Raydium 池失败的代币 -> 失败的时间
+}
+
+impl AmmPools {
+    pub fn new(rpc: Option<RpcEndpoint>) -> Self {
+        Self {
+            rpc,
+            pump_pools: RwLock::new(HashMap::new()),
+            derived: RwLock::new(HashSet::new()),
+            raydium: RwLock::new(HashMap::new()),
+            raydium_failed: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 已知的 Raydium 池，不发起查询。还没有查找过或查找失败时为 None
+    pub fn known_raydium_pool(&self, mint: &str) -> Option<Option<RaydiumPool>> {
+        self.raydium.read().unwrap().get(mint).cloned()
+    }
+
+    /// 已知的 PumpSwap 池的代币，不发起查询。没有记录时为 None
+    pub fn pump_mint(&self, pool: &str) -> Option<Option<String>> {
+        self.pump_pools.read().unwrap().get(pool).cloned()
+    }
+
+    /// 推导代币在 PumpSwap 上的迁移池并记录，已推导过的代币跳过
+    pub fn derive(&self, mint: &str) {
+        if self.derived.read().unwrap().contains(mint) {
+            return;
+        }
+        match canonical_pool(mint) {
+            Ok(pool) => {
+                self.pump_pools.write().unwrap().insert(pool.to_string(), Some(mint.to_string()));
+            }
+            Err(e) => debug!("Failed to derive PumpSwap pool of {}: {}", mint, e),
+        }
+        self.derived.write().unwrap().insert(mint.to_string());
+    }
+
+    /// 读取 PumpSwap 池账户得到代币，查询失败时不缓存
+    pub async fn resolve_pump_pool(&self, pool: &str) -> Option<String> {
+        if let Some(mint) = self.pump_mint(pool) {
+            return mint;
+        }
+        let rpc = self.rpc.as_ref()?;
+        match fetch_pump_pool(&rpc.client(), pool).await {
+            Ok(mint) => {
+                self.pump_pools.write().unwrap().insert(pool.to_string(), mint.clone());
+                mint
+            }
+            Err(e) => {
+                debug!("Failed to look up PumpSwap pool {}: {:?}", pool, e);
+                None
+            }
+        }
+    }
+
+    /// 迁移到 Raydium 的代币的池。还在 bonding curve 上、迁移到 PumpSwap 或查询失败时为 None。
+    /// 现在毕业的代币都迁移到 PumpSwap，还在 bonding curve 上的代币以后也不会有 Raydium 池，
+    /// 所以没有池的结果同样缓存；查询失败的代币在 `LOOKUP_RETRY` 之后才再次查找
+    pub async fn raydium_pool(&self, mint: &str) -> Option<RaydiumPool> {
+        if let Some(pool) = self.known_raydium_pool(mint) {
+            return pool;
+        }
+        if self.raydium_failed.read().unwrap().get(mint).is_some_and(|at| at.elapsed() < LOOKUP_RETRY) {
+            return None;
+        }
+        let rpc = self.rpc.as_ref()?;
+        let client = rpc.client();
+        let result: Result<Option<RaydiumPool>> = async {
+            if !fetch_bonding_curve(&client, mint).await?.complete {
+                return Ok(None);
+            }
+            if client.get_account(&canonical_pool(mint)?).await.is_ok() {
+                return Ok(None);
+            }
+            find_raydium_pool(&client, mint).await
+        }
+        .await;
+        match result {
+            Ok(pool) => {
+                self.raydium.write().unwrap().insert(mint.to_string(), pool.clone());
+                pool
+            }
+            Err(e) => {
+                debug!("Failed to look up Raydium pool of {}: {:?}", mint, e);
+                self.raydium_failed.write().unwrap().insert(mint.to_string(), Instant::now());
+                None
+            }
+        }
+    }
+}
+
+/// pump 迁移时创建的 PumpSwap 池：pool authority 由 pump 程序按代币推导，池地址由 PumpSwap 推导
+pub fn canonical_pool(mint: &str) -> Result<Pubkey> {
+    let pump = Pubkey::from_str(PUMP_PROGRAM_ID)?;
+    let amm = Pubkey::from_str(PUMP_AMM_PROGRAM_ID)?;
+    let sol = Pubkey::from_str(SOL_MINT)?;
+    let mint = Pubkey::from_str(mint).map_err(|e| anyhow!("Invalid mint {}: {}", mint, e))?;
+    let (authority, _) = Pubkey::find_program_address(&[b"pool-authority", mint.as_ref()], &pump);
+    let (pool, _) = Pubkey::find_program_address(
+        &[b"pool", &CANONICAL_POOL_INDEX.to_le_bytes(), authority.as_ref(), mint.as_ref(), sol.as_ref()],
+        &amm,
+    );
+    Ok(pool)
+}
+
+/// 读取 PumpSwap 池的 base 代币，quote 不是 SOL 时为 None
+async fn fetch_pump_pool(client: &RpcClient, pool: &str) -> Result<Option<String>> {
+    let address = Pubkey::from_str(pool).map_err(|e| anyhow!("Invalid pool {}: {}", pool, e))?;
+    let data = client.get_account_data(&address).await?;
+    let (Some(base), Some(quote)) = (
+        data.get(POOL_BASE_MINT_OFFSET..POOL_BASE_MINT_OFFSET + 32),
+        data.get(POOL_QUOTE_MINT_OFFSET..POOL_QUOTE_MINT_OFFSET + 32),
+    ) else {
+        return Err(anyhow!("Pool account {} is too short ({} bytes)", pool, data.len()));
+    };
+    Ok((bs58::encode(quote).into_string() == SOL_MINT).then(|| bs58::encode(base).into_string()))
+}
+
+/// 查找代币与 SOL 的 Raydium AMM v4 池，代币可能是池的 coin 或 pc
+async fn find_raydium_pool(client: &RpcClient, mint: &str) -> Result<Option<RaydiumPool>> {
+    let program_id = Pubkey::from_str(RAYDIUM_AMM_PROGRAM_ID)?;
+    let mint_key = Pubkey::from_str(mint).map_err(|e| anyhow!("Invalid mint {}: {}", mint, e))?;
+    let sol = Pubkey::from_str(SOL_MINT)?;
+    for token_is_coin in [true, false] {
+        let (token_offset, sol_offset) = if token_is_coin {
+            (RAYDIUM_COIN_MINT_OFFSET, RAYDIUM_PC_MINT_OFFSET)
+        } else {
+            (RAYDIUM_PC_MINT_OFFSET, RAYDIUM_COIN_MINT_OFFSET)
+        };
+        let accounts = client
+            .get_program_accounts_with_config(
+                &program_id,
+                RpcProgramAccountsConfig {
+                    filters: Some(vec![
+                        RpcFilterType::DataSize(RAYDIUM_POOL_LEN),
+                        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(token_offset, mint_key.as_ref())),
+                        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(sol_offset, sol.as_ref())),
+                    ]),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        data_slice: Some(UiDataSliceConfig { offset: 0, length: 0 }),
+                        commitment: Some(CommitmentConfig::confirmed()),
+                        min_context_slot: None,
+                    },
+                    with_context: None,
+                },
+            )
+            .await?;
+        if let Some((address, _)) = accounts.first() {
+            return Ok(Some(RaydiumPool {
+                address: address.to_string(),
+                mint: mint.to_string(),
+                token_is_coin,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// 连接中 Raydium 池的日志订阅：请求 ID -> 池，确认后按订阅 ID 查找通知对应的池。每次连接重新开始
+#[derive(Debug, Default)]
+pub struct PoolSubscriptions {
+    requested: HashMap<u64, RaydiumPool>,
+    active: HashMap<u64, RaydiumPool>,
+}
+
+impl PoolSubscriptions {
+    pub fn clear(&mut self) {
+        self.requested.clear();
+        self.active.clear();
+    }
+
+    pub fn request(&mut self, request_id: u64, pool: RaydiumPool) {
+        self.requested.insert(request_id, pool);
+    }
+
+    /// 订阅请求得到确认，返回是否是池的订阅
+    pub fn confirm(&mut self, request_id: u64, subscription: u64) -> bool {
+        match self.requested.remove(&request_id) {
+            Some(pool) => {
+                self.active.insert(subscription, pool);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn pool(&self, subscription: u64) -> Option<RaydiumPool> {
+        self.active.get(&subscription).cloned()
+    }
+}
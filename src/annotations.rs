@@ -0,0 +1,152 @@
+use crate::{
+    journal::{TradeAnnotation, TradeJournal},
+    models::TradeRef,
+};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::{info, warn};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use teloxide::{
+    prelude::*,
+    types::{CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, ParseMode},
+};
+
+pub(crate) const TAG_PREFIX: &str = "tag:";  // 标注按钮的回调数据前缀
+pub(crate) const DEFAULT_TAGS: &[&str] = &["good entry", "FOMO", "should have skipped"];
+const MAX_TRACKED: usize = 500;  // 保留按钮对应交易的提醒数，更早的提醒按钮不再生效
+const TAGS_PER_ROW: usize = 3;
+
+/// 带标注按钮的提醒对应的交易和已选的标签
+struct Tracked {
+    trade: TradeRef,
+    tagged: BTreeSet<usize>,
+}
+
+/// 交易提醒的标注按钮（`TRADE_ANNOTATIONS`）：新建仓、卖出和清仓提醒下方显示一排标签，
+/// 按下后把标签记录到交易日志旁的 `annotations.jsonl`，供 `monitor report review` 复盘。
+/// 按钮的回调由 `TelegramBot` 接收；按钮和交易的对应只保存在内存中，重启前发出的提醒按钮不再生效
+pub struct TradeAnnotations {
+    profile: String,
+    bot: Bot,
+    chat_id: i64,
+    topic_id: Option<i32>,
+    tags: Vec<String>,
+    journal: TradeJournal,
+    tracked: Mutex<BTreeMap<u64, Tracked>>,
+    next_id: AtomicU64,
+}
+
+impl TradeAnnotations {
+    pub fn new(profile: &str, bot_token: &str, chat_id: i64, topic_id: Option<i32>, tags: Vec<String>, data_dir: &Path) -> Self {
+        Self {
+            profile: profile.to_string(),
+            bot: Bot::new(bot_token),
+            chat_id,
+            topic_id,
+            tags,
+            journal: TradeJournal::new(data_dir),
+            tracked: Mutex::new(BTreeMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// 发送交易提醒并在下方附上标注按钮
+    pub async fn send(&self, text: &str, trade: &TradeRef) -> Result<()> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.bot
+            .send_message(ChatId(self.chat_id), text)
+            .message_thread_id(self.topic_id.unwrap_or(0))
+            .parse_mode(ParseMode::Html)
+            .reply_markup(self.keyboard(id, &BTreeSet::new()))
+            .await
+            .map_err(|e| anyhow!("Failed to send telegram message: {}", e))?;
+        let mut tracked = self.tracked.lock().unwrap();
+        if tracked.len() >= MAX_TRACKED {
+            tracked.pop_first();
+        }
+        tracked.insert(id, Tracked { trade: trade.clone(), tagged: BTreeSet::new() });
+        Ok(())
+    }
+
+    /// 已选的标签前面加上勾
+    fn keyboard(&self, id: u64, tagged: &BTreeSet<usize>) -> InlineKeyboardMarkup {
+        let buttons: Vec<InlineKeyboardButton> = self
+            .tags
+            .iter()
+            .enumerate()
+            .map(|(index, tag)| {
+                let label = if tagged.contains(&index) { format!("✅ {}", tag) } else { tag.clone() };
+                InlineKeyboardButton::callback(label, format!("{}{}:{}", TAG_PREFIX, id, index))
+            })
+            .collect();
+        InlineKeyboardMarkup::new(buttons.chunks(TAGS_PER_ROW).map(<[_]>::to_vec))
+    }
+
+    /// 处理标注按钮的回调，同一笔交易的同一个标签只记录一次
+    pub async fn answer(&self, query: CallbackQuery) {
+        let message = query.message.as_ref().filter(|message| message.chat.id.0 == self.chat_id);
+        let selected = query
+            .data
+            .as_deref()
+            .and_then(|data| data.strip_prefix(TAG_PREFIX))
+            .and_then(|data| data.split_once(':'))
+            .and_then(|(id, index)| Some((id.parse::<u64>().ok()?, index.parse::<usize>().ok()?)))
+            .filter(|(_, index)| *index < self.tags.len());
+        let (Some(message), Some((id, index))) = (message, selected) else {
+            let _ = self.bot.answer_callback_query(query.id).await;
+            return;
+        };
+        let user = query.from.username.clone().unwrap_or_else(|| query.from.first_name.clone());
+        let tag = &self.tags[index];
+
+        let recorded = {
+            let mut tracked = self.tracked.lock().unwrap();
+            tracked.get_mut(&id).map(|tracked| (tracked.tagged.insert(index), tracked.trade.clone(), tracked.tagged.clone()))
+        };
+        let reply = match recorded {
+            None => "This alert is too old to annotate".to_string(),
+            Some((false, _, _)) => format!("Already tagged \"{}\"", tag),
+            Some((true, trade, tagged)) => {
+                let annotation = TradeAnnotation {
+                    timestamp: Utc::now().timestamp(),
+                    signature: trade.signature,
+                    wallet: trade.wallet,
+                    mint: trade.mint,
+                    tag: tag.clone(),
+                    user: user.clone(),
+                };
+                match self.journal.record_annotation(&annotation) {
+                    Ok(()) => {
+                        info!("[{}] Trade {} tagged \"{}\" by {}", self.profile, annotation.signature, tag, user);
+                        if let Err(e) = self
+                            .bot
+                            .edit_message_reply_markup(ChatId(self.chat_id), message.id)
+                            .reply_markup(self.keyboard(id, &tagged))
+                            .await
+                        {
+                            warn!("[{}] Failed to update annotation buttons: {}", self.profile, e);
+                        }
+                        format!("Tagged \"{}\"", tag)
+                    }
+                    Err(e) => {
+                        warn!("[{}] Failed to record annotation of {}: {:?}", self.profile, annotation.signature, e);
+                        if let Some(tracked) = self.tracked.lock().unwrap().get_mut(&id) {
+                            tracked.tagged.remove(&index);
+                        }
+                        format!("Failed to record the tag: {}", e)
+                    }
+                }
+            }
+        };
+        if let Err(e) = self.bot.answer_callback_query(query.id).text(reply).await {
+            warn!("[{}] Failed to answer annotation: {}", self.profile, e);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use crate::{
+    display::{self, Column, SortKey},
+    error::MonitorError,
+    logging, metrics,
+    wallet_monitor::WalletMonitor,
+};
+use anyhow::{anyhow, Result};
+use hyper::{
+    header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use log::{error, info, warn};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use subtle::ConstantTimeEq;
+use std::{collections::{BTreeSet, HashMap}, convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Deserialize)]
+struct WalletRequest {
+    wallet: String,
+}
+
+#[derive(Deserialize)]
+struct MintRequest {
+    mint: String,
+}
+
+#[derive(Deserialize)]
+struct ThresholdRequest {
+    threshold: Option<f64>,
+    thresholds: Option<Vec<f64>>,
+}
+
+#[derive(Deserialize)]
+struct HoldPlanRequest {
+    mint: String,
+    max_hold_mins: u64,
+    plan: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TagsRequest {
+    mint: String,
+    tags: BTreeSet<String>,
+}
+
+#[derive(Deserialize)]
+struct NoteRequest {
+    mint: String,
+    note: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ReconcileRequest {
+    #[serde(default)]
+    fix: bool,
+}
+
+#[derive(Deserialize)]
+struct DisplayRequest {
+    columns: Option<Vec<Column>>,
+    sort: Option<SortKey>,
+    by_wallet: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct LogLevelRequest {
+    directives: String,
+}
+
+/// 远程控制 API，所有请求都需要 `Authorization: Bearer <CONTROL_API_TOKEN>`。
+/// 多 profile 时通过 `?profile=<name>` 选择目标 profile
+pub async fn serve(addr: SocketAddr, admin_token: String, monitors: Vec<WalletMonitor>, shutdown: CancellationToken) -> Result<()> {
+    let admin_token = Arc::new(admin_token);
+    let monitors: Arc<HashMap<String, WalletMonitor>> = Arc::new(
+        monitors
+            .into_iter()
+            .map(|monitor| (monitor.profile().to_string(), monitor))
+            .collect(),
+    );
+
+    let make_svc = make_service_fn(move |_| {
+        let monitors = monitors.clone();
+        let admin_token = admin_token.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                handle_request(req, monitors.clone(), admin_token.clone())
+            }))
+        }
+    });
+
+    // 关闭时不再接受新连接，等待进行中的请求完成
+    let server = Server::try_bind(&addr)?
+        .serve(make_svc)
+        .with_graceful_shutdown(async move { shutdown.cancelled().await });
+    info!("Control API listening on {}", addr);
+    server.await?;
+    Ok(())
+}
+
+async fn handle_request(
+    req: Request<Body>,
+    monitors: Arc<HashMap<String, WalletMonitor>>,
+    admin_token: Arc<String>,
+) -> Result<Response<Body>, Infallible> {
+    if !is_authorized(&req, &admin_token) {
+        warn!("Rejected unauthorized control API request: {} {}", req.method(), req.uri().path());
+        return Ok(json_response(StatusCode::UNAUTHORIZED, json!({ "error": "unauthorized" })));
+    }
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    info!("Control API request: {} {}", method, path);
+
+    // 指标包含所有 profile，不需要选择 profile
+    if method == Method::GET && path == "/metrics" {
+        let profiles: Vec<(&str, &metrics::Metrics)> = monitors
+            .values()
+            .map(|monitor| {
+                monitor.sample_metrics();
+                (monitor.profile(), monitor.metrics())
+            })
+            .collect();
+        let mut response = Response::new(Body::from(metrics::render_prometheus(&profiles)));
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("text/plain; version=0.0.4"));
+        return Ok(response);
+    }
+
+    // 日志级别是进程级别的设置
+    if path == "/loglevel" {
+        let result = match method {
+            Method::GET => Ok(json!({ "directives": logging::directives() })),
+            Method::POST => match read_json::<LogLevelRequest>(req).await {
+                Ok(body) => logging::set_directives(&body.directives).map(|_| {
+                    info!("Log directives changed to {}", logging::directives());
+                    json!({ "ok": true, "directives": logging::directives() })
+                }),
+                Err(e) => Err(e),
+            },
+            _ => return Ok(json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" }))),
+        };
+        return Ok(match result {
+            Ok(body) => json_response(StatusCode::OK, body),
+            Err(e) => json_response(StatusCode::BAD_REQUEST, json!({ "error": e.to_string() })),
+        });
+    }
+
+    // 持仓表显示设置也是进程级别的
+    if path == "/display" {
+        let result = match method {
+            Method::GET => Ok(json!(display::settings())),
+            Method::POST => match read_json::<DisplayRequest>(req).await {
+                Ok(body) => update_display(body),
+                Err(e) => Err(e),
+            },
+            _ => return Ok(json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" }))),
+        };
+        return Ok(match result {
+            Ok(body) => json_response(StatusCode::OK, body),
+            Err(e) => json_response(StatusCode::BAD_REQUEST, json!({ "error": e.to_string() })),
+        });
+    }
+
+    let monitor = match select_profile(&req, &monitors) {
+        Ok(monitor) => monitor,
+        Err(e) => return Ok(json_response(StatusCode::BAD_REQUEST, json!({ "error": e.to_string() }))),
+    };
+
+    match route(req, monitor).await {
+        Ok(Some(body)) => Ok(json_response(StatusCode::OK, body)),
+        Ok(None) => Ok(json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" }))),
+        Err(e) => {
+            error!("Control API request {} {} failed: {}", method, path, e);
+            // RPC 和提醒通道的故障不是请求本身的问题
+            let status = match e.downcast_ref::<MonitorError>() {
+                Some(MonitorError::Rpc(_) | MonitorError::Sink(_) | MonitorError::Ws(_)) => StatusCode::BAD_GATEWAY,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            Ok(json_response(status, json!({ "error": e.to_string() })))
+        }
+    }
+}
+
+const DEFAULT_RECENT_TRADES: usize = 20;
+
+async fn route(req: Request<Body>, monitor: &WalletMonitor) -> Result<Option<Value>> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+
+    if method == Method::GET {
+        return match path.as_str() {
+            "/trades/recent" => {
+                let limit = match query_param(&req, "limit") {
+                    Some(limit) => limit.parse::<usize>().map_err(|_| anyhow!("Invalid limit: {}", limit))?,
+                    None => DEFAULT_RECENT_TRADES,
+                };
+                Ok(Some(json!({ "trades": monitor.recent_trades(limit).await })))
+            }
+            "/stress" => Ok(Some(serde_json::to_value(monitor.stress_report().await)?)),
+            "/tags" => Ok(Some(json!({ "tags": monitor.tag_breakdown().await }))),
+            "/keys" => Ok(Some(json!({ "keys": monitor.key_usage() }))),
+            "/channels" => Ok(Some(json!({ "channels": monitor.channel_health().await }))),
+            "/diag" => Ok(Some(json!({ "endpoints": monitor.endpoint_diag(), "helius_keys": monitor.key_usage() }))),
+            _ => Ok(None),
+        };
+    }
+    if method != Method::POST {
+        return Ok(None);
+    }
+    if monitor.is_replica() {
+        return Err(anyhow!("Profile '{}' is a read-only replica, make changes on the monitoring instance", monitor.profile()));
+    }
+
+    match path.as_str() {
+        "/wallets/add" => {
+            let body: WalletRequest = read_json(req).await?;
+            monitor.add_wallet(&body.wallet).await?;
+        }
+        "/wallets/remove" => {
+            let body: WalletRequest = read_json(req).await?;
+            monitor.remove_wallet(&body.wallet).await?;
+        }
+        "/mints/mute" => {
+            let body: MintRequest = read_json(req).await?;
+            monitor.mute_mint(&body.mint).await;
+        }
+        "/mints/unmute" => {
+            let body: MintRequest = read_json(req).await?;
+            monitor.unmute_mint(&body.mint).await?;
+        }
+        "/threshold" => {
+            let body: ThresholdRequest = read_json(req).await?;
+            let thresholds = match (body.thresholds, body.threshold) {
+                (Some(thresholds), None) => thresholds,
+                (None, Some(threshold)) => vec![threshold],
+                _ => return Err(anyhow!("Set either threshold or thresholds")),
+            };
+            monitor.set_price_change_thresholds(thresholds).await?;
+        }
+        "/report" => {
+            monitor.send_report().await?;
+        }
+        "/stress" => {
+            monitor.send_stress_report().await?;
+        }
+        "/positions/max-hold" => {
+            let body: HoldPlanRequest = read_json(req).await?;
+            if body.max_hold_mins == 0 {
+                return Err(anyhow!("max_hold_mins must be greater than 0"));
+            }
+            monitor
+                .set_hold_plan(&body.mint, Duration::from_secs(body.max_hold_mins * 60), body.plan)
+                .await?;
+        }
+        "/positions/tags" => {
+            let body: TagsRequest = read_json(req).await?;
+            monitor.set_tags(&body.mint, body.tags).await?;
+        }
+        "/positions/note" => {
+            let body: NoteRequest = read_json(req).await?;
+            monitor.set_note(&body.mint, body.note).await?;
+        }
+        "/reconcile" => {
+            let body: ReconcileRequest = read_json(req).await?;
+            let discrepancies = monitor.reconcile(body.fix).await?;
+            return Ok(Some(json!({ "discrepancies": discrepancies, "fixed": body.fix })));
+        }
+        _ => return Ok(None),
+    }
+
+    Ok(Some(json!({ "ok": true })))
+}
+
+fn update_display(body: DisplayRequest) -> Result<Value> {
+    if let Some(columns) = &body.columns {
+        if columns.is_empty() {
+            return Err(anyhow!("At least one column must be displayed"));
+        }
+        display::set_columns(columns);
+    }
+    if let Some(sort) = body.sort {
+        display::set_sort(sort);
+    }
+    if let Some(by_wallet) = body.by_wallet {
+        display::set_by_wallet(by_wallet);
+    }
+    let settings = display::settings();
+    info!("Display settings changed to {:?}", settings);
+    Ok(json!(settings))
+}
+
+fn select_profile<'a>(
+    req: &Request<Body>,
+    monitors: &'a HashMap<String, WalletMonitor>,
+) -> Result<&'a WalletMonitor> {
+    match query_param(req, "profile") {
+        Some(name) => monitors
+            .get(&name)
+            .ok_or_else(|| anyhow!("Unknown profile '{}'", name)),
+        None if monitors.len() == 1 => Ok(monitors.values().next().unwrap()),
+        None => Err(anyhow!("Multiple profiles configured, specify ?profile=<name>")),
+    }
+}
+
+fn query_param(req: &Request<Body>, name: &str) -> Option<String> {
+    req.uri().query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    })
+}
+
+fn is_authorized(req: &Request<Body>, admin_token: &str) -> bool {
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        // 按常量时间比较，响应时间不会泄露令牌的前缀
+        .map(|token| bool::from(token.as_bytes().ct_eq(admin_token.as_bytes())))
+        .unwrap_or(false)
+}
+
+async fn read_json<T: DeserializeOwned>(req: Request<Body>) -> Result<T> {
+    let bytes = hyper::body::to_bytes(req.into_body()).await?;
+    serde_json::from_slice(&bytes).map_err(|e| anyhow!("Invalid request body: {}", e))
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
@@ -0,0 +1,85 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+pub(crate) const AUDIT_FILE: &str = "audit.jsonl";
+
+/// 持仓变动类型
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Buy,
+    Sell,
+    Close,   // 卖出后数量过小，清仓
+    Remove,  // 价格更新或定期清理时移除
+    Reconcile,  // 按链上余额修正
+    Release,    // 移除目标钱包时扣除它的仓位
+    Sync,       // 启动时从链上余额加入的持仓
+}
+
+/// 变动前后的持仓状态（数量为原始单位）
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct HoldingState {
+    pub amount: u64,
+    pub total_cost: f64,
+    pub current_price: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_cost_usd: Option<f64>,  // 按交易时汇率计的 USD 成本
+}
+
+/// 一次持仓变动
+#[derive(Debug, Serialize)]
+pub struct AuditEvent<'a> {
+    pub action: AuditAction,
+    pub mint: &'a str,
+    pub signature: Option<&'a str>,  // 触发变动的交易签名
+    pub token_amount: Option<u64>,
+    pub price: Option<f64>,
+    pub before: Option<HoldingState>,
+    pub after: Option<HoldingState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sol_usd: Option<f64>,  // 交易时的 SOL/USD 汇率
+}
+
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    timestamp: i64,
+    profile: &'a str,
+    #[serde(flatten)]
+    event: &'a AuditEvent<'a>,
+}
+
+/// 只追加的审计日志 `audit.jsonl`，记录每次持仓变动，用于事后核对成本
+pub struct AuditLog {
+    profile: String,
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(profile: &str, data_dir: &Path) -> Self {
+        Self {
+            profile: profile.to_string(),
+            path: data_dir.join(AUDIT_FILE),
+        }
+    }
+
+    pub fn record(&self, event: &AuditEvent) -> Result<()> {
+        let record = AuditRecord {
+            timestamp: Utc::now().timestamp(),
+            profile: &self.profile,
+            event,
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{
+    amm::canonical_pool,
+    helius::{HeliusKeys, Limit},
+    oracle::bonding_curve_address,
+};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::time::Duration;
+
+const HISTORY_PAGE: usize = 100;  // 解析交易历史接口单次返回的上限
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const PUMP_SOURCES: [&str; 2] = ["PUMP_FUN", "PUMP_AMM"];  // bonding curve 和毕业后的 PumpSwap
+
+/// Helius 解析后的一笔交易，只保留回放需要的字段
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParsedTransaction {
+    signature: String,
+    slot: u64,
+    #[serde(default)]
+    timestamp: i64,  // 区块时间
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    transaction_error: Option<serde_json::Value>,
+    #[serde(default)]
+    account_data: Vec<AccountData>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountData {
+    account: String,
+    #[serde(default)]
+    native_balance_change: i64,
+    #[serde(default)]
+    token_balance_changes: Vec<TokenBalanceChange>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenBalanceChange {
+    user_account: String,
+    mint: String,
+    raw_token_amount: RawTokenAmount,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTokenAmount {
+    token_amount: String,
+    decimals: u8,
+}
+
+/// 从历史中还原的一笔 pump 交易
+#[derive(Debug, Clone)]
+pub struct HistoricalTrade {
+    pub signature: String,
+    pub slot: u64,
+    pub timestamp: i64,
+    pub mint: String,
+    pub is_buy: bool,
+    pub sol_amount: u64,  // 钱包 SOL 余额的变化，包含手续费
+    pub token_amount: u64,
+    pub decimals: u8,
+}
+
+/// 钱包最近 `lookback` 笔交易中成功的 pump 交易，按 slot 从早到晚排列
+pub async fn fetch_pump_trades(client: &reqwest::Client, keys: &HeliusKeys, wallet: &str, lookback: usize) -> Result<Vec<HistoricalTrade>> {
+    let mut trades = Vec::new();
+    let mut before: Option<String> = None;
+    let mut fetched = 0;
+    while fetched < lookback {
+        let limit = HISTORY_PAGE.min(lookback - fetched);
+        let page = fetch_page(client, keys, wallet, limit, before.as_deref()).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(last.signature.clone());
+        fetched += page.len();
+        let exhausted = page.len() < limit;
+        trades.extend(page.iter().filter_map(|transaction| pump_trade(transaction, wallet)));
+        if exhausted {
+            break;
+        }
+    }
+    trades.reverse();
+    Ok(trades)
+}
+
+/// 请求一页历史，当前 key 被限流时换下一个 key 重试，每个 key 最多试一次
+async fn fetch_page(client: &reqwest::Client, keys: &HeliusKeys, wallet: &str, limit: usize, before: Option<&str>) -> Result<Vec<ParsedTransaction>> {
+    let mut attempts = 0;
+    loop {
+        let (index, url) = keys.api_url(&format!("/v0/addresses/{}/transactions", wallet));
+        let mut request = client
+            .get(url)
+            .query(&[("limit", limit.to_string())])
+            .timeout(REQUEST_TIMEOUT);
+        if let Some(before) = before {
+            request = request.query(&[("before", before)]);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response.json().await?);
+        }
+        attempts += 1;
+        match Limit::from_status(status.as_u16()) {
+            Some(limit) if keys.report(index, limit) && attempts < keys.len() => continue,
+            _ => return Err(anyhow!("Helius transaction history of {} returned {}", wallet, status)),
+        }
+    }
+}
+
+/// 交易涉及代币的 bonding curve 或 PumpSwap 标准池时，代币是 pump 发行的。
+/// 其他程序的代币即使地址以 pump 结尾也不会用到这两个账户
+fn is_pump_mint(transaction: &ParsedTransaction, mint: &str) -> bool {
+    let accounts: Vec<String> = [bonding_curve_address(mint).ok(), canonical_pool(mint).ok()]
+        .into_iter()
+        .flatten()
+        .map(|address| address.to_string())
+        .collect();
+    transaction.account_data.iter().any(|account| accounts.contains(&account.account))
+}
+
+/// 按钱包的代币和 SOL 余额变化还原 pump 交易，不是 pump 交易或失败的交易为 None
+fn pump_trade(transaction: &ParsedTransaction, wallet: &str) -> Option<HistoricalTrade> {
+    if !PUMP_SOURCES.contains(&transaction.source.as_str()) || transaction.transaction_error.is_some() {
+        return None;
+    }
+    let change = transaction
+        .account_data
+        .iter()
+        .flat_map(|account| &account.token_balance_changes)
+        .find(|change| change.user_account == wallet && is_pump_mint(transaction, &change.mint))?;
+    let token_change: i128 = change.raw_token_amount.token_amount.parse().ok()?;
+    let native_change = transaction
+        .account_data
+        .iter()
+        .find(|account| account.account == wallet)
+        .map_or(0, |account| account.native_balance_change);
+    let is_buy = token_change > 0;
+    // 买入时 SOL 减少，卖出时 SOL 增加，方向不符的不是普通的买卖
+    let sol_change = if is_buy { -native_change } else { native_change };
+    if token_change == 0 || sol_change <= 0 {
+        return None;
+    }
+    Some(HistoricalTrade {
+        signature: transaction.signature.clone(),
+        slot: transaction.slot,
+        timestamp: transaction.timestamp,
+        mint: change.mint.clone(),
+        is_buy,
+        sol_amount: sol_change as u64,
+        token_amount: u64::try_from(token_change.unsigned_abs()).ok()?,
+        decimals: change.raw_token_amount.decimals,
+    })
+}
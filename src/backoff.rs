@@ -0,0 +1,32 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// 带随机抖动的指数退避，多个 profile 同时断线时不会在同一时刻重连
+#[derive(Debug)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max: max.max(initial),
+            current: initial,
+        }
+    }
+
+    /// 下一次等待的时间，在当前间隔的一半到全部之间随机选取，之后间隔翻倍直到上限
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current.mul_f64(rand::thread_rng().gen_range(0.5..=1.0));
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+
+    /// 连接恢复正常后从初始间隔重新开始
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
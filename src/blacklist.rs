@@ -0,0 +1,80 @@
+use anyhow::Result;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+/// 从外部黑名单同步的代币，按来源分别保存，某个来源拉取失败时保留它上一次的列表
+#[derive(Debug, Default)]
+pub struct Blacklist {
+    sources: HashMap<String, HashSet<String>>,  // 来源 URL -> 代币
+}
+
+impl Blacklist {
+    /// 替换一个来源的列表，返回之前不在任何来源中的代币
+    pub fn update(&mut self, source: &str, mints: HashSet<String>) -> Vec<String> {
+        let added: Vec<String> = mints
+            .iter()
+            .filter(|mint| self.sources_of(mint).is_empty())
+            .cloned()
+            .collect();
+        self.sources.insert(source.to_string(), mints);
+        added
+    }
+
+    /// 列出包含该代币的来源
+    pub fn sources_of(&self, mint: &str) -> Vec<&str> {
+        self.sources
+            .iter()
+            .filter(|(_, mints)| mints.contains(mint))
+            .map(|(source, _)| source.as_str())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.values().flatten().collect::<HashSet<_>>().len()
+    }
+}
+
+/// 拉取一个黑名单
+pub async fn fetch(client: &reqwest::Client, url: &str) -> Result<HashSet<String>> {
+    let body = client.get(url).send().await?.error_for_status()?.text().await?;
+    Ok(parse(&body))
+}
+
+/// 解析黑名单内容。支持 JSON（任意位置的地址字符串，例如地址数组或带 `mint` 字段的对象数组）
+/// 和纯文本（每行一个地址，`#` 之后为注释，行内逗号或空白之后的内容忽略）
+pub fn parse(body: &str) -> HashSet<String> {
+    let mut mints = HashSet::new();
+    match serde_json::from_str::<Value>(body) {
+        Ok(value) => collect_addresses(&value, &mut mints),
+        Err(_) => {
+            for line in body.lines() {
+                let line = line.split('#').next().unwrap_or_default();
+                if let Some(address) = line.split(|c: char| c == ',' || c.is_whitespace()).find(|part| !part.is_empty()) {
+                    if is_address(address) {
+                        mints.insert(address.to_string());
+                    }
+                }
+            }
+        }
+    }
+    mints
+}
+
+fn collect_addresses(value: &Value, mints: &mut HashSet<String>) {
+    match value {
+        Value::String(text) if is_address(text) => {
+            mints.insert(text.clone());
+        }
+        Value::Array(items) => items.iter().for_each(|item| collect_addresses(item, mints)),
+        Value::Object(fields) => fields.values().for_each(|field| collect_addresses(field, mints)),
+        _ => {}
+    }
+}
+
+fn is_address(text: &str) -> bool {
+    (32..=44).contains(&text.len()) && Pubkey::from_str(text).is_ok()
+}
@@ -0,0 +1,246 @@
+use crate::{
+    alert_service::{render_alert, DEFAULT_ALERT_TEMPLATE},
+    annotations::TradeAnnotations,
+    chaos,
+    escalation::Escalations,
+    models::{Alert, AlertSeverity, TradeSignal},
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use futures_util::SinkExt;
+use log::{error, info, warn};
+use serde::Serialize;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use teloxide::{
+    prelude::*,
+    types::{ChatId, ParseMode},
+};
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use url::Url;
+
+/// 提醒的投递通道。实现这个 trait 并通过 `AlertServiceBuilder::channel` 注册即可增加通道，
+/// 投递链和路由规则按 `name` 引用通道。熔断、超时和提醒历史由 `AlertService` 统一处理
+#[async_trait]
+pub trait AlertChannel: Send + Sync {
+    /// 投递链、路由规则和提醒历史中使用的名称，例如 `telegram`
+    fn name(&self) -> &str;
+
+    /// 没有配置模板（`alert_templates` 中的通道名或 `default`）时使用的提醒模板
+    fn default_template(&self) -> &str {
+        DEFAULT_ALERT_TEMPLATE
+    }
+
+    /// 按模板把提醒格式化为这个通道发送的内容，返回 None 表示这个通道不接收这条提醒
+    fn format(&self, alert: &Alert, template: &str) -> Option<String> {
+        Some(render_alert(template, alert))
+    }
+
+    /// 发送 `format` 的结果，`alert` 提供代币、持仓等结构化字段
+    async fn send(&self, message: &str, alert: &Alert) -> Result<()>;
+
+    /// 检查通道是否可用（例如凭据是否有效），不发送提醒
+    async fn health_check(&self) -> Result<()>;
+}
+
+pub struct TelegramChannel {
+    bot: Bot,
+    chat_id: i64,
+    topic_id: Option<i32>,
+    escalations: Option<Arc<Escalations>>,  // 配置了 `escalation` 时 critical 提醒带提及和确认按钮
+    annotations: Option<Arc<TradeAnnotations>>,  // 开启了 `TRADE_ANNOTATIONS` 时交易提醒带标注按钮
+}
+
+impl TelegramChannel {
+    pub fn new(
+        bot_token: &str,
+        chat_id: i64,
+        topic_id: Option<i32>,
+        escalations: Option<Arc<Escalations>>,
+        annotations: Option<Arc<TradeAnnotations>>,
+    ) -> Self {
+        Self {
+            bot: Bot::new(bot_token),
+            chat_id,
+            topic_id,
+            escalations,
+            annotations,
+        }
+    }
+
+    /// 需要升级的提醒，没有配置 `escalation` 或不是 critical 提醒时为 None
+    fn escalations_for(&self, alert: &Alert) -> Option<&Escalations> {
+        self.escalations
+            .as_deref()
+            .filter(|_| alert.alert_type.severity() == AlertSeverity::Critical)
+    }
+}
+
+#[async_trait]
+impl AlertChannel for TelegramChannel {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    /// critical 提醒的末尾附上配置的提及
+    fn format(&self, alert: &Alert, template: &str) -> Option<String> {
+        let mut message = render_alert(template, alert);
+        if let Some(mentions) = self.escalations_for(alert).and_then(|escalations| escalations.config().telegram_mentions()) {
+            message.push_str("\n\n");
+            message.push_str(&mentions);
+        }
+        Some(message)
+    }
+
+    async fn send(&self, message: &str, alert: &Alert) -> Result<()> {
+        if chaos::should_fail_telegram() {
+            warn!("Chaos: injecting Telegram failure");
+            return Err(anyhow!("Chaos: injected Telegram failure"));
+        }
+        if let Some(escalations) = self.escalations_for(alert) {
+            return escalations.send(message).await;
+        }
+        if let (Some(annotations), Some(trade)) = (&self.annotations, &alert.trade) {
+            return annotations.send(message, trade).await;
+        }
+
+        let chat_id = ChatId(self.chat_id);
+        match self.bot.send_message(chat_id, message)
+            .message_thread_id(self.topic_id.unwrap_or(0))
+            .parse_mode(ParseMode::Html)
+            .await {
+            Ok(sent_message) => {
+                info!("Successfully sent message to Telegram. Message ID: {}", sent_message.id);
+                info!("Chat ID used: {}", chat_id.0);
+                if let Some(topic_id) = self.topic_id {
+                    info!("Topic ID used: {}", topic_id);
+                }
+                Ok(())
+            },
+            Err(e) => {
+                error!("Failed to send telegram message: {}", e);
+                Err(anyhow!("Failed to send telegram message: {}", e))
+            }
+        }
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.bot.get_me().await.map_err(|e| anyhow!("Telegram getMe failed: {}", e))?;
+        Ok(())
+    }
+}
+
+/// 交易信号的 WebSocket 端点，只接收带信号名的提醒（价格提醒和规则提醒）。
+/// 信号从提醒时间起 `ttl` 后过期，过期的信号（例如断线期间积压的提醒）不再发送
+pub struct WebSocketChannel {
+    url: String,
+    ttl: Option<Duration>,
+}
+
+impl WebSocketChannel {
+    pub fn new(url: &str, ttl: Option<Duration>) -> Self {
+        Self {
+            url: url.to_string(),
+            ttl,
+        }
+    }
+}
+
+#[async_trait]
+impl AlertChannel for WebSocketChannel {
+    fn name(&self) -> &str {
+        "websocket"
+    }
+
+    /// 信号是固定格式的 JSON，不使用模板
+    fn format(&self, alert: &Alert, _template: &str) -> Option<String> {
+        let signal = TradeSignal {
+            signal: alert.signal.clone()?,
+            mint: alert.mint.clone()?,
+            timestamp: alert.timestamp,
+            expires_at: self.ttl.map(|ttl| alert.timestamp + ttl.as_secs() as i64),
+            provenance: alert.provenance.clone(),
+        };
+        if signal.expires_at.is_some_and(|expires_at| Utc::now().timestamp() > expires_at) {
+            warn!(
+                "Dropping expired signal {} for {}, emitted {}s ago",
+                signal.signal,
+                signal.mint,
+                Utc::now().timestamp() - signal.timestamp
+            );
+            return None;
+        }
+        serde_json::to_string(&signal).ok()
+    }
+
+    async fn send(&self, message: &str, _alert: &Alert) -> Result<()> {
+        let url = Url::parse(&self.url)?;
+        let (mut ws_stream, _) = connect_async(url).await?;
+        ws_stream.send(Message::Text(message.to_string())).await?;
+        info!("Signal sent to WebSocket: {}", message);
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        let (mut ws_stream, _) = connect_async(Url::parse(&self.url)?).await?;
+        let _ = ws_stream.close(None).await;
+        Ok(())
+    }
+}
+
+/// 无法送达的提醒，写入备用文件
+#[derive(Serialize)]
+struct UndeliveredAlert<'a> {
+    timestamp: i64,
+    kind: &'a str,  // alert、dropped（队列已满被丢弃）或没有送达的通道名
+    message: &'a str,
+}
+
+/// 追加写入 `undelivered.jsonl`，作为投递链的最后一个通道，也记录其他通道没有送达的内容
+pub struct FileChannel {
+    path: PathBuf,
+}
+
+impl FileChannel {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn write(&self, kind: &str, message: &str) -> Result<()> {
+        let entry = UndeliveredAlert {
+            timestamp: Utc::now().timestamp(),
+            kind,
+            message,
+        };
+        append_json_line(&self.path, &entry)
+    }
+}
+
+#[async_trait]
+impl AlertChannel for FileChannel {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn send(&self, message: &str, _alert: &Alert) -> Result<()> {
+        self.write("alert", message)
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        fs::metadata(dir).map_err(|e| anyhow!("{} is not accessible: {}", dir.display(), e))?;
+        Ok(())
+    }
+}
+
+pub(crate) fn append_json_line<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(value)?)?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use log::warn;
+use rand::Rng;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+static CHAOS: OnceLock<Chaos> = OnceLock::new();
+
+/// 混沌测试模式：随机注入 WebSocket 断线、消息延迟和 Telegram 发送失败，
+/// 用于验证断线重连、去重和重试逻辑。可以回放录制的消息代替实时订阅。只应在测试环境中开启
+#[derive(Debug, Clone, Default)]
+pub struct Chaos {
+    pub disconnect_rate: f64,
+    pub max_delay_ms: u64,
+    pub telegram_failure_rate: f64,
+    pub replay: Option<PathBuf>,  // 回放 `WS_RECORD` 录制的消息，不连接节点
+}
+
+impl Chaos {
+    /// 从 `CHAOS_MODE` 读取配置，例如 `disconnect=0.001,delay_ms=500,telegram_failure=0.2,replay=session.ws.jsonl`
+    fn from_env() -> Option<Self> {
+        let spec = env::var("CHAOS_MODE").ok()?;
+        let mut chaos = Chaos::default();
+        for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').unwrap_or((part, ""));
+            match key {
+                "disconnect" => chaos.disconnect_rate = value.parse().unwrap_or(0.0),
+                "delay_ms" => chaos.max_delay_ms = value.parse().unwrap_or(0),
+                "telegram_failure" => chaos.telegram_failure_rate = value.parse().unwrap_or(0.0),
+                "replay" if !value.is_empty() => chaos.replay = Some(PathBuf::from(value)),
+                _ => warn!("Unknown CHAOS_MODE option: {}", key),
+            }
+        }
+        Some(chaos)
+    }
+}
+
+/// 在启动时调用一次，开启时返回当前的混沌配置
+pub fn init() -> Option<&'static Chaos> {
+    let chaos = Chaos::from_env()?;
+    Some(CHAOS.get_or_init(|| chaos))
+}
+
+fn roll(rate: f64) -> bool {
+    rate > 0.0 && rand::thread_rng().gen_bool(rate.min(1.0))
+}
+
+/// 是否模拟一次 WebSocket 断线
+pub fn should_disconnect() -> bool {
+    CHAOS.get().is_some_and(|chaos| roll(chaos.disconnect_rate))
+}
+
+/// 要回放的录制文件，没有开启回放时为 None
+pub fn replay_file() -> Option<&'static Path> {
+    CHAOS.get().and_then(|chaos| chaos.replay.as_deref())
+}
+
+/// 是否模拟一次 Telegram 发送失败
+pub fn should_fail_telegram() -> bool {
+    CHAOS.get().is_some_and(|chaos| roll(chaos.telegram_failure_rate))
+}
+
+/// 为消息处理注入随机延迟
+pub async fn delay() {
+    if let Some(chaos) = CHAOS.get().filter(|chaos| chaos.max_delay_ms > 0) {
+        let delay_ms = rand::thread_rng().gen_range(0..=chaos.max_delay_ms);
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+    }
+}
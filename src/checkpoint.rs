@@ -0,0 +1,228 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_request::RpcRequest,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+pub(crate) const SIGNATURES_PAGE: usize = 1000;  // getSignaturesForAddress 单次返回的上限
+pub(crate) const MAX_CATCH_UP: usize = 1000;  // 每个钱包最多补处理的交易数
+
+/// 日志订阅处理到的位置，随持仓状态一起保存：`slot` 及之前的消息都已处理完，
+/// `applied` 是之后已经计入持仓的目标钱包交易（签名 -> slot），补处理时跳过。
+/// `time` 是保存的时间；没有记录位置的旧状态迁移后 `slot` 为 0，只能按时间判断哪些交易已经计入
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub slot: u64,
+    #[serde(default)]
+    pub applied: HashMap<String, u64>,
+    #[serde(default)]
+    pub time: i64,
+}
+
+/// 跟踪分配给 worker 还没处理完的消息所在的 slot。worker 并发处理，
+/// 所以处理完的位置是最早一条未处理完的消息之前的 slot
+#[derive(Debug, Default)]
+pub struct Progress {
+    pending: BTreeMap<u64, usize>,  // slot -> 未处理完的消息数
+    highest: u64,  // 已分配的最大 slot
+    applied: HashMap<String, u64>,  // 处理完的位置之后计入持仓的目标钱包交易
+    restored_at: Option<i64>,  // 恢复的状态保存的时间，没有处理过消息时保存位置沿用它
+}
+
+impl Progress {
+    /// 从保存的位置继续
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.highest = self.highest.max(checkpoint.slot);
+        self.applied.extend(checkpoint.applied);
+        self.restored_at = (checkpoint.time > 0).then_some(checkpoint.time);
+    }
+
+    /// 恢复的状态保存的时间，之前的交易都已计入持仓
+    pub fn restored_at(&self) -> Option<i64> {
+        self.restored_at
+    }
+
+    /// 消息分配给 worker。补处理期间也用它挡住处理完的位置，直到补处理结束
+    pub fn dispatch(&mut self, slot: u64) {
+        *self.pending.entry(slot).or_default() += 1;
+        self.highest = self.highest.max(slot);
+    }
+
+    pub fn complete(&mut self, slot: u64) {
+        if let Some(count) = self.pending.get_mut(&slot) {
+            *count -= 1;
+            if *count == 0 {
+                self.pending.remove(&slot);
+            }
+        }
+    }
+
+    /// 交易已计入持仓，在持仓的写锁内调用，保存的位置和持仓总是一致
+    pub fn apply(&mut self, signature: &str, slot: u64) {
+        self.applied.insert(signature.to_string(), slot);
+    }
+
+    pub fn is_applied(&self, signature: &str) -> bool {
+        self.applied.contains_key(signature)
+    }
+
+    /// 之前的消息都已处理完的 slot，还没有处理过任何消息时为 None
+    pub fn watermark(&self) -> Option<u64> {
+        let slot = match self.pending.keys().next() {
+            Some(first) => first.saturating_sub(1),
+            None => self.highest,
+        };
+        (slot > 0).then_some(slot)
+    }
+
+    /// 当前的位置，丢弃已经在位置之前的交易签名。还没有处理过消息时沿用恢复的保存时间
+    pub fn checkpoint(&mut self) -> Option<Checkpoint> {
+        let Some(slot) = self.watermark() else {
+            return self.restored_at.map(|time| Checkpoint {
+                slot: 0,
+                applied: self.applied.clone(),
+                time,
+            });
+        };
+        self.applied.retain(|_, applied_slot| *applied_slot > slot);
+        Some(Checkpoint {
+            slot,
+            applied: self.applied.clone(),
+            time: Utc::now().timestamp(),
+        })
+    }
+}
+
+/// 补处理查询交易历史使用的确认级别。交易历史接口不支持 processed，这时使用 confirmed
+fn history_commitment(commitment: CommitmentConfig) -> CommitmentConfig {
+    if commitment.is_finalized() {
+        CommitmentConfig::finalized()
+    } else {
+        CommitmentConfig::confirmed()
+    }
+}
+
+/// 钱包在 (`after`, `before`) 两个 slot 之间成功的交易，按 slot 从早到晚排列。
+/// 每个钱包最多 `MAX_CATCH_UP` 笔，返回的第二项表示是否有更早的交易因此被略过
+pub async fn missed_signatures(
+    client: &RpcClient,
+    wallet: &str,
+    after: u64,
+    before: u64,
+    commitment: CommitmentConfig,
+) -> Result<(Vec<(u64, String)>, bool)> {
+    let address = Pubkey::from_str(wallet).map_err(|e| anyhow!("Invalid wallet address {}: {}", wallet, e))?;
+    let mut missed = Vec::new();
+    let mut cursor: Option<Signature> = None;
+    loop {
+        let page = client
+            .get_signatures_for_address_with_config(
+                &address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before: cursor,
+                    until: None,
+                    limit: Some(SIGNATURES_PAGE),
+                    commitment: Some(history_commitment(commitment)),
+                },
+            )
+            .await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        cursor = Some(Signature::from_str(&last.signature)?);
+        let reached = last.slot <= after;
+        for status in page {
+            if status.slot > after && status.slot < before && status.err.is_none() {
+                if missed.len() >= MAX_CATCH_UP {
+                    missed.reverse();
+                    return Ok((missed, true));
+                }
+                missed.push((status.slot, status.signature));
+            }
+        }
+        if reached {
+            break;
+        }
+    }
+    missed.reverse();
+    Ok((missed, false))
+}
+
+/// 交易的日志，交易不存在时为 None
+pub async fn transaction_logs(client: &RpcClient, signature: &str, commitment: CommitmentConfig) -> Result<Option<Vec<String>>> {
+    let commitment = history_commitment(commitment).commitment.to_string();
+    let transaction: Value = client
+        .send(
+            RpcRequest::GetTransaction,
+            json!([signature, { "encoding": "json", "commitment": commitment, "maxSupportedTransactionVersion": 0 }]),
+        )
+        .await?;
+    Ok(transaction["meta"]["logMessages"].as_array().map(|logs| {
+        logs.iter().filter_map(|log| log.as_str().map(str::to_string)).collect()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watermark_stops_before_oldest_pending_slot() {
+        let mut progress = Progress::default();
+        assert_eq!(progress.watermark(), None);
+
+        progress.dispatch(10);
+        progress.dispatch(10);
+        progress.dispatch(12);
+        assert_eq!(progress.watermark(), Some(9));
+
+        // 后面的 slot 先处理完不推进位置
+        progress.complete(12);
+        assert_eq!(progress.watermark(), Some(9));
+        progress.complete(10);
+        assert_eq!(progress.watermark(), Some(9));
+        progress.complete(10);
+        assert_eq!(progress.watermark(), Some(12));
+
+        // 多余的完成不影响位置
+        progress.complete(10);
+        assert_eq!(progress.watermark(), Some(12));
+    }
+
+    #[test]
+    fn watermark_continues_from_restored_slot() {
+        let mut progress = Progress::default();
+        progress.restore(Checkpoint { slot: 100, applied: HashMap::new(), time: 0 });
+        assert_eq!(progress.watermark(), Some(100));
+
+        progress.dispatch(1);
+        assert_eq!(progress.watermark(), None);
+        progress.complete(1);
+        assert_eq!(progress.watermark(), Some(100));
+    }
+
+    #[test]
+    fn checkpoint_drops_signatures_at_or_before_watermark() {
+        let mut progress = Progress::default();
+        progress.dispatch(5);
+        progress.dispatch(8);
+        progress.apply("early", 5);
+        progress.apply("late", 8);
+        progress.complete(5);
+
+        let checkpoint = progress.checkpoint().unwrap();
+        assert_eq!(checkpoint.slot, 7);
+        assert_eq!(checkpoint.applied.keys().collect::<Vec<_>>(), ["late"]);
+        assert!(!progress.is_applied("early"));
+        assert!(progress.is_applied("late"));
+    }
+}
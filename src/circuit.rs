@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+/// 一次发送请求是否允许执行
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permit {
+    Allowed,
+    Probe,     // 冷却结束后的探测请求
+    Rejected,  // 熔断中，直接走备用通道
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Closed,
+    Open { until: Instant },
+    HalfOpen,
+}
+
+/// 提醒通道的熔断器：连续失败达到阈值后断开，冷却后放行一次探测请求
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    failures: u32,
+    state: State,
+    diverted: u64,  // 断开期间转到备用通道的提醒数
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            failures: 0,
+            state: State::Closed,
+            diverted: 0,
+        }
+    }
+
+    pub fn acquire(&mut self) -> Permit {
+        match self.state {
+            State::Closed => Permit::Allowed,
+            State::Open { until } if Instant::now() >= until => {
+                self.state = State::HalfOpen;
+                Permit::Probe
+            }
+            // 探测请求还没有结果时，其他请求继续走备用通道
+            State::Open { .. } | State::HalfOpen => {
+                self.diverted += 1;
+                Permit::Rejected
+            }
+        }
+    }
+
+    /// 发送成功。如果熔断器因此恢复，返回断开期间转走的提醒数
+    pub fn on_success(&mut self) -> Option<u64> {
+        self.failures = 0;
+        match self.state {
+            State::Closed => None,
+            _ => {
+                self.state = State::Closed;
+                Some(std::mem::take(&mut self.diverted))
+            }
+        }
+    }
+
+    /// 发送失败，返回 true 表示熔断器刚刚断开
+    pub fn on_failure(&mut self) -> bool {
+        self.failures += 1;
+        match self.state {
+            State::HalfOpen => {
+                self.diverted += 1;
+                self.state = State::Open { until: Instant::now() + self.cooldown };
+                false
+            }
+            State::Closed if self.failures >= self.threshold => {
+                self.diverted += 1;
+                self.state = State::Open { until: Instant::now() + self.cooldown };
+                true
+            }
+            _ => false,
+        }
+    }
+}
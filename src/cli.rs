@@ -0,0 +1,322 @@
+use crate::{
+    alert_service::{render_alert, DEFAULT_ALERT_TEMPLATE},
+    config::{self, DEFAULT_PROFILE},
+    display::{self, ColorMode},
+    journal,
+    metadata::TokenMetadata,
+    mints::{to_tokens, DEFAULT_TOKEN_DECIMALS},
+    models::{Alert, AlertType, HoldingSnapshot, TradeEvent},
+    outcomes,
+    recording,
+    rules::RuleContext,
+    store,
+    tune,
+    wallet_report,
+};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::{env, path::Path};
+
+/// 执行命令行子命令
+pub async fn run(command: &str, args: &[String], color: ColorMode) -> Result<()> {
+    match command {
+        "reconcile" => reconcile(args).await,
+        "playback" => playback(args, color).await,
+        "preview-template" => preview_template(args),
+        "report" => report(args).await,
+        "tune" => tune(args),
+        _ => Err(anyhow!(
+            "Unknown command '{}'. Available commands: reconcile, playback, preview-template, report, tune",
+            command
+        )),
+    }
+}
+
+/// 取出全局选项 `--name <value>` 或 `--name=<value>`，其余参数保持原顺序
+pub fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>> {
+    let prefix = format!("{}=", name);
+    let Some(index) = args.iter().position(|arg| arg == name || arg.starts_with(&prefix)) else {
+        return Ok(None);
+    };
+    let arg = args.remove(index);
+    if let Some(value) = arg.strip_prefix(&prefix) {
+        return Ok(Some(value.to_string()));
+    }
+    if index >= args.len() {
+        return Err(anyhow!("{} requires a value", name));
+    }
+    Ok(Some(args.remove(index)))
+}
+
+/// `monitor reconcile [--fix] [--profile <name>]`
+async fn reconcile(args: &[String]) -> Result<()> {
+    let mut fix = false;
+    let mut profile = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fix" => fix = true,
+            "--profile" => {
+                profile = Some(args.next().ok_or_else(|| anyhow!("--profile requires a value"))?.clone())
+            }
+            _ => return Err(anyhow!("Unknown argument '{}'", arg)),
+        }
+    }
+
+    let response = api_post("/reconcile", profile.as_deref(), json!({ "fix": fix })).await?;
+    let discrepancies = response["discrepancies"].as_array().cloned().unwrap_or_default();
+
+    if discrepancies.is_empty() {
+        println!("Holdings match on-chain balances");
+        return Ok(());
+    }
+
+    println!("{:<46} {:>18} {:>18} {:>18}", "Mint", "Tracked", "On-chain", "Difference");
+    for discrepancy in &discrepancies {
+        let decimals = discrepancy["decimals"].as_u64().map_or(DEFAULT_TOKEN_DECIMALS, |decimals| decimals as u8);
+        let on_chain = to_tokens(discrepancy["on_chain"].as_u64().unwrap_or(0), decimals);
+        let tracked = discrepancy["tracked"].as_u64().map(|amount| to_tokens(amount, decimals));
+        println!(
+            "{:<46} {:>18} {:>18.1} {:>18.1}",
+            discrepancy["mint"].as_str().unwrap_or("-"),
+            tracked.map_or_else(|| "untracked".to_string(), |amount| format!("{:.1}", amount)),
+            on_chain,
+            on_chain - tracked.unwrap_or(0.0)
+        );
+    }
+    if fix {
+        println!("Tracked amounts corrected (untracked tokens are left unchanged)");
+    }
+    Ok(())
+}
+
+/// `monitor playback <file> [--speed <n>]`，回放 `DISPLAY_RECORD` 录制的持仓表
+async fn playback(args: &[String], color: ColorMode) -> Result<()> {
+    let mut path = None;
+    let mut speed = 1.0;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--speed" => {
+                let value = args.next().ok_or_else(|| anyhow!("--speed requires a value"))?;
+                speed = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|speed| *speed > 0.0)
+                    .ok_or_else(|| anyhow!("Invalid speed '{}'", value))?;
+            }
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.clone()),
+            _ => return Err(anyhow!("Unknown argument '{}'", arg)),
+        }
+    }
+    let path = path.ok_or_else(|| anyhow!("Usage: monitor playback <file> [--speed <n>]"))?;
+
+    display::init(color)?;
+    recording::playback(Path::new(&path), speed).await
+}
+
+/// `monitor preview-template <type|all> [--profile <name>]`，用示例交易渲染规则的提醒模板
+fn preview_template(args: &[String]) -> Result<()> {
+    let mut alert_type: Option<Option<AlertType>> = None;  // Some(None) 表示 all
+    let mut profile = DEFAULT_PROFILE.to_string();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--profile" => {
+                profile = args.next().ok_or_else(|| anyhow!("--profile requires a value"))?.clone()
+            }
+            "all" if alert_type.is_none() => alert_type = Some(None),
+            _ if alert_type.is_none() && !arg.starts_with("--") => {
+                let parsed = serde_json::from_value(json!(arg)).map_err(|_| anyhow!("Unknown alert type '{}'", arg))?;
+                alert_type = Some(Some(parsed));
+            }
+            _ => return Err(anyhow!("Unknown argument '{}'", arg)),
+        }
+    }
+    let alert_type = alert_type.ok_or_else(|| anyhow!("Usage: monitor preview-template <type|all> [--profile <name>]"))?;
+
+    let rules: Vec<_> = config::load_rules(&profile)?
+        .into_iter()
+        .filter(|rule| alert_type.as_ref().is_none_or(|alert_type| rule.action.alert_type == *alert_type))
+        .collect();
+    if rules.is_empty() {
+        println!("No rules with a matching alert type in profile '{}'", profile);
+        return Ok(());
+    }
+
+    // 示例数据：目标钱包以 1.5 SOL 买入，持仓上涨 42%
+    let trade = TradeEvent {
+        mint: "7GCihgDB8fe6KNjn2MYtkzZcRjQy3t9GHdC8uHYmW2hr".to_string(),
+        user: "5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1".to_string(),
+        is_buy: true,
+        sol_amount: 1_500_000_000,
+        token_amount: 42_000_000_000,
+        slot: 0,
+        decimals: DEFAULT_TOKEN_DECIMALS,
+        layout: "sample",
+        reserves: None,
+    };
+    let token = TokenMetadata {
+        name: "Pepe Two".to_string(),
+        symbol: "PEPE2".to_string(),
+        image: None,
+    };
+    let holding = HoldingSnapshot {
+        mint: trade.mint.clone(),
+        amount: 42_000.0,
+        avg_price: 0.000_025,
+        current_price: 0.000_035_5,
+        total_cost: 1.05,
+        price_change: 42,
+    };
+    let ctx = RuleContext {
+        trade: &trade,
+        is_target: true,
+        price: holding.current_price,
+        holding: Some(&holding),
+        token_age_secs: Some(180),
+        token: Some(&token),
+        wallet_label: Some("alpha"),
+    };
+
+    for rule in rules {
+        println!("=== {} ({:?}) ===", rule.name, rule.action.alert_type);
+        let alert = Alert {
+            message: rule.render(&ctx),
+            alert_type: rule.action.alert_type.clone(),
+            timestamp: Utc::now().timestamp(),
+            mint: Some(trade.mint.clone()),
+            holding: Some(holding.clone()),
+            signal: None,
+            provenance: None,
+            token: None,
+            trade: None,
+        };
+        println!("{}\n", render_alert(DEFAULT_ALERT_TEMPLATE, &alert));
+    }
+    Ok(())
+}
+
+/// `monitor report compare <wallet_a> <wallet_b>`、`monitor report timing [wallet...]`、
+/// `monitor report stats [wallet...]`、`monitor report review [wallet...]` 或 `monitor report outcomes [days]`
+/// （均可加 `--profile <name>`），根据交易日志生成钱包报告和交易标注的复盘，或根据提醒结果统计命中率
+async fn report(args: &[String]) -> Result<()> {
+    let mut profile = DEFAULT_PROFILE.to_string();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--profile" => {
+                profile = args.next().ok_or_else(|| anyhow!("--profile requires a value"))?.clone()
+            }
+            _ if !arg.starts_with("--") => positional.push(arg.as_str()),
+            _ => return Err(anyhow!("Unknown argument '{}'", arg)),
+        }
+    }
+
+    let data_dir = config::data_dir(&profile);
+    if let ["outcomes", days @ ..] = positional.as_slice() {
+        let days = match days {
+            [] => 7,
+            [days] => days.parse::<u64>().ok().filter(|days| *days > 0).ok_or_else(|| anyhow!("Invalid number of days '{}'", days))?,
+            _ => return Err(anyhow!("Usage: monitor report outcomes [days] [--profile <name>]")),
+        };
+        let since = Utc::now().timestamp() - (days * 24 * 60 * 60) as i64;
+        print!("{}", outcomes::summarize(&outcomes::load(&data_dir)?, since, days));
+        return Ok(());
+    }
+    let entries = store::open(&profile, config::instance_id(&profile).as_deref(), &config::state_store(&profile)?, &data_dir, false).await?.load_trades().await?;
+    match positional.as_slice() {
+        ["compare", wallet_a, wallet_b] => {
+            print!("{}", wallet_report::compare(&entries, wallet_a, wallet_b).to_text());
+            Ok(())
+        }
+        [kind @ ("timing" | "stats"), wallets @ ..] => {
+            let wallets = if wallets.is_empty() {
+                wallet_report::wallets(&entries)
+            } else {
+                wallets.iter().map(|wallet| wallet.to_string()).collect()
+            };
+            let clusters = config::load_clusters(&profile)?;
+            let sandwiches = journal::load_sandwiches(&data_dir)?;
+            let reports: Vec<String> = wallets
+                .iter()
+                .map(|wallet| match *kind {
+                    "timing" => wallet_report::entry_timing(&entries, wallet).to_text(),
+                    _ => wallet_report::wallet_stats(&entries, sandwiches.as_deref(), &clusters, wallet),
+                })
+                .collect();
+            print!("{}", reports.join("\n"));
+            Ok(())
+        }
+        ["review", wallets @ ..] => {
+            let wallets: Vec<String> = wallets.iter().map(|wallet| wallet.to_string()).collect();
+            let annotations = journal::load_annotations(&data_dir)?;
+            print!("{}", wallet_report::annotation_review(&entries, &annotations, &wallets));
+            Ok(())
+        }
+        _ => Err(anyhow!(
+            "Usage: monitor report compare <wallet_a> <wallet_b> | timing [wallet...] | stats [wallet...] | review [wallet...] | outcomes [days] [--profile <name>]"
+        )),
+    }
+}
+
+/// `monitor tune --replay <file> --thresholds <list> [--profile <name>]`，用 `DISPLAY_RECORD` 录制的持仓表
+/// 模拟每个提醒阈值会发出多少提醒以及提醒之后的走势
+fn tune(args: &[String]) -> Result<()> {
+    let mut path = None;
+    let mut thresholds = None;
+    let mut profile = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--replay" => path = Some(args.next().ok_or_else(|| anyhow!("--replay requires a file"))?.clone()),
+            "--thresholds" => {
+                let value = args.next().ok_or_else(|| anyhow!("--thresholds requires a value"))?;
+                thresholds = Some(config::parse_thresholds(value)?);
+            }
+            "--profile" => {
+                profile = Some(args.next().ok_or_else(|| anyhow!("--profile requires a value"))?.clone())
+            }
+            _ => return Err(anyhow!("Unknown argument '{}'", arg)),
+        }
+    }
+    let (Some(path), Some(thresholds)) = (path, thresholds) else {
+        return Err(anyhow!("Usage: monitor tune --replay <file> --thresholds <list> [--profile <name>]"));
+    };
+
+    let frames = recording::frames(Path::new(&path))?
+        .map(|frame| frame.map(|(_, frame)| frame))
+        .collect::<Result<Vec<_>>>()?;
+    print!("{}", tune::simulate(frames, profile.as_deref(), &thresholds).to_text());
+    Ok(())
+}
+
+/// 向本机运行的控制 API 发送请求
+async fn api_post(path: &str, profile: Option<&str>, body: Value) -> Result<Value> {
+    let addr = env::var("CONTROL_API_ADDR")
+        .map_err(|_| anyhow!("CONTROL_API_ADDR must be set to reach the running monitor"))?;
+    let token = env::var("CONTROL_API_TOKEN")
+        .map_err(|_| anyhow!("CONTROL_API_TOKEN must be set to reach the running monitor"))?;
+
+    let mut request = reqwest::Client::new()
+        .post(format!("http://{}{}", addr, path))
+        .bearer_auth(token)
+        .json(&body);
+    if let Some(profile) = profile {
+        request = request.query(&[("profile", profile)]);
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    let value: Value = response.json().await?;
+    if !status.is_success() {
+        return Err(anyhow!(
+            "Control API returned {}: {}",
+            status,
+            value["error"].as_str().unwrap_or("unknown error")
+        ));
+    }
+    Ok(value)
+}
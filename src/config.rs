@@ -0,0 +1,619 @@
+use crate::{
+    activity::BurstRule,
+    annotations::DEFAULT_TAGS,
+    confirm::ConfirmRule,
+    email::EmailConfig,
+    counterparties::CounterpartyRule,
+    enrichment::EnrichmentConfig,
+    escalation::EscalationConfig,
+    federation::InboundSource,
+    error::MonitorError,
+    heartbeat::HeartbeatConfig,
+    helius::HeliusKeys,
+    holders::HolderRule,
+    models::{AlertSeverity, AlertType},
+    impersonation::DEFAULT_KNOWN_TOKENS_URL,
+    oracle::{BaseCurrency, OracleKind},
+    retention::RetentionConfig,
+    rules::AlertRule,
+    scheduler::{Job, ScheduleEntry, ScheduledJob, Trigger},
+    store::StoreConfig,
+    tags::TagRule,
+};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::{collections::{BTreeMap, HashMap}, env, fs, path::PathBuf, sync::Arc, thread, time::Duration};
+
+pub const DEFAULT_PROFILE: &str = "default";
+const REPLICA_INSTANCE: &str = "replica";
+
+/// 单个 profile 的配置。每个 profile 拥有独立的钱包、阈值、Telegram 聊天和数据目录
+#[derive(Debug, Clone)]
+pub struct ProfileConfig {
+    pub name: String,
+    pub wallets: Vec<String>,
+    pub price_change_thresholds: Vec<f64>,  // 提醒档位（百分比），从低到高，每档对每个仓位只提醒一次
+    pub telegram_bot_token: String,
+    pub telegram_chat_id: i64,
+    pub telegram_topic_id: Option<i32>,
+    pub ws_alert_url: Option<String>,
+    pub discord_webhook_url: Option<String>,  // 提醒同时发到这个 Discord webhook
+    pub email: Option<EmailConfig>,  // 配置了 SMTP 时可以在投递链中使用 `email`
+    pub signal_ttl: Option<Duration>,  // 交易信号的有效期，None 表示不过期
+    pub trade_confirm: Option<ConfirmRule>,  // 带信号的提醒先在 Telegram 确认，None 表示直接发送信号
+    pub telegram_commands: bool,  // 接收聊天中的 /holdings、/pnl 等命令
+    pub telegram_admins: Vec<u64>,  // 可以按 Buy/Skip 和修改设置的 Telegram 用户 ID
+    pub trade_annotations: Vec<String>,  // 交易提醒下方的标注按钮，空表示关闭
+    pub wallet_labels: HashMap<String, String>,  // 钱包地址 -> 名称，补充内置的地址簿，用于提醒和交易信号
+    pub inbound_signals: Vec<InboundSource>,  // 订阅其他监控的交易信号
+    pub data_dir: PathBuf,
+    pub state_store: StoreConfig,  // 持仓状态、交易日志和提醒历史的存储
+    pub instance_id: Option<String>,  // 多个实例共享数据库中的状态时本实例的名称，None 表示只有一个实例
+    pub replica: bool,  // 只读副本：读取共享存储中的持仓回答查询，不订阅日志也不发送提醒
+    pub rules_script: Option<PathBuf>,
+    pub rules: Vec<AlertRule>,
+    pub tag_rules: Vec<TagRule>,  // 按代币名称和符号自动打标签
+    pub recent_trades_capacity: usize,
+    pub decode_workers: usize,
+    pub dedup_capacity: usize,  // 记住最近多少个处理过的事件，用于跳过重复的通知
+    pub rpc_urls: Vec<String>,  // HTTP RPC 端点，多个时使用延迟最低的，未设置时使用 Helius key
+    pub ws_urls: Vec<String>,   // 订阅 pump 程序日志的 WebSocket 端点，同上
+    pub ws_record: Option<PathBuf>,  // 把收到的 WebSocket 消息录制到这个文件，供混沌测试回放
+    pub helius_keys: Option<Arc<HeliusKeys>>,
+    pub endpoint_probe: Option<Duration>,  // 重新探测端点延迟的间隔，None 表示只在启动时探测
+    pub ws_reconnect_max: Duration,  // WebSocket 重连的最长等待
+    pub ws_reconnect_attempts: Option<u32>,  // 连续重连失败多少次后停止，None 表示一直重连
+    pub commitment: CommitmentConfig,  // 订阅使用的确认级别
+    pub schedules: Vec<ScheduledJob>,
+    pub reconcile_tolerance: f64,
+    pub startup_sync: bool,  // 启动时从链上余额同步没有跟踪的持仓
+    pub backfill_txs: usize,  // 启动时从 Helius 交易历史回放每个钱包最近多少笔交易，0 表示不回放
+    pub track_token_accounts: bool,
+    pub flat_alert_window: Option<Duration>,
+    pub inactivity_alert: Option<Duration>,
+    pub burst_alert: Option<BurstRule>,
+    pub size_anomaly_multiplier: Option<f64>,
+    pub max_hold: Option<Duration>,
+    pub holder_alert: Option<HolderRule>,
+    pub counterparty_alert: Option<CounterpartyRule>,  // 持仓代币的交易对手统计和新买家减少提醒
+    pub heartbeat: Option<HeartbeatConfig>,  // 定期请求外部监控服务，停止处理消息时由对方报警
+    pub flow_window: Option<Duration>,  // 价格提醒中净买卖流量的统计窗口
+    pub circuit_failures: u32,
+    pub alert_queue_capacity: usize,
+    pub circuit_cooldown: Duration,
+    pub alert_send_timeout: Duration,  // 单个通道发送一条提醒的超时，超时视为失败
+    pub alert_chains: HashMap<AlertSeverity, Vec<String>>,  // 按严重程度的投递链，元素为通道名
+    pub alert_routes: HashMap<AlertType, Vec<String>>,  // 指定提醒类型的投递链，优先于按严重程度的投递链
+    pub alert_templates: HashMap<String, String>,  // 通道名或 `default` -> 提醒模板
+    pub escalation: Option<EscalationConfig>,  // critical 提醒的提及和重发，None 表示关闭
+    pub display_oracle: OracleKind,  // 终端持仓表使用的价格来源
+    pub alert_oracle: OracleKind,    // 价格提醒使用的价格来源
+    pub oracle_cache_ttl: Duration,
+    pub exit_quotes: bool,  // 按当前流动性估算卖出价值和价格冲击
+    pub stress_drawdowns: Vec<f64>,  // 压力测试模拟的回撤百分比
+    pub base_currency: BaseCurrency,  // 成本和盈亏的记账货币
+    pub mev_detection: bool,  // 检测目标钱包交易是否被三明治攻击
+    pub blacklist_urls: Vec<String>,  // 外部代币黑名单
+    pub blacklist_refresh: Duration,
+    pub known_tokens_url: Option<String>,  // 知名代币列表，用于识别冒名代币；None 表示关闭
+    pub enrichment: Option<EnrichmentConfig>,  // 提醒补充信息流水线，None 表示不补充
+    pub retention: RetentionConfig,  // 数据目录中各类记录的保留期
+}
+
+/// JSON 配置文件（`MONITOR_CONFIG`）中的结构化配置
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    rules: Vec<AlertRule>,
+    alert_chains: HashMap<AlertSeverity, Vec<String>>,
+    alert_routes: HashMap<AlertType, Vec<String>>,
+    alert_templates: HashMap<String, String>,
+    escalation: Option<EscalationConfig>,
+    schedules: Vec<ScheduleEntry>,
+    tags: Vec<TagRule>,
+    clusters: BTreeMap<String, Vec<String>>,  // 关联钱包组，名称 -> 钱包地址
+    wallet_labels: HashMap<String, String>,
+    inbound_signals: Vec<InboundSource>,
+    enrichment: Option<EnrichmentConfig>,
+    retention: RetentionConfig,
+}
+
+impl FileConfig {
+    fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read config file {}: {}", path, e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Invalid config file {}: {}", path, e))
+    }
+}
+
+impl ProfileConfig {
+    /// 读取 profile 配置，优先使用 `<PROFILE>_<KEY>`，没有时回退到 `<KEY>`
+    pub fn from_env(name: &str) -> Result<Self, MonitorError> {
+        let prefix = if name == DEFAULT_PROFILE {
+            None
+        } else {
+            Some(name.to_uppercase())
+        };
+        Self::read(name, |key| profile_var(prefix.as_deref(), key)).map_err(MonitorError::config)
+    }
+
+    /// 所有可选项取默认值的配置，不读取环境变量，没有钱包和 Telegram 凭据。供 builder 使用
+    pub fn with_defaults(name: &str) -> Self {
+        Self::read(name, |key| match key {
+            "MONITOR_WALLET" | "TELEGRAM_BOT_TOKEN" => Some(String::new()),
+            "TELEGRAM_CHAT_ID" => Some("0".to_string()),
+            _ => None,
+        })
+        .expect("default profile config is valid")
+    }
+
+    /// 按 `var` 读取各项配置，未设置的项使用默认值
+    fn read(name: &str, var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+
+        let wallets: Vec<String> = var("MONITOR_WALLET")
+            .ok_or_else(|| anyhow!("MONITOR_WALLET must be set for profile '{}'", name))?
+            .split(',')
+            .map(|wallet| wallet.trim().to_string())
+            .filter(|wallet| !wallet.is_empty())
+            .collect();
+
+        let price_change_thresholds = match var("PRICE_CHANGE_THRESHOLD") {
+            Some(value) => parse_thresholds(&value).map_err(|e| anyhow!("Invalid PRICE_CHANGE_THRESHOLD: {}", e))?,
+            None => vec![5.0],
+        };
+
+        let telegram_bot_token = var("TELEGRAM_BOT_TOKEN")
+            .ok_or_else(|| anyhow!("TELEGRAM_BOT_TOKEN must be set for profile '{}'", name))?;
+        let telegram_chat_id = var("TELEGRAM_CHAT_ID")
+            .ok_or_else(|| anyhow!("TELEGRAM_CHAT_ID must be set for profile '{}'", name))?
+            .parse::<i64>()
+            .map_err(|_| anyhow!("TELEGRAM_CHAT_ID must be a valid integer for profile '{}'", name))?;
+        let telegram_topic_id = var("TELEGRAM_TOPIC_ID").and_then(|id| id.parse::<i32>().ok());
+
+        let trade_confirm = matches!(var("TRADE_CONFIRM").as_deref(), Some("1") | Some("true")).then(|| ConfirmRule {
+            timeout: Duration::from_secs(
+                var("TRADE_CONFIRM_TIMEOUT_SECS")
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .filter(|secs| *secs > 0)
+                    .unwrap_or(60),
+            ),
+            execute: matches!(var("TRADE_EXECUTE").as_deref(), Some("1") | Some("true")),
+        });
+        if trade_confirm.is_some_and(|rule| rule.execute) && var("WS_ALERT_URL").is_none() {
+            return Err(anyhow!("TRADE_EXECUTE needs WS_ALERT_URL as the executor for profile '{}'", name));
+        }
+        let telegram_admins = list(var("TELEGRAM_ADMINS"))
+            .iter()
+            .map(|id| id.parse::<u64>().map_err(|_| anyhow!("TELEGRAM_ADMINS must be numeric user IDs, got '{}'", id)))
+            .collect::<Result<Vec<u64>>>()?;
+        let trade_annotations = match var("TRADE_ANNOTATIONS").as_deref() {
+            Some("1") | Some("true") => match list(var("TRADE_ANNOTATION_TAGS")) {
+                tags if tags.is_empty() => DEFAULT_TAGS.iter().map(|tag| tag.to_string()).collect(),
+                tags => tags,
+            },
+            _ => Vec::new(),
+        };
+
+        let email = match var("SMTP_URL") {
+            Some(smtp_url) => Some(EmailConfig {
+                smtp_url,
+                from: var("EMAIL_FROM").ok_or_else(|| anyhow!("EMAIL_FROM must be set for profile '{}' when SMTP_URL is set", name))?,
+                to: match list(var("EMAIL_TO")) {
+                    to if to.is_empty() => return Err(anyhow!("EMAIL_TO must be set for profile '{}' when SMTP_URL is set", name)),
+                    to => to,
+                },
+            }),
+            None => None,
+        };
+
+        let file_config = match var("MONITOR_CONFIG") {
+            Some(path) => FileConfig::load(&path)?,
+            None => FileConfig::default(),
+        };
+
+        let mut schedules = file_config
+            .schedules
+            .iter()
+            .map(ScheduleEntry::parse)
+            .collect::<Result<Vec<_>>>()?;
+        // RECONCILE_INTERVAL_MINS 是固定间隔对账的简写
+        if let Some(mins) = var("RECONCILE_INTERVAL_MINS")
+            .and_then(|value| value.parse::<u64>().ok())
+            .filter(|mins| *mins > 0)
+        {
+            schedules.push(ScheduledJob {
+                job: Job::Reconcile,
+                trigger: Trigger::Every(Duration::from_secs(mins * 60)),
+            });
+        }
+        // 配置了保留期但没有安排清理时，每天清理一次
+        if !file_config.retention.is_empty() && !schedules.iter().any(|scheduled| scheduled.job == Job::Prune) {
+            schedules.push(ScheduledJob {
+                job: Job::Prune,
+                trigger: Trigger::Every(Duration::from_secs(24 * 60 * 60)),
+            });
+        }
+        // 钱包活跃度和新买家的检查没有安排时使用默认的间隔
+        for (job, secs) in [(Job::Activity, 60), (Job::Counterparties, 30)] {
+            if !schedules.iter().any(|scheduled| scheduled.job == job) {
+                schedules.push(ScheduledJob {
+                    job,
+                    trigger: Trigger::Every(Duration::from_secs(secs)),
+                });
+            }
+        }
+
+        let data_dir = data_dir_in(var("DATA_DIR"), name);
+        let state_store = state_store_in(&var)?;
+        let replica = matches!(var("REPLICA").as_deref(), Some("1") | Some("true"));
+        if replica && matches!(state_store, StoreConfig::Json) {
+            return Err(anyhow!("REPLICA needs STATE_STORE=sqlite or postgres shared with the monitoring instance"));
+        }
+        // 多个 key 用逗号分隔，被限流或额度用完时轮换
+        let helius_keys = list(var("HELIUS_API_KEY"));
+        let helius_keys = (!helius_keys.is_empty()).then(|| Arc::new(HeliusKeys::new(name, helius_keys)));
+
+        Ok(Self {
+            name: name.to_string(),
+            wallets,
+            price_change_thresholds,
+            telegram_bot_token,
+            telegram_chat_id,
+            telegram_topic_id,
+            ws_alert_url: var("WS_ALERT_URL"),
+            discord_webhook_url: var("DISCORD_WEBHOOK_URL"),
+            email,
+            // 0 表示信号不过期
+            signal_ttl: Some(
+                var("SIGNAL_TTL_SECS")
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(60),
+            )
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs),
+            trade_confirm,
+            trade_annotations,
+            telegram_commands: matches!(var("TELEGRAM_COMMANDS").as_deref(), Some("1") | Some("true")),
+            telegram_admins,
+            data_dir,
+            state_store,
+            // 副本也按共享持仓的方式读取存储，没有设置名称时使用 `replica`
+            instance_id: instance_id_in(var("INSTANCE_ID")).or_else(|| replica.then(|| REPLICA_INSTANCE.to_string())),
+            replica,
+            rules_script: var("RULES_SCRIPT").map(PathBuf::from),
+            rules: file_config.rules,
+            tag_rules: file_config.tags,
+            enrichment: file_config.enrichment,
+            retention: file_config.retention,
+            alert_chains: file_config.alert_chains,
+            alert_routes: file_config.alert_routes,
+            alert_templates: file_config.alert_templates,
+            escalation: file_config.escalation,
+            wallet_labels: file_config.wallet_labels,
+            inbound_signals: file_config.inbound_signals,
+            recent_trades_capacity: var("RECENT_TRADES_CAPACITY")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(200),
+            // 默认按 CPU 核数，最多 4 个
+            decode_workers: var("DECODE_WORKERS")
+                .and_then(|value| value.parse::<usize>().ok())
+                .unwrap_or_else(|| thread::available_parallelism().map_or(1, |cores| cores.get().min(4)))
+                .max(1),
+            dedup_capacity: var("DEDUP_CAPACITY")
+                .and_then(|value| value.parse::<usize>().ok())
+                .filter(|capacity| *capacity > 0)
+                .unwrap_or(10_000),
+            rpc_urls: list(var("RPC_URL")),
+            ws_urls: list(var("WS_URL")),
+            ws_record: var("WS_RECORD").map(PathBuf::from),
+            ws_reconnect_max: Duration::from_secs(
+                var("WS_RECONNECT_MAX_SECS")
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .filter(|secs| *secs > 0)
+                    .unwrap_or(60),
+            ),
+            ws_reconnect_attempts: var("WS_RECONNECT_ATTEMPTS")
+                .and_then(|value| value.parse::<u32>().ok())
+                .filter(|attempts| *attempts > 0),
+            commitment: var("COMMITMENT")
+                .map(|level| parse_commitment(&level))
+                .transpose()?
+                .unwrap_or_else(CommitmentConfig::confirmed),
+            helius_keys,
+            // 0 表示只在启动时探测
+            endpoint_probe: Some(
+                var("ENDPOINT_PROBE_MINS")
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(10),
+            )
+            .filter(|mins| *mins > 0)
+            .map(|mins| Duration::from_secs(mins * 60)),
+            schedules,
+            reconcile_tolerance: var("RECONCILE_TOLERANCE_PERCENT")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1.0),
+            startup_sync: !matches!(var("STARTUP_SYNC").as_deref(), Some("0") | Some("false")),
+            backfill_txs: var("BACKFILL_TXS").and_then(|value| value.parse().ok()).unwrap_or(0),
+            track_token_accounts: matches!(
+                var("TRACK_TOKEN_ACCOUNTS").as_deref(),
+                Some("1") | Some("true")
+            ),
+            // 0 表示关闭清仓提醒
+            flat_alert_window: Some(
+                var("FLAT_ALERT_WINDOW_SECS")
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(900),
+            )
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs),
+            inactivity_alert: var("INACTIVITY_ALERT_HOURS")
+                .and_then(|value| value.parse::<u64>().ok())
+                .filter(|hours| *hours > 0)
+                .map(|hours| Duration::from_secs(hours * 60 * 60)),
+            burst_alert: var("BURST_ALERT_BUYS")
+                .and_then(|value| value.parse::<usize>().ok())
+                .filter(|buys| *buys > 0)
+                .map(|buys| BurstRule {
+                    buys,
+                    window: Duration::from_secs(
+                        var("BURST_ALERT_WINDOW_MINS")
+                            .and_then(|value| value.parse::<u64>().ok())
+                            .unwrap_or(10)
+                            * 60,
+                    ),
+                }),
+            size_anomaly_multiplier: var("SIZE_ANOMALY_MULTIPLIER")
+                .and_then(|value| value.parse::<f64>().ok())
+                .filter(|multiplier| *multiplier > 1.0),
+            max_hold: var("MAX_HOLD_MINS")
+                .and_then(|value| value.parse::<u64>().ok())
+                .filter(|mins| *mins > 0)
+                .map(|mins| Duration::from_secs(mins * 60)),
+            holder_alert: var("HOLDER_SAMPLE_MINS")
+                .and_then(|value| value.parse::<u64>().ok())
+                .filter(|mins| *mins > 0)
+                .map(|mins| HolderRule {
+                    interval: Duration::from_secs(mins * 60),
+                    window: Duration::from_secs(
+                        var("HOLDER_ALERT_WINDOW_MINS")
+                            .and_then(|value| value.parse::<u64>().ok())
+                            .unwrap_or(30)
+                            * 60,
+                    ),
+                    change_percent: var("HOLDER_ALERT_PERCENT")
+                        .and_then(|value| value.parse::<f64>().ok())
+                        .filter(|percent| *percent > 0.0)
+                        .unwrap_or(25.0),
+                }),
+            counterparty_alert: var("COUNTERPARTY_WINDOW_MINS")
+                .and_then(|value| value.parse::<u64>().ok())
+                .filter(|mins| *mins > 0)
+                .map(|mins| CounterpartyRule {
+                    window: Duration::from_secs(mins * 60),
+                    drop_percent: var("FRESH_BUYERS_DROP_PERCENT")
+                        .and_then(|value| value.parse::<f64>().ok())
+                        .filter(|percent| *percent > 0.0 && *percent <= 100.0)
+                        .unwrap_or(50.0),
+                }),
+            heartbeat: var("HEARTBEAT_URL").filter(|url| !url.is_empty()).map(|url| HeartbeatConfig {
+                url,
+                interval: Duration::from_secs(
+                    var("HEARTBEAT_INTERVAL_SECS")
+                        .and_then(|value| value.parse::<u64>().ok())
+                        .filter(|secs| *secs > 0)
+                        .unwrap_or(60),
+                ),
+            }),
+            flow_window: Some(
+                var("FLOW_WINDOW_MINS")
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or(10),
+            )
+            .filter(|mins| *mins > 0)
+            .map(|mins| Duration::from_secs(mins * 60)),
+            circuit_failures: var("ALERT_CIRCUIT_FAILURES")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(5),
+            alert_queue_capacity: var("ALERT_QUEUE_CAPACITY")
+                .and_then(|value| value.parse::<usize>().ok())
+                .filter(|capacity| *capacity > 0)
+                .unwrap_or(1000),
+            circuit_cooldown: Duration::from_secs(
+                var("ALERT_CIRCUIT_COOLDOWN_SECS")
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(60),
+            ),
+            alert_send_timeout: Duration::from_secs(
+                var("ALERT_SEND_TIMEOUT_SECS")
+                    .and_then(|value| value.parse().ok())
+                    .filter(|secs| *secs > 0)
+                    .unwrap_or(10),
+            ),
+            display_oracle: var("PRICE_ORACLE_DISPLAY")
+                .map(|kind| kind.parse())
+                .transpose()?
+                .unwrap_or(OracleKind::Stream),
+            alert_oracle: var("PRICE_ORACLE_ALERTS")
+                .map(|kind| kind.parse())
+                .transpose()?
+                .unwrap_or(OracleKind::Stream),
+            stress_drawdowns: match var("STRESS_DRAWDOWNS") {
+                Some(value) => parse_drawdowns(&value)?,
+                None => vec![30.0, 50.0, 80.0],
+            },
+            base_currency: var("BASE_CURRENCY")
+                .map(|currency| currency.parse())
+                .transpose()?
+                .unwrap_or(BaseCurrency::Sol),
+            exit_quotes: matches!(var("EXIT_QUOTES").as_deref(), Some("1") | Some("true")),
+            blacklist_urls: var("BLACKLIST_URLS")
+                .map(|urls| {
+                    urls.split(',')
+                        .map(|url| url.trim().to_string())
+                        .filter(|url| !url.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            blacklist_refresh: Duration::from_secs(
+                var("BLACKLIST_REFRESH_MINS")
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .filter(|mins| *mins > 0)
+                    .unwrap_or(60)
+                    * 60,
+            ),
+            known_tokens_url: matches!(var("IMPERSONATION_CHECK").as_deref(), Some("1") | Some("true"))
+                .then(|| var("KNOWN_TOKENS_URL").unwrap_or_else(|| DEFAULT_KNOWN_TOKENS_URL.to_string())),
+            mev_detection: matches!(var("MEV_DETECTION").as_deref(), Some("1") | Some("true")),
+            oracle_cache_ttl: Duration::from_secs(
+                var("PRICE_ORACLE_CACHE_SECS")
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(10),
+            ),
+        })
+    }
+}
+
+/// 解析逗号分隔的列表，未设置时为空
+fn list(value: Option<String>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// 解析逗号分隔的提醒档位，例如 `50,100,200,500`，返回从低到高排序、去重后的档位
+pub fn parse_thresholds(value: &str) -> Result<Vec<f64>> {
+    let mut thresholds = value
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            part.parse::<f64>()
+                .ok()
+                .filter(|threshold| threshold.is_finite() && *threshold >= 0.0)
+                .ok_or_else(|| anyhow!("'{}' is not a non-negative percentage", part))
+        })
+        .collect::<Result<Vec<f64>>>()?;
+    normalize_thresholds(&mut thresholds)?;
+    Ok(thresholds)
+}
+
+/// 档位从低到高排序并去重，至少要有一档
+pub fn normalize_thresholds(thresholds: &mut Vec<f64>) -> Result<()> {
+    if thresholds.is_empty() {
+        return Err(anyhow!("At least one threshold is required"));
+    }
+    thresholds.sort_by(f64::total_cmp);
+    thresholds.dedup();
+    Ok(())
+}
+
+/// 解析逗号分隔的回撤百分比，例如 `30,50,80`
+fn parse_drawdowns(value: &str) -> Result<Vec<f64>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            part.parse::<f64>()
+                .ok()
+                .filter(|drawdown| *drawdown > 0.0 && *drawdown <= 100.0)
+                .ok_or_else(|| anyhow!("Invalid drawdown '{}' in STRESS_DRAWDOWNS, expected a percentage between 0 and 100", part))
+        })
+        .collect()
+}
+
+/// 解析订阅的确认级别，只接受当前的三个级别，不接受 solana 已废弃的别名
+fn parse_commitment(level: &str) -> Result<CommitmentConfig> {
+    match level.trim().to_ascii_lowercase().as_str() {
+        "processed" => Ok(CommitmentConfig::processed()),
+        "confirmed" => Ok(CommitmentConfig::confirmed()),
+        "finalized" => Ok(CommitmentConfig::finalized()),
+        _ => Err(anyhow!("Unknown commitment '{}', expected processed, confirmed or finalized", level)),
+    }
+}
+
+/// 只读取 profile 的配置文件，不要求完整的运行配置，供命令行子命令使用
+fn load_file_config(name: &str) -> Result<FileConfig> {
+    let prefix = (name != DEFAULT_PROFILE).then(|| name.to_uppercase());
+    match profile_var(prefix.as_deref(), "MONITOR_CONFIG") {
+        Some(path) => FileConfig::load(&path),
+        None => Ok(FileConfig::default()),
+    }
+}
+
+/// profile 的提醒规则，供 `monitor preview-template` 使用
+pub fn load_rules(name: &str) -> Result<Vec<AlertRule>, MonitorError> {
+    Ok(load_file_config(name).map_err(MonitorError::config)?.rules)
+}
+
+/// profile 的关联钱包组，供 `monitor report` 使用
+pub fn load_clusters(name: &str) -> Result<BTreeMap<String, Vec<String>>, MonitorError> {
+    Ok(load_file_config(name).map_err(MonitorError::config)?.clusters)
+}
+
+/// 每个 profile 的持久化数据放在独立的子目录中
+pub fn data_dir(name: &str) -> PathBuf {
+    let prefix = (name != DEFAULT_PROFILE).then(|| name.to_uppercase());
+    data_dir_in(profile_var(prefix.as_deref(), "DATA_DIR"), name)
+}
+
+fn data_dir_in(base: Option<String>, name: &str) -> PathBuf {
+    PathBuf::from(base.unwrap_or_else(|| "data".to_string())).join(name)
+}
+
+/// profile 的状态存储，供 `monitor report` 读取交易日志
+pub fn state_store(name: &str) -> Result<StoreConfig, MonitorError> {
+    let prefix = (name != DEFAULT_PROFILE).then(|| name.to_uppercase());
+    state_store_in(&|key| profile_var(prefix.as_deref(), key)).map_err(MonitorError::config)
+}
+
+/// profile 共享状态的实例名，与监控使用同一份数据库状态
+pub fn instance_id(name: &str) -> Option<String> {
+    let prefix = (name != DEFAULT_PROFILE).then(|| name.to_uppercase());
+    instance_id_in(profile_var(prefix.as_deref(), "INSTANCE_ID"))
+}
+
+fn instance_id_in(value: Option<String>) -> Option<String> {
+    value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+/// `STATE_STORE`：`json`（默认）、`sqlite`（`SQLITE_PATH`）或 `postgres`（`DATABASE_URL`）
+fn state_store_in(var: &impl Fn(&str) -> Option<String>) -> Result<StoreConfig> {
+    match var("STATE_STORE").map(|value| value.trim().to_lowercase()).as_deref() {
+        None | Some("") | Some("json") => Ok(StoreConfig::Json),
+        Some("sqlite") => Ok(StoreConfig::Sqlite(var("SQLITE_PATH").map(PathBuf::from))),
+        Some("postgres") | Some("postgresql") => var("DATABASE_URL")
+            .map(StoreConfig::Postgres)
+            .ok_or_else(|| anyhow!("DATABASE_URL is required when STATE_STORE=postgres")),
+        Some(other) => Err(anyhow!("Invalid STATE_STORE '{}', expected json, sqlite or postgres", other)),
+    }
+}
+
+/// 读取 `MONITOR_PROFILES`（逗号分隔）中的所有 profile，未设置时只有一个默认 profile
+pub fn load_profiles() -> Result<Vec<ProfileConfig>, MonitorError> {
+    let names: Vec<String> = match env::var("MONITOR_PROFILES") {
+        Ok(value) => value
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect(),
+        Err(_) => vec![DEFAULT_PROFILE.to_string()],
+    };
+
+    if names.is_empty() {
+        return Err(MonitorError::config("MONITOR_PROFILES does not contain any profile"));
+    }
+
+    names.iter().map(|name| ProfileConfig::from_env(name)).collect()
+}
+
+fn profile_var(prefix: Option<&str>, key: &str) -> Option<String> {
+    prefix
+        .and_then(|prefix| env::var(format!("{}_{}", prefix, key)).ok())
+        .or_else(|| env::var(key).ok())
+}
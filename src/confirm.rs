@@ -0,0 +1,222 @@
+use crate::{channels::AlertChannel, enrichment::escape_html, models::Alert};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use teloxide::{
+    prelude::*,
+    types::{CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, MessageId, ParseMode},
+};
+
+/// 交易确认的配置（`TRADE_CONFIRM`）
+#[derive(Debug, Clone, Copy)]
+pub struct ConfirmRule {
+    pub timeout: Duration,  // 等待按下按钮的时间，超时视为跳过
+    pub execute: bool,      // 按下 Buy 后把信号发给执行器（`WS_ALERT_URL`），否则只记录决定
+}
+
+/// 等待回答的确认
+struct Pending {
+    alert: Alert,
+    message_id: MessageId,
+    text: String,    // 确认消息的内容，回答后去掉按钮并附上结果
+    deadline: i64,
+}
+
+/// 带 Buy/Skip 按钮的交易确认。带信号的提醒投递后在 Telegram 询问是否买入，
+/// 在超时前按下 Buy 时才把信号发给执行器，代替直接发送 WebSocket 信号。按钮的回调由 `TelegramBot` 接收
+pub struct TradeConfirmations {
+    profile: String,
+    bot: Bot,
+    chat_id: i64,
+    topic_id: Option<i32>,
+    rule: ConfirmRule,
+    admins: Vec<u64>,  // 可以回答确认的 Telegram 用户
+    executor: Option<Arc<dyn AlertChannel>>,
+    pending: Mutex<HashMap<u64, Pending>>,
+    next_id: AtomicU64,
+}
+
+impl TradeConfirmations {
+    pub fn new(
+        profile: &str,
+        bot_token: &str,
+        chat_id: i64,
+        topic_id: Option<i32>,
+        rule: ConfirmRule,
+        admins: Vec<u64>,
+        executor: Option<Arc<dyn AlertChannel>>,
+    ) -> Self {
+        Self {
+            profile: profile.to_string(),
+            bot: Bot::new(bot_token),
+            chat_id,
+            topic_id,
+            rule,
+            admins,
+            executor,
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// 处理 Buy/Skip 按钮的回调，只接受 `TELEGRAM_ADMINS` 中的用户，其他人按下时确认保持不变
+    pub async fn answer(&self, query: CallbackQuery) {
+        let chat_id = query.message.as_ref().map(|message| message.chat.id.0);
+        let Some((action, id)) = query
+            .data
+            .as_deref()
+            .and_then(|data| data.split_once(':'))
+            .and_then(|(action, id)| Some((action, id.parse::<u64>().ok()?)))
+            .filter(|_| chat_id == Some(self.chat_id))
+        else {
+            let _ = self.bot.answer_callback_query(query.id).await;
+            return;
+        };
+        let user = query.from.username.clone().unwrap_or_else(|| query.from.first_name.clone());
+        if !self.admins.contains(&query.from.id.0) {
+            warn!("[{}] Trade confirmation {} answered by {} ({}), who is not in TELEGRAM_ADMINS", self.profile, id, user, query.from.id);
+            let _ = self.bot.answer_callback_query(query.id).text("Only TELEGRAM_ADMINS can answer trade confirmations").await;
+            return;
+        }
+
+        let pending = self.pending.lock().unwrap().remove(&id);
+        let reply = match pending {
+            None => "This trade is no longer pending".to_string(),
+            Some(pending) if Utc::now().timestamp() > pending.deadline => {
+                self.close(&pending, "⌛ Expired before an answer, skipped").await;
+                "Too late, the confirmation expired".to_string()
+            }
+            Some(pending) if action == "buy" => {
+                let outcome = self.execute(&pending.alert).await;
+                info!("[{}] Trade confirmation {} for {:?}: buy by {} ({})", self.profile, id, pending.alert.mint, user, outcome);
+                self.close(&pending, &format!("🟢 Buy by {}: {}", escape_html(&user), escape_html(&outcome))).await;
+                outcome
+            }
+            Some(pending) => {
+                info!("[{}] Trade confirmation {} for {:?}: skipped by {}", self.profile, id, pending.alert.mint, user);
+                self.close(&pending, &format!("⏭ Skipped by {}", escape_html(&user))).await;
+                "Skipped".to_string()
+            }
+        };
+        if let Err(e) = self.bot.answer_callback_query(query.id).text(reply).await {
+            warn!("[{}] Failed to answer trade confirmation: {}", self.profile, e);
+        }
+    }
+
+    /// 按下 Buy 后发送信号，返回显示给用户的结果
+    async fn execute(&self, alert: &Alert) -> String {
+        if !self.rule.execute {
+            return "execute mode is off, no trade sent".to_string();
+        }
+        let Some(executor) = &self.executor else {
+            return "no executor configured, no trade sent".to_string();
+        };
+        let Some(signal) = executor.format(alert, executor.default_template()) else {
+            return "the signal expired, no trade sent".to_string();
+        };
+        match executor.send(&signal, alert).await {
+            Ok(()) => "sent to the executor".to_string(),
+            Err(e) => format!("executor failed: {}", e),
+        }
+    }
+
+    /// 关闭超时的确认
+    pub async fn expire(&self) {
+        let now = Utc::now().timestamp();
+        let expired: Vec<Pending> = {
+            let mut pending = self.pending.lock().unwrap();
+            let ids: Vec<u64> = pending.iter().filter(|(_, pending)| now > pending.deadline).map(|(id, _)| *id).collect();
+            ids.into_iter().filter_map(|id| pending.remove(&id)).collect()
+        };
+        for pending in expired {
+            info!("[{}] Trade confirmation for {:?} expired", self.profile, pending.alert.mint);
+            self.close(&pending, "⌛ No answer in time, skipped").await;
+        }
+    }
+
+    /// 去掉按钮并在消息末尾附上结果
+    async fn close(&self, pending: &Pending, result: &str) {
+        let text = format!("{}\n\n{}", pending.text, result);
+        if let Err(e) = self
+            .bot
+            .edit_message_text(ChatId(self.chat_id), pending.message_id, text)
+            .parse_mode(ParseMode::Html)
+            .await
+        {
+            warn!("[{}] Failed to update trade confirmation: {}", self.profile, e);
+        }
+    }
+}
+
+#[async_trait]
+impl AlertChannel for TradeConfirmations {
+    fn name(&self) -> &str {
+        "confirm"
+    }
+
+    /// 只有带信号和代币的提醒需要确认，信号已经过期时不再询问。确认消息有固定的按钮和格式，不使用模板
+    fn format(&self, alert: &Alert, _template: &str) -> Option<String> {
+        let signal = alert.signal.as_ref()?;
+        let mint = alert.mint.as_ref()?;
+        if self.executor.as_ref().is_some_and(|executor| executor.format(alert, executor.default_template()).is_none()) {
+            return None;
+        }
+        let mut text = format!("🤖 <b>Buy?</b> Signal <code>{}</code>\nToken: <code>{}</code>", escape_html(signal), mint);
+        if let Some(holding) = &alert.holding {
+            let _ = write!(text, "\nPrice: {:.9} SOL ({:+}%)", holding.current_price, holding.price_change);
+        }
+        if let Some(provenance) = &alert.provenance {
+            let wallet = provenance.wallet_label.as_deref().or(provenance.wallet.as_deref()).unwrap_or("-");
+            let _ = write!(
+                text,
+                "\nRule: {} · Wallet: {} · Confidence: {:.0}%",
+                escape_html(&provenance.rule),
+                escape_html(wallet),
+                provenance.confidence * 100.0
+            );
+        }
+        let _ = write!(text, "\nAnswer within {}s", self.rule.timeout.as_secs());
+        Some(text)
+    }
+
+    async fn send(&self, message: &str, alert: &Alert) -> Result<()> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let keyboard = InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::callback("Buy", format!("buy:{}", id)),
+            InlineKeyboardButton::callback("Skip", format!("skip:{}", id)),
+        ]]);
+        let sent = self
+            .bot
+            .send_message(ChatId(self.chat_id), message)
+            .message_thread_id(self.topic_id.unwrap_or(0))
+            .parse_mode(ParseMode::Html)
+            .reply_markup(keyboard)
+            .await
+            .map_err(|e| anyhow!("Failed to send trade confirmation: {}", e))?;
+        self.pending.lock().unwrap().insert(
+            id,
+            Pending {
+                alert: alert.clone(),
+                message_id: sent.id,
+                text: message.to_string(),
+                deadline: Utc::now().timestamp() + self.rule.timeout.as_secs() as i64,
+            },
+        );
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.bot.get_me().await.map_err(|e| anyhow!("Telegram getMe failed: {}", e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use crate::activity::format_duration;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::Duration,
+};
+
+const MIN_FRESH_BUYERS: usize = 5;  // 上一个窗口的新买家少于此数时减少没有意义
+
+/// 持仓代币交易对手的统计窗口和新买家减少的提醒条件
+#[derive(Debug, Clone, Copy)]
+pub struct CounterpartyRule {
+    pub window: Duration,
+    pub drop_percent: f64,  // 新买家数量比上一个窗口减少多少时提醒
+}
+
+/// 一个窗口内的交易对手
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CounterpartySummary {
+    pub buyers: usize,      // 不同的买家
+    pub sellers: usize,     // 不同的卖家
+    pub new_buyers: usize,  // 在窗口内第一次买入这个代币的买家，其余的是回头买家
+}
+
+impl CounterpartySummary {
+    pub fn returning_buyers(&self) -> usize {
+        self.buyers - self.new_buyers
+    }
+}
+
+/// 新买家比上一个窗口明显减少，买盘通常在价格见顶前先变弱
+#[derive(Debug, Clone)]
+pub struct FreshBuyersDrying {
+    pub mint: String,
+    pub before: CounterpartySummary,
+    pub now: CounterpartySummary,
+    pub window_secs: i64,
+}
+
+impl FreshBuyersDrying {
+    pub fn drop_percent(&self) -> f64 {
+        (1.0 - self.now.new_buyers as f64 / self.before.new_buyers as f64) * 100.0
+    }
+
+    pub fn message(&self) -> String {
+        let window = format_duration(self.window_secs);
+        format!(
+            "🥀 Fresh buyers drying up\n\n\
+            Token: <a href=\"https://gmgn.ai/sol/token/{mint}\">{mint}</a>\n\
+            New buyers: {} → {} (-{:.0}%) per {}\n\
+            Buyers last {}: {} ({} returning), sellers: {}",
+            self.before.new_buyers,
+            self.now.new_buyers,
+            self.drop_percent(),
+            window,
+            window,
+            self.now.buyers,
+            self.now.returning_buyers(),
+            self.now.sellers,
+            mint = self.mint
+        )
+    }
+}
+
+/// 一个代币的交易对手记录
+#[derive(Debug)]
+struct MintCounterparties {
+    since: i64,  // 开始跟踪的时间，之前买入过的钱包也会被算作新买家
+    first_buys: HashMap<String, i64>,  // 买家 -> 第一次买入的时间
+    trades: VecDeque<(i64, String, bool)>,  // 最近两个窗口内的 (时间, 交易者, 是否买入)
+    warned_at: Option<i64>,
+}
+
+impl MintCounterparties {
+    /// (`from`, `to`] 内的交易对手
+    fn summary(&self, from: i64, to: i64) -> CounterpartySummary {
+        let mut buyers = HashSet::new();
+        let mut sellers = HashSet::new();
+        for (_, user, is_buy) in self.trades.iter().filter(|(at, _, _)| *at > from && *at <= to) {
+            if *is_buy {
+                buyers.insert(user.as_str());
+            } else {
+                sellers.insert(user.as_str());
+            }
+        }
+        let new_buyers = buyers
+            .iter()
+            .filter(|buyer| self.first_buys.get(**buyer).is_some_and(|first| *first > from))
+            .count();
+        CounterpartySummary {
+            buyers: buyers.len(),
+            sellers: sellers.len(),
+            new_buyers,
+        }
+    }
+}
+
+/// 按滚动窗口统计持仓代币的不同买家、卖家和新买家，比较相邻两个窗口发现新买家减少
+#[derive(Debug)]
+pub struct CounterpartyTracker {
+    rule: CounterpartyRule,
+    mints: HashMap<String, MintCounterparties>,
+}
+
+impl CounterpartyTracker {
+    pub fn new(rule: CounterpartyRule) -> Self {
+        Self {
+            rule,
+            mints: HashMap::new(),
+        }
+    }
+
+    pub fn rule(&self) -> CounterpartyRule {
+        self.rule
+    }
+
+    /// 只保留仍在持仓中的代币
+    pub fn retain(&mut self, held: impl Fn(&str) -> bool) {
+        self.mints.retain(|mint, _| held(mint));
+    }
+
+    pub fn record(&mut self, mint: &str, user: &str, is_buy: bool, now: i64) {
+        let horizon = 2 * self.rule.window.as_secs() as i64;
+        let counterparties = self.mints.entry(mint.to_string()).or_insert_with(|| MintCounterparties {
+            since: now,
+            first_buys: HashMap::new(),
+            trades: VecDeque::new(),
+            warned_at: None,
+        });
+        while counterparties.trades.front().is_some_and(|(at, _, _)| now - at > horizon) {
+            counterparties.trades.pop_front();
+        }
+        if is_buy {
+            counterparties.first_buys.entry(user.to_string()).or_insert(now);
+        }
+        counterparties.trades.push_back((now, user.to_string(), is_buy));
+    }
+
+    /// 最近一个窗口的交易对手，没有跟踪这个代币时为 None
+    pub fn summary(&self, mint: &str, now: i64) -> Option<CounterpartySummary> {
+        let window = self.rule.window.as_secs() as i64;
+        self.mints.get(mint).map(|counterparties| counterparties.summary(now - window, now))
+    }
+
+    /// 检查所有代币，返回新买家比上一个窗口减少超过阈值的代币。
+    /// 跟踪满两个窗口后才比较；提醒后两个窗口内不再提醒同一代币
+    pub fn check(&mut self, now: i64) -> Vec<FreshBuyersDrying> {
+        let window = self.rule.window.as_secs() as i64;
+        let mut drying = Vec::new();
+        for (mint, counterparties) in &mut self.mints {
+            if now - counterparties.since < 2 * window || counterparties.warned_at.is_some_and(|at| now - at < 2 * window) {
+                continue;
+            }
+            let before = counterparties.summary(now - 2 * window, now - window);
+            let current = counterparties.summary(now - window, now);
+            if before.new_buyers < MIN_FRESH_BUYERS {
+                continue;
+            }
+            let change = FreshBuyersDrying {
+                mint: mint.clone(),
+                before,
+                now: current,
+                window_secs: window,
+            };
+            if change.drop_percent() >= self.rule.drop_percent {
+                counterparties.warned_at = Some(now);
+                drying.push(change);
+            }
+        }
+        drying
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::{HashMap, VecDeque};
+
+/// 交易中的一个事件：(签名, 事件所在的日志行号)。同一笔交易的日志在每个订阅中都相同，行号可以区分交易中的多个事件
+type EventKey = (String, usize);
+
+/// 最近处理过的事件，最多保留 `capacity` 个，超出时淘汰最久没有出现过的。
+/// 重连后重复的通知、节点重新推送的通知和多个订阅都收到的交易只处理一次
+#[derive(Debug)]
+pub struct SeenEvents {
+    capacity: usize,
+    seen: HashMap<EventKey, u64>,  // 事件 -> 最后一次出现的序号
+    order: VecDeque<(u64, EventKey)>,  // 按出现顺序，序号不等于 `seen` 中的序号时已经过时
+    next: u64,
+}
+
+impl SeenEvents {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+            next: 0,
+        }
+    }
+
+    /// 记录一个事件，之前已经出现过时返回 false
+    pub fn insert(&mut self, signature: &str, index: usize) -> bool {
+        let key = (signature.to_string(), index);
+        let sequence = self.next;
+        self.next += 1;
+        let first = self.seen.insert(key.clone(), sequence).is_none();
+        self.order.push_back((sequence, key));
+        while self.seen.len() > self.capacity {
+            let Some((sequence, key)) = self.order.pop_front() else {
+                break;
+            };
+            if self.seen.get(&key) == Some(&sequence) {
+                self.seen.remove(&key);
+            }
+        }
+        // 重复出现的事件在队列中留下过时的条目，过多时整理
+        if self.order.len() > 2 * self.capacity {
+            let seen = &self.seen;
+            self.order.retain(|(sequence, key)| seen.get(key) == Some(sequence));
+        }
+        first
+    }
+}
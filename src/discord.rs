@@ -0,0 +1,177 @@
+use crate::{
+    alert_service::render_alert,
+    channels::AlertChannel,
+    models::{Alert, AlertSeverity},
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+
+const MAX_TITLE: usize = 256;         // Discord embed 标题的长度上限
+const MAX_DESCRIPTION: usize = 4096;  // Discord embed 正文的长度上限
+
+/// 通过 webhook 把提醒发到 Discord 频道，每条提醒一个 embed
+#[derive(Debug)]
+pub struct DiscordChannel {
+    client: reqwest::Client,
+    url: String,
+    roles: Vec<String>,  // critical 提醒提及的角色 ID（`escalation.discord_roles`）
+}
+
+impl DiscordChannel {
+    pub fn new(url: &str, roles: Vec<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            roles,
+        }
+    }
+}
+
+#[async_trait]
+impl AlertChannel for DiscordChannel {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    /// 类型和时间已经显示在 embed 的页脚中
+    fn default_template(&self) -> &str {
+        "{message}"
+    }
+
+    fn format(&self, alert: &Alert, template: &str) -> Option<String> {
+        Some(to_markdown(&render_alert(template, alert)))
+    }
+
+    async fn send(&self, message: &str, alert: &Alert) -> Result<()> {
+        let mut body = json!({
+            "embeds": [embed(message, alert)],
+            "allowed_mentions": { "parse": [] },  // 提醒内容不触发 @ 提及
+        });
+        // critical 提醒只提及配置的角色，embed 中的提及不会通知
+        if alert.alert_type.severity() == AlertSeverity::Critical && !self.roles.is_empty() {
+            let mentions: Vec<String> = self.roles.iter().map(|role| format!("<@&{}>", role)).collect();
+            body["content"] = json!(mentions.join(" "));
+            body["allowed_mentions"] = json!({ "parse": [], "roles": self.roles });
+        }
+        let response = self.client.post(&self.url).json(&body).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Discord webhook returned {}: {}", status, body));
+        }
+        Ok(())
+    }
+
+    /// webhook 地址有效时 GET 返回 webhook 的信息
+    async fn health_check(&self) -> Result<()> {
+        let status = self.client.get(&self.url).send().await?.status();
+        if !status.is_success() {
+            return Err(anyhow!("Discord webhook returned {}", status));
+        }
+        Ok(())
+    }
+}
+
+/// 提醒的第一行作为标题，其余作为正文；有代币和持仓时加上链接、价格和盈亏字段，有代币图片时作为缩略图
+fn embed(text: &str, alert: &Alert) -> Value {
+    let (title, description) = text.split_once('\n').unwrap_or((text, ""));
+    let mut fields = Vec::new();
+    if let Some(mint) = &alert.mint {
+        let label = alert.token.as_ref().map_or_else(|| short(mint), |token| token.label(mint));
+        fields.push(json!({
+            "name": "Token",
+            "value": format!("[{}](https://gmgn.ai/sol/token/{})", label, mint),
+            "inline": true,
+        }));
+    }
+    if let Some(holding) = &alert.holding {
+        let pnl = holding.amount * holding.current_price - holding.total_cost;
+        fields.push(json!({
+            "name": "Price",
+            "value": format!("{:.9} SOL", holding.current_price),
+            "inline": true,
+        }));
+        fields.push(json!({
+            "name": "PnL",
+            "value": format!("{:+.4} SOL ({:+}%)", pnl, holding.price_change),
+            "inline": true,
+        }));
+    }
+    let mut embed = json!({
+        "title": truncate(title.trim_start_matches("**").trim_end_matches("**"), MAX_TITLE),
+        "description": truncate(description.trim(), MAX_DESCRIPTION),
+        "color": color(alert.alert_type.severity()),
+        "fields": fields,
+        "footer": { "text": format!("{:?}", alert.alert_type) },
+        "timestamp": Utc.timestamp_opt(alert.timestamp, 0).single().unwrap_or_else(Utc::now).to_rfc3339(),
+    });
+    if let Some(image) = alert.token.as_ref().and_then(|token| token.image.as_ref()) {
+        embed["thumbnail"] = json!({ "url": image });
+    }
+    embed
+}
+
+fn color(severity: AlertSeverity) -> u32 {
+    match severity {
+        AlertSeverity::Info => 0x3498db,
+        AlertSeverity::Notice => 0x2ecc71,
+        AlertSeverity::Critical => 0xe74c3c,
+    }
+}
+
+fn short(mint: &str) -> String {
+    if mint.len() <= 12 {
+        return mint.to_string();
+    }
+    format!("{}…{}", &mint[..4], &mint[mint.len() - 4..])
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// 把提醒中 Telegram 的 HTML 标记转换为 Discord 的 Markdown
+fn to_markdown(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    let mut link: Option<String> = None;
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            output.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        match tag {
+            "b" | "/b" | "strong" | "/strong" => output.push_str("**"),
+            "i" | "/i" | "em" | "/em" => output.push('*'),
+            "code" | "/code" => output.push('`'),
+            "pre" | "/pre" => output.push_str("```"),
+            "/a" => {
+                output.push_str("](");
+                output.push_str(link.take().as_deref().unwrap_or_default());
+                output.push(')');
+            }
+            _ if tag.starts_with("a ") => {
+                link = tag.split('"').nth(1).map(str::to_string);
+                output.push('[');
+            }
+            _ => {}
+        }
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+    output
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,640 @@
+use crate::{
+    activity::format_duration,
+    metadata::short_mint,
+    mints::{default_decimals, to_tokens},
+    wallet_monitor::{format_f64, format_market_cap, format_signed_sol, format_token_amount, format_usd, truncate_address},
+};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{ContentStyle, Print, Stylize},
+    terminal::{Clear, ClearType},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    fmt::Write,
+    io::{self, IsTerminal, Write as _},
+    str::FromStr,
+    sync::{OnceLock, RwLock},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+pub(crate) const PUMP_FEE_RATE: f64 = 0.01; // 估算卖出价值时扣除的 pump 手续费
+const HIGH_IMPACT: f64 = 0.05; // 价格冲击超过 5% 时标红
+const MAX_CELL_WIDTH: usize = 24; // 单元格最大显示宽度，超出时截断
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+static SETTINGS: RwLock<DisplaySettings> = RwLock::new(DisplaySettings {
+    columns: Vec::new(),
+    sort: SortKey::Value,
+    by_wallet: false,
+});
+
+/// 是否输出颜色，对应 `--color` 参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    Auto,  // 输出到终端且没有设置 NO_COLOR 时着色
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = anyhow::Error;
+
+    fn from_str(mode: &str) -> Result<Self> {
+        match mode {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(anyhow!("Invalid color mode '{}', expected auto, always or never", mode)),
+        }
+    }
+}
+
+/// 着色的用途，颜色由主题决定
+#[derive(Debug, Clone, Copy)]
+enum Role {
+    Header,  // 标题和表头
+    Info,    // 更新时间等次要信息
+    Token,
+    Gain,
+    Loss,
+}
+
+/// 各用途对应的样式，默认样式表示不着色
+#[derive(Debug, Clone, Default)]
+struct Theme {
+    header: ContentStyle,
+    info: ContentStyle,
+    token: ContentStyle,
+    gain: ContentStyle,
+    loss: ContentStyle,
+}
+
+impl Theme {
+    fn dark() -> Self {
+        Self {
+            header: ContentStyle::new().bold(),
+            info: ContentStyle::new().cyan(),
+            token: ContentStyle::new().yellow(),
+            gain: ContentStyle::new().green(),
+            loss: ContentStyle::new().red(),
+        }
+    }
+
+    /// 浅色背景下黄色和青色看不清，改用蓝色和洋红
+    fn light() -> Self {
+        Self {
+            header: ContentStyle::new().bold(),
+            info: ContentStyle::new().magenta(),
+            token: ContentStyle::new().blue(),
+            gain: ContentStyle::new().green(),
+            loss: ContentStyle::new().red(),
+        }
+    }
+
+    /// 解析 `DISPLAY_THEME`：`dark`、`light`，或者在默认主题上覆盖，
+    /// 例如 `gain=bold+green,loss=magenta,token=none`
+    fn parse(spec: &str) -> Result<Self> {
+        match spec {
+            "dark" => return Ok(Self::dark()),
+            "light" => return Ok(Self::light()),
+            _ => {}
+        }
+        let mut theme = Self::dark();
+        for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid theme entry '{}', expected <role>=<color>", part))?;
+            let value = style(value.trim())?;
+            match key.trim() {
+                "header" => theme.header = value,
+                "info" => theme.info = value,
+                "token" => theme.token = value,
+                "gain" => theme.gain = value,
+                "loss" => theme.loss = value,
+                other => return Err(anyhow!("Unknown theme role '{}', expected header, info, token, gain or loss", other)),
+            }
+        }
+        Ok(theme)
+    }
+
+    fn style(&self, role: Role) -> ContentStyle {
+        match role {
+            Role::Header => self.header,
+            Role::Info => self.info,
+            Role::Token => self.token,
+            Role::Gain => self.gain,
+            Role::Loss => self.loss,
+        }
+    }
+}
+
+/// 把 `bold+green` 这样的颜色名转换成样式
+fn style(spec: &str) -> Result<ContentStyle> {
+    let mut style = ContentStyle::new();
+    for name in spec.split('+').map(str::trim) {
+        style = match name {
+            "none" => style,
+            "bold" => style.bold(),
+            "dim" => style.dim(),
+            "underline" => style.underlined(),
+            "black" => style.black(),
+            "red" => style.red(),
+            "green" => style.green(),
+            "yellow" => style.yellow(),
+            "blue" => style.blue(),
+            "magenta" => style.magenta(),
+            "cyan" => style.cyan(),
+            "white" => style.white(),
+            _ => return Err(anyhow!("Unknown color '{}'", name)),
+        };
+    }
+    Ok(style)
+}
+
+/// 按主题着色，关闭颜色时原样返回
+fn paint(text: &str, role: Role) -> String {
+    match THEME.get().map(|theme| theme.style(role)).filter(|style| *style != ContentStyle::new()) {
+        Some(style) => style.apply(text).to_string(),
+        None => text.to_string(),
+    }
+}
+
+/// 终端能否解析 ANSI 颜色序列。旧版 Windows 控制台需要先开启虚拟终端处理，开启失败时不着色
+#[cfg(windows)]
+fn supports_ansi() -> bool {
+    crossterm::ansi_support::supports_ansi()
+}
+
+#[cfg(not(windows))]
+fn supports_ansi() -> bool {
+    true
+}
+
+/// 持仓表可以显示的列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Token,
+    Amount,
+    AvgPrice,
+    Price,
+    MarketCap,  // 按现价计算的市值
+    Change,
+    Value,
+    Cost,
+    Pnl,
+    Age,
+    ExitValue,  // 估算卖出价值：有流动性估算时按估算，否则扣除 pump 手续费
+    Impact,     // 按当前流动性卖出全部持仓的价格冲击
+    Wallet,     // 最近一次交易该代币的目标钱包
+    Realized,   // 按先进先出计算的已实现盈亏
+}
+
+const ALL_COLUMNS: [(Column, &str); 14] = [
+    (Column::Token, "token"),
+    (Column::Amount, "amount"),
+    (Column::AvgPrice, "avg_price"),
+    (Column::Price, "price"),
+    (Column::MarketCap, "market_cap"),
+    (Column::Change, "change"),
+    (Column::Value, "value"),
+    (Column::Cost, "cost"),
+    (Column::Pnl, "pnl"),
+    (Column::Age, "age"),
+    (Column::ExitValue, "exit_value"),
+    (Column::Impact, "impact"),
+    (Column::Wallet, "wallet"),
+    (Column::Realized, "realized"),
+];
+
+const DEFAULT_COLUMNS: [Column; 6] = [Column::Token, Column::Amount, Column::AvgPrice, Column::Price, Column::MarketCap, Column::Change];
+
+impl Column {
+    fn header(self) -> &'static str {
+        match self {
+            Column::Token => "Token",
+            Column::Amount => "Amount",
+            Column::AvgPrice => "Avg Price",
+            Column::Price => "Price",
+            Column::MarketCap => "MCap",
+            Column::Change => "Change",
+            Column::Value => "Value",
+            Column::Cost => "Cost",
+            Column::Pnl => "PnL",
+            Column::Age => "Age",
+            Column::ExitValue => "Exit Value",
+            Column::Impact => "Impact",
+            Column::Wallet => "Wallet",
+            Column::Realized => "Realized",
+        }
+    }
+
+    fn left_aligned(self) -> bool {
+        matches!(self, Column::Token | Column::Wallet)
+    }
+}
+
+impl FromStr for Column {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        ALL_COLUMNS
+            .iter()
+            .find(|(_, column_name)| *column_name == name)
+            .map(|(column, _)| *column)
+            .ok_or_else(|| {
+                let names: Vec<&str> = ALL_COLUMNS.iter().map(|(_, name)| *name).collect();
+                anyhow!("Unknown column '{}', expected one of {}", name, names.join(", "))
+            })
+    }
+}
+
+/// 持仓表的排序方式，都是从大到小
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    Pnl,
+    Value,
+    Recency,  // 最近交易的在前
+}
+
+impl FromStr for SortKey {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "pnl" => Ok(SortKey::Pnl),
+            "value" => Ok(SortKey::Value),
+            "recency" => Ok(SortKey::Recency),
+            _ => Err(anyhow!("Unknown sort key '{}', expected one of pnl, value, recency", name)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DisplaySettings {
+    pub columns: Vec<Column>,
+    pub sort: SortKey,
+    pub by_wallet: bool,  // 每个目标钱包的仓位单独一行
+}
+
+/// 读取显示设置，在启动时调用一次：`DISPLAY_COLUMNS`（逗号分隔）、`DISPLAY_SORT`、
+/// `DISPLAY_BY_WALLET`、`DISPLAY_THEME`，以及颜色模式（`--color` 参数，auto 模式下遵循 `NO_COLOR`）
+pub fn init(color: ColorMode) -> Result<()> {
+    let colored = match color {
+        ColorMode::Always => {
+            // 仍然尝试开启虚拟终端处理，但无论结果如何都着色
+            let _ = supports_ansi();
+            true
+        }
+        ColorMode::Never => false,
+        ColorMode::Auto => {
+            io::stdout().is_terminal() && env::var("NO_COLOR").map_or(true, |value| value.is_empty()) && supports_ansi()
+        }
+    };
+    let theme = match env::var("DISPLAY_THEME") {
+        Ok(spec) => Theme::parse(spec.trim())?,
+        Err(_) => Theme::dark(),
+    };
+    let _ = THEME.set(if colored { theme } else { Theme::default() });
+
+    if let Ok(spec) = env::var("DISPLAY_COLUMNS") {
+        set_columns(&parse_columns(&spec)?);
+    }
+    if let Ok(sort) = env::var("DISPLAY_SORT") {
+        set_sort(sort.trim().parse()?);
+    }
+    if let Ok(by_wallet) = env::var("DISPLAY_BY_WALLET") {
+        set_by_wallet(
+            by_wallet
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid DISPLAY_BY_WALLET '{}', expected true or false", by_wallet))?,
+        );
+    }
+    Ok(())
+}
+
+pub fn parse_columns(spec: &str) -> Result<Vec<Column>> {
+    let columns = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(Column::from_str)
+        .collect::<Result<Vec<_>>>()?;
+    if columns.is_empty() {
+        return Err(anyhow!("At least one column must be displayed"));
+    }
+    Ok(columns)
+}
+
+pub fn settings() -> DisplaySettings {
+    let settings = SETTINGS.read().unwrap();
+    DisplaySettings {
+        columns: if settings.columns.is_empty() {
+            DEFAULT_COLUMNS.to_vec()
+        } else {
+            settings.columns.clone()
+        },
+        sort: settings.sort,
+        by_wallet: settings.by_wallet,
+    }
+}
+
+pub fn set_columns(columns: &[Column]) {
+    SETTINGS.write().unwrap().columns = columns.to_vec();
+}
+
+pub fn set_sort(sort: SortKey) {
+    SETTINGS.write().unwrap().sort = sort;
+}
+
+pub fn set_by_wallet(by_wallet: bool) {
+    SETTINGS.write().unwrap().by_wallet = by_wallet;
+}
+
+/// 持仓表的一行，也是显示录制的内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoldingRow {
+    pub mint: String,
+    pub wallet: Option<String>,
+    pub amount: u64,  // 原始数量
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,
+    pub avg_price: f64,
+    pub price: f64,
+    pub change: i32,
+    pub value: f64,
+    pub cost: f64,
+    pub opened_at: i64,
+    pub last_trade_at: i64,
+    #[serde(default)]
+    pub exit_quote: Option<f64>,  // 按当前流动性卖出全部持仓能拿到的 SOL
+    #[serde(default)]
+    pub price_impact: Option<f64>,  // 卖出全部持仓的价格冲击（0.05 表示 5%）
+    #[serde(default)]
+    pub cost_usd: Option<f64>,  // 按交易时汇率计的 USD 成本，只在 USD 记账时填写
+    #[serde(default)]
+    pub realized: f64,  // 这个代币卖出的已实现盈亏（SOL），按钱包显示时计在第一行
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,  // 代币符号，没有元数据时显示截断的 mint
+    #[serde(default)]
+    pub cost_unknown: bool,  // 均价是同步时的估算，显示为 `~`
+}
+
+impl HoldingRow {
+    /// 估算的卖出价值，没有流动性估算时按现价扣除手续费
+    pub fn exit_value(&self) -> f64 {
+        self.exit_quote.unwrap_or(self.value * (1.0 - PUMP_FEE_RATE))
+    }
+
+    /// 按另一个价格来源的报价重新计算价格、涨跌幅和价值
+    pub fn reprice(&mut self, price: f64) {
+        self.price = price;
+        self.value = to_tokens(self.amount, self.decimals) * price;
+        self.change = if self.avg_price == 0.0 {
+            0
+        } else {
+            ((price - self.avg_price) / self.avg_price * 100.0) as i32
+        };
+    }
+
+    fn pnl(&self) -> f64 {
+        self.value - self.cost
+    }
+
+    /// (文本, 着色用途)
+    fn cell(&self, column: Column, now: i64) -> (String, Option<Role>) {
+        match column {
+            Column::Token => {
+                let label = match &self.symbol {
+                    Some(symbol) => format!("{} ({})", symbol, short_mint(&self.mint)),
+                    None => truncate_address(&self.mint, 16),
+                };
+                (label, Some(Role::Token))
+            }
+            Column::Amount => (format_token_amount(self.amount, self.decimals), None),
+            Column::AvgPrice => (
+                format!("{}{} SOL", if self.cost_unknown { "~" } else { "" }, format_f64(self.avg_price)),
+                None,
+            ),
+            Column::Price => (format!("{} SOL", format_f64(self.price)), None),
+            Column::MarketCap => (format_market_cap(self.price), None),
+            Column::Change => format_change(self.change),
+            Column::Value => (format!("{} SOL", format_f64(self.value)), None),
+            Column::Cost => (format!("{} SOL", format_f64(self.cost)), None),
+            Column::Pnl => (format!("{} SOL", format_signed_sol(self.pnl())), gain_role(self.pnl())),
+            Column::Age => (format_duration(now - self.opened_at), None),
+            Column::ExitValue => (format!("{} SOL", format_f64(self.exit_value())), None),
+            Column::Impact => match self.price_impact {
+                Some(impact) => {
+                    let role = (impact >= HIGH_IMPACT).then_some(Role::Loss);
+                    (format!("-{:.1}%", impact * 100.0), role)
+                }
+                None => ("-".to_string(), None),
+            },
+            Column::Wallet => (self.wallet.as_deref().map_or_else(String::new, |wallet| truncate_address(wallet, 12)), None),
+            Column::Realized => (format!("{} SOL", format_signed_sol(self.realized)), gain_role(self.realized)),
+        }
+    }
+}
+
+fn gain_role(value: f64) -> Option<Role> {
+    if value > 0.0 {
+        Some(Role::Gain)
+    } else if value < 0.0 {
+        Some(Role::Loss)
+    } else {
+        None
+    }
+}
+
+fn format_change(change: i32) -> (String, Option<Role>) {
+    let text = if change > 0 { format!("+{}%", change) } else { format!("{}%", change) };
+    (text, gain_role(change as f64))
+}
+
+/// 按当前设置排序后渲染持仓表和汇总
+pub fn render_holdings(profile: Option<&str>, mut rows: Vec<HoldingRow>, sol_usd: Option<f64>, now: i64) -> String {
+    let settings = settings();
+    let mut output = String::new();
+    if rows.is_empty() {
+        return output;
+    }
+    if let Some(profile) = profile {
+        let _ = writeln!(output, "{}", paint(&format!("Profile: {}", profile), Role::Header));
+    }
+
+    match settings.sort {
+        SortKey::Pnl => rows.sort_by(|a, b| b.pnl().total_cmp(&a.pnl())),
+        SortKey::Value => rows.sort_by(|a, b| b.value.total_cmp(&a.value)),
+        SortKey::Recency => rows.sort_by_key(|row| std::cmp::Reverse(row.last_trade_at)),
+    }
+
+    let mut columns = settings.columns.clone();
+    let cells: Vec<Vec<(String, Option<Role>)>> = rows
+        .iter()
+        .map(|row| {
+            columns
+                .iter()
+                .map(|column| {
+                    let (text, color) = row.cell(*column, now);
+                    (truncate_to_width(&text, MAX_CELL_WIDTH), color)
+                })
+                .collect()
+        })
+        .collect();
+    // 按显示宽度计算列宽，emoji 和中日韩字符占两列
+    let mut widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            cells
+                .iter()
+                .map(|row| row[index].0.width())
+                .chain([column.header().width()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    // 终端太窄时从右侧隐藏列，每帧重新计算以适应窗口大小变化
+    let mut hidden = 0;
+    if let Some(terminal_width) = terminal_width() {
+        while columns.len() > 1 && table_width(&widths) > terminal_width {
+            columns.pop();
+            widths.pop();
+            hidden += 1;
+        }
+    }
+
+    let border = |left: &str, middle: &str, right: &str| {
+        let segments: Vec<String> = widths.iter().map(|width| "═".repeat(width + 2)).collect();
+        format!("{}{}{}", left, segments.join(middle), right)
+    };
+
+    let _ = writeln!(output, "{}", border("╔", "╦", "╗"));
+    output.push('║');
+    for (column, width) in columns.iter().zip(&widths) {
+        let padding = width - column.header().width();
+        let _ = write!(
+            output,
+            " {}{}{} ║",
+            " ".repeat(padding / 2),
+            paint(column.header(), Role::Header),
+            " ".repeat(padding - padding / 2)
+        );
+    }
+    output.push('\n');
+    let _ = writeln!(output, "{}", border("╠", "╬", "╣"));
+
+    for row in &cells {
+        output.push('║');
+        for ((column, width), (text, color)) in columns.iter().zip(&widths).zip(row) {
+            let padding = " ".repeat(width - text.width());
+            let text = match color {
+                Some(role) => paint(text, *role),
+                None => text.clone(),
+            };
+            if column.left_aligned() {
+                let _ = write!(output, " {}{} ║", text, padding);
+            } else {
+                let _ = write!(output, " {}{} ║", padding, text);
+            }
+        }
+        output.push('\n');
+    }
+    let _ = writeln!(output, "{}", border("╚", "╩", "╝"));
+    if hidden > 0 {
+        let _ = writeln!(output, "({} column(s) hidden, terminal too narrow)", hidden);
+    }
+
+    // 汇总
+    let total_value: f64 = rows.iter().map(|row| row.value).sum();
+    let total_cost: f64 = rows.iter().map(|row| row.cost).sum();
+    let total_exit_value: f64 = rows.iter().map(HoldingRow::exit_value).sum();
+    let total_pnl = total_value - total_cost;
+    let total_pnl_percentage = if total_cost > 0.0 { (total_pnl / total_cost * 100.0) as i32 } else { 0 };
+    let (change, role) = format_change(total_pnl_percentage);
+
+    let _ = writeln!(output, "\n{}", paint("Portfolio Summary:", Role::Header));
+    match sol_usd {
+        Some(sol_usd) => {
+            let _ = writeln!(output, "Total Value: {} SOL (≈ ${:.2})", format_f64(total_value), total_value * sol_usd);
+        }
+        None => {
+            let _ = writeln!(output, "Total Value: {} SOL", format_f64(total_value));
+        }
+    }
+    let _ = writeln!(output, "Exit Value:  {} SOL", format_f64(total_exit_value));
+    let _ = writeln!(output, "Total Cost:  {} SOL", format_f64(total_cost));
+    let _ = writeln!(
+        output,
+        "Total PnL:   {} SOL ({})",
+        format_f64(total_pnl),
+        role.map_or_else(|| change.clone(), |role| paint(&change, role))
+    );
+    // 部分卖出的已实现盈亏，加上未实现盈亏是这些代币的总盈亏
+    let total_realized: f64 = rows.iter().map(|row| row.realized).sum();
+    if total_realized != 0.0 {
+        let _ = writeln!(output, "Realized:    {} SOL", format_signed_sol(total_realized));
+        let _ = writeln!(output, "Net PnL:     {} SOL", format_signed_sol(total_realized + total_pnl));
+    }
+    // USD 记账：当前价值按现汇率，成本按交易时汇率
+    let total_cost_usd: Option<f64> = rows.iter().map(|row| row.cost_usd).sum();
+    if let (Some(sol_usd), Some(total_cost_usd)) = (sol_usd, total_cost_usd.filter(|_| !rows.is_empty())) {
+        let _ = writeln!(output, "USD PnL:     {} (cost ${:.2})", format_usd(total_value * sol_usd - total_cost_usd), total_cost_usd);
+    }
+    output.push('\n');
+    output
+}
+
+/// 表格的总显示宽度：每列左右各一个空格加一条竖线
+fn table_width(widths: &[usize]) -> usize {
+    widths.iter().map(|width| width + 3).sum::<usize>() + 1
+}
+
+fn terminal_width() -> Option<usize> {
+    crossterm::terminal::size().ok().map(|(columns, _)| columns as usize)
+}
+
+/// 按显示宽度截断，超出时以省略号结尾
+fn truncate_to_width(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    let mut truncated = String::new();
+    let mut width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width > max_width - 1 {
+            break;
+        }
+        truncated.push(c);
+        width += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// 清屏并输出标题和所有持仓表。
+///
+/// 清屏和光标移动通过 crossterm 完成，在不支持 ANSI 的 Windows 控制台上会改用控制台 API
+pub fn draw_frame(tables: &[String], updated_at: DateTime<Local>) -> io::Result<()> {
+    let mut output = String::new();
+    let _ = writeln!(output, "\n{}", paint("📊 Sol Pump Monitor Holdings", Role::Header));
+    let last_update = format!("Last Update: {}", updated_at.format("%Y-%m-%d %H:%M:%S"));
+    let _ = writeln!(output, "{}\n", paint(&last_update, Role::Info));
+    for table in tables {
+        output.push_str(table);
+    }
+
+    let mut stdout = io::stdout().lock();
+    queue!(stdout, Clear(ClearType::All), MoveTo(0, 0), Print(output))?;
+    stdout.flush()
+}
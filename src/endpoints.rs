@@ -0,0 +1,273 @@
+use crate::helius::{self, HeliusKeys};
+use async_trait::async_trait;
+use chrono::Utc;
+use futures_util::future::join_all;
+use log::{info, warn};
+use serde::Serialize;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::RpcClientConfig,
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::time::timeout;
+use tokio_tungstenite::connect_async;
+use url::Url;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const SWITCH_MARGIN: f64 = 0.8;  // 新端点比当前端点快 20% 以上才切换，避免在相近的端点间来回切换
+
+/// 一次探测的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct Probe {
+    pub latency_ms: Option<u64>,  // 不可达时为 None
+    pub error: Option<String>,
+    pub probed_at: i64,
+}
+
+/// `GET /diag` 中的一个端点
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointStatus {
+    pub url: String,  // 去掉查询参数，不输出其中的 API key
+    pub preferred: bool,
+    pub probe: Option<Probe>,  // 还没有探测过时为 None
+}
+
+#[derive(Debug)]
+struct Candidate {
+    url: String,
+    probe: Mutex<Option<Probe>>,
+}
+
+/// 同一用途的候选端点，`preferred` 是当前使用的端点
+#[derive(Debug)]
+struct Pool {
+    candidates: Vec<Candidate>,
+    preferred: AtomicUsize,
+}
+
+impl Pool {
+    fn new(urls: Vec<String>) -> Self {
+        Self {
+            candidates: urls
+                .into_iter()
+                .map(|url| Candidate { url, probe: Mutex::new(None) })
+                .collect(),
+            preferred: AtomicUsize::new(0),
+        }
+    }
+
+    fn preferred(&self) -> usize {
+        self.preferred.load(Ordering::Relaxed)
+    }
+
+    /// 记录探测结果，选择最快的可达端点。返回切换前后的端点
+    fn update(&self, probes: Vec<Probe>) -> Option<(usize, usize)> {
+        let current = self.preferred();
+        let best = probes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, probe)| probe.latency_ms.map(|latency| (index, latency)))
+            .min_by_key(|(_, latency)| *latency);
+        let switch = match (best, probes.get(current).and_then(|probe| probe.latency_ms)) {
+            (Some((best, _)), None) => best != current,
+            (Some((best, latency)), Some(current_latency)) => {
+                best != current && (latency as f64) < current_latency as f64 * SWITCH_MARGIN
+            }
+            (None, _) => false,
+        };
+        for (candidate, probe) in self.candidates.iter().zip(probes) {
+            *candidate.probe.lock().unwrap() = Some(probe);
+        }
+        let (best, _) = best?;
+        if !switch {
+            return None;
+        }
+        self.preferred.store(best, Ordering::Relaxed);
+        Some((current, best))
+    }
+
+    fn status(&self) -> Vec<EndpointStatus> {
+        let preferred = self.preferred();
+        self.candidates
+            .iter()
+            .enumerate()
+            .map(|(index, candidate)| EndpointStatus {
+                url: redact(&candidate.url),
+                preferred: index == preferred,
+                probe: candidate.probe.lock().unwrap().clone(),
+            })
+            .collect()
+    }
+}
+
+/// 配置的 RPC 和 WebSocket 端点（`RPC_URL`、`WS_URL`），定期探测延迟并优先使用最快的可达端点
+#[derive(Debug)]
+pub struct Endpoints {
+    profile: String,
+    rpc: Pool,
+    ws: Pool,
+}
+
+/// `GET /diag` 返回的端点测量结果
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointDiag {
+    pub rpc: Vec<EndpointStatus>,
+    pub ws: Vec<EndpointStatus>,
+}
+
+impl Endpoints {
+    pub fn new(profile: &str, rpc_urls: Vec<String>, ws_urls: Vec<String>) -> Self {
+        Self {
+            profile: profile.to_string(),
+            rpc: Pool::new(rpc_urls),
+            ws: Pool::new(ws_urls),
+        }
+    }
+
+    pub fn has_rpc(&self) -> bool {
+        !self.rpc.candidates.is_empty()
+    }
+
+    /// 当前最快的 WebSocket 端点，没有配置 `WS_URL` 时为 None
+    pub fn ws_url(&self) -> Option<String> {
+        self.ws.candidates.get(self.ws.preferred()).map(|candidate| candidate.url.clone())
+    }
+
+    /// 同时探测所有端点：RPC 端点计 `getSlot` 的耗时，WebSocket 端点计握手的耗时
+    pub async fn probe(&self) {
+        let rpc = join_all(self.rpc.candidates.iter().map(|candidate| probe_rpc(&candidate.url)));
+        let ws = join_all(self.ws.candidates.iter().map(|candidate| probe_ws(&candidate.url)));
+        let (rpc, ws) = tokio::join!(rpc, ws);
+        for (kind, pool, probes) in [("RPC", &self.rpc, rpc), ("WebSocket", &self.ws, ws)] {
+            if probes.iter().all(|probe| probe.latency_ms.is_none()) && !probes.is_empty() {
+                warn!("[{}] No {} endpoint is reachable", self.profile, kind);
+            }
+            if let Some((from, to)) = pool.update(probes) {
+                info!(
+                    "[{}] Switched {} endpoint from {} to {} ({}ms)",
+                    self.profile,
+                    kind,
+                    redact(&pool.candidates[from].url),
+                    redact(&pool.candidates[to].url),
+                    pool.candidates[to].probe.lock().unwrap().as_ref().and_then(|probe| probe.latency_ms).unwrap_or_default()
+                );
+            }
+        }
+    }
+
+    pub fn diag(&self) -> EndpointDiag {
+        EndpointDiag {
+            rpc: self.rpc.status(),
+            ws: self.ws.status(),
+        }
+    }
+}
+
+async fn probe_rpc(url: &str) -> Probe {
+    let client = RpcClient::new_with_timeout(url.to_string(), PROBE_TIMEOUT);
+    let started = Instant::now();
+    let result = client.get_slot().await.map_err(|e| e.to_string());
+    probe_result(started, result)
+}
+
+async fn probe_ws(url: &str) -> Probe {
+    let started = Instant::now();
+    let result = match Url::parse(url) {
+        Ok(url) => match timeout(PROBE_TIMEOUT, connect_async(url)).await {
+            Ok(Ok((mut stream, _))) => {
+                let _ = stream.close(None).await;
+                Ok(())
+            }
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err("timed out".to_string()),
+        },
+        Err(e) => Err(e.to_string()),
+    };
+    probe_result(started, result)
+}
+
+fn probe_result<T>(started: Instant, result: Result<T, String>) -> Probe {
+    let latency_ms = started.elapsed().as_millis() as u64;
+    Probe {
+        latency_ms: result.is_ok().then_some(latency_ms),
+        error: result.err(),
+        probed_at: Utc::now().timestamp(),
+    }
+}
+
+/// 日志和 API 中显示的端点地址，去掉可能包含 API key 的查询参数
+fn redact(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.set_query(None);
+            parsed.to_string()
+        }
+        Err(_) => "<invalid url>".to_string(),
+    }
+}
+
+/// HTTP RPC 的端点：探测延迟选择的 `RPC_URL`，或者按 Helius key 轮换
+#[derive(Debug, Clone)]
+pub enum RpcEndpoint {
+    Probed(Arc<Endpoints>),
+    Helius(Arc<HeliusKeys>),
+}
+
+impl RpcEndpoint {
+    pub fn client(&self) -> RpcClient {
+        match self {
+            RpcEndpoint::Probed(endpoints) => RpcClient::new_sender(
+                PreferredSender {
+                    clients: endpoints.rpc.candidates.iter().map(|candidate| RpcClient::new(candidate.url.clone())).collect(),
+                    endpoints: endpoints.clone(),
+                },
+                RpcClientConfig::with_commitment(CommitmentConfig::default()),
+            ),
+            RpcEndpoint::Helius(keys) => helius::rpc_client(keys),
+        }
+    }
+}
+
+/// 每个端点一个 HTTP 客户端，请求发往当前最快的端点；连接失败或超时时依次尝试其他端点
+struct PreferredSender {
+    endpoints: Arc<Endpoints>,
+    clients: Vec<RpcClient>,
+}
+
+#[async_trait]
+impl RpcSender for PreferredSender {
+    async fn send(&self, request: RpcRequest, params: serde_json::Value) -> ClientResult<serde_json::Value> {
+        let preferred = self.endpoints.rpc.preferred();
+        let count = self.clients.len();
+        let mut last_error = None;
+        for index in (0..count).map(|offset| (preferred + offset) % count) {
+            match self.clients[index].send::<serde_json::Value>(request, params.clone()).await {
+                Err(e) if is_unreachable(&e) => last_error = Some(e),
+                result => return result,
+            }
+        }
+        Err(last_error.expect("at least one RPC endpoint is configured"))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        self.endpoints.rpc.candidates[self.endpoints.rpc.preferred()].url.clone()
+    }
+}
+
+fn is_unreachable(error: &ClientError) -> bool {
+    matches!(&error.kind, ClientErrorKind::Reqwest(e) if e.is_connect() || e.is_timeout())
+}
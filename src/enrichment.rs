@@ -0,0 +1,359 @@
+use crate::{
+    activity::{format_duration, to_sol},
+    blacklist::Blacklist,
+    endpoints::RpcEndpoint,
+    flow::SolFlow,
+    impersonation::KnownTokens,
+    metadata::MetadataResolver,
+    models::{AlertSeverity, AlertType},
+    oracle::fetch_bonding_curve,
+    wallet_monitor::{format_f64, SeenTokens, SOL_DECIMALS},
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use log::{debug, warn};
+use serde::Deserialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::{Mutex, RwLock},
+    task::JoinHandle,
+    time::{timeout, timeout_at, Instant},
+};
+
+const VOLUME_WINDOW: Duration = Duration::from_secs(5 * 60);
+const INITIAL_VIRTUAL_SOL: u64 = 30_000_000_000; // bonding curve 初始的虚拟 SOL 储备（lamports）
+
+/// 配置文件中的 `enrichment` 部分
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnrichmentConfig {
+    #[serde(default = "default_budget_ms")]
+    pub budget_ms: u64,  // 提醒最多等待补充信息的时间，超出的部分作为后续消息发送
+    pub enrichers: Vec<EnricherConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnricherConfig {
+    pub name: String,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default)]
+    pub required: bool,  // 非 critical 提醒等待它到自身超时，不受总预算限制
+}
+
+fn default_budget_ms() -> u64 {
+    1000
+}
+
+fn default_timeout_ms() -> u64 {
+    500
+}
+
+/// 需要补充信息的提醒
+pub struct EnrichContext {
+    pub mint: String,
+    pub alert_type: AlertType,
+}
+
+/// 为提醒补充一段信息，没有可补充的内容时返回 None
+#[async_trait]
+pub trait Enricher: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn enrich(&self, ctx: &EnrichContext) -> Result<Option<String>>;
+}
+
+struct Stage {
+    enricher: Arc<dyn Enricher>,
+    timeout: Duration,
+    required: bool,
+}
+
+/// 提醒的补充信息流水线。
+///
+/// 所有 enricher 并发执行，各自有超时。提醒最多等待总预算，之后先发送已有的补充信息，
+/// 其余 enricher 完成后再作为后续消息发送；必需的 enricher 等待到自身超时，
+/// 但 critical 提醒一律只等待总预算。超时或失败的 enricher 被跳过，结果按配置顺序拼接
+#[derive(Default)]
+pub struct EnrichmentPipeline {
+    stages: Vec<Stage>,
+    budget: Duration,
+}
+
+type StageResult = Result<Option<String>, String>;  // Err 为失败原因
+
+/// 在预算内完成的补充信息
+pub struct Enrichment {
+    pub sections: Vec<String>,
+    pub late: Option<LateEnrichment>,  // 超出预算仍在运行的 enricher
+}
+
+/// 超出预算的 enricher，完成后的结果作为后续消息发送
+pub struct LateEnrichment {
+    mint: String,
+    stages: Vec<(&'static str, JoinHandle<StageResult>)>,
+}
+
+impl LateEnrichment {
+    /// 等待剩余的 enricher 完成，它们仍受各自超时的限制
+    pub async fn finish(self) -> Vec<String> {
+        let mut sections = Vec::new();
+        for (name, stage) in self.stages {
+            sections.extend(collect(&self.mint, name, stage.await));
+        }
+        sections
+    }
+}
+
+fn collect(mint: &str, name: &str, result: Result<StageResult, tokio::task::JoinError>) -> Option<String> {
+    match result.map_err(|e| e.to_string()).and_then(|result| result) {
+        Ok(section) => section,
+        Err(reason) => {
+            warn!("Enricher {} skipped for {}: {}", name, mint, reason);
+            None
+        }
+    }
+}
+
+impl EnrichmentPipeline {
+    pub fn new(config: &EnrichmentConfig, sources: &EnrichmentSources) -> Result<Self> {
+        let stages = config
+            .enrichers
+            .iter()
+            .map(|stage| {
+                Ok(Stage {
+                    enricher: build(&stage.name, sources)?,
+                    timeout: Duration::from_millis(stage.timeout_ms),
+                    required: stage.required,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            stages,
+            budget: Duration::from_millis(config.budget_ms),
+        })
+    }
+
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    pub async fn run(&self, ctx: EnrichContext) -> Enrichment {
+        let critical = ctx.alert_type.severity() == AlertSeverity::Critical;
+        let deadline = Instant::now() + self.budget;
+        let ctx = Arc::new(ctx);
+        let running: Vec<_> = self
+            .stages
+            .iter()
+            .map(|stage| {
+                let enricher = stage.enricher.clone();
+                let ctx = ctx.clone();
+                let limit = stage.timeout;
+                let handle = tokio::spawn(async move {
+                    match timeout(limit, enricher.enrich(&ctx)).await {
+                        Ok(result) => result.map_err(|e| format!("{:?}", e)),
+                        Err(_) => Err(format!("timed out after {}ms", limit.as_millis())),
+                    }
+                });
+                (stage, handle)
+            })
+            .collect();
+
+        let mut sections = Vec::new();
+        let mut late = Vec::new();
+        for (stage, mut handle) in running {
+            let name = stage.enricher.name();
+            let waited = if stage.required && !critical {
+                Ok((&mut handle).await)
+            } else {
+                timeout_at(deadline, &mut handle).await
+            };
+            match waited {
+                Ok(result) => sections.extend(collect(&ctx.mint, name, result)),
+                Err(_) => late.push((name, handle)),
+            }
+        }
+        if !late.is_empty() {
+            debug!(
+                "Enrichment of {} exceeded the {}ms budget, deferring {}",
+                ctx.mint,
+                self.budget.as_millis(),
+                late.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+            );
+        }
+        Enrichment {
+            sections,
+            late: (!late.is_empty()).then(|| LateEnrichment {
+                mint: ctx.mint.clone(),
+                stages: late,
+            }),
+        }
+    }
+}
+
+/// enricher 使用的监控状态
+#[derive(Clone)]
+pub struct EnrichmentSources {
+    pub token_first_seen: Arc<Mutex<SeenTokens>>,
+    pub blacklist: Arc<RwLock<Blacklist>>,
+    pub known_tokens: Arc<RwLock<KnownTokens>>,
+    pub flow: Arc<SolFlow>,
+    pub rpc: Option<RpcEndpoint>,
+    pub metadata: Arc<MetadataResolver>,
+}
+
+fn build(name: &str, sources: &EnrichmentSources) -> Result<Arc<dyn Enricher>> {
+    Ok(match name {
+        "metadata" => Arc::new(MetadataEnricher { sources: sources.clone() }),
+        "risk" => Arc::new(RiskEnricher { sources: sources.clone() }),
+        "volume" => Arc::new(VolumeEnricher { flow: sources.flow.clone() }),
+        "curve" => {
+            let rpc = sources.rpc.as_ref().ok_or_else(|| anyhow!("The curve enricher requires RPC_URL or HELIUS_API_KEY"))?;
+            Arc::new(CurveEnricher { client: rpc.client() })
+        }
+        "links" => Arc::new(LinksEnricher),
+        _ => return Err(anyhow!("Unknown enricher '{}', expected metadata, risk, volume, curve or links", name)),
+    })
+}
+
+/// 代币名称、符号和年龄。没有看到 CreateEvent 的代币使用查询到的元数据
+struct MetadataEnricher {
+    sources: EnrichmentSources,
+}
+
+#[async_trait]
+impl Enricher for MetadataEnricher {
+    fn name(&self) -> &'static str {
+        "metadata"
+    }
+
+    async fn enrich(&self, ctx: &EnrichContext) -> Result<Option<String>> {
+        let seen = self.sources.token_first_seen.lock().await.get(&ctx.mint).cloned();
+        let names = match seen.as_ref().and_then(|seen| seen.names.clone()) {
+            Some(names) => Some(names),
+            // 提醒不等待查询，只使用后台已经查到的元数据
+            None => self.sources.metadata.cached(&ctx.mint).map(|metadata| (metadata.name, metadata.symbol)),
+        };
+        let age = seen.map(|seen| format_duration(Utc::now().timestamp() - seen.at));
+        Ok(match (names, age) {
+            (Some((name, symbol)), Some(age)) => Some(format!("🏷 {} (${}) | age {}", escape_html(&name), escape_html(&symbol), age)),
+            (Some((name, symbol)), None) => Some(format!("🏷 {} (${})", escape_html(&name), escape_html(&symbol))),
+            (None, Some(age)) => Some(format!("🏷 first seen {} ago", age)),
+            (None, None) => None,
+        })
+    }
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// 按黑名单、冒名和代币年龄估算的风险分
+struct RiskEnricher {
+    sources: EnrichmentSources,
+}
+
+#[async_trait]
+impl Enricher for RiskEnricher {
+    fn name(&self) -> &'static str {
+        "risk"
+    }
+
+    async fn enrich(&self, ctx: &EnrichContext) -> Result<Option<String>> {
+        let mut score = 0;
+        let mut reasons = Vec::new();
+        let listed_by = self.sources.blacklist.read().await.sources_of(&ctx.mint).len();
+        if listed_by > 0 {
+            score += 60;
+            reasons.push(format!("blacklisted by {} feed(s)", listed_by));
+        }
+        let seen = self.sources.token_first_seen.lock().await.get(&ctx.mint).cloned();
+        if let Some((name, symbol)) = seen.as_ref().and_then(|seen| seen.names.as_ref()) {
+            if let Some(known) = self.sources.known_tokens.read().await.impersonated(&ctx.mint, name, symbol) {
+                score += 30;
+                reasons.push(format!("impersonates {}", escape_html(&known.symbol)));
+            }
+        }
+        if let Some(seen) = seen.filter(|seen| seen.created) {
+            if Utc::now().timestamp() - seen.at < 5 * 60 {
+                score += 10;
+                reasons.push("younger than 5m".to_string());
+            }
+        }
+        Ok(Some(if reasons.is_empty() {
+            format!("🛡 Risk: {}/100", score)
+        } else {
+            format!("🛡 Risk: {}/100 ({})", score, reasons.join(", "))
+        }))
+    }
+}
+
+/// 最近 5 分钟的成交量
+struct VolumeEnricher {
+    flow: Arc<SolFlow>,
+}
+
+#[async_trait]
+impl Enricher for VolumeEnricher {
+    fn name(&self) -> &'static str {
+        "volume"
+    }
+
+    async fn enrich(&self, ctx: &EnrichContext) -> Result<Option<String>> {
+        let flow = self.flow.summary(&ctx.mint, VOLUME_WINDOW);
+        if flow.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(format!(
+            "📊 5m volume: {} SOL ({} buys {} SOL / {} sells {} SOL)",
+            format_f64(to_sol(flow.bought + flow.sold)),
+            flow.buys,
+            format_f64(to_sol(flow.bought)),
+            flow.sells,
+            format_f64(to_sol(flow.sold))
+        )))
+    }
+}
+
+/// bonding curve 的进度，需要 RPC
+struct CurveEnricher {
+    client: RpcClient,
+}
+
+#[async_trait]
+impl Enricher for CurveEnricher {
+    fn name(&self) -> &'static str {
+        "curve"
+    }
+
+    async fn enrich(&self, ctx: &EnrichContext) -> Result<Option<String>> {
+        let curve = fetch_bonding_curve(&self.client, &ctx.mint).await?;
+        if curve.complete {
+            return Ok(Some("📈 Bonding curve complete, trading on AMM".to_string()));
+        }
+        let raised = curve.virtual_sol_reserves.saturating_sub(INITIAL_VIRTUAL_SOL) as f64 / 10f64.powi(SOL_DECIMALS as i32);
+        Ok(Some(format!("📈 Bonding curve: {} SOL raised", format_f64(raised))))
+    }
+}
+
+/// 常用的代币页面链接
+struct LinksEnricher;
+
+#[async_trait]
+impl Enricher for LinksEnricher {
+    fn name(&self) -> &'static str {
+        "links"
+    }
+
+    async fn enrich(&self, ctx: &EnrichContext) -> Result<Option<String>> {
+        Ok(Some(format!(
+            "🔗 <a href=\"https://pump.fun/coin/{mint}\">pump.fun</a> | \
+            <a href=\"https://dexscreener.com/solana/{mint}\">DexScreener</a> | \
+            <a href=\"https://solscan.io/token/{mint}\">Solscan</a>",
+            mint = ctx.mint
+        )))
+    }
+}
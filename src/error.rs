@@ -0,0 +1,67 @@
+use std::{error::Error, fmt};
+
+type Source = Box<dyn Error + Send + Sync>;
+
+/// 对外接口的错误分类，调用方可以按类别处理，例如配置错误直接退出、RPC 错误稍后重试。
+///
+/// 每个类别保留原始错误，`Display` 输出类别和原始错误的说明
+#[derive(Debug)]
+pub enum MonitorError {
+    Config(Source),  // 配置或输入无效
+    Ws(Source),      // WebSocket 连接、订阅或读取失败
+    Decode(Source),  // 链上事件无法解码
+    Sink(Source),    // 提醒无法放入队列或投递
+    Rpc(Source),     // RPC 请求失败或返回的数据无效
+}
+
+impl MonitorError {
+    pub fn config(source: impl Into<Source>) -> Self {
+        Self::Config(source.into())
+    }
+
+    pub fn ws(source: impl Into<Source>) -> Self {
+        Self::Ws(source.into())
+    }
+
+    pub fn decode(source: impl Into<Source>) -> Self {
+        Self::Decode(source.into())
+    }
+
+    pub fn sink(source: impl Into<Source>) -> Self {
+        Self::Sink(source.into())
+    }
+
+    pub fn rpc(source: impl Into<Source>) -> Self {
+        Self::Rpc(source.into())
+    }
+
+    fn inner(&self) -> &Source {
+        match self {
+            Self::Config(source)
+            | Self::Ws(source)
+            | Self::Decode(source)
+            | Self::Sink(source)
+            | Self::Rpc(source) => source,
+        }
+    }
+}
+
+impl fmt::Display for MonitorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            Self::Config(_) => "Configuration error",
+            Self::Ws(_) => "WebSocket error",
+            Self::Decode(_) => "Decode error",
+            Self::Sink(_) => "Alert sink error",
+            Self::Rpc(_) => "RPC error",
+        };
+        write!(f, "{}: {}", kind, self.inner())
+    }
+}
+
+impl Error for MonitorError {
+    // 原始错误的说明已经包含在 Display 中，错误链从它的来源继续
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.inner().source()
+    }
+}
@@ -0,0 +1,250 @@
+use crate::enrichment::escape_html;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::{info, warn};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use teloxide::{
+    prelude::*,
+    types::{CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, MessageId, ParseMode},
+};
+
+pub(crate) const ACK_PREFIX: &str = "ack:";  // 确认按钮的回调数据前缀，其余的回调属于交易确认和交易标注
+
+/// 配置文件中的 `escalation` 部分，只用于 critical 提醒
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EscalationConfig {
+    #[serde(default)]
+    pub telegram_mentions: Vec<String>,  // Telegram 用户名，附在消息末尾
+    #[serde(default)]
+    pub discord_roles: Vec<String>,  // Discord 角色 ID，发送时提及
+    #[serde(default = "default_repeat_secs")]
+    pub repeat_secs: u64,  // 没有确认时重发的间隔
+    #[serde(default)]
+    pub schedule_secs: Vec<u64>,  // 依次使用的重发间隔，用完后一直使用最后一个，代替 `repeat_secs`
+    #[serde(default = "default_max_repeats")]
+    pub max_repeats: u32,  // 最多重发的次数，之后不再提醒
+}
+
+fn default_repeat_secs() -> u64 {
+    300
+}
+
+fn default_max_repeats() -> u32 {
+    6
+}
+
+impl EscalationConfig {
+    /// 附在 Telegram 消息末尾的提及，例如 `@alice @bob`
+    pub fn telegram_mentions(&self) -> Option<String> {
+        if self.telegram_mentions.is_empty() {
+            return None;
+        }
+        let mentions: Vec<String> = self
+            .telegram_mentions
+            .iter()
+            .map(|name| format!("@{}", escape_html(name.trim_start_matches('@'))))
+            .collect();
+        Some(mentions.join(" "))
+    }
+
+    /// 第 `repeats` 次重发之后等待的时间
+    fn delay_after(&self, repeats: u32) -> i64 {
+        let secs = match self.schedule_secs.last() {
+            Some(last) => *self.schedule_secs.get(repeats as usize).unwrap_or(last),
+            None => self.repeat_secs,
+        };
+        secs as i64
+    }
+}
+
+/// 等待确认的提醒，`/ack` 不带参数时列出
+pub struct Unacknowledged {
+    pub id: u64,
+    pub title: String,  // 提醒的第一行
+    pub repeats: u32,
+    pub sent_at: i64,
+}
+
+/// 等待确认的 critical 提醒
+struct Pending {
+    text: String,  // 提醒的内容，不含重发的前缀
+    message_ids: Vec<MessageId>,  // 首次发送和每次重发的消息，确认后都去掉按钮
+    sent_at: i64,
+    next_at: i64,
+    repeats: u32,
+}
+
+/// critical 提醒的升级：Telegram 消息带 ID 和确认按钮，没有人按下按钮或发送 `/ack <id>` 时
+/// 按 `schedule_secs`（或每隔 `repeat_secs`）重发，最多 `max_repeats` 次。
+/// 按钮的回调和命令由 `TelegramBot` 接收，重发也在它的轮询中检查
+pub struct Escalations {
+    profile: String,
+    bot: Bot,
+    chat_id: i64,
+    topic_id: Option<i32>,
+    config: EscalationConfig,
+    pending: Mutex<HashMap<u64, Pending>>,
+    next_id: AtomicU64,
+}
+
+impl Escalations {
+    pub fn new(profile: &str, bot_token: &str, chat_id: i64, topic_id: Option<i32>, config: EscalationConfig) -> Self {
+        Self {
+            profile: profile.to_string(),
+            bot: Bot::new(bot_token),
+            chat_id,
+            topic_id,
+            config,
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn config(&self) -> &EscalationConfig {
+        &self.config
+    }
+
+    /// 发送 critical 提醒并等待确认，消息末尾附上确认用的 ID
+    pub async fn send(&self, text: &str) -> Result<()> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let text = format!("{}\n\nAlert ID: <code>{}</code> · /ack {}", text, id, id);
+        let message_id = self.send_message(id, &text).await?;
+        let now = Utc::now().timestamp();
+        self.pending.lock().unwrap().insert(
+            id,
+            Pending {
+                text,
+                message_ids: vec![message_id],
+                sent_at: now,
+                next_at: now + self.config.delay_after(0),
+                repeats: 0,
+            },
+        );
+        Ok(())
+    }
+
+    /// 还在等待确认的提醒，按 ID 排列
+    pub fn unacknowledged(&self) -> Vec<Unacknowledged> {
+        let mut pending: Vec<Unacknowledged> = self
+            .pending
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, pending)| Unacknowledged {
+                id: *id,
+                title: pending.text.lines().next().unwrap_or_default().to_string(),
+                repeats: pending.repeats,
+                sent_at: pending.sent_at,
+            })
+            .collect();
+        pending.sort_by_key(|pending| pending.id);
+        pending
+    }
+
+    /// 确认提醒，停止重发。提醒不存在或已经确认时返回 false
+    pub async fn acknowledge(&self, id: u64, user: &str) -> bool {
+        let Some(pending) = self.pending.lock().unwrap().remove(&id) else {
+            return false;
+        };
+        info!("[{}] Critical alert {} acknowledged by {}", self.profile, id, user);
+        self.close(&pending, user).await;
+        true
+    }
+
+    async fn send_message(&self, id: u64, text: &str) -> Result<MessageId> {
+        let keyboard = InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+            "✅ Acknowledge",
+            format!("{}{}", ACK_PREFIX, id),
+        )]]);
+        let sent = self
+            .bot
+            .send_message(ChatId(self.chat_id), text)
+            .message_thread_id(self.topic_id.unwrap_or(0))
+            .parse_mode(ParseMode::Html)
+            .reply_markup(keyboard)
+            .await
+            .map_err(|e| anyhow!("Failed to send telegram message: {}", e))?;
+        Ok(sent.id)
+    }
+
+    /// 重发到期的提醒，重发次数用完的不再等待确认
+    pub async fn repeat_due(&self) {
+        let now = Utc::now().timestamp();
+        let due: Vec<(u64, String, u32)> = {
+            let mut pending = self.pending.lock().unwrap();
+            let exhausted: Vec<u64> = pending
+                .iter()
+                .filter(|(_, pending)| pending.repeats >= self.config.max_repeats)
+                .map(|(id, _)| *id)
+                .collect();
+            for id in exhausted {
+                pending.remove(&id);
+                warn!("[{}] Critical alert {} was not acknowledged after {} repeat(s)", self.profile, id, self.config.max_repeats);
+            }
+            pending
+                .iter_mut()
+                .filter(|(_, pending)| now >= pending.next_at)
+                .map(|(id, pending)| {
+                    pending.repeats += 1;
+                    pending.next_at = now + self.config.delay_after(pending.repeats);
+                    (*id, pending.text.clone(), pending.repeats)
+                })
+                .collect()
+        };
+        for (id, text, repeats) in due {
+            info!("[{}] Repeating unacknowledged critical alert {} ({}/{})", self.profile, id, repeats, self.config.max_repeats);
+            let text = format!("🔁 Not acknowledged, repeat {}/{}\n\n{}", repeats, self.config.max_repeats, text);
+            match self.send_message(id, &text).await {
+                Ok(message_id) => {
+                    if let Some(pending) = self.pending.lock().unwrap().get_mut(&id) {
+                        pending.message_ids.push(message_id);
+                    }
+                }
+                Err(e) => warn!("[{}] Failed to repeat critical alert {}: {}", self.profile, id, e),
+            }
+        }
+    }
+
+    /// 处理确认按钮的回调
+    pub async fn answer(&self, query: CallbackQuery) {
+        let chat_id = query.message.as_ref().map(|message| message.chat.id.0);
+        let id = query
+            .data
+            .as_deref()
+            .and_then(|data| data.strip_prefix(ACK_PREFIX))
+            .and_then(|id| id.parse::<u64>().ok())
+            .filter(|_| chat_id == Some(self.chat_id));
+        let user = query.from.username.clone().unwrap_or_else(|| query.from.first_name.clone());
+        let acknowledged = match id {
+            Some(id) => self.acknowledge(id, &user).await,
+            None => false,
+        };
+        let reply = if acknowledged { "Acknowledged" } else { "This alert is no longer pending" };
+        if let Err(e) = self.bot.answer_callback_query(query.id).text(reply).await {
+            warn!("[{}] Failed to answer acknowledgement: {}", self.profile, e);
+        }
+    }
+
+    /// 去掉所有消息的按钮，并在消息末尾注明确认人
+    async fn close(&self, pending: &Pending, user: &str) {
+        let text = format!("{}\n\n✅ Acknowledged by {}", pending.text, escape_html(user));
+        for message_id in &pending.message_ids {
+            if let Err(e) = self
+                .bot
+                .edit_message_text(ChatId(self.chat_id), *message_id, text.as_str())
+                .parse_mode(ParseMode::Html)
+                .await
+            {
+                warn!("[{}] Failed to update acknowledged alert: {}", self.profile, e);
+            }
+        }
+    }
+}
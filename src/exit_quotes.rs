@@ -0,0 +1,136 @@
+use crate::{
+    display::PUMP_FEE_RATE,
+    endpoints::RpcEndpoint,
+    oracle::{fetch_bonding_curve, BondingCurve, SOL_MINT},
+    wallet_monitor::SOL_DECIMALS,
+};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const JUPITER_QUOTE_URL: &str = "https://lite-api.jup.ag/swap/v1/quote";
+const QUOTE_SLIPPAGE_BPS: &str = "50";
+
+/// 卖出全部持仓的估算
+#[derive(Debug, Clone, Copy)]
+pub struct ExitEstimate {
+    pub out_sol: f64,       // 能拿到的 SOL
+    pub price_impact: f64,  // 相对现价的价格冲击（0.05 表示 5%）
+}
+
+/// 按当前流动性估算卖出全部持仓的结果：仍在 bonding curve 上的代币按曲线的虚拟储备计算，
+/// 已毕业的代币用 Jupiter 报价（已包含路由的价格冲击）
+pub struct ExitQuoter {
+    client: reqwest::Client,
+    rpc: RpcClient,
+    ttl: Duration,
+    graduated: Mutex<HashSet<String>>,  // 毕业不可逆，确认后不再查询曲线
+    curves: Mutex<HashMap<String, (BondingCurve, Instant)>>,  // 未毕业代币的曲线状态
+    quotes: Mutex<HashMap<String, (u64, ExitEstimate, Instant)>>,  // mint -> (数量, Jupiter 报价, 时间)
+}
+
+impl ExitQuoter {
+    pub fn new(rpc: &RpcEndpoint, ttl: Duration) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            rpc: rpc.client(),
+            ttl,
+            graduated: Mutex::new(HashSet::new()),
+            curves: Mutex::new(HashMap::new()),
+            quotes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 估算卖出 `amount`（原始单位）的结果
+    pub async fn estimate(&self, mint: &str, amount: u64) -> Result<Option<ExitEstimate>> {
+        if amount == 0 {
+            return Ok(None);
+        }
+        match self.bonding_curve(mint).await? {
+            Some(curve) => Ok(curve_exit(&curve, amount)),
+            None => self.jupiter_quote(mint, amount).await.map(Some),
+        }
+    }
+
+    /// 未毕业代币的曲线状态，已毕业时为 None。曲线状态缓存一个 TTL
+    async fn bonding_curve(&self, mint: &str) -> Result<Option<BondingCurve>> {
+        if self.graduated.lock().unwrap().contains(mint) {
+            return Ok(None);
+        }
+        if let Some((curve, fetched_at)) = self.curves.lock().unwrap().get(mint) {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(Some(*curve));
+            }
+        }
+
+        let curve = fetch_bonding_curve(&self.rpc, mint).await?;
+        let mut curves = self.curves.lock().unwrap();
+        curves.retain(|_, (_, fetched_at)| fetched_at.elapsed() < self.ttl);
+        if curve.complete {
+            curves.remove(mint);
+            self.graduated.lock().unwrap().insert(mint.to_string());
+            return Ok(None);
+        }
+        curves.insert(mint.to_string(), (curve, Instant::now()));
+        Ok(Some(curve))
+    }
+
+    async fn jupiter_quote(&self, mint: &str, amount: u64) -> Result<ExitEstimate> {
+        if let Some((quoted_amount, estimate, quoted_at)) = self.quotes.lock().unwrap().get(mint) {
+            if *quoted_amount == amount && quoted_at.elapsed() < self.ttl {
+                return Ok(*estimate);
+            }
+        }
+
+        let amount_param = amount.to_string();
+        let response: Value = self
+            .client
+            .get(JUPITER_QUOTE_URL)
+            .query(&[
+                ("inputMint", mint),
+                ("outputMint", SOL_MINT),
+                ("amount", amount_param.as_str()),
+                ("slippageBps", QUOTE_SLIPPAGE_BPS),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let (Some(out_lamports), Some(price_impact)) = (
+            response["outAmount"].as_str().and_then(|out| out.parse::<u64>().ok()),
+            response["priceImpactPct"].as_str().and_then(|impact| impact.parse::<f64>().ok()),
+        ) else {
+            return Err(anyhow!("Unexpected Jupiter quote response: {}", response));
+        };
+        let estimate = ExitEstimate {
+            out_sol: out_lamports as f64 / 10f64.powi(SOL_DECIMALS as i32),
+            price_impact: price_impact.abs(),
+        };
+
+        let mut quotes = self.quotes.lock().unwrap();
+        quotes.retain(|_, (_, _, quoted_at)| quoted_at.elapsed() < self.ttl);
+        quotes.insert(mint.to_string(), (amount, estimate, Instant::now()));
+        Ok(estimate)
+    }
+}
+
+/// 按恒定乘积曲线计算卖出：out = sol * Δ / (tokens + Δ)，冲击为 Δ / (tokens + Δ)，再扣除手续费
+fn curve_exit(curve: &BondingCurve, amount: u64) -> Option<ExitEstimate> {
+    if curve.virtual_token_reserves == 0 {
+        return None;
+    }
+    let tokens = curve.virtual_token_reserves as f64;
+    let sold = amount as f64;
+    let price_impact = sold / (tokens + sold);
+    let out_lamports = curve.virtual_sol_reserves as f64 * price_impact;
+    Some(ExitEstimate {
+        out_sol: out_lamports / 10f64.powi(SOL_DECIMALS as i32) * (1.0 - PUMP_FEE_RATE),
+        price_impact,
+    })
+}
@@ -0,0 +1,210 @@
+use crate::{backoff::Backoff, models::TradeSignal};
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
+use tokio::{sync::mpsc, time::sleep};
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use url::Url;
+
+const MAX_WATCHED: usize = 1000;  // 观察列表的上限，超出时移除最早到期的代币
+
+/// 配置文件 `inbound_signals` 中的一个信号来源：其他人的监控发送信号的 WebSocket
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InboundSource {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub rules: Vec<String>,  // 只接收这些规则的信号（没有来源信息的信号按信号名），空表示全部
+    #[serde(default)]
+    pub min_confidence: f64,
+    #[serde(default)]
+    pub action: InboundAction,
+    #[serde(default = "default_watch_mins")]
+    pub watch_mins: u64,  // 代币留在观察列表中的时间
+}
+
+/// 接收到的信号的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InboundAction {
+    #[default]
+    Watch,  // 只加入观察列表
+    Alert,  // 加入观察列表并发送提醒
+}
+
+fn default_watch_mins() -> u64 {
+    60
+}
+
+impl InboundSource {
+    /// 信号是否通过这个来源的筛选，不通过时返回原因
+    pub fn accepts(&self, signal: &TradeSignal, now: i64) -> Result<(), String> {
+        if signal.expires_at.is_some_and(|expires_at| now > expires_at) {
+            return Err("expired".to_string());
+        }
+        if Pubkey::from_str(&signal.mint).is_err() {
+            return Err("the mint is not a valid address".to_string());
+        }
+        let rule = signal.provenance.as_ref().map_or(&signal.signal, |provenance| &provenance.rule);
+        if !self.rules.is_empty() && !self.rules.contains(rule) {
+            return Err(format!("rule {} is not selected", rule));
+        }
+        // 没有来源信息的信号置信度未知，只在没有要求置信度时接收
+        let confidence = signal.provenance.as_ref().map_or(0.0, |provenance| provenance.confidence);
+        if self.min_confidence > 0.0 && confidence < self.min_confidence {
+            return Err(format!("confidence {:.2} is below {:.2}", confidence, self.min_confidence));
+        }
+        Ok(())
+    }
+}
+
+/// 观察列表中的代币
+#[derive(Debug, Clone)]
+pub struct WatchEntry {
+    pub mint: String,
+    pub source: String,
+    pub rule: String,
+    pub signal_price: Option<f64>,  // 信号中的价格（SOL）
+    pub last_price: Option<f64>,    // 之后观察到的最新成交价
+    pub added_at: i64,
+    pub expires_at: i64,
+}
+
+impl WatchEntry {
+    pub fn new(source: &InboundSource, signal: &TradeSignal, now: i64) -> Self {
+        Self {
+            mint: signal.mint.clone(),
+            source: source.name.clone(),
+            rule: signal.provenance.as_ref().map_or_else(|| signal.signal.clone(), |provenance| provenance.rule.clone()),
+            signal_price: signal.provenance.as_ref().map(|provenance| provenance.price).filter(|price| *price > 0.0),
+            last_price: None,
+            added_at: now,
+            expires_at: now + (source.watch_mins * 60) as i64,
+        }
+    }
+
+    /// 信号之后的价格变化（百分比），缺少任一价格时为 None
+    pub fn price_change(&self) -> Option<f64> {
+        let (signal, last) = (self.signal_price?, self.last_price?);
+        Some((last - signal) / signal * 100.0)
+    }
+}
+
+/// 由其他监控的信号加入的代币，到期后移除，最多 `MAX_WATCHED` 个。只保存在内存中
+#[derive(Debug, Default)]
+pub struct Watchlist {
+    entries: HashMap<String, WatchEntry>,
+}
+
+impl Watchlist {
+    /// 加入观察列表，返回是否是新加入的代币。已在列表中时只延长到期时间
+    pub fn add(&mut self, entry: WatchEntry) -> bool {
+        self.expire(entry.added_at);
+        match self.entries.get_mut(&entry.mint) {
+            Some(existing) => {
+                existing.expires_at = existing.expires_at.max(entry.expires_at);
+                false
+            }
+            None => {
+                if self.entries.len() >= MAX_WATCHED {
+                    let first = self.entries.values().min_by_key(|entry| entry.expires_at).map(|entry| entry.mint.clone());
+                    if let Some(mint) = first {
+                        self.entries.remove(&mint);
+                    }
+                }
+                self.entries.insert(entry.mint.clone(), entry);
+                true
+            }
+        }
+    }
+
+    /// 记录观察中的代币的成交价
+    pub fn observe(&mut self, mint: &str, price: f64) {
+        if let Some(entry) = self.entries.get_mut(mint) {
+            entry.last_price = Some(price);
+        }
+    }
+
+    pub fn get(&self, mint: &str) -> Option<&WatchEntry> {
+        self.entries.get(mint)
+    }
+
+    pub fn expire(&mut self, now: i64) {
+        self.entries.retain(|_, entry| entry.expires_at >= now);
+    }
+
+    /// 按加入时间从新到旧
+    pub fn entries(&self) -> Vec<WatchEntry> {
+        let mut entries: Vec<WatchEntry> = self.entries.values().cloned().collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.added_at));
+        entries
+    }
+}
+
+/// 订阅一个来源，把收到的信号交给 `sender`，断线后按退避重连，直到接收方关闭
+pub async fn subscribe(
+    profile: String,
+    source: Arc<InboundSource>,
+    reconnect_max: Duration,
+    sender: mpsc::Sender<(Arc<InboundSource>, TradeSignal)>,
+) {
+    let mut backoff = Backoff::new(Duration::from_secs(1), reconnect_max);
+    loop {
+        let reason = match read_signals(&profile, &source, &sender).await {
+            Ok(received) => {
+                // 收到过消息说明连接曾经正常，重新计算退避
+                if received {
+                    backoff.reset();
+                }
+                "connection closed".to_string()
+            }
+            Err(e) => format!("{:?}", e),
+        };
+        if sender.is_closed() {
+            return;
+        }
+        let delay = backoff.next_delay();
+        warn!(
+            "[{}] Signal source {} disconnected ({}), reconnecting in {}ms",
+            profile,
+            source.name,
+            reason,
+            delay.as_millis()
+        );
+        sleep(delay).await;
+    }
+}
+
+/// 建立一次连接并读取信号直到断开，返回是否收到过消息
+async fn read_signals(
+    profile: &str,
+    source: &Arc<InboundSource>,
+    sender: &mpsc::Sender<(Arc<InboundSource>, TradeSignal)>,
+) -> Result<bool> {
+    let (ws_stream, _) = connect_async(Url::parse(&source.url)?).await?;
+    let (mut write, mut read) = ws_stream.split();
+    info!("[{}] Subscribed to signals from {}", profile, source.name);
+
+    let mut received = false;
+    while let Some(msg) = read.next().await {
+        received = true;
+        match msg? {
+            Message::Text(text) => match serde_json::from_str::<TradeSignal>(&text) {
+                Ok(signal) => {
+                    if sender.send((source.clone(), signal)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => debug!("[{}] Ignoring message from {} that is not a signal: {}", profile, source.name, e),
+            },
+            Message::Ping(_) => write.send(Message::Pong(vec![])).await?,
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    Ok(received)
+}
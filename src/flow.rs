@@ -0,0 +1,77 @@
+use crate::activity::to_sol;
+use chrono::Utc;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// (时间, 是否买入, lamports)
+type FlowTrade = (i64, bool, u64);
+
+/// 一段时间内的买卖统计
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlowSummary {
+    pub buys: usize,
+    pub bought: u64,  // lamports
+    pub sells: usize,
+    pub sold: u64,    // lamports
+}
+
+impl FlowSummary {
+    pub fn is_empty(&self) -> bool {
+        self.buys + self.sells == 0
+    }
+
+    /// 净流入的 SOL，流出时为负
+    pub fn net_sol(&self) -> f64 {
+        to_sol(self.bought) - to_sol(self.sold)
+    }
+}
+
+/// 按代币统计所有交易者的 SOL 买卖流量，只记录目标钱包的交易和持仓代币的交易
+#[derive(Debug)]
+pub struct SolFlow {
+    retention: i64,  // 秒
+    trades: Mutex<HashMap<String, VecDeque<FlowTrade>>>,
+}
+
+impl SolFlow {
+    /// `retention` 是查询可用的最长时间窗口
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            retention: retention.as_secs() as i64,
+            trades: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record(&self, mint: &str, is_buy: bool, sol_amount: u64) {
+        let now = Utc::now().timestamp();
+        let mut trades = self.trades.lock().unwrap();
+        trades.retain(|_, window| {
+            while window.front().is_some_and(|(at, _, _)| now - at > self.retention) {
+                window.pop_front();
+            }
+            !window.is_empty()
+        });
+        trades.entry(mint.to_string()).or_default().push_back((now, is_buy, sol_amount));
+    }
+
+    /// 最近 `window` 内的买卖统计
+    pub fn summary(&self, mint: &str, window: Duration) -> FlowSummary {
+        let now = Utc::now().timestamp();
+        let window = window.as_secs() as i64;
+        let trades = self.trades.lock().unwrap();
+        let mut summary = FlowSummary::default();
+        for (_, is_buy, lamports) in trades.get(mint).into_iter().flatten().filter(|(at, _, _)| now - at <= window) {
+            if *is_buy {
+                summary.buys += 1;
+                summary.bought += lamports;
+            } else {
+                summary.sells += 1;
+                summary.sold += lamports;
+            }
+        }
+        summary
+    }
+}
@@ -0,0 +1,19 @@
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+
+/// 心跳的配置（`HEARTBEAT_URL`）
+#[derive(Debug, Clone)]
+pub struct HeartbeatConfig {
+    pub url: String,
+    pub interval: Duration,  // 两次心跳之间的间隔，期间处理过消息才发送
+}
+
+/// 请求心跳地址。healthchecks.io 等服务一段时间没有收到请求时发出通知，
+/// 所以进程还在运行但已经不再处理消息时也能发现
+pub async fn ping(client: &reqwest::Client, url: &str) -> Result<()> {
+    let status = client.get(url).send().await?.status();
+    if !status.is_success() {
+        return Err(anyhow!("Heartbeat URL returned {}", status));
+    }
+    Ok(())
+}
@@ -0,0 +1,233 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use log::warn;
+use serde::Serialize;
+use solana_client::{
+    client_error::{reqwest::StatusCode, ClientError, ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::RpcClientConfig,
+    rpc_request::RpcRequest,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+const RATE_LIMIT_COOLDOWN_SECS: i64 = 60;   // 被限流的 key 暂停使用的时间
+const QUOTA_COOLDOWN_SECS: i64 = 60 * 60;   // 额度用完或被拒绝的 key 暂停使用的时间
+
+/// 一个 key 为什么被暂停使用
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    RateLimited,  // HTTP 429
+    Exhausted,    // HTTP 401/402/403，额度用完或 key 失效
+}
+
+impl Limit {
+    /// 按 HTTP 状态码判断，其他状态不是 key 的问题，不轮换
+    pub fn from_status(status: u16) -> Option<Self> {
+        match status {
+            429 => Some(Limit::RateLimited),
+            401..=403 => Some(Limit::Exhausted),
+            _ => None,
+        }
+    }
+
+    fn cooldown_secs(self) -> i64 {
+        match self {
+            Limit::RateLimited => RATE_LIMIT_COOLDOWN_SECS,
+            Limit::Exhausted => QUOTA_COOLDOWN_SECS,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct KeyState {
+    key: String,
+    requests: AtomicU64,      // HTTP 请求和 WebSocket 连接次数
+    rate_limited: AtomicU64,  // 被限流或拒绝的次数
+    paused_until: AtomicI64,  // 暂停到这个时间（unix 秒）
+}
+
+/// 一个 key 的使用情况，`GET /keys` 返回
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyUsage {
+    pub key: String,  // 只显示最后 4 位
+    pub active: bool,
+    pub requests: u64,
+    pub rate_limited: u64,
+    pub paused_secs: i64,  // 剩余暂停时间，0 表示可用
+}
+
+/// 多个 Helius API key，当前 key 被限流或额度用完时轮换到下一个可用的 key
+pub struct HeliusKeys {
+    profile: String,
+    keys: Vec<KeyState>,
+    current: AtomicUsize,
+}
+
+impl fmt::Debug for HeliusKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 不输出完整的 key
+        let labels: Vec<String> = self.keys.iter().map(|state| label(&state.key)).collect();
+        f.debug_struct("HeliusKeys").field("keys", &labels).finish()
+    }
+}
+
+impl HeliusKeys {
+    pub fn new(profile: &str, keys: Vec<String>) -> Self {
+        Self {
+            profile: profile.to_string(),
+            keys: keys
+                .into_iter()
+                .map(|key| KeyState {
+                    key,
+                    requests: AtomicU64::new(0),
+                    rate_limited: AtomicU64::new(0),
+                    paused_until: AtomicI64::new(0),
+                })
+                .collect(),
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// 当前 key 的下标，同时记一次使用
+    fn acquire(&self) -> usize {
+        let index = self.current.load(Ordering::Relaxed);
+        self.keys[index].requests.fetch_add(1, Ordering::Relaxed);
+        index
+    }
+
+    fn rpc_url_of(&self, index: usize) -> String {
+        format!("https://mainnet.helius-rpc.com/?api-key={}", self.keys[index].key)
+    }
+
+    /// 用当前 key 建立 WebSocket 连接的地址，记一次使用。返回 key 的下标，连接被拒绝时传给 `report`
+    pub fn ws_url(&self) -> (usize, String) {
+        let index = self.acquire();
+        (index, format!("wss://mainnet.helius-rpc.com/?api-key={}", self.keys[index].key))
+    }
+
+    /// 用当前 key 请求 Helius REST API（例如 `/v0/addresses/{address}/transactions`）的地址，记一次使用。
+    /// 返回 key 的下标，被限流时传给 `report`
+    pub fn api_url(&self, path: &str) -> (usize, String) {
+        let index = self.acquire();
+        (index, format!("https://api.helius.xyz{}?api-key={}", path, self.keys[index].key))
+    }
+
+    /// 暂停被限流的 key 并轮换到下一个没有暂停的 key；都在暂停中时选最早恢复的。
+    /// 返回是否换了 key（只有一个 key 时不换）
+    pub fn report(&self, index: usize, limit: Limit) -> bool {
+        let now = Utc::now().timestamp();
+        let state = &self.keys[index];
+        state.rate_limited.fetch_add(1, Ordering::Relaxed);
+        state.paused_until.fetch_max(now + limit.cooldown_secs(), Ordering::Relaxed);
+
+        let next = (1..self.keys.len())
+            .map(|offset| (index + offset) % self.keys.len())
+            .find(|candidate| self.keys[*candidate].paused_until.load(Ordering::Relaxed) <= now)
+            .unwrap_or_else(|| {
+                (0..self.keys.len())
+                    .min_by_key(|candidate| self.keys[*candidate].paused_until.load(Ordering::Relaxed))
+                    .unwrap_or(index)
+            });
+        if next == index {
+            warn!("[{}] Helius key {} {:?}, no other key available", self.profile, label(&state.key), limit);
+            return false;
+        }
+        // 并发请求同时被限流时只轮换一次
+        if self.current.compare_exchange(index, next, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+            warn!(
+                "[{}] Helius key {} {:?}, switching to key {}",
+                self.profile,
+                label(&state.key),
+                limit,
+                label(&self.keys[next].key)
+            );
+        }
+        true
+    }
+
+    pub fn usage(&self) -> Vec<KeyUsage> {
+        let now = Utc::now().timestamp();
+        let current = self.current.load(Ordering::Relaxed);
+        self.keys
+            .iter()
+            .enumerate()
+            .map(|(index, state)| KeyUsage {
+                key: label(&state.key),
+                active: index == current,
+                requests: state.requests.load(Ordering::Relaxed),
+                rate_limited: state.rate_limited.load(Ordering::Relaxed),
+                paused_secs: (state.paused_until.load(Ordering::Relaxed) - now).max(0),
+            })
+            .collect()
+    }
+}
+
+/// 日志和 API 中显示的 key，只保留最后 4 位
+fn label(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    let tail: String = chars[chars.len().saturating_sub(4)..].iter().collect();
+    format!("…{}", tail)
+}
+
+/// 按 key 轮换的 HTTP RPC 客户端
+pub fn rpc_client(keys: &Arc<HeliusKeys>) -> RpcClient {
+    RpcClient::new_sender(
+        RotatingSender {
+            clients: (0..keys.len()).map(|index| RpcClient::new(keys.rpc_url_of(index))).collect(),
+            keys: keys.clone(),
+        },
+        RpcClientConfig::with_commitment(CommitmentConfig::default()),
+    )
+}
+
+/// 每个 key 一个 HTTP 客户端，当前 key 被限流时换下一个 key 重试，每个 key 最多试一次
+struct RotatingSender {
+    keys: Arc<HeliusKeys>,
+    clients: Vec<RpcClient>,
+}
+
+#[async_trait]
+impl RpcSender for RotatingSender {
+    async fn send(&self, request: RpcRequest, params: serde_json::Value) -> ClientResult<serde_json::Value> {
+        let mut attempts = 0;
+        loop {
+            let index = self.keys.acquire();
+            let error = match self.clients[index].send::<serde_json::Value>(request, params.clone()).await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            attempts += 1;
+            match limit_of(&error) {
+                Some(limit) if self.keys.report(index, limit) && attempts < self.keys.len() => continue,
+                _ => return Err(error),
+            }
+        }
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        self.keys.rpc_url_of(self.keys.current.load(Ordering::Relaxed))
+    }
+}
+
+fn limit_of(error: &ClientError) -> Option<Limit> {
+    match &error.kind {
+        ClientErrorKind::Reqwest(e) => e.status().as_ref().map(StatusCode::as_u16).and_then(Limit::from_status),
+        _ => None,
+    }
+}
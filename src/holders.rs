@@ -0,0 +1,146 @@
+use crate::{error::MonitorError, reconcile::TOKEN_PROGRAM_IDS};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+    time::Duration,
+};
+
+const TOKEN_ACCOUNT_LEN: u64 = 165;  // SPL Token 账户大小，Token-2022 账户带扩展时更大
+const AMOUNT_OFFSET: usize = 64;     // 代币账户中 amount 字段的位置，前面是 mint 和 owner
+const MIN_HOLDER_CHANGE: u64 = 10;   // 持有人很少时百分比变化没有意义
+
+/// 持有人数量变化的提醒条件
+#[derive(Debug, Clone, Copy)]
+pub struct HolderRule {
+    pub interval: Duration,  // 采样间隔
+    pub window: Duration,    // 与多久之前的样本比较
+    pub change_percent: f64,
+}
+
+/// 查询代币的持有人数量，即余额不为 0 的代币账户数。
+///
+/// 只取每个账户的 amount 字段，但热门代币仍可能有上万个账户，采样间隔不宜太短
+pub async fn count_holders(client: &RpcClient, mint: &str) -> Result<u64, MonitorError> {
+    let mint_key = Pubkey::from_str(mint).map_err(|e| MonitorError::config(format!("Invalid mint {}: {}", mint, e)))?;
+    let mut holders = 0;
+    for (index, program_id) in TOKEN_PROGRAM_IDS.iter().enumerate() {
+        let program_id = Pubkey::from_str(program_id).map_err(MonitorError::config)?;
+        let mut filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, mint_key.as_ref()))];
+        if index == 0 {
+            filters.push(RpcFilterType::DataSize(TOKEN_ACCOUNT_LEN));
+        }
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                data_slice: Some(UiDataSliceConfig { offset: AMOUNT_OFFSET, length: 8 }),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = client
+            .get_program_accounts_with_config(&program_id, config)
+            .await
+            .map_err(|e| MonitorError::rpc(format!("getProgramAccounts failed for {}: {}", mint, e)))?;
+        holders += accounts
+            .iter()
+            .filter(|(_, account)| account.data.iter().any(|byte| *byte != 0))
+            .count() as u64;
+    }
+    Ok(holders)
+}
+
+/// 持有人数量在窗口内的明显变化
+#[derive(Debug, Clone)]
+pub struct HolderChange {
+    pub mint: String,
+    pub before: u64,
+    pub now: u64,
+    pub window_secs: i64,
+}
+
+impl HolderChange {
+    pub fn percent(&self) -> f64 {
+        (self.now as f64 - self.before as f64) / self.before as f64 * 100.0
+    }
+
+    pub fn message(&self) -> String {
+        let (icon, title) = if self.now > self.before {
+            ("👥", "Holders growing fast")
+        } else {
+            ("🏃", "Holders leaving")
+        };
+        format!(
+            "{} {}\n\n\
+            Token: <a href=\"https://gmgn.ai/sol/token/{mint}\">{mint}</a>\n\
+            Holders: {} → {} ({:+.1}%) within {}",
+            icon,
+            title,
+            self.before,
+            self.now,
+            self.percent(),
+            crate::activity::format_duration(self.window_secs),
+            mint = self.mint
+        )
+    }
+}
+
+/// 持仓代币的持有人数量样本
+#[derive(Debug)]
+pub struct HolderTracker {
+    rule: HolderRule,
+    samples: HashMap<String, VecDeque<(i64, u64)>>,  // 代币 -> (时间, 持有人数量)
+}
+
+impl HolderTracker {
+    pub fn new(rule: HolderRule) -> Self {
+        Self {
+            rule,
+            samples: HashMap::new(),
+        }
+    }
+
+    pub fn rule(&self) -> HolderRule {
+        self.rule
+    }
+
+    /// 只保留仍在持仓中的代币
+    pub fn retain(&mut self, held: impl Fn(&str) -> bool) {
+        self.samples.retain(|mint, _| held(mint));
+    }
+
+    /// 记录一次采样，与窗口内最早的样本相比变化超过阈值时返回变化。
+    /// 提醒后重新开始比较，同一轮增长不会重复提醒
+    pub fn record(&mut self, mint: &str, holders: u64, now: i64) -> Option<HolderChange> {
+        let window = self.rule.window.as_secs() as i64;
+        let samples = self.samples.entry(mint.to_string()).or_default();
+        while samples.front().is_some_and(|(at, _)| now - at > window) {
+            samples.pop_front();
+        }
+        let change = samples.front().and_then(|(at, before)| {
+            let before = *before;
+            let change = HolderChange {
+                mint: mint.to_string(),
+                before,
+                now: holders,
+                window_secs: now - at,
+            };
+            (before > 0
+                && before.abs_diff(holders) >= MIN_HOLDER_CHANGE
+                && change.percent().abs() >= self.rule.change_percent)
+                .then_some(change)
+        });
+        if change.is_some() {
+            samples.clear();
+        }
+        samples.push_back((now, holders));
+        change
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, fmt};
+
+pub const DEFAULT_KNOWN_TOKENS_URL: &str = "https://lite-api.jup.ag/tokens/v2/tag?query=verified";
+
+/// 知名代币
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KnownToken {
+    pub mint: String,
+    pub name: String,
+    pub symbol: String,
+}
+
+impl fmt::Display for KnownToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}) {}", self.name, self.symbol, self.mint)
+    }
+}
+
+/// 知名代币的名称和符号索引，用于识别冒名代币
+#[derive(Debug, Default)]
+pub struct KnownTokens {
+    by_name: HashMap<String, KnownToken>,
+    by_symbol: HashMap<String, KnownToken>,
+}
+
+impl KnownTokens {
+    /// 名称或符号（忽略大小写、空格和标点）与某个知名代币相同、但 mint 不同时返回该代币
+    pub fn impersonated(&self, mint: &str, name: &str, symbol: &str) -> Option<&KnownToken> {
+        [self.by_symbol.get(&normalize(symbol)), self.by_name.get(&normalize(name))]
+            .into_iter()
+            .flatten()
+            .find(|known| known.mint != mint)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_symbol.len()
+    }
+
+    fn insert(&mut self, token: KnownToken) {
+        // 同名的代币保留列表中靠前的一个
+        for (index, key) in [(&mut self.by_name, normalize(&token.name)), (&mut self.by_symbol, normalize(&token.symbol))] {
+            if !key.is_empty() {
+                index.entry(key).or_insert_with(|| token.clone());
+            }
+        }
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 拉取知名代币列表：对象数组，地址字段为 `id`、`address` 或 `mint`，以及 `name` 和 `symbol`
+pub async fn fetch(client: &reqwest::Client, url: &str) -> Result<KnownTokens> {
+    let response: Value = client.get(url).send().await?.error_for_status()?.json().await?;
+    let items = response
+        .as_array()
+        .ok_or_else(|| anyhow!("Known token list {} is not an array", url))?;
+
+    let mut tokens = KnownTokens::default();
+    for item in items {
+        let mint = ["id", "address", "mint"].iter().find_map(|field| item[field].as_str());
+        if let (Some(mint), Some(name), Some(symbol)) = (mint, item["name"].as_str(), item["symbol"].as_str()) {
+            tokens.insert(KnownToken {
+                mint: mint.to_string(),
+                name: name.to_string(),
+                symbol: symbol.to_string(),
+            });
+        }
+    }
+    Ok(tokens)
+}
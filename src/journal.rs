@@ -0,0 +1,118 @@
+use crate::mints::default_decimals;
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+pub const JOURNAL_FILE: &str = "trades.jsonl";
+pub const SANDWICH_FILE: &str = "sandwiches.jsonl";
+pub const ANNOTATION_FILE: &str = "annotations.jsonl";
+
+/// 目标钱包的一笔交易
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub timestamp: i64,
+    pub signature: String,
+    pub wallet: String,
+    pub mint: String,
+    pub is_buy: bool,
+    pub sol_amount: u64,    // lamports
+    pub token_amount: u64,  // 原始数量
+    #[serde(default = "default_decimals")]
+    pub decimals: u8,  // 代币的小数位数
+    pub price: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_age_secs: Option<i64>,  // 距代币 CreateEvent 的时间，代币在监控启动前创建时为 None
+}
+
+/// 目标钱包被夹的一笔交易
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandwichRecord {
+    pub timestamp: i64,
+    pub slot: u64,
+    pub signature: String,
+    pub wallet: String,
+    pub mint: String,
+    pub attacker: String,
+    pub front_signature: String,
+    pub back_signature: String,
+    pub loss: f64,  // SOL
+}
+
+/// 在 Telegram 中给目标钱包的一笔交易打的标签，按签名对应交易日志中的交易
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeAnnotation {
+    pub timestamp: i64,
+    pub signature: String,
+    pub wallet: String,
+    pub mint: String,
+    pub tag: String,
+    pub user: String,  // 按下按钮的 Telegram 用户
+}
+
+/// 检测到的三明治攻击记录在 `sandwiches.jsonl`，交易的标注记录在 `annotations.jsonl`。
+/// 目标钱包的交易日志 `trades.jsonl` 由 `StateStore` 写入，使用数据库时保存在数据库中
+pub struct TradeJournal {
+    data_dir: PathBuf,
+}
+
+impl TradeJournal {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            data_dir: data_dir.to_path_buf(),
+        }
+    }
+
+    pub fn record_sandwich(&self, record: &SandwichRecord) -> Result<()> {
+        append(&self.data_dir.join(SANDWICH_FILE), record)
+    }
+
+    pub fn record_annotation(&self, annotation: &TradeAnnotation) -> Result<()> {
+        append(&self.data_dir.join(ANNOTATION_FILE), annotation)
+    }
+}
+
+fn append<T: Serialize>(path: &Path, record: &T) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// 读取交易日志，跳过无法解析的行（例如写入中断的最后一行）
+pub fn load(data_dir: &Path) -> Result<Vec<JournalEntry>> {
+    let path = data_dir.join(JOURNAL_FILE);
+    let content = fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Failed to read trade journal {}: {}", path.display(), e))?;
+    Ok(parse_lines(&content))
+}
+
+/// 读取三明治攻击记录，从未检测到时文件不存在，返回 None
+pub fn load_sandwiches(data_dir: &Path) -> Result<Option<Vec<SandwichRecord>>> {
+    match fs::read_to_string(data_dir.join(SANDWICH_FILE)) {
+        Ok(content) => Ok(Some(parse_lines(&content))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// 读取交易的标注，从未标注时文件不存在，返回空列表
+pub fn load_annotations(data_dir: &Path) -> Result<Vec<TradeAnnotation>> {
+    match fs::read_to_string(data_dir.join(ANNOTATION_FILE)) {
+        Ok(content) => Ok(parse_lines(&content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn parse_lines<T: DeserializeOwned>(content: &str) -> Vec<T> {
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
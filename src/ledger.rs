@@ -0,0 +1,285 @@
+use crate::mints::to_tokens;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+const MAX_ENTRIES: usize = 100;  // 账本保留的最近交易数，更早的只计数
+const MAX_LOTS: usize = 100;  // 剩余批次的上限，超出时合并最早的两批
+
+/// 一笔买入还没有卖出的部分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Lot {
+    amount: u64,  // 原始数量
+    cost: f64,    // 这部分的成本（SOL）
+    cost_usd: Option<f64>,  // 按买入时汇率计的成本，缺少汇率时为 None
+    bought_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// 账本中的一笔交易
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub timestamp: i64,
+    pub side: Side,
+    pub amount: u64,
+    pub price: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realized_pnl: Option<f64>,  // 卖出的已实现盈亏（SOL）
+}
+
+/// 一次卖出按先进先出计算的已实现盈亏
+#[derive(Debug, Clone, Copy)]
+pub struct Realized {
+    pub pnl: f64,
+    pub pnl_usd: Option<f64>,  // 卖出时或对应的买入缺少汇率时为 None
+}
+
+/// 一个代币的交易账本：记录买卖，按先进先出（FIFO）的批次计算卖出的已实现盈亏和剩余仓位的成本。
+/// 账本随持仓保存，只保留最近 `MAX_ENTRIES` 笔交易和最多 `MAX_LOTS` 个批次
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradeLedger {
+    lots: VecDeque<Lot>,
+    entries: VecDeque<LedgerEntry>,
+    #[serde(default)]
+    trades: usize,  // 所有交易的笔数，包括不再保留的
+    realized_pnl: f64,
+}
+
+impl TradeLedger {
+    /// 剩余批次的数量
+    pub fn amount(&self) -> u64 {
+        self.lots.iter().map(|lot| lot.amount).sum()
+    }
+
+    /// 剩余批次的成本（SOL）
+    pub fn cost(&self) -> f64 {
+        self.lots.iter().map(|lot| lot.cost).sum()
+    }
+
+    /// 剩余批次按买入时汇率计的成本，有批次缺少汇率时为 None
+    pub fn cost_usd(&self) -> Option<f64> {
+        self.lots.iter().map(|lot| lot.cost_usd).sum()
+    }
+
+    /// 这个代币所有卖出的已实现盈亏（SOL）
+    pub fn realized_pnl(&self) -> f64 {
+        self.realized_pnl
+    }
+
+    /// 所有交易的笔数。没有记录笔数的旧账本按保留的交易计
+    pub fn trades(&self) -> usize {
+        self.trades.max(self.entries.len())
+    }
+
+    fn record(&mut self, entry: LedgerEntry) {
+        self.trades = self.trades() + 1;
+        self.entries.push_back(entry);
+        while self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    /// 账本之外已有的数量（例如从没有账本的旧状态恢复的持仓），作为最早的一批
+    pub fn open_with(&mut self, amount: u64, cost: f64, cost_usd: Option<f64>, at: i64) {
+        self.lots.push_front(Lot {
+            amount,
+            cost,
+            cost_usd,
+            bought_at: at,
+        });
+    }
+
+    pub fn buy(&mut self, amount: u64, decimals: u8, price: f64, sol_usd: Option<f64>, at: i64) {
+        if amount > 0 {
+            let cost = to_tokens(amount, decimals) * price;
+            self.lots.push_back(Lot {
+                amount,
+                cost,
+                cost_usd: sol_usd.map(|sol_usd| cost * sol_usd),
+                bought_at: at,
+            });
+            // 最早的两批合并，卖出时按合并后的均价计算成本
+            while self.lots.len() > MAX_LOTS {
+                if let (Some(first), Some(second)) = (self.lots.pop_front(), self.lots.pop_front()) {
+                    self.lots.push_front(Lot {
+                        amount: first.amount + second.amount,
+                        cost: first.cost + second.cost,
+                        cost_usd: first.cost_usd.zip(second.cost_usd).map(|(first, second)| first + second),
+                        bought_at: first.bought_at,
+                    });
+                }
+            }
+        }
+        self.record(LedgerEntry {
+            timestamp: at,
+            side: Side::Buy,
+            amount,
+            price,
+            realized_pnl: None,
+        });
+    }
+
+    /// 从最早的批次开始卖出。超出剩余批次的数量是账本之外的代币，成本未知，不计入盈亏
+    pub fn sell(&mut self, amount: u64, decimals: u8, price: f64, sol_usd: Option<f64>, at: i64) -> Realized {
+        let mut remaining = amount;
+        let mut matched = 0;
+        let mut cost = 0.0;
+        let mut cost_usd = Some(0.0);
+        while remaining > 0 {
+            let Some(lot) = self.lots.front_mut() else {
+                break;
+            };
+            let take = remaining.min(lot.amount);
+            let share = take as f64 / lot.amount as f64;
+            let lot_cost = lot.cost * share;
+            let lot_cost_usd = lot.cost_usd.map(|cost| cost * share);
+            cost += lot_cost;
+            cost_usd = cost_usd.zip(lot_cost_usd).map(|(total, cost)| total + cost);
+            lot.amount -= take;
+            lot.cost -= lot_cost;
+            lot.cost_usd = lot.cost_usd.zip(lot_cost_usd).map(|(total, cost)| total - cost);
+            if lot.amount == 0 {
+                self.lots.pop_front();
+            }
+            remaining -= take;
+            matched += take;
+        }
+
+        let proceeds = to_tokens(matched, decimals) * price;
+        let realized = Realized {
+            pnl: proceeds - cost,
+            pnl_usd: sol_usd.zip(cost_usd).map(|(sol_usd, cost_usd)| proceeds * sol_usd - cost_usd),
+        };
+        self.realized_pnl += realized.pnl;
+        self.record(LedgerEntry {
+            timestamp: at,
+            side: Side::Sell,
+            amount,
+            price,
+            realized_pnl: Some(realized.pnl),
+        });
+        realized
+    }
+
+    /// 按比例把剩余批次调整到 `amount`（对账修正、移除钱包），均价不变，不计入盈亏
+    pub fn scale_to(&mut self, amount: u64) {
+        let current = self.amount();
+        if current == 0 {
+            return;
+        }
+        let ratio = amount as f64 / current as f64;
+        for lot in self.lots.iter_mut() {
+            lot.amount = (lot.amount as f64 * ratio) as u64;
+            lot.cost *= ratio;
+            lot.cost_usd = lot.cost_usd.map(|cost| cost * ratio);
+        }
+        // 取整的误差计入最后一批
+        let scaled = self.amount();
+        if let Some(last) = self.lots.back_mut() {
+            last.amount = (last.amount + amount).saturating_sub(scaled);
+        }
+        self.lots.retain(|lot| lot.amount > 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DECIMALS: u8 = 6;
+    const TOKEN: u64 = 1_000_000;  // 一个代币的原始数量
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn partial_sell_matches_oldest_lots_first() {
+        let mut ledger = TradeLedger::default();
+        ledger.buy(TOKEN, DECIMALS, 1.0, Some(100.0), 1);
+        ledger.buy(TOKEN, DECIMALS, 2.0, Some(200.0), 2);
+
+        // 第一批全部卖出，第二批卖出一半：成本 1.0 + 1.0，收入 1.5 * 3.0
+        let realized = ledger.sell(TOKEN * 3 / 2, DECIMALS, 3.0, Some(100.0), 3);
+        assert_close(realized.pnl, 2.5);
+        assert_close(realized.pnl_usd.unwrap(), 450.0 - (100.0 + 200.0));
+        assert_close(ledger.realized_pnl(), 2.5);
+        assert_eq!(ledger.amount(), TOKEN / 2);
+        assert_close(ledger.cost(), 1.0);
+        assert_close(ledger.cost_usd().unwrap(), 200.0);
+        assert_eq!(ledger.trades(), 3);
+    }
+
+    #[test]
+    fn sell_beyond_lots_ignores_unknown_cost() {
+        let mut ledger = TradeLedger::default();
+        ledger.buy(TOKEN, DECIMALS, 1.0, None, 1);
+
+        // 只有账本内的一个代币计入盈亏
+        let realized = ledger.sell(TOKEN * 3, DECIMALS, 2.0, Some(100.0), 2);
+        assert_close(realized.pnl, 1.0);
+        assert_eq!(realized.pnl_usd, None);
+        assert_eq!(ledger.amount(), 0);
+        assert_close(ledger.cost(), 0.0);
+        assert!(ledger.lots.is_empty());
+    }
+
+    #[test]
+    fn buys_past_max_lots_merge_oldest() {
+        let mut ledger = TradeLedger::default();
+        for at in 0..=MAX_LOTS as i64 {
+            ledger.buy(TOKEN, DECIMALS, 1.0 + at as f64, Some(100.0), at);
+        }
+
+        assert_eq!(ledger.lots.len(), MAX_LOTS);
+        let first = ledger.lots.front().unwrap();
+        assert_eq!(first.amount, TOKEN * 2);
+        assert_close(first.cost, 1.0 + 2.0);
+        assert_close(first.cost_usd.unwrap(), 300.0);
+        assert_eq!(first.bought_at, 0);
+        assert_eq!(ledger.amount(), TOKEN * (MAX_LOTS as u64 + 1));
+
+        // 合并的批次按均价 1.5 计算成本
+        let realized = ledger.sell(TOKEN, DECIMALS, 2.0, None, 200);
+        assert_close(realized.pnl, 0.5);
+        assert_eq!(ledger.lots.len(), MAX_LOTS);
+    }
+
+    #[test]
+    fn scale_down_keeps_average_and_total() {
+        let mut ledger = TradeLedger::default();
+        for at in 0..3 {
+            ledger.buy(TOKEN, DECIMALS, 1.0, Some(100.0), at);
+        }
+
+        // 每批取整为 333_333，误差计入最后一批
+        ledger.scale_to(TOKEN);
+        assert_eq!(ledger.amount(), TOKEN);
+        assert_eq!(ledger.lots.back().unwrap().amount, 333_334);
+        assert_close(ledger.cost(), 1.0);
+        assert_close(ledger.cost_usd().unwrap(), 100.0);
+        assert_close(ledger.realized_pnl(), 0.0);
+
+        ledger.scale_to(0);
+        assert!(ledger.lots.is_empty());
+        assert_close(ledger.cost(), 0.0);
+    }
+
+    #[test]
+    fn scale_up_keeps_average() {
+        let mut ledger = TradeLedger::default();
+        ledger.buy(TOKEN, DECIMALS, 1.0, None, 1);
+        ledger.buy(TOKEN, DECIMALS, 3.0, None, 2);
+
+        ledger.scale_to(TOKEN * 5);
+        assert_eq!(ledger.amount(), TOKEN * 5);
+        assert_close(ledger.cost(), 10.0);
+        assert_eq!(ledger.cost_usd(), None);
+        assert_eq!(ledger.lots.front().unwrap().amount, TOKEN * 5 / 2);
+    }
+}
@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use env_logger::{Builder, Logger, Target};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::{fs::File, io::Write, str::FromStr, sync::RwLock};
+
+const DEFAULT_DIRECTIVES: &str = "info";
+
+static DIRECTIVES: RwLock<Vec<Directive>> = RwLock::new(Vec::new());
+
+/// 一条过滤规则，例如 `wallet_monitor=debug`；没有模块名时作为全局级别
+#[derive(Debug, Clone)]
+struct Directive {
+    module: Option<String>,
+    level: LevelFilter,
+}
+
+impl Directive {
+    fn matches(&self, target: &str) -> bool {
+        match &self.module {
+            None => true,
+            Some(module) => {
+                target == module
+                    || target.starts_with(&format!("{}::", module))
+                    || target.ends_with(&format!("::{}", module))
+                    || target.contains(&format!("::{}::", module))
+            }
+        }
+    }
+}
+
+/// 可在运行时修改过滤规则的日志器，实际输出交给 env_logger
+struct DynamicLogger {
+    inner: Logger,
+}
+
+impl Log for DynamicLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// 初始化日志输出到文件，初始过滤规则取自 `RUST_LOG`（默认 `info`）
+pub fn init(log_file: File) -> Result<()> {
+    let inner = Builder::new()
+        .target(Target::Pipe(Box::new(log_file)))
+        .format(|buf, record| {
+            writeln!(
+                buf,
+                "{} [{}] {}",
+                Local::now().format("%Y-%m-%d %H:%M:%S"),
+                record.level(),
+                record.args()
+            )
+        })
+        .filter(None, LevelFilter::Trace)
+        .build();
+
+    let spec = std::env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_DIRECTIVES.to_string());
+    set_directives(&spec)?;
+    log::set_boxed_logger(Box::new(DynamicLogger { inner }))?;
+    Ok(())
+}
+
+/// 替换过滤规则，格式与 `RUST_LOG` 相同，例如 `info,wallet_monitor=debug`。
+/// 未指定全局级别时默认为 `info`
+pub fn set_directives(spec: &str) -> Result<()> {
+    let mut directives = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let directive = match part.split_once('=') {
+            Some((module, level)) => Directive {
+                module: Some(module.trim().to_string()),
+                level: parse_level(level)?,
+            },
+            None => match LevelFilter::from_str(part) {
+                Ok(level) => Directive { module: None, level },
+                Err(_) => Directive {
+                    module: Some(part.to_string()),
+                    level: LevelFilter::Trace,
+                },
+            },
+        };
+        directives.push(directive);
+    }
+
+    if !directives.iter().any(|directive| directive.module.is_none()) {
+        directives.insert(0, Directive {
+            module: None,
+            level: LevelFilter::from_str(DEFAULT_DIRECTIVES).unwrap_or(LevelFilter::Info),
+        });
+    }
+
+    let max_level = directives.iter().map(|directive| directive.level).max().unwrap_or(LevelFilter::Info);
+    *DIRECTIVES.write().map_err(|_| anyhow!("Log directives lock poisoned"))? = directives;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// 当前的过滤规则
+pub fn directives() -> String {
+    DIRECTIVES
+        .read()
+        .map(|directives| {
+            directives
+                .iter()
+                .map(|directive| match &directive.module {
+                    Some(module) => format!("{}={}", module, directive.level.as_str().to_lowercase()),
+                    None => directive.level.as_str().to_lowercase(),
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_default()
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter> {
+    LevelFilter::from_str(level.trim()).map_err(|_| anyhow!("Invalid log level: {}", level))
+}
+
+/// 选择最具体（模块名最长）的匹配规则
+fn level_for(target: &str) -> LevelFilter {
+    let Ok(directives) = DIRECTIVES.read() else {
+        return LevelFilter::Info;
+    };
+    directives
+        .iter()
+        .filter(|directive| directive.matches(target))
+        .max_by_key(|directive| directive.module.as_ref().map_or(0, |module| module.len() + 1))
+        .map_or(LevelFilter::Off, |directive| directive.level)
+}
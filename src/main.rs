@@ -1,68 +1,232 @@
 use anyhow::Result;
-use chrono::Local;
-use env_logger::{Builder, Target};
-use log::{info, LevelFilter};
+use futures_util::future::{join_all, try_join_all};
+use log::{error, info, warn};
 use std::fs::OpenOptions;
-use std::io::Write;
-use tokio;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
-use crate::models::AlertType;
+mod activity;
+mod address_book;
+mod amm;
+mod annotations;
+mod api;
+mod cli;
+mod audit;
+mod backfill;
+mod backoff;
+mod blacklist;
+mod chaos;
+mod channels;
+mod circuit;
+mod checkpoint;
+mod config;
+mod confirm;
+mod counterparties;
+mod dedup;
+mod discord;
+mod email;
+mod display;
+mod endpoints;
+mod enrichment;
+mod error;
+mod escalation;
+mod exit_quotes;
+mod federation;
+mod flow;
+mod heartbeat;
+mod helius;
+mod holders;
+mod impersonation;
+mod journal;
+mod ledger;
+mod logging;
+mod persistence;
+mod wallet_actor;
 mod wallet_monitor;
 mod alert_service;
+mod metadata;
+mod metrics;
+mod mev;
+mod migrations;
+mod mints;
 mod models;
+mod oracle;
+mod outbox;
+mod outcomes;
+mod plugins;
+#[cfg(feature = "postgres")]
+mod postgres;
+mod price_updates;
+mod pump_events;
+mod quarantine;
+mod reconcile;
+mod retention;
+mod secrets;
+mod recording;
+mod replay;
+mod rules;
+mod scheduler;
+mod shared_holdings;
+mod sound;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod store;
+mod stress;
+mod systemd;
+mod tags;
+mod tasks;
+mod telegram_bot;
+mod timers;
+mod tune;
+mod validation;
+mod wallet_report;
+
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);  // 关闭时等待后台任务结束的时间
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // 加载 .env 文件
     dotenv::dotenv().ok();
 
+    // 子命令通过控制 API 操作正在运行的监控进程
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let color = match cli::take_option(&mut args, "--color")? {
+        Some(mode) => mode.parse()?,
+        None => display::ColorMode::Auto,
+    };
+    if let Some((command, args)) = args.split_first() {
+        // 管理密钥文件时不需要先解密它
+        if command == "secrets" {
+            return secrets::run(args);
+        }
+        secrets::load_into_env()?;
+        return cli::run(command, args, color).await;
+    }
+
+    // 加密的密钥文件中的 Telegram token、Helius key 等，环境变量和 .env 中已有的优先
+    let loaded_secrets = secrets::load_into_env()?;
+
     // 设置日志输出到文件
     let log_file = OpenOptions::new()
         .create(true)
         .append(true)
         .open("monitor.log")?;
         
-    Builder::new()
-        .target(Target::Pipe(Box::new(log_file)))
-        .format(|buf, record| {
-            writeln!(
-                buf,
-                "{} [{}] {}",
-                Local::now().format("%Y-%m-%d %H:%M:%S"),
-                record.level(),
-                record.args()
-            )
-        })
-        .filter(None, LevelFilter::Info)
-        .init();
+    logging::init(log_file)?;
 
     info!("Starting PUMP program monitor...");
+    if loaded_secrets > 0 {
+        info!("Loaded {} secret(s) from {}", loaded_secrets, secrets::path().display());
+    }
+
+    if let Some(chaos) = chaos::init() {
+        warn!("Chaos mode enabled: {:?}", chaos);
+    }
+
+    let profiles = config::load_profiles()?;
+    display::init(color)?;
+    sound::init()?;
+
+    let mut monitors = Vec::new();
+    for profile in &profiles {
+        // 为每个 profile 准备独立的数据目录，并升级到当前格式
+        std::fs::create_dir_all(&profile.data_dir)?;
+        migrations::apply(&profile.data_dir)?;
+
+        let alert_service = alert_service::AlertService::new(profile).await?;
+        monitors.push(wallet_monitor::WalletMonitor::new(profile, alert_service).await?);
+        info!("Loaded profile '{}' with {} wallet(s)", profile.name, profile.wallets.len());
+    }
 
-    let bot_token = std::env::var("TELEGRAM_BOT_TOKEN")
-        .expect("TELEGRAM_BOT_TOKEN must be set");
-    let chat_id = std::env::var("TELEGRAM_CHAT_ID")
-        .expect("TELEGRAM_CHAT_ID must be set")
-        .parse::<i64>()
-        .expect("TELEGRAM_CHAT_ID must be a valid integer");
-    let topic_id = std::env::var("TELEGRAM_TOPIC_ID")
-        .ok()
-        .and_then(|id| id.parse::<i32>().ok());
-    let ws_url = std::env::var("WS_ALERT_URL").ok();
-
-    let alert_service = alert_service::AlertService::new(
-        &bot_token,
-        chat_id,
-        topic_id,
-        ws_url
+    // 连接之前测量配置的端点的延迟，各 profile 同时探测
+    join_all(monitors.iter().map(|monitor| monitor.probe_endpoints())).await;
+    join_all(monitors.iter().map(|monitor| monitor.check_channels())).await;
+
+    // 所有后台任务都登记在 tasks 中，退出时统一停止
+    let shutdown = CancellationToken::new();
+    let mut tasks = tasks::TaskSet::default();
+
+    // 启动远程控制 API（需要同时配置监听地址和管理令牌）
+    if let Ok(api_addr) = std::env::var("CONTROL_API_ADDR") {
+        match std::env::var("CONTROL_API_TOKEN") {
+            Ok(api_token) if !api_token.is_empty() => {
+                let addr = api_addr.parse::<SocketAddr>()
+                    .expect("CONTROL_API_ADDR must be a valid socket address");
+                let api_monitors = monitors.clone();
+                let api_shutdown = shutdown.clone();
+                tasks.spawn("control API", async move {
+                    if let Err(e) = api::serve(addr, api_token, api_monitors, api_shutdown).await {
+                        error!("Control API stopped: {:?}", e);
+                    }
+                });
+            }
+            _ => error!("CONTROL_API_ADDR is set but CONTROL_API_TOKEN is missing, control API disabled"),
+        }
+    }
+
+    // 启动提醒投递、Telegram 命令、接收信号、价格更新、定时任务（含钱包活跃度和新买家检查）、持仓时间提醒、黑名单同步、持有人数量采样、状态保存、共享持仓同步和心跳任务
+    for monitor in &monitors {
+        let token = monitor.cancellation();
+        // 只读副本只回答 Telegram 命令并读取共享持仓
+        if monitor.is_replica() {
+            tasks.spawn_cancellable("telegram bot", token.clone(), monitor.clone().run_telegram_bot());
+            tasks.spawn_cancellable("state sync", token, monitor.clone().run_state_sync());
+            continue;
+        }
+        tasks.spawn_cancellable("scheduler", token.clone(), monitor.clone().run_scheduler());
+        tasks.spawn_cancellable("hold reminders", token.clone(), monitor.clone().run_hold_reminders());
+        tasks.spawn_cancellable("alert queue", token.clone(), monitor.clone().run_alert_queue());
+        tasks.spawn_cancellable("telegram bot", token.clone(), monitor.clone().run_telegram_bot());
+        tasks.spawn_cancellable("inbound signals", token.clone(), monitor.clone().run_inbound_signals());
+        tasks.spawn_cancellable("price updates", token.clone(), monitor.clone().run_price_updates());
+        tasks.spawn_cancellable("blacklist sync", token.clone(), monitor.clone().run_blacklist_sync());
+        tasks.spawn_cancellable("known tokens sync", token.clone(), monitor.clone().run_known_tokens_sync());
+        tasks.spawn_cancellable("holder watch", token.clone(), monitor.clone().run_holder_watch());
+        tasks.spawn_cancellable("state saver", token.clone(), monitor.clone().run_state_saver());
+        tasks.spawn_cancellable("state sync", token.clone(), monitor.clone().run_state_sync());
+        tasks.spawn_cancellable("alert outcomes", token.clone(), monitor.clone().run_outcome_tracker());
+        tasks.spawn_cancellable("heartbeat", token.clone(), monitor.clone().run_heartbeat());
+        tasks.spawn_cancellable("endpoint probe", token, monitor.clone().run_endpoint_probe());
+    }
+
+    // 启动持仓打印任务
+    let recorder = recording::Recorder::from_env()?;
+    tasks.spawn_cancellable(
+        "display",
+        shutdown.clone(),
+        wallet_monitor::WalletMonitor::run_display(monitors.clone(), recorder),
     );
-    
-    let price_change_threshold = std::env::var("PRICE_CHANGE_THRESHOLD")
-        .unwrap_or_else(|_| "100".to_string())
-        .parse::<f64>()
-        .unwrap_or(100.0);
 
-    let mut monitor = wallet_monitor::WalletMonitor::new(alert_service)?;
-    monitor.start_monitoring().await?;
+    // Ctrl+C 时停止所有 profile
+    let signal_monitors = monitors.clone();
+    let signal_shutdown = shutdown.clone();
+    tasks.spawn_cancellable("signal handler", shutdown.clone(), async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            info!("Received Ctrl+C, shutting down");
+            signal_shutdown.cancel();
+            signal_monitors.iter().for_each(|monitor| monitor.shutdown());
+        }
+    });
+
+    // 所有 profile 订阅成功后通知 systemd 已就绪，并喂看门狗
+    tasks.spawn_cancellable("systemd", shutdown.clone(), systemd::run(monitors.clone()));
+
+    let result = try_join_all(monitors.iter_mut().map(|monitor| monitor.start_monitoring())).await;
+
+    // 所有订阅结束或任一出错后，停止其余 profile 和所有后台任务
+    systemd::notify_stopping();
+    shutdown.cancel();
+    monitors.iter().for_each(|monitor| monitor.shutdown());
+    tasks.join(SHUTDOWN_GRACE).await;
+    // 所有任务停止后保存最终的持仓状态
+    for monitor in &monitors {
+        if let Err(e) = monitor.save_state().await {
+            error!("[{}] Failed to save holdings state: {:?}", monitor.profile(), e);
+        }
+    }
+    info!("Monitor stopped");
 
+    result?;
     Ok(())
 }
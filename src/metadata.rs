@@ -0,0 +1,250 @@
+use crate::endpoints::RpcEndpoint;
+use anyhow::{anyhow, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_request::RpcRequest};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+use url::Url;
+
+const METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+const NAME_OFFSET: usize = 65;  // key(1) + update_authority(32) + mint(32) 之后是 name、symbol、uri
+const MAX_CACHED: usize = 10_000;
+const URI_TIMEOUT: Duration = Duration::from_secs(5);  // 读取 uri 指向的链下 JSON（图片地址）的超时
+const MAX_URI_BYTES: usize = 64 * 1024;  // 链下 JSON 的大小上限
+const FAILED_RETRY: Duration = Duration::from_secs(10 * 60);  // 查询失败的代币在这段时间内不再查询
+// uri 只能指向这些主机（及其子域名），元数据中的地址由代币创建者填写，不能让它访问内网或任意服务
+const URI_HOSTS: [&str; 8] = [
+    "ipfs.io",
+    "cf-ipfs.com",
+    "gateway.pinata.cloud",
+    "mypinata.cloud",
+    "arweave.net",
+    "nftstorage.link",
+    "w3s.link",
+    "dweb.link",
+];
+
+/// 代币的名称、符号和图片
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+impl TokenMetadata {
+    /// 代币的显示名，例如 `PEPE2 (6EF8…)`
+    pub fn label(&self, mint: &str) -> String {
+        format!("{} ({})", self.symbol, short_mint(mint))
+    }
+
+    fn sanitized(self) -> Self {
+        Self {
+            name: strip_control(&self.name),
+            symbol: strip_control(&self.symbol),
+            image: self.image,
+        }
+    }
+}
+
+/// 去掉控制字符。名称和符号由代币创建者填写，会显示在终端中，不能带有 ESC 等转义序列
+pub(crate) fn strip_control(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+/// mint 的前 4 个字符
+pub fn short_mint(mint: &str) -> String {
+    format!("{}…", mint.chars().take(4).collect::<String>())
+}
+
+/// 代币元数据的查询和缓存。Helius 通过 DAS `getAsset` 一次取得名称、符号和图片；
+/// 其他 RPC 读取 Metaplex 的 metadata 账户，再从 uri 指向的 JSON 中读取图片。
+/// 查到的结果和确认没有元数据的代币都会缓存，查询失败的代币在 `FAILED_RETRY` 之后才再次查询。
+/// 提醒只读取缓存，查询在后台进行
+pub struct MetadataResolver {
+    rpc: Option<RpcEndpoint>,
+    client: reqwest::Client,
+    cache: RwLock<HashMap<String, Option<TokenMetadata>>>,
+    failed: RwLock<HashMap<String, Instant>>,  // 查询失败的代币 -> 失败的时间
+    pending: Mutex<HashSet<String>>,  // 正在后台查询的代币
+}
+
+impl MetadataResolver {
+    pub fn new(rpc: Option<RpcEndpoint>) -> Self {
+        Self {
+            rpc,
+            // 允许的主机不能把请求重定向到其他地方
+            client: reqwest::Client::builder()
+                .timeout(URI_TIMEOUT)
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap_or_default(),
+            cache: RwLock::new(HashMap::new()),
+            failed: RwLock::new(HashMap::new()),
+            pending: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// 准备在后台查询代币：已缓存、最近查询失败或正在查询时返回 false
+    pub fn begin_lookup(&self, mint: &str) -> bool {
+        if self.cache.read().unwrap().contains_key(mint) || self.failed_recently(mint) {
+            return false;
+        }
+        self.pending.lock().unwrap().insert(mint.to_string())
+    }
+
+    fn failed_recently(&self, mint: &str) -> bool {
+        self.failed.read().unwrap().get(mint).is_some_and(|at| at.elapsed() < FAILED_RETRY)
+    }
+
+    /// 已缓存的元数据，不发起查询
+    pub fn cached(&self, mint: &str) -> Option<TokenMetadata> {
+        self.cache.read().unwrap().get(mint).cloned().flatten()
+    }
+
+    /// 记录从其他来源得知的元数据，例如 CreateEvent 中的名称和符号
+    pub fn insert(&self, mint: &str, metadata: TokenMetadata) {
+        let mut cache = self.cache.write().unwrap();
+        if cache.len() >= MAX_CACHED {
+            cache.clear();
+        }
+        cache.insert(mint.to_string(), Some(metadata.sanitized()));
+    }
+
+    /// 代币的元数据，没有缓存时查询。没有配置 RPC、没有元数据或查询失败时为 None，最近失败过时不查询
+    pub async fn resolve(&self, mint: &str) -> Option<TokenMetadata> {
+        let metadata = self.lookup(mint).await;
+        self.pending.lock().unwrap().remove(mint);
+        metadata
+    }
+
+    async fn lookup(&self, mint: &str) -> Option<TokenMetadata> {
+        if let Some(metadata) = self.cache.read().unwrap().get(mint) {
+            return metadata.clone();
+        }
+        if self.failed_recently(mint) {
+            return None;
+        }
+        let rpc = self.rpc.as_ref()?;
+        let result = match rpc {
+            RpcEndpoint::Helius(_) => fetch_asset(&rpc.client(), mint).await,
+            RpcEndpoint::Probed(_) => self.fetch_metaplex(&rpc.client(), mint).await,
+        };
+        match result {
+            Ok(metadata) => {
+                let metadata = metadata.map(TokenMetadata::sanitized);
+                debug!("Metadata of {}: {:?}", mint, metadata);
+                let mut cache = self.cache.write().unwrap();
+                if cache.len() >= MAX_CACHED {
+                    cache.clear();
+                }
+                cache.insert(mint.to_string(), metadata.clone());
+                metadata
+            }
+            Err(e) => {
+                debug!("Failed to look up metadata of {}: {:?}", mint, e);
+                let mut failed = self.failed.write().unwrap();
+                if failed.len() >= MAX_CACHED {
+                    failed.retain(|_, at| at.elapsed() < FAILED_RETRY);
+                }
+                failed.insert(mint.to_string(), Instant::now());
+                None
+            }
+        }
+    }
+
+    /// 读取 Metaplex metadata 账户，账户不存在时为 None。图片只是尽量读取
+    async fn fetch_metaplex(&self, client: &RpcClient, mint: &str) -> Result<Option<TokenMetadata>> {
+        let program_id = Pubkey::from_str(METADATA_PROGRAM_ID)?;
+        let mint_key = Pubkey::from_str(mint).map_err(|e| anyhow!("Invalid mint {}: {}", mint, e))?;
+        let (address, _) = Pubkey::find_program_address(&[b"metadata", program_id.as_ref(), mint_key.as_ref()], &program_id);
+        let Some(account) = client.get_account_with_commitment(&address, CommitmentConfig::confirmed()).await?.value else {
+            return Ok(None);
+        };
+
+        let mut offset = NAME_OFFSET;
+        let name = read_string(&account.data, &mut offset)?;
+        let symbol = read_string(&account.data, &mut offset)?;
+        let uri = read_string(&account.data, &mut offset)?;
+        let image = if uri.is_empty() { None } else { self.fetch_image(&uri).await };
+        Ok(Some(TokenMetadata { name, symbol, image }))
+    }
+
+    async fn fetch_image(&self, uri: &str) -> Option<String> {
+        if !allowed_uri(uri) {
+            debug!("Not reading token metadata JSON at {}, the host is not allowed", uri);
+            return None;
+        }
+        let result: Result<Value> = async {
+            let response = self.client.get(uri).send().await?.error_for_status()?;
+            if response.content_length().is_some_and(|length| length > MAX_URI_BYTES as u64) {
+                return Err(anyhow!("the response is larger than {} bytes", MAX_URI_BYTES));
+            }
+            let body = response.bytes().await?;
+            if body.len() > MAX_URI_BYTES {
+                return Err(anyhow!("the response is larger than {} bytes", MAX_URI_BYTES));
+            }
+            Ok(serde_json::from_slice(&body)?)
+        }
+        .await;
+        match result {
+            Ok(json) => json["image"].as_str().map(str::to_string),
+            Err(e) => {
+                debug!("Failed to read token metadata JSON at {}: {:?}", uri, e);
+                None
+            }
+        }
+    }
+}
+
+/// uri 是否是 https 并且指向 `URI_HOSTS` 中的主机
+fn allowed_uri(uri: &str) -> bool {
+    let Ok(url) = Url::parse(uri) else {
+        return false;
+    };
+    let Some(host) = url.host_str().filter(|_| url.scheme() == "https") else {
+        return false;
+    };
+    URI_HOSTS
+        .iter()
+        .any(|allowed| host == *allowed || host.strip_suffix(allowed).is_some_and(|sub| sub.ends_with('.')))
+}
+
+/// Helius DAS `getAsset`，同时支持 Metaplex 和 Token-2022 metadata 扩展的代币
+async fn fetch_asset(client: &RpcClient, mint: &str) -> Result<Option<TokenMetadata>> {
+    let asset: Value = client
+        .send(RpcRequest::Custom { method: "getAsset" }, json!({ "id": mint }))
+        .await?;
+    let content = &asset["content"];
+    let (Some(name), Some(symbol)) = (
+        content["metadata"]["name"].as_str(),
+        content["metadata"]["symbol"].as_str(),
+    ) else {
+        return Ok(None);
+    };
+    Ok(Some(TokenMetadata {
+        name: name.trim().to_string(),
+        symbol: symbol.trim().to_string(),
+        image: content["links"]["image"].as_str().filter(|image| !image.is_empty()).map(str::to_string),
+    }))
+}
+
+/// Borsh 字符串（u32 长度 + 内容），Metaplex 用 \0 补齐到固定长度
+fn read_string(data: &[u8], offset: &mut usize) -> Result<String> {
+    let len_bytes = data
+        .get(*offset..*offset + 4)
+        .ok_or_else(|| anyhow!("Metadata account is too short"))?;
+    let len = u32::from_le_bytes(len_bytes.try_into()?) as usize;
+    let start = *offset + 4;
+    let bytes = data.get(start..start + len).ok_or_else(|| anyhow!("Metadata string overruns the account"))?;
+    *offset = start + len;
+    Ok(String::from_utf8_lossy(bytes).trim_end_matches('\0').trim().to_string())
+}
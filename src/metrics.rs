@@ -0,0 +1,210 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// 直方图的桶上限（秒），覆盖从微秒级的锁等待到秒级的阻塞
+const LATENCY_BUCKETS: [f64; 10] = [0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// 固定桶的耗时直方图
+#[derive(Debug, Default)]
+pub struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],  // 每个桶的计数（非累计）
+    sum_nanos: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    pub fn observe(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if let Some(index) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        }
+        self.sum_nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+/// 运行时指标，通过控制 API 的 `GET /metrics` 以 Prometheus 文本格式导出
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub decode_failures: AtomicU64,
+    pub unknown_events: AtomicU64,
+    pub rejected_trades: AtomicU64,
+    pub failed_transactions: AtomicU64,
+    pub duplicate_events: AtomicU64,
+    pub balance_mismatches: AtomicU64,
+    pub price_updates_coalesced: AtomicU64,
+    pub alerts_dropped: AtomicU64,  // 以下三项抓取时从提醒队列采样
+    pub alerts_coalesced: AtomicU64,
+    pub ws_reconnects: AtomicU64,
+    pub alert_queue_depth: AtomicU64,
+    pub worker_queue_depth: AtomicU64,
+    pub holdings_read_wait: Histogram,
+    pub holdings_write_wait: Histogram,
+    pub message_handling: Histogram,
+}
+
+impl Metrics {
+    pub fn inc(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// (名称, 说明, 当前值)
+    fn counters(&self) -> Vec<(&'static str, &'static str, u64)> {
+        vec![
+            (
+                "monitor_decode_failures_total",
+                "Program data payloads that failed to decode and were quarantined",
+                self.decode_failures.load(Ordering::Relaxed),
+            ),
+            (
+                "monitor_unknown_events_total",
+                "Pump events with an unknown discriminator",
+                self.unknown_events.load(Ordering::Relaxed),
+            ),
+            (
+                "monitor_rejected_trades_total",
+                "Decoded trades rejected by the plausibility checks",
+                self.rejected_trades.load(Ordering::Relaxed),
+            ),
+            (
+                "monitor_failed_transactions_skipped_total",
+                "Log notifications of failed transactions skipped without decoding",
+                self.failed_transactions.load(Ordering::Relaxed),
+            ),
+            (
+                "monitor_duplicate_events_skipped_total",
+                "Events of already processed transactions skipped, e.g. redelivered after a reconnect",
+                self.duplicate_events.load(Ordering::Relaxed),
+            ),
+            (
+                "monitor_balance_mismatches_total",
+                "Token account updates whose balance differs from the tracked holding",
+                self.balance_mismatches.load(Ordering::Relaxed),
+            ),
+            (
+                "monitor_price_updates_coalesced_total",
+                "Price updates replaced by a newer price of the same token before being applied",
+                self.price_updates_coalesced.load(Ordering::Relaxed),
+            ),
+            (
+                "monitor_alerts_dropped_total",
+                "Low severity alerts dropped because the alert queue was full",
+                self.alerts_dropped.load(Ordering::Relaxed),
+            ),
+            (
+                "monitor_alerts_coalesced_total",
+                "Pending price alerts replaced by a newer alert for the same token",
+                self.alerts_coalesced.load(Ordering::Relaxed),
+            ),
+            (
+                "monitor_ws_reconnects_total",
+                "WebSocket reconnection attempts after the connection dropped",
+                self.ws_reconnects.load(Ordering::Relaxed),
+            ),
+        ]
+    }
+
+    /// (名称, 说明, 当前值)
+    fn gauges(&self) -> Vec<(&'static str, &'static str, u64)> {
+        vec![
+            (
+                "monitor_alert_queue_depth",
+                "Alerts waiting in the outbox for delivery",
+                self.alert_queue_depth.load(Ordering::Relaxed),
+            ),
+            (
+                "monitor_worker_queue_depth",
+                "WebSocket messages waiting for a decode worker",
+                self.worker_queue_depth.load(Ordering::Relaxed),
+            ),
+        ]
+    }
+
+    /// (名称, 说明, 额外标签, 直方图)
+    fn histograms(&self) -> Vec<(&'static str, &'static str, &'static str, &Histogram)> {
+        vec![
+            (
+                "monitor_holdings_lock_wait_seconds",
+                "Time spent waiting to acquire the holdings lock",
+                "mode=\"read\"",
+                &self.holdings_read_wait,
+            ),
+            (
+                "monitor_holdings_lock_wait_seconds",
+                "Time spent waiting to acquire the holdings lock",
+                "mode=\"write\"",
+                &self.holdings_write_wait,
+            ),
+            (
+                "monitor_message_handling_seconds",
+                "Time spent handling one WebSocket notification, including alert enqueueing",
+                "",
+                &self.message_handling,
+            ),
+        ]
+    }
+}
+
+/// 按 Prometheus 文本格式输出所有 profile 的指标
+pub fn render_prometheus(profiles: &[(&str, &Metrics)]) -> String {
+    let mut output = String::new();
+    let Some((_, first)) = profiles.first() else {
+        return output;
+    };
+
+    for (index, (name, help, _)) in first.counters().into_iter().enumerate() {
+        let _ = writeln!(output, "# HELP {} {}", name, help);
+        let _ = writeln!(output, "# TYPE {} counter", name);
+        for (profile, metrics) in profiles {
+            let value = metrics.counters()[index].2;
+            let _ = writeln!(output, "{}{{profile=\"{}\"}} {}", name, profile, value);
+        }
+    }
+
+    for (index, (name, help, _)) in first.gauges().into_iter().enumerate() {
+        let _ = writeln!(output, "# HELP {} {}", name, help);
+        let _ = writeln!(output, "# TYPE {} gauge", name);
+        for (profile, metrics) in profiles {
+            let value = metrics.gauges()[index].2;
+            let _ = writeln!(output, "{}{{profile=\"{}\"}} {}", name, profile, value);
+        }
+    }
+
+    let mut previous = "";
+    for (index, (name, help, _, _)) in first.histograms().into_iter().enumerate() {
+        // 同名直方图（不同标签）只输出一次说明
+        if name != previous {
+            let _ = writeln!(output, "# HELP {} {}", name, help);
+            let _ = writeln!(output, "# TYPE {} histogram", name);
+            previous = name;
+        }
+        for (profile, metrics) in profiles {
+            let (_, _, extra, histogram) = metrics.histograms()[index];
+            let labels = if extra.is_empty() {
+                format!("profile=\"{}\"", profile)
+            } else {
+                format!("profile=\"{}\",{}", profile, extra)
+            };
+            render_histogram(&mut output, name, &labels, histogram);
+        }
+    }
+    output
+}
+
+fn render_histogram(output: &mut String, name: &str, labels: &str, histogram: &Histogram) {
+    let mut cumulative = 0;
+    for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&histogram.buckets) {
+        cumulative += bucket.load(Ordering::Relaxed);
+        let _ = writeln!(output, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, bound, cumulative);
+    }
+    let count = histogram.count.load(Ordering::Relaxed);
+    let sum = histogram.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+    let _ = writeln!(output, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, count);
+    let _ = writeln!(output, "{}_sum{{{}}} {}", name, labels, sum);
+    let _ = writeln!(output, "{}_count{{{}}} {}", name, labels, count);
+}
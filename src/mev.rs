@@ -0,0 +1,110 @@
+use crate::mints::to_tokens;
+use std::collections::{HashMap, VecDeque};
+
+// 目标交易所在 slot 之后再经过多少个 slot 才检查，等待其他 worker 处理完同一 slot 的交易
+const SETTLE_SLOTS: u64 = 2;
+// 保留最近多少个 slot 的交易
+const RETAINED_SLOTS: u64 = 4;
+
+/// 一笔 pump 交易，同一代币的交易按在 slot 中的顺序记录
+#[derive(Debug, Clone)]
+pub struct SlotTrade {
+    pub slot: u64,
+    pub signature: String,
+    pub user: String,
+    pub is_buy: bool,
+    pub token_amount: u64,
+    pub decimals: u8,
+    pub price: f64,
+    pub is_target: bool,
+}
+
+/// 检测到的三明治攻击
+#[derive(Debug, Clone)]
+pub struct Sandwich {
+    pub slot: u64,
+    pub mint: String,
+    pub victim: SlotTrade,
+    pub attacker: String,
+    pub front_signature: String,
+    pub back_signature: String,
+    pub loss: f64,  // 目标钱包按抢跑前的价格少得或多付的 SOL
+}
+
+/// 缓存最近几个 slot 的交易，在目标钱包交易所在的 slot 结束后检查是否被夹。
+///
+/// 同一代币的交易由同一个 worker 按顺序处理，所以同一 slot 内的顺序与接收顺序一致
+#[derive(Debug, Default)]
+pub struct SandwichDetector {
+    recent: HashMap<String, VecDeque<SlotTrade>>,  // 代币 -> 最近的交易
+    pending: Vec<(u64, String)>,  // 等待检查的目标交易 (slot, 代币)
+    latest_slot: u64,
+}
+
+impl SandwichDetector {
+    /// 记录一笔交易，返回已经可以检查的目标交易中检测到的三明治攻击
+    pub fn observe(&mut self, mint: &str, trade: SlotTrade) -> Vec<Sandwich> {
+        if trade.is_target {
+            self.pending.push((trade.slot, mint.to_string()));
+        }
+        let slot = trade.slot;
+        self.recent.entry(mint.to_string()).or_default().push_back(trade);
+        if slot <= self.latest_slot {
+            return Vec::new();
+        }
+        self.latest_slot = slot;
+
+        let settled = slot.saturating_sub(SETTLE_SLOTS);
+        let mut sandwiches = Vec::new();
+        let (ready, waiting): (Vec<_>, Vec<_>) = self.pending.drain(..).partition(|(slot, _)| *slot <= settled);
+        self.pending = waiting;
+        for (target_slot, mint) in ready {
+            if let Some(trades) = self.recent.get(&mint) {
+                sandwiches.extend(detect(&mint, target_slot, trades));
+            }
+        }
+
+        let oldest = slot.saturating_sub(RETAINED_SLOTS);
+        self.recent.retain(|_, trades| {
+            while trades.front().is_some_and(|trade| trade.slot < oldest) {
+                trades.pop_front();
+            }
+            !trades.is_empty()
+        });
+        sandwiches
+    }
+}
+
+/// 在一个 slot 内找出夹住目标交易的一对交易：之前同方向、之后反方向，且来自同一个钱包
+fn detect(mint: &str, slot: u64, trades: &VecDeque<SlotTrade>) -> Vec<Sandwich> {
+    let in_slot: Vec<&SlotTrade> = trades.iter().filter(|trade| trade.slot == slot).collect();
+    let mut sandwiches = Vec::new();
+    for (index, victim) in in_slot.iter().enumerate().filter(|(_, trade)| trade.is_target) {
+        let found = in_slot[..index]
+            .iter()
+            .filter(|front| front.is_buy == victim.is_buy && front.user != victim.user)
+            .find_map(|front| {
+                in_slot[index + 1..]
+                    .iter()
+                    .find(|back| back.user == front.user && back.is_buy != victim.is_buy)
+                    .map(|back| (*front, *back))
+            });
+        let Some((front, back)) = found else {
+            continue;
+        };
+
+        // 买入时多付、卖出时少得的部分，按抢跑交易的成交价估算
+        let tokens = to_tokens(victim.token_amount, victim.decimals);
+        let price_diff = if victim.is_buy { victim.price - front.price } else { front.price - victim.price };
+        sandwiches.push(Sandwich {
+            slot,
+            mint: mint.to_string(),
+            victim: (*victim).clone(),
+            attacker: front.user.clone(),
+            front_signature: front.signature.clone(),
+            back_signature: back.signature.clone(),
+            loss: (tokens * price_diff).max(0.0),
+        });
+    }
+    sandwiches
+}
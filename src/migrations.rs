@@ -0,0 +1,323 @@
+use crate::{
+    persistence,
+    store::{SqlConnection, SqlValue},
+};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use log::info;
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeSet, fs, io::ErrorKind, path::Path};
+
+const VERSION_FILE: &str = "schema_version";
+const LEGACY_ALERT_TIER: f64 = 100.0;  // 可配置档位之前唯一的提醒阈值
+
+/// 数据目录格式的一次升级，编译进程序，启动时按版本顺序执行
+struct Migration {
+    version: u32,
+    name: &'static str,
+    up: fn(&Path) -> Result<()>,
+}
+
+/// 所有迁移，版本号必须连续递增。已发布的迁移不能修改，格式变化时追加新的迁移
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        up: |_| Ok(()),  // 记录版本之前的数据目录格式与版本 1 相同
+    },
+    Migration {
+        version: 2,
+        name: "alert tiers",
+        up: alert_tiers,
+    },
+    Migration {
+        version: 3,
+        name: "alert outcomes",
+        up: pending_outcomes,
+    },
+    Migration {
+        version: 4,
+        name: "FIFO trade ledger",
+        up: trade_ledgers,
+    },
+    Migration {
+        version: 5,
+        name: "checkpoint",
+        up: checkpoint,
+    },
+    Migration {
+        version: 6,
+        name: "held tokens",
+        up: held_tokens,
+    },
+];
+
+/// 修改保存的持仓状态，没有状态文件时不做任何事。旧版本写入的字段已经是新格式时 `update` 应保持不变
+fn update_state(data_dir: &Path, update: impl FnOnce(&mut Map<String, Value>)) -> Result<()> {
+    let Some(content) = persistence::read(data_dir)? else {
+        return Ok(());
+    };
+    let mut state: Map<String, Value> = serde_json::from_str(&content)?;
+    update(&mut state);
+    persistence::save(data_dir, &serde_json::to_string(&state)?)
+}
+
+/// 已提醒的 (钱包, 代币) 改为带档位的 `alert_tiers`，之前的提醒都是 100% 的档位
+fn alert_tiers(data_dir: &Path) -> Result<()> {
+    update_state(data_dir, |state| {
+        let Some(Value::Array(alerted)) = state.remove("alerted") else {
+            return;
+        };
+        let tiers: Vec<Value> = alerted
+            .into_iter()
+            .filter_map(|position| match position {
+                Value::Array(mut pair) if pair.len() == 2 => {
+                    pair.push(json!(LEGACY_ALERT_TIER));
+                    Some(Value::Array(pair))
+                }
+                _ => None,
+            })
+            .collect();
+        state.entry("alert_tiers").or_insert(Value::Array(tiers));
+    })
+}
+
+/// 还在采样之后价格的提醒，之前的状态没有
+fn pending_outcomes(data_dir: &Path) -> Result<()> {
+    update_state(data_dir, |state| {
+        state.entry("pending_outcomes").or_insert(json!([]));
+    })
+}
+
+/// 没有交易账本的持仓用当前的数量和成本建立最早的一批，卖出时按它计算已实现盈亏
+fn trade_ledgers(data_dir: &Path) -> Result<()> {
+    update_state(data_dir, |state| {
+        let Some(Value::Array(holdings)) = state.get_mut("holdings") else {
+            return;
+        };
+        for holding in holdings.iter_mut().filter_map(Value::as_object_mut) {
+            if holding.contains_key("ledger") {
+                continue;
+            }
+            let amount = holding.get("amount").and_then(Value::as_u64).unwrap_or(0);
+            let lots = match amount {
+                0 => json!([]),
+                _ => json!([{
+                    "amount": amount,
+                    "cost": holding.get("total_cost").cloned().unwrap_or(json!(0.0)),
+                    "cost_usd": holding.get("cost_usd").cloned().unwrap_or(Value::Null),
+                    "bought_at": holding.get("opened_at").cloned().unwrap_or(json!(0)),
+                }]),
+            };
+            holding.insert("ledger".to_string(), json!({ "lots": lots, "entries": [], "realized_pnl": 0.0 }));
+        }
+    })
+}
+
+/// 没有处理位置的状态记录保存的时间，补处理和回放跳过之前的交易
+fn checkpoint(data_dir: &Path) -> Result<()> {
+    update_state(data_dir, |state| {
+        if state.get("checkpoint").is_some_and(|checkpoint| !checkpoint.is_null()) {
+            return;
+        }
+        let saved_at = state.get("saved_at").cloned().unwrap_or(json!(0));
+        state.insert("checkpoint".to_string(), json!({ "slot": 0, "applied": {}, "time": saved_at }));
+    })
+}
+
+/// 持有过的代币从当前的持仓和已提醒的仓位中得到，这些代币之后的买入不再发送新仓位提醒
+fn held_tokens(data_dir: &Path) -> Result<()> {
+    update_state(data_dir, |state| {
+        if state.contains_key("held") {
+            return;
+        }
+        let mut held = BTreeSet::new();
+        for holding in state.get("holdings").and_then(Value::as_array).into_iter().flatten() {
+            let Some(mint) = holding.get("mint").and_then(Value::as_str) else {
+                continue;
+            };
+            for wallet in holding.get("wallets").and_then(Value::as_object).into_iter().flat_map(Map::keys) {
+                held.insert((wallet.clone(), mint.to_string()));
+            }
+        }
+        for tier in state.get("alert_tiers").and_then(Value::as_array).into_iter().flatten() {
+            if let (Some(wallet), Some(mint)) = (tier.get(0).and_then(Value::as_str), tier.get(1).and_then(Value::as_str)) {
+                if !wallet.is_empty() {
+                    held.insert((wallet.to_string(), mint.to_string()));
+                }
+            }
+        }
+        state.insert("held".to_string(), json!(held));
+    })
+}
+
+/// 把数据目录升级到当前版本，返回执行的迁移数。
+///
+/// 每次迁移成功后立即写入版本号，中途失败时下次启动从失败的迁移继续。
+/// 数据目录的版本比程序新时拒绝启动，避免旧版本程序写坏新格式的数据
+pub fn apply(data_dir: &Path) -> Result<usize> {
+    let path = data_dir.join(VERSION_FILE);
+    let current = match fs::read_to_string(&path) {
+        Ok(content) => content
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| anyhow!("Invalid schema version '{}' in {}", content.trim(), path.display()))?,
+        Err(e) if e.kind() == ErrorKind::NotFound => 0,
+        Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+    };
+    let latest = latest_version();
+    if current > latest {
+        return Err(anyhow!(
+            "Data directory {} has schema version {}, newer than version {} supported by this build",
+            data_dir.display(),
+            current,
+            latest
+        ));
+    }
+
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|migration| migration.version > current).collect();
+    for migration in &pending {
+        info!("Migrating {} to schema version {} ({})", data_dir.display(), migration.version, migration.name);
+        (migration.up)(data_dir)
+            .with_context(|| format!("Migration {} ({}) failed for {}", migration.version, migration.name, data_dir.display()))?;
+        fs::write(&path, migration.version.to_string())
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(pending.len())
+}
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// 状态数据库的一次升级。语句中的 `{serial}` 换成连接的自增主键类型
+struct SqlMigration {
+    version: i64,
+    name: &'static str,
+    statements: &'static [&'static str],
+}
+
+/// 状态数据库的所有迁移，规则与数据目录的迁移相同
+const SQL_MIGRATIONS: &[SqlMigration] = &[
+    SqlMigration {
+        version: 1,
+        name: "baseline",
+        // 记录版本之前创建的数据库已经有这些表
+        statements: &[
+            "CREATE TABLE IF NOT EXISTS monitor_state (
+                profile TEXT PRIMARY KEY,
+                saved_at BIGINT NOT NULL,
+                content TEXT NOT NULL
+            )",
+            "CREATE TABLE IF NOT EXISTS instance_state (
+                profile TEXT NOT NULL,
+                instance TEXT NOT NULL,
+                saved_at BIGINT NOT NULL,
+                content TEXT NOT NULL,
+                PRIMARY KEY (profile, instance)
+            )",
+            "CREATE TABLE IF NOT EXISTS holdings (
+                profile TEXT NOT NULL,
+                mint TEXT NOT NULL,
+                revision BIGINT NOT NULL,
+                content TEXT,
+                updated_at BIGINT NOT NULL,
+                updated_by TEXT NOT NULL,
+                locked_by TEXT,
+                locked_until BIGINT NOT NULL DEFAULT 0,
+                PRIMARY KEY (profile, mint)
+            )",
+            "CREATE TABLE IF NOT EXISTS trade_claims (
+                profile TEXT NOT NULL,
+                signature TEXT NOT NULL,
+                wallet TEXT NOT NULL,
+                mint TEXT NOT NULL,
+                side TEXT NOT NULL,
+                instance TEXT NOT NULL,
+                claimed_at BIGINT NOT NULL,
+                PRIMARY KEY (profile, signature, wallet, mint, side)
+            )",
+            "CREATE TABLE IF NOT EXISTS alert_keys (
+                profile TEXT NOT NULL,
+                key TEXT NOT NULL,
+                instance TEXT NOT NULL,
+                claimed_at BIGINT NOT NULL,
+                PRIMARY KEY (profile, key)
+            )",
+            "CREATE TABLE IF NOT EXISTS trades (
+                id {serial},
+                profile TEXT NOT NULL,
+                timestamp BIGINT NOT NULL,
+                signature TEXT NOT NULL,
+                wallet TEXT NOT NULL,
+                entry TEXT NOT NULL
+            )",
+            "CREATE INDEX IF NOT EXISTS trades_profile_timestamp ON trades (profile, timestamp)",
+            "CREATE TABLE IF NOT EXISTS alert_history (
+                id {serial},
+                profile TEXT NOT NULL,
+                timestamp BIGINT NOT NULL,
+                entry TEXT NOT NULL
+            )",
+            "CREATE INDEX IF NOT EXISTS alert_history_profile_timestamp ON alert_history (profile, timestamp)",
+        ],
+    },
+];
+
+/// 数据库当前的版本，还没有执行过迁移时为 0
+pub fn sql_version<C: SqlConnection>(connection: &mut C) -> Result<i64> {
+    let rows = connection.query("SELECT MAX(version) FROM schema_migrations", &[])?;
+    let version = rows.into_iter().next().and_then(|row| row.into_iter().next().flatten());
+    Ok(version.map(|version| version.parse()).transpose()?.unwrap_or(0))
+}
+
+pub fn latest_sql_version() -> i64 {
+    SQL_MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// 把状态数据库升级到当前版本，返回执行的迁移数。
+///
+/// 版本记录在 `schema_migrations` 表中，每个迁移和它的版本在同一个事务中写入。多个实例同时启动时
+/// 后提交的实例写入版本失败，重新读取版本后跳过已经由其他实例完成的迁移。数据库的版本比程序新时拒绝使用
+pub fn apply_sql<C: SqlConnection>(connection: &mut C) -> Result<usize> {
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at BIGINT NOT NULL
+        )",
+        &[],
+    )?;
+    let latest = latest_sql_version();
+    let current = sql_version(connection)?;
+    if current > latest {
+        return Err(anyhow!("Database has schema version {}, newer than version {} supported by this build", current, latest));
+    }
+
+    let mut applied = 0;
+    for migration in SQL_MIGRATIONS.iter().filter(|migration| migration.version > current) {
+        info!("Migrating {} to schema version {} ({})", connection.describe(), migration.version, migration.name);
+        connection.execute("BEGIN", &[])?;
+        let result = migration
+            .statements
+            .iter()
+            .try_for_each(|statement| connection.execute(&statement.replace("{serial}", C::SERIAL), &[]).map(|_| ()))
+            .and_then(|()| {
+                connection.execute(
+                    "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, ?3)",
+                    &[SqlValue::Int(migration.version), SqlValue::Text(migration.name), SqlValue::Int(Utc::now().timestamp())],
+                )
+            })
+            .and_then(|_| connection.execute("COMMIT", &[]));
+        match result {
+            Ok(_) => applied += 1,
+            Err(e) => {
+                let _ = connection.execute("ROLLBACK", &[]);
+                if sql_version(connection)? < migration.version {
+                    return Err(e).with_context(|| format!("Migration {} ({}) failed", migration.version, migration.name));
+                }
+            }
+        }
+    }
+    Ok(applied)
+}
@@ -0,0 +1,139 @@
+use crate::{checkpoint::SIGNATURES_PAGE, endpoints::RpcEndpoint, error::MonitorError};
+use log::{debug, warn};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+/// 无法查询 mint 账户时使用的小数位数。pump 发行的代币都是 6 位
+pub(crate) const DEFAULT_TOKEN_DECIMALS: u8 = 6;
+const DECIMALS_OFFSET: usize = 44;  // mint 账户中 decimals 的位置，前面是 mint_authority(36) 和 supply(8)
+const FAILED_LOOKUP_TTL: Duration = Duration::from_secs(60);  // 查询失败后这段时间内直接使用默认值
+const MAX_FAILED_LOOKUPS: usize = 1000;  // 超过后清理过期的失败记录
+const CREATION_LOOKUP_PAGES: usize = 3;  // 查询创建时间时最多读取的交易历史页数，交易更多的代币查不到
+
+/// pump 发行的代币总量固定为 10 亿
+pub(crate) const PUMP_TOKEN_SUPPLY: f64 = 1_000_000_000.0;
+
+/// serde 默认值，没有记录小数位数的旧数据按默认值处理
+pub(crate) fn default_decimals() -> u8 {
+    DEFAULT_TOKEN_DECIMALS
+}
+
+/// 按小数位数把原始数量换算为代币数量
+pub(crate) fn to_tokens(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
+
+/// pump 代币按价格（SOL）计算的市值（SOL）
+pub(crate) fn market_cap(price: f64) -> f64 {
+    price * PUMP_TOKEN_SUPPLY
+}
+
+/// 代币的小数位数，通过 RPC 读取 mint 账户后缓存。查询失败时使用默认值，`FAILED_LOOKUP_TTL` 内不再查询，
+/// RPC 不可用时同一代币的每笔交易不会各等一次超时
+pub struct MintDecimals {
+    rpc: Option<RpcEndpoint>,
+    cache: RwLock<HashMap<String, u8>>,
+    failed: RwLock<HashMap<String, Instant>>,  // 代币 -> 上次查询失败的时间
+}
+
+impl MintDecimals {
+    pub fn new(rpc: Option<RpcEndpoint>) -> Self {
+        Self {
+            rpc,
+            cache: RwLock::new(HashMap::new()),
+            failed: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 已缓存的小数位数，不发起查询，没有缓存时为默认值
+    pub fn cached(&self, mint: &str) -> u8 {
+        self.cache.read().unwrap().get(mint).copied().unwrap_or(DEFAULT_TOKEN_DECIMALS)
+    }
+
+    /// 记录从其他来源得知的小数位数，例如恢复的持仓或代币账户通知
+    pub fn insert(&self, mint: &str, decimals: u8) {
+        self.cache.write().unwrap().insert(mint.to_string(), decimals);
+    }
+
+    /// 代币的小数位数，没有缓存时查询 mint 账户。没有配置 RPC 时直接使用默认值
+    pub async fn get(&self, mint: &str) -> u8 {
+        if let Some(decimals) = self.cache.read().unwrap().get(mint) {
+            return *decimals;
+        }
+        let Some(rpc) = &self.rpc else {
+            return DEFAULT_TOKEN_DECIMALS;
+        };
+        if self.failed.read().unwrap().get(mint).is_some_and(|at| at.elapsed() < FAILED_LOOKUP_TTL) {
+            return DEFAULT_TOKEN_DECIMALS;
+        }
+        match fetch_decimals(&rpc.client(), mint).await {
+            Ok(decimals) => {
+                debug!("{} has {} decimals", mint, decimals);
+                self.insert(mint, decimals);
+                self.failed.write().unwrap().remove(mint);
+                decimals
+            }
+            Err(e) => {
+                warn!("Failed to look up decimals of {}, assuming {}: {}", mint, DEFAULT_TOKEN_DECIMALS, e);
+                let mut failed = self.failed.write().unwrap();
+                if failed.len() >= MAX_FAILED_LOOKUPS {
+                    failed.retain(|_, at| at.elapsed() < FAILED_LOOKUP_TTL);
+                }
+                failed.insert(mint.to_string(), Instant::now());
+                DEFAULT_TOKEN_DECIMALS
+            }
+        }
+    }
+}
+
+/// 读取 mint 账户中的小数位数，SPL Token 和 Token-2022 的 mint 布局前部相同
+pub async fn fetch_decimals(client: &RpcClient, mint: &str) -> Result<u8, MonitorError> {
+    let key = Pubkey::from_str(mint).map_err(|e| MonitorError::config(format!("Invalid mint {}: {}", mint, e)))?;
+    let data = client
+        .get_account_data(&key)
+        .await
+        .map_err(|e| MonitorError::rpc(format!("Failed to fetch mint account {}: {}", mint, e)))?;
+    data.get(DECIMALS_OFFSET)
+        .copied()
+        .ok_or_else(|| MonitorError::rpc(format!("Mint account {} is too short ({} bytes)", mint, data.len())))
+}
+
+/// 代币的创建时间：mint 账户最早一笔交易的区块时间。交易历史超过 `CREATION_LOOKUP_PAGES` 页时为 None
+pub async fn fetch_creation_time(client: &RpcClient, mint: &str) -> Result<Option<i64>, MonitorError> {
+    let key = Pubkey::from_str(mint).map_err(|e| MonitorError::config(format!("Invalid mint {}: {}", mint, e)))?;
+    let mut before: Option<Signature> = None;
+    let mut oldest = None;
+    for _ in 0..CREATION_LOOKUP_PAGES {
+        let page = client
+            .get_signatures_for_address_with_config(
+                &key,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: None,
+                    limit: Some(SIGNATURES_PAGE),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                },
+            )
+            .await
+            .map_err(|e| MonitorError::rpc(format!("Failed to fetch signatures of {}: {}", mint, e)))?;
+        // 上一页刚好是最后一页
+        let Some(last) = page.last() else {
+            return Ok(oldest);
+        };
+        if page.len() < SIGNATURES_PAGE {
+            return Ok(last.block_time);
+        }
+        oldest = last.block_time;
+        before = Some(
+            Signature::from_str(&last.signature)
+                .map_err(|e| MonitorError::rpc(format!("Invalid signature {}: {}", last.signature, e)))?,
+        );
+    }
+    Ok(None)
+}
@@ -1,7 +1,13 @@
-use serde::{Deserialize, Serialize};
+use crate::{metadata::TokenMetadata, oracle::BondingCurve};
+use serde::{
+    de::{self, IgnoredAny, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
 use serde_json::Value;
+use std::{borrow::Cow, fmt, marker::PhantomData, ops::Deref};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[allow(dead_code)]
 pub struct TokenPosition {
     pub mint: String,
     pub amount: f64,
@@ -9,6 +15,7 @@ pub struct TokenPosition {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[allow(dead_code)]
 pub struct HeliusMessage {
     pub json_rpc: Option<String>,
     pub method: Option<String>,
@@ -17,6 +24,7 @@ pub struct HeliusMessage {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[allow(dead_code)]
 pub struct HeliusParams {
     #[serde(rename = "result")]
     pub result: HeliusResult,
@@ -24,17 +32,20 @@ pub struct HeliusParams {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[allow(dead_code)]
 pub struct HeliusResult {
     pub context: Context,
     pub value: AccountValue,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[allow(dead_code)]
 pub struct Context {
     pub slot: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[allow(dead_code)]
 pub struct AccountValue {
     pub lamports: u64,
     pub data: Value,
@@ -44,7 +55,85 @@ pub struct AccountValue {
     pub rent_epoch: u64,
 }
 
+/// WebSocket 推送的订阅消息。字符串尽量借用原始文本，只有包含转义字符时才分配
+#[derive(Debug, Deserialize)]
+pub struct Notification<'a> {
+    pub id: Option<u64>,  // 只有订阅请求的回复带 id
+    pub error: Option<IgnoredAny>,  // 订阅请求失败时的错误
+    pub result: Option<u64>,  // 订阅请求成功时的订阅 ID，通知中的 `subscription` 与它对应
+    #[serde(borrow)]
+    pub method: Option<JsonStr<'a>>,
+    #[serde(borrow)]
+    pub params: Option<NotificationParams<'a>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationParams<'a> {
+    #[serde(borrow)]
+    pub result: NotificationResult<'a>,
+    pub subscription: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationResult<'a> {
+    pub context: Option<Context>,
+    #[serde(borrow)]
+    pub value: NotificationValue<'a>,
+}
+
+/// logsNotification 和 programNotification 的 value 字段合并在一起
+#[derive(Debug, Deserialize)]
+pub struct NotificationValue<'a> {
+    #[serde(borrow)]
+    pub signature: Option<JsonStr<'a>>,
+    #[serde(borrow, default)]
+    pub logs: Vec<JsonStr<'a>>,
+    #[serde(borrow)]
+    pub pubkey: Option<JsonStr<'a>>,
+    pub account: Option<Value>,  // 只有代币账户通知使用，数量很少
+    pub err: Option<Value>,  // 日志通知中交易失败的原因，成功时为 null
+}
+
+/// 尽量借用的 JSON 字符串。serde 只对直接的 `Cow` 字段借用，
+/// 放在 `Option` 或集合中的 `Cow` 总是分配，所以手动实现
+#[derive(Debug)]
+pub struct JsonStr<'a>(pub Cow<'a, str>);
+
+impl Deref for JsonStr<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for JsonStr<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct JsonStrVisitor<'a>(PhantomData<JsonStr<'a>>);
+
+        impl<'de: 'a, 'a> Visitor<'de> for JsonStrVisitor<'a> {
+            type Value = JsonStr<'a>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string")
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, value: &'de str) -> Result<Self::Value, E> {
+                Ok(JsonStr(Cow::Borrowed(value)))
+            }
+
+            // 包含转义字符时无法借用
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                Ok(JsonStr(Cow::Owned(value.to_string())))
+            }
+        }
+
+        deserializer.deserialize_str(JsonStrVisitor(PhantomData))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
+#[allow(dead_code)]
 pub struct TokenTransfer {
     pub mint: String,
     pub amount: f64,
@@ -53,22 +142,154 @@ pub struct TokenTransfer {
     pub to_user_account: String,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AlertType {
     PriceAlert,
+    Report,
+    Custom,
+    Warning,
+    WalletFlat,
+    NewPosition,  // 目标钱包第一次买入一个代币
+    Sell,  // 目标钱包卖出
+    PositionClosed,  // 目标钱包卖出后清仓
+    Activity,
+    Reminder,
+    Holders,
+    Buyers,  // 持仓代币的新买家减少
+    Graduation,  // 持仓代币的 bonding curve 完成
+    Signal,  // 其他监控发来的信号
+    #[allow(dead_code)]
     Error,
 }
 
+impl AlertType {
+    pub fn severity(&self) -> AlertSeverity {
+        match self {
+            AlertType::Report | AlertType::Custom | AlertType::Activity | AlertType::Reminder => AlertSeverity::Info,
+            AlertType::PriceAlert
+            | AlertType::WalletFlat
+            | AlertType::NewPosition
+            | AlertType::Sell
+            | AlertType::PositionClosed
+            | AlertType::Holders
+            | AlertType::Buyers
+            | AlertType::Graduation
+            | AlertType::Signal => AlertSeverity::Notice,
+            AlertType::Warning | AlertType::Error => AlertSeverity::Critical,
+        }
+    }
+}
+
+/// 提醒的严重程度，用于选择投递链
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSeverity {
+    Info,
+    Notice,
+    Critical,
+}
+
+/// 提醒的发送目标
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSink {
+    #[default]
+    All,
+    Telegram,
+    Websocket,
+}
+
 #[derive(Debug, Clone)]
 pub struct Alert {
     pub message: String,
     pub alert_type: AlertType,
     pub timestamp: i64,
+    pub mint: Option<String>,
+    pub holding: Option<HoldingSnapshot>,  // Discord embed 中的价格和盈亏字段
+    pub signal: Option<String>,  // WebSocket 通道发送的信号名
+    pub provenance: Option<SignalProvenance>,  // 信号的来源，随信号一起发送
+    pub token: Option<TokenMetadata>,  // 代币的名称、符号和图片，投递时从缓存中读取
+    pub trade: Option<TradeRef>,  // 提醒对应的目标钱包交易，Telegram 消息带标注按钮
+}
+
+/// 交易提醒对应的目标钱包交易，标注按签名记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeRef {
+    pub signature: String,
+    pub wallet: String,
+    pub mint: String,
 }
 
-#[derive(Debug, Serialize)]
+/// 信号的来源和置信度，下游可以按规则筛选信号、按置信度决定仓位大小
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalProvenance {
+    pub rule: String,  // 触发信号的规则：价格提醒为 `price_alert`，声明式规则为规则名
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<String>,  // 触发信号的目标钱包
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet_label: Option<String>,  // 配置文件 `wallet_labels` 中的钱包名称
+    pub confidence: f64,  // 0 到 1
+    pub price: f64,  // 触发时观察到的价格（SOL）
+    pub slot: u64,  // 触发信号的交易所在的 slot
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TradeSignal {
     pub signal: String,
     pub mint: String,
+    pub timestamp: i64,  // 产生信号的提醒时间
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,  // 过期时间，之后不再发送；None 表示不过期
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<SignalProvenance>,
+}
+
+/// 从 pump 程序日志中解码出的交易事件
+#[derive(Debug, Clone)]
+pub struct TradeEvent {
+    pub mint: String,
+    pub user: String,
+    pub is_buy: bool,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    pub slot: u64,  // 交易所在的 slot
+    pub decimals: u8,  // 代币的小数位数
+    pub layout: &'static str,  // 解码时使用的事件布局版本
+    pub reserves: Option<BondingCurve>,  // 成交后 bonding curve 的虚拟储备，PumpSwap 和回放的历史交易没有
+}
+
+impl TradeEvent {
+    /// 成交后 bonding curve 的现价（SOL）。`sol_amount / token_amount` 是这笔交易的成交均价，包含滑点，
+    /// 不是成交后的价格。没有储备时为 None
+    pub fn spot_price(&self) -> Option<f64> {
+        self.reserves?.spot_price()
+    }
+}
+
+/// 持仓的只读快照，供插件、报告等外部模块使用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HoldingSnapshot {
+    pub mint: String,
+    pub amount: f64,
+    pub avg_price: f64,
+    pub current_price: f64,
+    pub total_cost: f64,
+    pub price_change: i32,
+}
+
+/// 最近处理过的交易，保存原始数据用于排查异常提醒
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordedTrade {
     pub timestamp: i64,
+    pub slot: u64,
+    pub signature: String,
+    pub mint: String,
+    pub user: String,
+    pub is_buy: bool,
+    pub is_target: bool,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    pub price: f64,
+    pub raw: String,
 }
@@ -0,0 +1,430 @@
+use crate::{
+    endpoints::RpcEndpoint,
+    error::MonitorError,
+    mints::{to_tokens, DEFAULT_TOKEN_DECIMALS},
+    wallet_monitor::{PUMP_PROGRAM_ID, SOL_DECIMALS},
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::debug;
+use serde_json::Value;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+pub(crate) const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const JUPITER_PRICE_URL: &str = "https://lite-api.jup.ag/price/v3";
+const PYTH_HERMES_URL: &str = "https://hermes.pyth.network/v2/updates/price/latest";
+// Pyth 的 SOL/USD 价格源
+const PYTH_SOL_USD_FEED: &str = "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
+const STREAM_PRICE_RETENTION: Duration = Duration::from_secs(60 * 60); // 超过后清理没有新交易的代币价格
+const MAX_CACHED_PRICES: usize = 1000; // 超过后清理过期的缓存
+const ERROR_CACHE_TTL: Duration = Duration::from_secs(5); // 查询失败后这段时间内直接返回同样的错误，不再请求
+
+/// 价格来源。代币价格以 SOL 计价；不支持的查询返回 None，调用方回退到链上交易价格
+#[async_trait]
+pub trait PriceOracle: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// SOL 的美元价格
+    async fn get_sol_usd(&self) -> Result<Option<f64>>;
+
+    /// 代币的 SOL 价格
+    async fn get_token_price(&self, mint: &str) -> Result<Option<f64>>;
+
+    /// 观察到一笔交易，只有基于交易流的 oracle 需要
+    fn observe_trade(&self, _mint: &str, _price: f64) {}
+}
+
+/// 可配置的 oracle 类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OracleKind {
+    Stream,        // 最近一笔交易的成交价
+    BondingCurve,  // 按 bonding curve 账户的虚拟储备计算
+    Jupiter,       // Jupiter Price API
+    Pyth,          // 只提供 SOL/USD
+}
+
+impl FromStr for OracleKind {
+    type Err = anyhow::Error;
+
+    fn from_str(kind: &str) -> Result<Self> {
+        match kind {
+            "stream" => Ok(OracleKind::Stream),
+            "bonding_curve" => Ok(OracleKind::BondingCurve),
+            "jupiter" => Ok(OracleKind::Jupiter),
+            "pyth" => Ok(OracleKind::Pyth),
+            _ => Err(anyhow!(
+                "Unknown price oracle '{}', expected stream, bonding_curve, jupiter or pyth",
+                kind
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OracleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OracleKind::Stream => write!(f, "stream"),
+            OracleKind::BondingCurve => write!(f, "bonding_curve"),
+            OracleKind::Jupiter => write!(f, "jupiter"),
+            OracleKind::Pyth => write!(f, "pyth"),
+        }
+    }
+}
+
+/// 成本和盈亏的记账货币
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaseCurrency {
+    Sol,
+    Usd,  // 按每笔交易时的 SOL/USD 汇率记录成本
+}
+
+impl FromStr for BaseCurrency {
+    type Err = anyhow::Error;
+
+    fn from_str(currency: &str) -> Result<Self> {
+        match currency.to_ascii_lowercase().as_str() {
+            "sol" => Ok(BaseCurrency::Sol),
+            "usd" => Ok(BaseCurrency::Usd),
+            _ => Err(anyhow!("Unknown base currency '{}', expected sol or usd", currency)),
+        }
+    }
+}
+
+/// 按用途选择的 oracle：终端显示、价格提醒和记账用的 SOL/USD 汇率
+#[derive(Clone)]
+pub struct PriceOracles {
+    pub display: Arc<dyn PriceOracle>,
+    pub alerts: Arc<dyn PriceOracle>,
+    sol_usd: Option<Arc<dyn PriceOracle>>,  // 没有汇率来源时不记录
+}
+
+impl PriceOracles {
+    /// 两种用途选择同一类型时共用一个实例和缓存。
+    /// 汇率优先复用已配置的 jupiter 或 pyth，USD 记账时没有则单独使用 pyth
+    pub fn new(
+        display: OracleKind,
+        alerts: OracleKind,
+        rpc: Option<&RpcEndpoint>,
+        cache_ttl: Duration,
+        base_currency: BaseCurrency,
+    ) -> Result<Self> {
+        let display_oracle = build(display, rpc, cache_ttl)?;
+        let alerts_oracle = if alerts == display {
+            display_oracle.clone()
+        } else {
+            build(alerts, rpc, cache_ttl)?
+        };
+        let has_sol_usd = |kind: OracleKind| matches!(kind, OracleKind::Jupiter | OracleKind::Pyth);
+        let sol_usd = if has_sol_usd(display) {
+            Some(display_oracle.clone())
+        } else if has_sol_usd(alerts) {
+            Some(alerts_oracle.clone())
+        } else if base_currency == BaseCurrency::Usd {
+            Some(build(OracleKind::Pyth, rpc, cache_ttl)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            display: display_oracle,
+            alerts: alerts_oracle,
+            sol_usd,
+        })
+    }
+
+    /// 当前的 SOL/USD 汇率，没有来源或请求失败时为 None
+    pub async fn sol_usd(&self) -> Option<f64> {
+        let oracle = self.sol_usd.as_ref()?;
+        oracle
+            .get_sol_usd()
+            .await
+            .inspect_err(|e| debug!("{} price oracle failed for SOL/USD: {:?}", oracle.name(), e))
+            .ok()
+            .flatten()
+    }
+
+    pub fn observe_trade(&self, mint: &str, price: f64) {
+        self.display.observe_trade(mint, price);
+        if !Arc::ptr_eq(&self.display, &self.alerts) {
+            self.alerts.observe_trade(mint, price);
+        }
+    }
+}
+
+fn build(kind: OracleKind, rpc: Option<&RpcEndpoint>, cache_ttl: Duration) -> Result<Arc<dyn PriceOracle>> {
+    Ok(match kind {
+        OracleKind::Stream => Arc::new(StreamOracle::default()),
+        OracleKind::BondingCurve => {
+            let rpc = rpc.ok_or_else(|| anyhow!("The bonding_curve price oracle requires RPC_URL or HELIUS_API_KEY"))?;
+            Arc::new(CachedOracle::new(BondingCurveOracle::new(rpc), cache_ttl))
+        }
+        OracleKind::Jupiter => Arc::new(CachedOracle::new(JupiterOracle::default(), cache_ttl)),
+        OracleKind::Pyth => Arc::new(CachedOracle::new(PythOracle::default(), cache_ttl)),
+    })
+}
+
+/// 最近一笔交易的成交价，与持仓的当前价格一致
+#[derive(Default)]
+pub struct StreamOracle {
+    prices: Mutex<HashMap<String, (f64, Instant)>>,
+}
+
+#[async_trait]
+impl PriceOracle for StreamOracle {
+    fn name(&self) -> &'static str {
+        "stream"
+    }
+
+    async fn get_sol_usd(&self) -> Result<Option<f64>> {
+        Ok(None)
+    }
+
+    async fn get_token_price(&self, mint: &str) -> Result<Option<f64>> {
+        Ok(self.prices.lock().unwrap().get(mint).map(|(price, _)| *price))
+    }
+
+    fn observe_trade(&self, mint: &str, price: f64) {
+        let mut prices = self.prices.lock().unwrap();
+        if prices.len() >= MAX_CACHED_PRICES {
+            prices.retain(|_, (_, observed_at)| observed_at.elapsed() < STREAM_PRICE_RETENTION);
+        }
+        prices.insert(mint.to_string(), (price, Instant::now()));
+    }
+}
+
+/// pump bonding curve 账户中与定价相关的字段（原始单位）
+#[derive(Debug, Clone, Copy)]
+pub struct BondingCurve {
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub complete: bool,  // 已毕业，交易转到 AMM
+}
+
+impl BondingCurve {
+    /// 按虚拟储备计算的现价（SOL）
+    pub fn spot_price(&self) -> Option<f64> {
+        if self.complete || self.virtual_token_reserves == 0 {
+            return None;
+        }
+        let sol = self.virtual_sol_reserves as f64 / 10f64.powi(SOL_DECIMALS as i32);
+        // bonding curve 上的代币都是 pump 发行的，小数位数固定
+        let tokens = to_tokens(self.virtual_token_reserves, DEFAULT_TOKEN_DECIMALS);
+        Some(sol / tokens)
+    }
+}
+
+/// 代币的 bonding curve 账户地址，由 pump 程序按代币推导
+pub fn bonding_curve_address(mint: &str) -> Result<Pubkey, MonitorError> {
+    let program_id = Pubkey::from_str(PUMP_PROGRAM_ID).map_err(MonitorError::config)?;
+    let mint = Pubkey::from_str(mint).map_err(|e| MonitorError::config(format!("Invalid mint {}: {}", mint, e)))?;
+    Ok(Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &program_id).0)
+}
+
+/// 读取代币的 bonding curve 账户
+pub async fn fetch_bonding_curve(client: &RpcClient, mint: &str) -> Result<BondingCurve, MonitorError> {
+    find_bonding_curve(client, mint)
+        .await?
+        .ok_or_else(|| MonitorError::rpc(format!("{} has no bonding curve", mint)))
+}
+
+/// 读取代币的 bonding curve 账户，代币不是 pump 发行的（账户不存在）时为 None。
+/// 毕业后账户仍然保留，`complete` 为 true
+pub async fn find_bonding_curve(client: &RpcClient, mint: &str) -> Result<Option<BondingCurve>, MonitorError> {
+    let curve = bonding_curve_address(mint)?;
+    let account = client
+        .get_account_with_commitment(&curve, client.commitment())
+        .await
+        .map_err(|e| MonitorError::rpc(format!("Failed to fetch bonding curve {}: {}", curve, e)))?
+        .value;
+    let Some(data) = account.map(|account| account.data) else {
+        return Ok(None);
+    };
+
+    // 账户布局：discriminator(8) + virtual_token_reserves + virtual_sol_reserves
+    // + real_token_reserves + real_sol_reserves + token_total_supply（均为 u64）+ complete(bool)
+    if data.len() < 49 {
+        return Err(MonitorError::rpc(format!("Bonding curve account {} is too short ({} bytes)", curve, data.len())));
+    }
+    let read_u64 = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    Ok(Some(BondingCurve {
+        virtual_token_reserves: read_u64(8),
+        virtual_sol_reserves: read_u64(16),
+        complete: data[48] != 0,
+    }))
+}
+
+/// 读取 pump bonding curve 账户，用虚拟储备计算现价。代币毕业后返回 None
+pub struct BondingCurveOracle {
+    client: RpcClient,
+}
+
+impl BondingCurveOracle {
+    pub fn new(rpc: &RpcEndpoint) -> Self {
+        Self {
+            client: rpc.client(),
+        }
+    }
+}
+
+#[async_trait]
+impl PriceOracle for BondingCurveOracle {
+    fn name(&self) -> &'static str {
+        "bonding_curve"
+    }
+
+    async fn get_sol_usd(&self) -> Result<Option<f64>> {
+        Ok(None)
+    }
+
+    async fn get_token_price(&self, mint: &str) -> Result<Option<f64>> {
+        Ok(fetch_bonding_curve(&self.client, mint).await?.spot_price())
+    }
+}
+
+/// Jupiter Price API，返回美元价格，代币价格按 SOL/USD 换算
+#[derive(Default)]
+pub struct JupiterOracle {
+    client: reqwest::Client,
+}
+
+impl JupiterOracle {
+    async fn usd_prices(&self, mints: &[&str]) -> Result<Value> {
+        let response = self
+            .client
+            .get(JUPITER_PRICE_URL)
+            .query(&[("ids", mints.join(","))])
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+}
+
+#[async_trait]
+impl PriceOracle for JupiterOracle {
+    fn name(&self) -> &'static str {
+        "jupiter"
+    }
+
+    async fn get_sol_usd(&self) -> Result<Option<f64>> {
+        let prices = self.usd_prices(&[SOL_MINT]).await?;
+        Ok(prices[SOL_MINT]["usdPrice"].as_f64())
+    }
+
+    async fn get_token_price(&self, mint: &str) -> Result<Option<f64>> {
+        let prices = self.usd_prices(&[mint, SOL_MINT]).await?;
+        let (Some(token_usd), Some(sol_usd)) = (prices[mint]["usdPrice"].as_f64(), prices[SOL_MINT]["usdPrice"].as_f64()) else {
+            return Ok(None);
+        };
+        Ok((sol_usd > 0.0).then(|| token_usd / sol_usd))
+    }
+}
+
+/// Pyth Hermes，只提供 SOL/USD
+#[derive(Default)]
+pub struct PythOracle {
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl PriceOracle for PythOracle {
+    fn name(&self) -> &'static str {
+        "pyth"
+    }
+
+    async fn get_sol_usd(&self) -> Result<Option<f64>> {
+        let response: Value = self
+            .client
+            .get(PYTH_HERMES_URL)
+            .query(&[("ids[]", PYTH_SOL_USD_FEED), ("parsed", "true")])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let price = &response["parsed"][0]["price"];
+        let (Some(value), Some(expo)) = (price["price"].as_str().and_then(|p| p.parse::<i64>().ok()), price["expo"].as_i64()) else {
+            return Err(anyhow!("Unexpected Pyth response: {}", response));
+        };
+        Ok(Some(value as f64 * 10f64.powi(expo as i32)))
+    }
+
+    async fn get_token_price(&self, _mint: &str) -> Result<Option<f64>> {
+        Ok(None)
+    }
+}
+
+/// 查询结果或失败的原因，以及查询的时间
+type CachedPrice = (Result<Option<f64>, String>, Instant);
+
+/// 缓存远程 oracle 的结果，避免每次刷新都发请求。失败也缓存 `ERROR_CACHE_TTL`，
+/// oracle 不可用时每次刷新不会对每个持仓各等一次超时
+pub struct CachedOracle<O> {
+    inner: O,
+    ttl: Duration,
+    sol_usd: Mutex<Option<CachedPrice>>,
+    tokens: Mutex<HashMap<String, CachedPrice>>,
+}
+
+impl<O: PriceOracle> CachedOracle<O> {
+    pub fn new(inner: O, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            sol_usd: Mutex::new(None),
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 缓存仍然有效时返回缓存的结果
+    fn fresh(&self, cached: &CachedPrice) -> Option<Result<Option<f64>>> {
+        let (result, fetched_at) = cached;
+        let ttl = if result.is_ok() { self.ttl } else { ERROR_CACHE_TTL.min(self.ttl) };
+        (fetched_at.elapsed() < ttl).then(|| result.clone().map_err(|e| anyhow!(e)))
+    }
+}
+
+/// 要缓存的查询结果
+fn to_cached(result: &Result<Option<f64>>) -> CachedPrice {
+    let result = match result {
+        Ok(price) => Ok(*price),
+        Err(e) => Err(e.to_string()),
+    };
+    (result, Instant::now())
+}
+
+#[async_trait]
+impl<O: PriceOracle> PriceOracle for CachedOracle<O> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn get_sol_usd(&self) -> Result<Option<f64>> {
+        if let Some(result) = self.sol_usd.lock().unwrap().as_ref().and_then(|cached| self.fresh(cached)) {
+            return result;
+        }
+        let result = self.inner.get_sol_usd().await;
+        *self.sol_usd.lock().unwrap() = Some(to_cached(&result));
+        result
+    }
+
+    async fn get_token_price(&self, mint: &str) -> Result<Option<f64>> {
+        if let Some(result) = self.tokens.lock().unwrap().get(mint).and_then(|cached| self.fresh(cached)) {
+            return result;
+        }
+        let result = self.inner.get_token_price(mint).await;
+        let mut tokens = self.tokens.lock().unwrap();
+        if tokens.len() >= MAX_CACHED_PRICES {
+            tokens.retain(|_, (_, fetched_at)| fetched_at.elapsed() < self.ttl);
+        }
+        tokens.insert(mint.to_string(), to_cached(&result));
+        result
+    }
+}
@@ -0,0 +1,431 @@
+use crate::models::{AlertSeverity, AlertType, HoldingSnapshot, SignalProvenance, TradeRef};
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use solana_sdk::hash::hashv;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+const OUTBOX_FILE: &str = "outbox.jsonl";
+pub(crate) const DELIVERED_KEY_RETENTION_SECS: i64 = 24 * 60 * 60; // 已投递的幂等键保留时长
+const COMPACT_AFTER_LINES: usize = 10_000; // 日志超过此行数后压缩
+
+/// 等待投递的提醒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedAlert {
+    pub key: String,  // 幂等键，同一个键只投递一次
+    pub alert_type: AlertType,
+    pub message: String,
+    pub mint: Option<String>,
+    pub signal: Option<String>,  // 投递成功后发送的 WebSocket 信号
+    pub timestamp: i64,
+    #[serde(default)]
+    pub holding: Option<HoldingSnapshot>,  // 提醒时的持仓
+    #[serde(default)]
+    pub provenance: Option<Box<SignalProvenance>>,  // 信号的来源
+    #[serde(default)]
+    pub trade: Option<Box<TradeRef>>,  // 交易提醒对应的交易
+}
+
+impl QueuedAlert {
+    pub fn new(alert_type: AlertType, message: &str, mint: Option<String>, signal: Option<String>) -> Self {
+        let timestamp = Utc::now().timestamp();
+        // 幂等键只由类型、代币和内容决定，不含时间：保留期内内容相同的提醒视为重复，重启后重新生成的也一样
+        let key = hashv(&[
+            format!("{:?}", alert_type).as_bytes(),
+            message.as_bytes(),
+            mint.as_deref().unwrap_or_default().as_bytes(),
+        ])
+        .to_string();
+        Self {
+            key,
+            alert_type,
+            message: message.to_string(),
+            mint,
+            signal,
+            timestamp,
+            holding: None,
+            provenance: None,
+            trade: None,
+        }
+    }
+
+    pub fn with_holding(mut self, holding: HoldingSnapshot) -> Self {
+        self.holding = Some(holding);
+        self
+    }
+
+    pub fn with_provenance(mut self, provenance: Option<SignalProvenance>) -> Self {
+        self.provenance = provenance.map(Box::new);
+        self
+    }
+
+    /// 交易提醒的幂等键由交易决定，与消息内容和时间无关，共享状态的实例各自发现同一笔交易时只发送一次
+    pub fn with_trade(mut self, trade: TradeRef) -> Self {
+        self.key = event_key(&self.alert_type, &format!("trade:{}:{}:{}", trade.signature, trade.wallet, trade.mint));
+        self.trade = Some(Box::new(trade));
+        self
+    }
+
+    /// 提醒对应的事件（例如代币越过的价格档位），幂等键由事件决定，同上
+    pub fn with_event(mut self, event: &str) -> Self {
+        self.key = event_key(&self.alert_type, event);
+        self
+    }
+}
+
+fn event_key(alert_type: &AlertType, event: &str) -> String {
+    hashv(&[format!("{:?}", alert_type).as_bytes(), event.as_bytes()]).to_string()
+}
+
+/// 入队结果
+#[derive(Debug)]
+pub enum Pushed {
+    Queued,
+    Duplicate,            // 幂等键已存在
+    Coalesced,            // 替换了同一代币未投递的价格提醒
+    Dropped(QueuedAlert), // 队列已满，丢弃了最旧的低优先级提醒
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalEntry {
+    Enqueue(QueuedAlert),
+    Sending { key: String },  // 开始投递，之后中断的提醒可能已经送达
+    Done { key: String, timestamp: i64 },
+}
+
+/// 持久化的提醒发送队列。
+///
+/// 入队、开始投递和投递完成都追加写入 `outbox.jsonl`，重启时回放日志恢复未投递的提醒，
+/// 已投递的幂等键在保留期内会被拒绝再次入队。开始投递前先记录，投递中断（崩溃或重启）的提醒
+/// 重启后不再发送，由调用方写入 undelivered，避免重复送达。
+///
+/// 队列有容量上限：同一代币未投递的价格提醒只保留最新一条；队列满时丢弃最旧的
+/// info 级提醒，其次是 notice 级，critical 级提醒从不丢弃。正在投递的队首不受影响
+pub struct Outbox {
+    path: PathBuf,
+    pending: VecDeque<QueuedAlert>,
+    delivered: HashMap<String, i64>,  // 幂等键 -> 投递时间
+    sending: Option<String>,  // 正在投递的队首的幂等键
+    interrupted: Vec<QueuedAlert>,  // 上次运行中开始投递但没有完成的提醒
+    journal_lines: usize,
+    capacity: usize,
+    dropped: u64,
+    coalesced: u64,
+}
+
+impl Outbox {
+    pub fn open(data_dir: &Path, capacity: usize) -> Result<Self> {
+        let path = data_dir.join(OUTBOX_FILE);
+        let mut pending: VecDeque<QueuedAlert> = VecDeque::new();
+        let mut delivered = HashMap::new();
+        let mut sending = HashSet::new();
+
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                // 崩溃时最后一行可能不完整，跳过无法解析的行
+                match serde_json::from_str::<JournalEntry>(&line?) {
+                    Ok(JournalEntry::Enqueue(alert)) => pending.push_back(alert),
+                    Ok(JournalEntry::Sending { key }) => {
+                        sending.insert(key);
+                    }
+                    Ok(JournalEntry::Done { key, timestamp }) => {
+                        pending.retain(|alert| alert.key != key);
+                        delivered.insert(key, timestamp);
+                    }
+                    Err(_) => continue,
+                }
+            }
+        }
+
+        // 开始投递后中断的提醒可能已经送达，不再放回队列；压缩时记为完成
+        let now = Utc::now().timestamp();
+        let (interrupted, pending): (VecDeque<QueuedAlert>, VecDeque<QueuedAlert>) =
+            pending.into_iter().partition(|alert| sending.contains(&alert.key));
+        for alert in &interrupted {
+            delivered.insert(alert.key.clone(), now);
+        }
+
+        let mut outbox = Self {
+            path,
+            pending,
+            delivered,
+            sending: None,
+            interrupted: interrupted.into(),
+            journal_lines: 0,
+            capacity,
+            dropped: 0,
+            coalesced: 0,
+        };
+        outbox.compact()?;
+        Ok(outbox)
+    }
+
+    /// 入队并持久化，按容量策略合并或丢弃旧的提醒
+    pub fn push(&mut self, alert: QueuedAlert) -> Result<Pushed> {
+        if self.delivered.contains_key(&alert.key) || self.pending.iter().any(|p| p.key == alert.key) {
+            return Ok(Pushed::Duplicate);
+        }
+
+        let replaced = match alert.alert_type {
+            AlertType::PriceAlert => self.position_after_front(|pending| {
+                pending.alert_type == AlertType::PriceAlert && pending.mint == alert.mint
+            }),
+            _ => None,
+        };
+        let evicted = match replaced {
+            Some(_) => None,
+            None if self.pending.len() >= self.capacity => [AlertSeverity::Info, AlertSeverity::Notice]
+                .into_iter()
+                .find_map(|severity| self.position_after_front(|pending| pending.alert_type.severity() == severity)),
+            None => None,
+        };
+
+        self.append(&JournalEntry::Enqueue(alert.clone()))?;
+        self.pending.push_back(alert);
+
+        if let Some(index) = replaced {
+            self.remove_at(index)?;
+            self.coalesced += 1;
+            return Ok(Pushed::Coalesced);
+        }
+        if let Some(index) = evicted {
+            let dropped = self.remove_at(index)?;
+            self.dropped += 1;
+            return Ok(Pushed::Dropped(dropped));
+        }
+        Ok(Pushed::Queued)
+    }
+
+    /// 跳过正在投递的队首，查找第一个符合条件的提醒
+    fn position_after_front(&self, predicate: impl Fn(&QueuedAlert) -> bool) -> Option<usize> {
+        self.pending.iter().skip(1).position(predicate).map(|index| index + 1)
+    }
+
+    /// 移除未投递的提醒，在日志中记为完成，避免重启后恢复
+    fn remove_at(&mut self, index: usize) -> Result<QueuedAlert> {
+        let alert = self.pending.remove(index).expect("index within pending alerts");
+        self.append(&JournalEntry::Done { key: alert.key.clone(), timestamp: Utc::now().timestamp() })?;
+        Ok(alert)
+    }
+
+    /// 因队列已满被丢弃的提醒数量
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// 被更新的价格提醒替换的数量
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced
+    }
+
+    pub fn front(&self) -> Option<&QueuedAlert> {
+        self.pending.front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 取出上次运行中投递中断的提醒
+    pub fn take_interrupted(&mut self) -> Vec<QueuedAlert> {
+        std::mem::take(&mut self.interrupted)
+    }
+
+    /// 投递前记录队首开始投递，重试同一提醒时不重复记录
+    pub fn start(&mut self, key: &str) -> Result<()> {
+        if self.sending.as_deref() == Some(key) {
+            return Ok(());
+        }
+        self.append(&JournalEntry::Sending { key: key.to_string() })?;
+        self.sending = Some(key.to_string());
+        Ok(())
+    }
+
+    /// 标记投递完成
+    pub fn complete(&mut self, key: &str) -> Result<()> {
+        let now = Utc::now().timestamp();
+        self.sending = None;
+        self.pending.retain(|alert| alert.key != key);
+        self.delivered.insert(key.to_string(), now);
+        self.append(&JournalEntry::Done { key: key.to_string(), timestamp: now })?;
+
+        if self.journal_lines > COMPACT_AFTER_LINES {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, entry: &JournalEntry) -> Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        self.journal_lines += 1;
+        Ok(())
+    }
+
+    /// 只保留未投递的提醒和保留期内的幂等键，写入临时文件后替换
+    fn compact(&mut self) -> Result<()> {
+        let now = Utc::now().timestamp();
+        self.delivered.retain(|_, delivered_at| now - *delivered_at < DELIVERED_KEY_RETENTION_SECS);
+
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        let mut file = File::create(&tmp_path)?;
+        let mut lines = 0;
+        for (key, timestamp) in &self.delivered {
+            let entry = JournalEntry::Done { key: key.clone(), timestamp: *timestamp };
+            writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+            lines += 1;
+        }
+        for alert in &self.pending {
+            writeln!(file, "{}", serde_json::to_string(&JournalEntry::Enqueue(alert.clone()))?)?;
+            lines += 1;
+        }
+        if let Some(key) = &self.sending {
+            writeln!(file, "{}", serde_json::to_string(&JournalEntry::Sending { key: key.clone() })?)?;
+            lines += 1;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        self.journal_lines = lines;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每个测试独立的空数据目录
+    fn data_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("outbox-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn alert(alert_type: AlertType, message: &str) -> QueuedAlert {
+        QueuedAlert::new(alert_type, message, None, None)
+    }
+
+    fn price_alert(mint: &str, message: &str) -> QueuedAlert {
+        QueuedAlert::new(AlertType::PriceAlert, message, Some(mint.to_string()), None)
+    }
+
+    fn journal(dir: &Path) -> Vec<String> {
+        fs::read_to_string(dir.join(OUTBOX_FILE)).unwrap().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn replay_restores_pending_and_holds_back_interrupted() {
+        let dir = data_dir("replay");
+        let (sent, interrupted, pending) = (
+            alert(AlertType::Sell, "sent"),
+            alert(AlertType::Sell, "interrupted"),
+            alert(AlertType::Sell, "pending"),
+        );
+        {
+            let mut outbox = Outbox::open(&dir, 10).unwrap();
+            for alert in [&sent, &interrupted, &pending] {
+                assert!(matches!(outbox.push(alert.clone()).unwrap(), Pushed::Queued));
+            }
+            outbox.start(&sent.key).unwrap();
+            outbox.complete(&sent.key).unwrap();
+            outbox.start(&interrupted.key).unwrap();
+        }
+
+        let mut outbox = Outbox::open(&dir, 10).unwrap();
+        let taken = outbox.take_interrupted();
+        assert_eq!(taken.iter().map(|alert| &alert.key).collect::<Vec<_>>(), [&interrupted.key]);
+        assert_eq!(outbox.len(), 1);
+        assert_eq!(outbox.front().unwrap().key, pending.key);
+        // 已投递和投递中断的提醒在保留期内都不再入队
+        assert!(matches!(outbox.push(sent).unwrap(), Pushed::Duplicate));
+        assert!(matches!(outbox.push(interrupted).unwrap(), Pushed::Duplicate));
+        assert!(matches!(outbox.push(pending).unwrap(), Pushed::Duplicate));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compaction_keeps_pending_and_recent_keys() {
+        let dir = data_dir("compact");
+        let (delivered, pending) = (alert(AlertType::Report, "delivered"), alert(AlertType::Report, "pending"));
+        let expired = JournalEntry::Done {
+            key: "expired".to_string(),
+            timestamp: Utc::now().timestamp() - DELIVERED_KEY_RETENTION_SECS - 1,
+        };
+        {
+            let mut outbox = Outbox::open(&dir, 10).unwrap();
+            outbox.append(&expired).unwrap();
+            outbox.push(delivered.clone()).unwrap();
+            outbox.start(&delivered.key).unwrap();
+            outbox.complete(&delivered.key).unwrap();
+            outbox.push(pending.clone()).unwrap();
+        }
+        assert_eq!(journal(&dir).len(), 5);
+
+        // 重启时压缩：过期的幂等键被丢弃，只剩已投递的键和未投递的提醒
+        let mut outbox = Outbox::open(&dir, 10).unwrap();
+        let lines = journal(&dir);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains(&delivered.key));
+        assert!(lines[1].contains(&pending.key));
+        assert_eq!(outbox.len(), 1);
+        let mut reused = alert(AlertType::Report, "expired");
+        reused.key = "expired".to_string();
+        assert!(matches!(outbox.push(reused).unwrap(), Pushed::Queued));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn full_queue_evicts_info_then_notice_but_never_critical() {
+        let dir = data_dir("evict");
+        let mut outbox = Outbox::open(&dir, 3).unwrap();
+        let info = alert(AlertType::Report, "info");
+        let notice = alert(AlertType::Sell, "notice");
+        outbox.push(alert(AlertType::Warning, "front")).unwrap();
+        outbox.push(info.clone()).unwrap();
+        outbox.push(notice.clone()).unwrap();
+
+        match outbox.push(alert(AlertType::Warning, "critical 1")).unwrap() {
+            Pushed::Dropped(dropped) => assert_eq!(dropped.key, info.key),
+            other => panic!("expected the info alert to be dropped, got {:?}", other),
+        }
+        match outbox.push(alert(AlertType::Warning, "critical 2")).unwrap() {
+            Pushed::Dropped(dropped) => assert_eq!(dropped.key, notice.key),
+            other => panic!("expected the notice alert to be dropped, got {:?}", other),
+        }
+        assert!(matches!(outbox.push(alert(AlertType::Warning, "critical 3")).unwrap(), Pushed::Queued));
+        assert_eq!(outbox.len(), 4);
+        assert_eq!(outbox.dropped(), 2);
+
+        // 丢弃的提醒记为完成，重启后不会恢复
+        drop(outbox);
+        let outbox = Outbox::open(&dir, 3).unwrap();
+        assert_eq!(outbox.len(), 4);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn price_alerts_coalesce_behind_front() {
+        let dir = data_dir("coalesce");
+        let mut outbox = Outbox::open(&dir, 10).unwrap();
+        let front = price_alert("mint", "front");
+        outbox.push(front.clone()).unwrap();
+        outbox.start(&front.key).unwrap();
+
+        // 正在投递的队首不被替换
+        assert!(matches!(outbox.push(price_alert("mint", "second")).unwrap(), Pushed::Queued));
+        assert!(matches!(outbox.push(price_alert("other", "other")).unwrap(), Pushed::Queued));
+        assert!(matches!(outbox.push(price_alert("mint", "third")).unwrap(), Pushed::Coalesced));
+        assert_eq!(outbox.len(), 3);
+        assert_eq!(outbox.coalesced(), 1);
+        assert_eq!(outbox.front().unwrap().key, front.key);
+        assert_eq!(outbox.pending.back().unwrap().message, "third");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
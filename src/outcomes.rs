@@ -0,0 +1,144 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+pub(crate) const OUTCOMES_FILE: &str = "alert_outcomes.jsonl";
+/// 提醒之后采样价格的时间点（秒）
+pub const HORIZONS: [(i64, &str); 3] = [(5 * 60, "5m"), (30 * 60, "30m"), (2 * 60 * 60, "2h")];
+const SAMPLE_GRACE_SECS: i64 = 120;  // 超过采样时间这么久才采样（例如重启期间错过）时不记录价格
+
+/// 一次价格提醒和之后各时间点的价格
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertOutcome {
+    pub timestamp: i64,
+    pub mint: String,
+    pub tier: f64,   // 提醒的档位
+    pub price: f64,  // 提醒时的价格
+    pub later: Vec<Option<f64>>,  // 按 HORIZONS 顺序的价格，没有报价或错过采样时为 None
+}
+
+impl AlertOutcome {
+    pub fn new(timestamp: i64, mint: &str, tier: f64, price: f64) -> Self {
+        Self {
+            timestamp,
+            mint: mint.to_string(),
+            tier,
+            price,
+            later: Vec::with_capacity(HORIZONS.len()),
+        }
+    }
+
+    /// 下一次采样的时间，所有时间点都采样后为 None
+    pub fn next_due(&self) -> Option<i64> {
+        HORIZONS.get(self.later.len()).map(|(secs, _)| self.timestamp + secs)
+    }
+
+    /// 记录到期的采样，错过采样时间太久的记为 None
+    pub fn sample(&mut self, now: i64, price: Option<f64>) {
+        let Some(due) = self.next_due() else {
+            return;
+        };
+        self.later.push(price.filter(|_| now - due <= SAMPLE_GRACE_SECS));
+    }
+
+    /// 第 `index` 个时间点相对提醒价格的涨跌幅（百分比）
+    fn change(&self, index: usize) -> Option<f64> {
+        let later = (*self.later.get(index)?)?;
+        (self.price > 0.0).then(|| (later / self.price - 1.0) * 100.0)
+    }
+}
+
+/// 采样完成的提醒结果，追加写入数据目录中的 `alert_outcomes.jsonl`
+pub struct OutcomeLog {
+    path: PathBuf,
+}
+
+impl OutcomeLog {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join(OUTCOMES_FILE),
+        }
+    }
+
+    pub fn append(&self, outcome: &AlertOutcome) -> Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(outcome)?)?;
+        Ok(())
+    }
+}
+
+/// 读取数据目录中的提醒结果，跳过无法解析的行
+pub fn load(data_dir: &Path) -> Result<Vec<AlertOutcome>> {
+    let path = data_dir.join(OUTCOMES_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+    };
+    Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+/// 一个时间点的命中率：之后价格高于提醒价格的比例
+fn hit_rate(outcomes: &[&AlertOutcome], index: usize) -> Option<(usize, usize, f64)> {
+    let mut changes: Vec<f64> = outcomes.iter().filter_map(|outcome| outcome.change(index)).collect();
+    if changes.is_empty() {
+        return None;
+    }
+    changes.sort_by(f64::total_cmp);
+    let hits = changes.iter().filter(|change| **change > 0.0).count();
+    Some((hits, changes.len(), changes[changes.len() / 2]))
+}
+
+/// 提醒结果的统计，`since` 之后的提醒，按整体和按档位列出各时间点的命中率
+pub fn summarize(outcomes: &[AlertOutcome], since: i64, days: u64) -> String {
+    let recent: Vec<&AlertOutcome> = outcomes.iter().filter(|outcome| outcome.timestamp >= since).collect();
+    let mut message = format!("📊 Alert outcomes, last {} day(s)\n{} pump alert(s)\n", days, recent.len());
+    if recent.is_empty() {
+        return message;
+    }
+
+    message.push('\n');
+    for (index, (_, label)) in HORIZONS.iter().enumerate() {
+        match hit_rate(&recent, index) {
+            Some((hits, total, median)) => {
+                let _ = writeln!(
+                    message,
+                    "After {}: {}/{} up ({:.0}%), median {:+.1}%",
+                    label,
+                    hits,
+                    total,
+                    hits as f64 / total as f64 * 100.0,
+                    median
+                );
+            }
+            None => {
+                let _ = writeln!(message, "After {}: no prices", label);
+            }
+        }
+    }
+
+    let mut tiers: Vec<f64> = recent.iter().map(|outcome| outcome.tier).collect();
+    tiers.sort_by(f64::total_cmp);
+    tiers.dedup();
+    if tiers.len() > 1 {
+        message.push_str("\nBy tier:\n");
+        for tier in tiers {
+            let outcomes: Vec<&AlertOutcome> = recent.iter().filter(|outcome| outcome.tier == tier).copied().collect();
+            let rates: Vec<String> = HORIZONS
+                .iter()
+                .enumerate()
+                .map(|(index, (_, label))| match hit_rate(&outcomes, index) {
+                    Some((hits, total, _)) => format!("{} {:.0}%", label, hits as f64 / total as f64 * 100.0),
+                    None => format!("{} -", label),
+                })
+                .collect();
+            let _ = writeln!(message, "+{}%: {} alert(s) | {}", tier, outcomes.len(), rates.join(" | "));
+        }
+    }
+    message
+}
@@ -0,0 +1,23 @@
+use anyhow::{anyhow, Result};
+use std::{fs, io::ErrorKind, path::Path};
+
+pub(crate) const STATE_FILE: &str = "state.json";
+
+/// 读取数据目录中保存的状态，文件不存在时返回 None
+pub fn read(data_dir: &Path) -> Result<Option<String>> {
+    let path = data_dir.join(STATE_FILE);
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// 替换保存的状态。先写入临时文件再替换，写到一半退出时保留上一次的状态
+pub fn save(data_dir: &Path, content: &str) -> Result<()> {
+    let path = data_dir.join(STATE_FILE);
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, content).map_err(|e| anyhow!("Failed to write {}: {}", tmp.display(), e))?;
+    fs::rename(&tmp, &path).map_err(|e| anyhow!("Failed to replace {}: {}", path.display(), e))?;
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use crate::models::{HoldingSnapshot, TradeEvent};
+use crate::{mints::{market_cap, to_tokens}, wallet_monitor::SOL_DECIMALS};
+use anyhow::{anyhow, Result};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+use std::path::{Path, PathBuf};
+
+const ENTRY_POINT: &str = "on_trade";
+const MAX_SCRIPT_OPERATIONS: u64 = 100_000; // 防止脚本死循环拖住消息处理
+
+/// 用户自定义的 rhai 规则脚本。
+///
+/// 脚本需要定义 `fn on_trade(trade, holding)`，返回字符串（一条提醒）、
+/// 字符串数组（多条提醒）或 `()`（不提醒）。`holding` 在代币未持仓时为 `()`。
+pub struct ScriptPlugin {
+    engine: Engine,
+    ast: AST,
+    path: PathBuf,
+}
+
+impl ScriptPlugin {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| anyhow!("Failed to compile script {}: {}", path.display(), e))?;
+
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == ENTRY_POINT && f.params.len() == 2)
+        {
+            return Err(anyhow!(
+                "Script {} must define fn {}(trade, holding)",
+                path.display(),
+                ENTRY_POINT
+            ));
+        }
+
+        Ok(Self {
+            engine,
+            ast,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 对一笔交易执行脚本，返回脚本生成的提醒内容
+    pub fn on_trade(
+        &self,
+        trade: &TradeEvent,
+        is_target: bool,
+        price: f64,
+        holding: Option<&HoldingSnapshot>,
+    ) -> Result<Vec<String>> {
+        let trade = trade_to_map(trade, is_target, price);
+        let holding = holding.map_or(Dynamic::UNIT, |h| Dynamic::from_map(holding_to_map(h)));
+
+        let mut scope = Scope::new();
+        let result: Dynamic = self
+            .engine
+            .call_fn(&mut scope, &self.ast, ENTRY_POINT, (Dynamic::from_map(trade), holding))
+            .map_err(|e| anyhow!("Script {} failed: {}", self.path.display(), e))?;
+
+        if result.is_unit() {
+            Ok(Vec::new())
+        } else if result.is_string() {
+            Ok(vec![result.to_string()])
+        } else if result.is_array() {
+            Ok(result
+                .into_array()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|item| item.is_string())
+                .map(|item| item.to_string())
+                .collect())
+        } else {
+            Err(anyhow!(
+                "Script {} returned unsupported type {}",
+                self.path.display(),
+                result.type_name()
+            ))
+        }
+    }
+}
+
+fn trade_to_map(trade: &TradeEvent, is_target: bool, price: f64) -> Map {
+    let mut map = Map::new();
+    map.insert("mint".into(), trade.mint.clone().into());
+    map.insert("user".into(), trade.user.clone().into());
+    map.insert("is_buy".into(), trade.is_buy.into());
+    map.insert("is_target".into(), is_target.into());
+    map.insert(
+        "sol_amount".into(),
+        (trade.sol_amount as f64 / 10f64.powi(SOL_DECIMALS as i32)).into(),
+    );
+    map.insert(
+        "token_amount".into(),
+        to_tokens(trade.token_amount, trade.decimals).into(),
+    );
+    map.insert("price".into(), price.into());
+    // 市值按成交后的现价计算，没有 bonding curve 储备时按成交价
+    map.insert("market_cap".into(), market_cap(trade.spot_price().unwrap_or(price)).into());
+    map
+}
+
+fn holding_to_map(holding: &HoldingSnapshot) -> Map {
+    let mut map = Map::new();
+    map.insert("mint".into(), holding.mint.clone().into());
+    map.insert("amount".into(), holding.amount.into());
+    map.insert("avg_price".into(), holding.avg_price.into());
+    map.insert("current_price".into(), holding.current_price.into());
+    map.insert("total_cost".into(), holding.total_cost.into());
+    map.insert("price_change".into(), (holding.price_change as i64).into());
+    map
+}
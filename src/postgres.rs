@@ -0,0 +1,140 @@
+use crate::store::{SqlConnection, SqlValue};
+use anyhow::{anyhow, Result};
+use log::error;
+use tokio::runtime::Handle;
+use tokio_postgres::{
+    types::{ToSql, Type},
+    Client, NoTls, Row,
+};
+
+/// PostgreSQL 的连接。请求在调用的线程中等待完成，只在阻塞线程中使用；连接断开时在下一次请求前重连一次
+pub struct PostgresConnection {
+    runtime: Handle,  // 连接的后台任务所在的运行时
+    client: Client,
+    url: String,
+    database: String,  // 日志中显示的主机和数据库，不含密码
+}
+
+impl PostgresConnection {
+    /// `url` 可以是 `postgres://` URL，也可以是 `host=... dbname=...` 形式的连接字符串
+    pub fn connect(url: &str) -> Result<Self> {
+        let runtime = Handle::current();
+        let database = describe_url(url);
+        let client = Self::open(&runtime, url, &database)?;
+        Ok(Self {
+            runtime,
+            client,
+            url: url.to_string(),
+            database,
+        })
+    }
+
+    fn open(runtime: &Handle, url: &str, database: &str) -> Result<Client> {
+        let (client, connection) = runtime
+            .block_on(tokio_postgres::connect(url, NoTls))
+            .map_err(|e| anyhow!("Failed to connect to {}: {}", database, e))?;
+        let name = database.to_string();
+        runtime.spawn(async move {
+            if let Err(e) = connection.await {
+                error!("Connection to {} failed: {}", name, e);
+            }
+        });
+        // 建表时“已存在”的 NOTICE 不需要
+        runtime.block_on(client.batch_execute("SET client_min_messages = warning"))?;
+        Ok(client)
+    }
+
+    fn client(&mut self) -> Result<&Client> {
+        if self.client.is_closed() {
+            self.client = Self::open(&self.runtime, &self.url, &self.database)
+                .map_err(|e| anyhow!("Lost connection to {}: {}", self.database, e))?;
+        }
+        Ok(&self.client)
+    }
+}
+
+/// 把 `?1` 形式的参数改为 PostgreSQL 的 `$1`
+fn placeholders(sql: &str) -> String {
+    let mut converted = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '?' && chars.peek().is_some_and(char::is_ascii_digit) {
+            converted.push('$');
+        } else {
+            converted.push(c);
+        }
+    }
+    converted
+}
+
+fn bind(params: &[SqlValue]) -> Vec<Box<dyn ToSql + Sync>> {
+    params
+        .iter()
+        .map(|param| -> Box<dyn ToSql + Sync> {
+            match param {
+                SqlValue::Text(text) => Box::new(text.to_string()),
+                SqlValue::Int(value) => Box::new(*value),
+                SqlValue::Null => Box::new(None::<String>),
+            }
+        })
+        .collect()
+}
+
+/// 各列的文本，数字列转换为十进制文本
+fn columns(row: &Row) -> Result<Vec<Option<String>>> {
+    row.columns()
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            Ok(match *column.type_() {
+                Type::INT8 => row.try_get::<_, Option<i64>>(index)?.map(|value| value.to_string()),
+                Type::INT4 => row.try_get::<_, Option<i32>>(index)?.map(|value| value.to_string()),
+                _ => row.try_get::<_, Option<String>>(index)?,
+            })
+        })
+        .collect()
+}
+
+impl SqlConnection for PostgresConnection {
+    const SERIAL: &'static str = "BIGSERIAL PRIMARY KEY";
+
+    fn execute(&mut self, sql: &str, params: &[SqlValue]) -> Result<u64> {
+        let values = bind(params);
+        let values: Vec<&(dyn ToSql + Sync)> = values.iter().map(|value| value.as_ref()).collect();
+        let sql = placeholders(sql);
+        let runtime = self.runtime.clone();
+        let client = self.client()?;
+        // 没有参数的语句（建表、事务控制）不需要准备
+        if values.is_empty() {
+            runtime.block_on(client.batch_execute(&sql))?;
+            return Ok(0);
+        }
+        Ok(runtime.block_on(client.execute(sql.as_str(), &values))?)
+    }
+
+    fn query(&mut self, sql: &str, params: &[SqlValue]) -> Result<Vec<Vec<Option<String>>>> {
+        let values = bind(params);
+        let values: Vec<&(dyn ToSql + Sync)> = values.iter().map(|value| value.as_ref()).collect();
+        let runtime = self.runtime.clone();
+        let rows = runtime.block_on(self.client()?.query(placeholders(sql).as_str(), &values))?;
+        rows.iter().map(columns).collect()
+    }
+
+    fn describe(&self) -> String {
+        format!("PostgreSQL database {}", self.database)
+    }
+}
+
+/// 连接字符串中的主机和数据库，去掉用户名和密码
+fn describe_url(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(parsed) if parsed.scheme().starts_with("postgres") => {
+            format!("{}{}", parsed.host_str().unwrap_or("localhost"), parsed.path())
+        }
+        _ => url
+            .split_whitespace()
+            .filter(|pair| pair.starts_with("host=") || pair.starts_with("dbname="))
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
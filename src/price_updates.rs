@@ -0,0 +1,48 @@
+use std::collections::{HashMap, VecDeque};
+
+/// 其他钱包的交易带来的价格更新
+#[derive(Debug, Clone)]
+pub struct PriceUpdate {
+    pub mint: String,
+    pub price: f64,
+    pub slot: u64,
+    pub signature: String,  // 产生该价格的交易，用于审计
+}
+
+/// 按代币合并的价格更新队列。
+///
+/// 同一代币只保留最新的价格，队列长度不会超过持仓代币数量；
+/// 代币按第一次入队的顺序处理
+#[derive(Debug, Default)]
+pub struct PriceUpdates {
+    pending: HashMap<String, PriceUpdate>,
+    order: VecDeque<String>,
+}
+
+impl PriceUpdates {
+    /// 入队，已有未处理的更新时替换并返回 true
+    pub fn push(&mut self, update: PriceUpdate) -> bool {
+        let mint = update.mint.clone();
+        if let Some(pending) = self.pending.get_mut(&mint) {
+            *pending = update;
+            return true;
+        }
+        self.pending.insert(mint.clone(), update);
+        self.order.push_back(mint);
+        false
+    }
+
+    pub fn pop(&mut self) -> Option<PriceUpdate> {
+        while let Some(mint) = self.order.pop_front() {
+            if let Some(update) = self.pending.remove(&mint) {
+                return Some(update);
+            }
+        }
+        None
+    }
+
+    /// 取出代币未处理的更新，持仓变动前先应用，保证顺序
+    pub fn take(&mut self, mint: &str) -> Option<PriceUpdate> {
+        self.pending.remove(mint)
+    }
+}
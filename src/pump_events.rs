@@ -0,0 +1,269 @@
+use anyhow::{anyhow, Result};
+use borsh::BorshDeserialize;
+use solana_sdk::pubkey::Pubkey;
+
+// pump 事件标识符（Anchor: sha256("event:<Name>") 的前 8 字节）
+pub(crate) const TRADE_EVENT_DISCRIMINATOR: [u8; 8] = [189, 219, 127, 211, 78, 230, 97, 238];
+pub(crate) const CREATE_EVENT_DISCRIMINATOR: [u8; 8] = [27, 114, 169, 77, 222, 235, 99, 118];
+pub(crate) const COMPLETE_EVENT_DISCRIMINATOR: [u8; 8] = [95, 114, 97, 156, 212, 46, 152, 8];
+const IGNORED_EVENTS: &[([u8; 8], &str)] = &[
+    ([223, 195, 159, 246, 62, 48, 143, 131], "SetParamsEvent"),
+    ([189, 233, 93, 185, 92, 148, 234, 148], "CompletePumpAmmMigrationEvent"),
+    ([122, 2, 127, 1, 14, 191, 12, 175], "CollectCreatorFeeEvent"),
+    ([97, 97, 215, 144, 93, 146, 22, 124], "ExtendAccountEvent"),
+    ([237, 52, 123, 37, 245, 251, 72, 210], "SetCreatorEvent"),
+    ([182, 195, 137, 42, 35, 206, 207, 247], "UpdateGlobalAuthorityEvent"),
+];
+
+/// TradeEvent。pump 升级时只在末尾追加字段：v1 到虚拟储备为止，
+/// 之后依次追加真实储备（v2）、手续费（v3）和交易量统计（v4），更新的字段忽略
+#[allow(dead_code)]
+#[derive(Debug, Clone, BorshDeserialize)]
+pub struct TradeEvent {
+    pub mint: Pubkey,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    pub is_buy: bool,
+    pub user: Pubkey,
+    pub timestamp: i64,
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    #[borsh(skip)]
+    pub real_reserves: Option<RealReserves>,
+    #[borsh(skip)]
+    pub fees: Option<TradeFees>,
+    #[borsh(skip)]
+    pub volume: Option<VolumeTracking>,
+}
+
+#[allow(dead_code)]  // 追加的字段完整解析后才能确定版本，内容暂未使用
+#[derive(Debug, Clone, BorshDeserialize)]
+pub struct RealReserves {
+    pub real_sol_reserves: u64,
+    pub real_token_reserves: u64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, BorshDeserialize)]
+pub struct TradeFees {
+    pub fee_recipient: Pubkey,
+    pub fee_basis_points: u64,
+    pub fee: u64,  // lamports
+    pub creator: Pubkey,
+    pub creator_fee_basis_points: u64,
+    pub creator_fee: u64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, BorshDeserialize)]
+pub struct VolumeTracking {
+    pub track_volume: bool,
+    pub total_unclaimed_tokens: u64,
+    pub total_claimed_tokens: u64,
+    pub current_sol_volume: u64,
+    pub last_update_timestamp: i64,
+}
+
+impl TradeEvent {
+    fn decode(data: &mut &[u8]) -> Result<Self> {
+        let mut event = Self::deserialize(data).map_err(|e| anyhow!("Invalid TradeEvent: {}", e))?;
+        event.real_reserves = extension(data);
+        if event.real_reserves.is_some() {
+            event.fees = extension(data);
+        }
+        if event.fees.is_some() {
+            event.volume = extension(data);
+        }
+        Ok(event)
+    }
+
+    /// 解码时识别出的布局版本
+    pub fn layout(&self) -> &'static str {
+        match (&self.real_reserves, &self.fees, &self.volume) {
+            (None, _, _) => "v1",
+            (Some(_), None, _) => "v2",
+            (Some(_), Some(_), None) => "v3",
+            (Some(_), Some(_), Some(_)) => "v4",
+        }
+    }
+}
+
+/// 剩余数据足够时解析追加的字段，数据不完整时视为旧版本的事件，不移动读取位置
+fn extension<T: BorshDeserialize>(data: &mut &[u8]) -> Option<T> {
+    let mut rest = *data;
+    let value = T::deserialize(&mut rest).ok()?;
+    *data = rest;
+    Some(value)
+}
+
+/// CreateEvent。之后追加了 creator 和创建时间（v2），再之后的初始储备不使用
+#[allow(dead_code)]
+#[derive(Debug, Clone, BorshDeserialize)]
+pub struct CreateEvent {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub user: Pubkey,
+    #[borsh(skip)]
+    pub creation: Option<Creation>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, BorshDeserialize)]
+pub struct Creation {
+    pub creator: Pubkey,
+    pub timestamp: i64,
+}
+
+impl CreateEvent {
+    fn decode(data: &mut &[u8]) -> Result<Self> {
+        let mut event = Self::deserialize(data).map_err(|e| anyhow!("Invalid CreateEvent: {}", e))?;
+        event.creation = extension(data);
+        Ok(event)
+    }
+}
+
+/// CompleteEvent：bonding curve 已满，代币之后在 AMM 池中交易
+#[allow(dead_code)]
+#[derive(Debug, Clone, BorshDeserialize)]
+pub struct CompleteEvent {
+    pub user: Pubkey,  // 完成 bonding curve 的最后一笔买入
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub timestamp: i64,
+}
+
+/// 按标识符解码的 pump 事件
+#[derive(Debug, Clone)]
+pub enum PumpEvent {
+    Trade(TradeEvent),
+    Create(CreateEvent),
+    Complete(CompleteEvent),
+    Ignored(&'static str),  // 已知但不需要处理的事件
+    Unknown([u8; 8]),
+}
+
+/// 解码 pump 程序的 Program data（含标识符）。已知事件的数据不符合布局时返回错误，
+/// 不会把截断或其他事件的数据读成交易
+pub fn decode(data: &[u8]) -> Result<PumpEvent> {
+    let Some((discriminator, mut body)) = data.split_first_chunk::<8>() else {
+        return Err(anyhow!("Payload too short: {} bytes", data.len()));
+    };
+    match *discriminator {
+        TRADE_EVENT_DISCRIMINATOR => TradeEvent::decode(&mut body).map(PumpEvent::Trade),
+        CREATE_EVENT_DISCRIMINATOR => CreateEvent::decode(&mut body).map(PumpEvent::Create),
+        COMPLETE_EVENT_DISCRIMINATOR => CompleteEvent::deserialize(&mut body)
+            .map(PumpEvent::Complete)
+            .map_err(|e| anyhow!("Invalid CompleteEvent: {}", e)),
+        discriminator => Ok(IGNORED_EVENTS
+            .iter()
+            .find(|(known, _)| *known == discriminator)
+            .map_or(PumpEvent::Unknown(discriminator), |(_, name)| PumpEvent::Ignored(name))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose, Engine as _};
+
+    const MINT: &str = "SBcLLDkvLXdQdYDeVvPPDN81LrmsmVSaiEihxgCSz2g";
+    const USER: &str = "Dk3Yffgk5ZjWhY6LCoFai9Gor36FHCBXgRwR3opZbHY6";
+    const BONDING_CURVE: &str = "A3wF3UVDWUPr8jDbv8r8rBn8joAybQ49aMWkkcVg9Tbd";
+
+    /// 日志中 `Program data: ` 之后的 base64
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = format!("{}/tests/fixtures/pump/{}.txt", env!("CARGO_MANIFEST_DIR"), name);
+        let content = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        general_purpose::STANDARD.decode(content.trim()).unwrap()
+    }
+
+    fn decode_trade(name: &str) -> TradeEvent {
+        match decode(&fixture(name)).unwrap() {
+            PumpEvent::Trade(trade) => trade,
+            other => panic!("{} decoded as {:?}", name, other),
+        }
+    }
+
+    #[test]
+    fn decodes_current_trade_event() {
+        let trade = decode_trade("trade_v4");
+        assert_eq!(trade.layout(), "v4");
+        assert_eq!(trade.mint.to_string(), MINT);
+        assert_eq!(trade.user.to_string(), USER);
+        assert!(trade.is_buy);
+        assert_eq!(trade.sol_amount, 1_500_000_000);
+        assert_eq!(trade.token_amount, 35_210_662_830_427);
+        assert_eq!(trade.timestamp, 1_760_000_000);
+        assert_eq!(trade.virtual_sol_reserves, 42_850_000_000);
+        assert_eq!(trade.virtual_token_reserves, 751_234_567_890_123);
+        assert_eq!(trade.fees.as_ref().map(|fees| fees.fee), Some(14_250_000));
+        assert_eq!(trade.volume.as_ref().map(|volume| volume.current_sol_volume), Some(3_200_000_000));
+    }
+
+    #[test]
+    fn decodes_older_trade_layouts() {
+        let trade = decode_trade("trade_v1");
+        assert_eq!(trade.layout(), "v1");
+        assert_eq!(trade.mint.to_string(), MINT);
+        assert_eq!(trade.virtual_token_reserves, 751_234_567_890_123);
+
+        let sell = decode_trade("trade_v2_sell");
+        assert_eq!(sell.layout(), "v2");
+        assert!(!sell.is_buy);
+        assert_eq!(sell.sol_amount, 820_000_000);
+        assert_eq!(sell.token_amount, 18_000_000_000_000);
+        assert_eq!(sell.real_reserves.as_ref().map(|reserves| reserves.real_sol_reserves), Some(11_030_000_000));
+    }
+
+    #[test]
+    fn ignores_fields_appended_after_known_layout() {
+        let trade = decode_trade("trade_v4_extended");
+        assert_eq!(trade.layout(), "v4");
+        assert_eq!(trade.user.to_string(), USER);
+    }
+
+    #[test]
+    fn rejects_malformed_trade_events() {
+        let truncated = decode(&fixture("trade_truncated")).unwrap_err();
+        assert!(truncated.to_string().starts_with("Invalid TradeEvent"), "{}", truncated);
+        // is_buy 不是 0 或 1 的数据不是 TradeEvent
+        assert!(decode(&fixture("trade_invalid_bool")).is_err());
+        assert!(decode(&[189, 219, 127]).is_err());
+    }
+
+    #[test]
+    fn decodes_create_event() {
+        let PumpEvent::Create(token) = decode(&fixture("create")).unwrap() else {
+            panic!("not a CreateEvent");
+        };
+        assert_eq!(token.name, "Moon Cat");
+        assert_eq!(token.symbol, "MCAT");
+        assert_eq!(token.mint.to_string(), MINT);
+        assert_eq!(token.bonding_curve.to_string(), BONDING_CURVE);
+        assert_eq!(token.user.to_string(), USER);
+        let creation = token.creation.expect("creator and timestamp");
+        assert_eq!(creation.creator.to_string(), "BvBXy3DguUBim9UqYyUsqWKMrapCPpxPWyBetatjkT3m");
+        assert_eq!(creation.timestamp, 1_759_999_000);
+        assert!(decode(&fixture("create_truncated")).is_err());
+    }
+
+    #[test]
+    fn decodes_complete_event() {
+        let PumpEvent::Complete(curve) = decode(&fixture("complete")).unwrap() else {
+            panic!("not a CompleteEvent");
+        };
+        assert_eq!(curve.mint.to_string(), MINT);
+        assert_eq!(curve.user.to_string(), USER);
+        assert_eq!(curve.bonding_curve.to_string(), BONDING_CURVE);
+        assert_eq!(curve.timestamp, 1_760_001_234);
+    }
+
+    #[test]
+    fn classifies_other_events() {
+        assert!(matches!(decode(&fixture("set_params")).unwrap(), PumpEvent::Ignored("SetParamsEvent")));
+        assert!(matches!(decode(&fixture("unknown")).unwrap(), PumpEvent::Unknown([1, 2, 3, 4, 5, 6, 7, 8])));
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use chrono::Utc;
+use log::error;
+use serde::Serialize;
+use std::path::Path;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::mpsc};
+
+pub(crate) const QUARANTINE_FILE: &str = "quarantine.jsonl";
+
+/// 无法解码或数值异常的原始事件数据
+#[derive(Debug, Serialize)]
+pub struct QuarantinedPayload<'a> {
+    pub timestamp: i64,
+    pub slot: u64,
+    pub signature: &'a str,
+    pub reason: String,
+    pub raw: &'a str,
+}
+
+/// 将解码失败的原始 Program data 追加写入 `quarantine.jsonl`，供后续分析。
+/// 写入在后台任务中进行，不阻塞消息处理；每次写入重新打开文件，清理过期记录替换文件后写入新的文件
+pub struct Quarantine {
+    sender: mpsc::UnboundedSender<String>,
+}
+
+impl Quarantine {
+    pub fn new(data_dir: &Path) -> Self {
+        let path = data_dir.join(QUARANTINE_FILE);
+        let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+        tokio::spawn(async move {
+            while let Some(mut lines) = receiver.recv().await {
+                // 积压的记录一次写入
+                while let Ok(line) = receiver.try_recv() {
+                    lines.push_str(&line);
+                }
+                if let Err(e) = append(&path, &lines).await {
+                    error!("Failed to write quarantine file {}: {}", path.display(), e);
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    pub fn record(&self, slot: u64, signature: &str, raw: &str, reason: String) -> Result<()> {
+        let entry = QuarantinedPayload {
+            timestamp: Utc::now().timestamp(),
+            slot,
+            signature,
+            reason,
+            raw,
+        };
+
+        let _ = self.sender.send(format!("{}\n", serde_json::to_string(&entry)?));
+        Ok(())
+    }
+}
+
+async fn append(path: &Path, lines: &str) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path).await?;
+    file.write_all(lines.as_bytes()).await
+}
@@ -0,0 +1,68 @@
+use crate::{endpoints::RpcEndpoint, error::MonitorError};
+use serde::Serialize;
+use solana_account_decoder::UiAccountData;
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, str::FromStr};
+
+// SPL Token 和 Token-2022 程序，pump 代币可能使用其中任意一个
+pub(crate) const TOKEN_PROGRAM_IDS: [&str; 2] = [
+    "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+    "TokenzQdBNbLqP5VEhdkAS14mpP7ujbVAiNGv3b8Qpn",
+];
+
+/// 内部持仓与链上余额不一致的代币（数量为原始单位）
+#[derive(Debug, Clone, Serialize)]
+pub struct Discrepancy {
+    pub mint: String,
+    pub tracked: Option<u64>,  // None 表示链上有余额但未被跟踪
+    pub on_chain: u64,
+    pub decimals: u8,
+}
+
+impl Discrepancy {
+    /// 差额占较大一方的百分比，未跟踪的代币为 100%
+    pub fn drift_percent(&self) -> f64 {
+        let tracked = self.tracked.unwrap_or(0);
+        let larger = tracked.max(self.on_chain);
+        if larger == 0 {
+            return 0.0;
+        }
+        tracked.abs_diff(self.on_chain) as f64 / larger as f64 * 100.0
+    }
+}
+
+/// 查询钱包在链上的代币余额，按 mint 汇总所有钱包的原始数量
+pub async fn fetch_token_balances(rpc: &RpcEndpoint, owners: &[String]) -> Result<HashMap<String, u64>, MonitorError> {
+    let client = rpc.client();
+    let mut balances: HashMap<String, u64> = HashMap::new();
+
+    for owner in owners {
+        let owner = Pubkey::from_str(owner)
+            .map_err(|e| MonitorError::config(format!("Invalid wallet address {}: {}", owner, e)))?;
+
+        for program_id in TOKEN_PROGRAM_IDS {
+            let program_id = Pubkey::from_str(program_id).map_err(MonitorError::config)?;
+            let accounts = client
+                .get_token_accounts_by_owner(&owner, TokenAccountsFilter::ProgramId(program_id))
+                .await
+                .map_err(|e| MonitorError::rpc(format!("getTokenAccountsByOwner failed for {}: {}", owner, e)))?;
+
+            for keyed in accounts {
+                let UiAccountData::Json(parsed) = keyed.account.data else {
+                    continue;
+                };
+                let info = &parsed.parsed["info"];
+                let (Some(mint), Some(amount)) = (
+                    info["mint"].as_str(),
+                    info["tokenAmount"]["amount"].as_str().and_then(|a| a.parse::<u64>().ok()),
+                ) else {
+                    continue;
+                };
+                *balances.entry(mint.to_string()).or_default() += amount;
+            }
+        }
+    }
+
+    Ok(balances)
+}
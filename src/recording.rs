@@ -0,0 +1,111 @@
+use crate::display::{self, HoldingRow};
+use anyhow::{anyhow, Context, Result};
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    time::Duration,
+};
+use tokio::time::sleep;
+
+const MAX_GAP: Duration = Duration::from_secs(2);  // 回放时两帧之间最长等待，跳过空闲时段
+
+/// 一个 profile 在某一帧的持仓
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileFrame {
+    pub profile: String,
+    pub rows: Vec<HoldingRow>,
+    #[serde(default)]
+    pub sol_usd: Option<f64>,  // 录制时的 SOL/USD 汇率
+}
+
+/// 录制的一帧，每行一个 JSON
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Frame {
+    pub timestamp: i64,
+    pub profiles: Vec<ProfileFrame>,
+}
+
+/// 把持仓表的每一帧追加到 `DISPLAY_RECORD` 指定的文件，持仓没有变化的帧不写入
+pub struct Recorder {
+    file: File,
+    last: Option<Vec<ProfileFrame>>,
+}
+
+impl Recorder {
+    /// 没有设置 `DISPLAY_RECORD` 时返回 None
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(path) = env::var("DISPLAY_RECORD") else {
+            return Ok(None);
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open display recording {}", path))?;
+        Ok(Some(Self { file, last: None }))
+    }
+
+    pub fn record(&mut self, timestamp: i64, profiles: &[ProfileFrame]) -> Result<()> {
+        if self.last.as_deref() == Some(profiles) {
+            return Ok(());
+        }
+        let frame = Frame {
+            timestamp,
+            profiles: profiles.to_vec(),
+        };
+        writeln!(self.file, "{}", serde_json::to_string(&frame)?)?;
+        self.last = Some(frame.profiles);
+        Ok(())
+    }
+}
+
+/// 按顺序读取录制的帧，跳过空行
+pub fn frames(path: &Path) -> Result<impl Iterator<Item = Result<(usize, Frame)>>> {
+    let file = File::open(path).with_context(|| format!("Failed to open display recording {}", path.display()))?;
+    Ok(BufReader::new(file).lines().enumerate().filter_map(|(index, line)| {
+        let line = match line {
+            Ok(line) if line.trim().is_empty() => return None,
+            Ok(line) => line,
+            Err(e) => return Some(Err(e.into())),
+        };
+        Some(
+            serde_json::from_str(&line)
+                .map(|frame| (index, frame))
+                .map_err(|e| anyhow!("Invalid frame on line {}: {}", index + 1, e)),
+        )
+    }))
+}
+
+/// 按录制时的节奏回放持仓表，`speed` 为倍速
+pub async fn playback(path: &Path, speed: f64) -> Result<()> {
+    let mut previous: Option<i64> = None;
+    for frame in frames(path)? {
+        let (index, frame) = frame?;
+
+        if let Some(previous) = previous {
+            let gap = Duration::from_secs_f64((frame.timestamp - previous).max(0) as f64 / speed);
+            sleep(gap.min(MAX_GAP)).await;
+        }
+        previous = Some(frame.timestamp);
+
+        let show_profile = frame.profiles.len() > 1;
+        let tables: Vec<String> = frame
+            .profiles
+            .into_iter()
+            .map(|profile| {
+                let name = show_profile.then_some(profile.profile.as_str());
+                display::render_holdings(name, profile.rows, profile.sol_usd, frame.timestamp)
+            })
+            .collect();
+        let updated_at = Local
+            .timestamp_opt(frame.timestamp, 0)
+            .single()
+            .ok_or_else(|| anyhow!("Invalid timestamp on line {}", index + 1))?;
+        display::draw_frame(&tables, updated_at)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use futures_util::{FutureExt, SinkExt, StreamExt};
+use log::{debug, info, warn};
+use serde_json::Value;
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::{
+    fs::OpenOptions,
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+const REDELIVERED: usize = 20;  // 重连后重新发送断线前的最后几条消息，和节点的重复推送一样
+
+/// 把收到的 WebSocket 消息追加到 `WS_RECORD` 指定的文件，每行一条，供 `CHAOS_MODE` 的 `replay` 回放。
+/// 写入在后台任务中进行，不阻塞消息处理
+pub struct MessageRecorder {
+    sender: mpsc::UnboundedSender<String>,
+}
+
+impl MessageRecorder {
+    pub async fn open(path: &Path) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("Failed to open WebSocket recording {}", path.display()))?;
+        let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+        let path = path.to_path_buf();
+        tokio::spawn(async move {
+            while let Some(text) = receiver.recv().await {
+                if let Err(e) = file.write_all(format!("{}\n", text).as_bytes()).await {
+                    warn!("Failed to write WebSocket recording {}: {}", path.display(), e);
+                }
+            }
+        });
+        Ok(Self { sender })
+    }
+
+    pub fn record(&self, text: &str) {
+        // 换行会破坏每行一条的格式，WebSocket 消息的 JSON 中本来就没有换行
+        let _ = self.sender.send(text.replace('\n', " "));
+    }
+}
+
+/// 在本地 WebSocket 服务上回放录制的消息，返回连接地址。监控像连接节点一样连接它：订阅请求得到确认后开始按顺序推送。
+/// 每个连接从上一个连接断开的位置继续，先重新发送最后 `REDELIVERED` 条，用于验证重连和去重
+pub async fn serve(path: &Path, cancel: CancellationToken) -> Result<Url> {
+    let recording = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read replay file {}", path.display()))?;
+    let messages: Arc<Vec<String>> = Arc::new(recording.lines().filter(|line| !line.trim().is_empty()).map(str::to_string).collect());
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = Url::parse(&format!("ws://{}", listener.local_addr()?))?;
+    info!("Replaying {} message(s) from {} on {}", messages.len(), path.display(), url);
+    let position = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(async move {
+        loop {
+            let stream = tokio::select! {
+                _ = cancel.cancelled() => return,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Replay server failed to accept a connection: {}", e);
+                        continue;
+                    }
+                },
+            };
+            let (messages, position) = (messages.clone(), position.clone());
+            tokio::spawn(async move {
+                if let Err(e) = replay_connection(stream, &messages, &position).await {
+                    debug!("Replay connection closed: {}", e);
+                }
+            });
+        }
+    });
+    Ok(url)
+}
+
+async fn replay_connection(stream: TcpStream, messages: &[String], position: &AtomicUsize) -> Result<()> {
+    let (mut write, mut read) = accept_async(stream).await?.split();
+    let mut next = position.load(Ordering::Relaxed).saturating_sub(REDELIVERED);
+    let mut subscribed = false;
+    loop {
+        let request = match messages.get(next).filter(|_| subscribed) {
+            // 还有消息要推送时只处理已经到达的请求
+            Some(message) => match read.next().now_or_never() {
+                Some(request) => request,
+                None => {
+                    write.send(Message::Text(message.clone())).await?;
+                    next += 1;
+                    position.fetch_max(next, Ordering::Relaxed);
+                    if next == messages.len() {
+                        info!("Replay finished after {} message(s)", messages.len());
+                    }
+                    continue;
+                }
+            },
+            None => read.next().await,
+        };
+        match request {
+            Some(Ok(Message::Text(text))) => {
+                // 订阅请求都确认，订阅 ID 使用请求的 id
+                let id = serde_json::from_str::<Value>(&text)?["id"].clone();
+                let reply = serde_json::json!({ "jsonrpc": "2.0", "result": id, "id": id });
+                write.send(Message::Text(reply.to_string())).await?;
+                subscribed = true;
+            }
+            Some(Ok(Message::Ping(data))) => write.send(Message::Pong(data)).await?,
+            Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return Ok(()),
+            Some(Ok(_)) => {}
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{
+    alert_service::UNDELIVERED_FILE,
+    audit::AUDIT_FILE,
+    journal::{ANNOTATION_FILE, SANDWICH_FILE},
+    outcomes::OUTCOMES_FILE,
+    quarantine::QUARANTINE_FILE,
+    store::{Records, StateStore},
+};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde::Deserialize;
+use std::{
+    fs,
+    io::ErrorKind,
+    path::Path,
+};
+
+/// 配置文件中的 `retention` 部分，各类记录保留的天数，未设置的永久保留
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionConfig {
+    pub trades_days: Option<u64>,      // 交易日志、sandwiches.jsonl 和 annotations.jsonl
+    pub audit_days: Option<u64>,       // audit.jsonl
+    pub alerts_days: Option<u64>,      // 提醒历史、undelivered.jsonl 和 alert_outcomes.jsonl
+    pub quarantine_days: Option<u64>,  // quarantine.jsonl
+}
+
+impl RetentionConfig {
+    pub fn is_empty(&self) -> bool {
+        self.files().is_empty() && self.records().is_empty()
+    }
+
+    /// 保存在状态存储中的记录
+    fn records(&self) -> Vec<(Records, u64)> {
+        [(Records::Trades, self.trades_days), (Records::Alerts, self.alerts_days)]
+            .into_iter()
+            .filter_map(|(records, days)| days.map(|days| (records, days)))
+            .collect()
+    }
+
+    /// 数据目录中的其他文件
+    fn files(&self) -> Vec<(&'static str, u64)> {
+        [
+            (SANDWICH_FILE, self.trades_days),
+            (ANNOTATION_FILE, self.trades_days),
+            (AUDIT_FILE, self.audit_days),
+            (UNDELIVERED_FILE, self.alerts_days),
+            (OUTCOMES_FILE, self.alerts_days),
+            (QUARANTINE_FILE, self.quarantine_days),
+        ]
+        .into_iter()
+        .filter_map(|(file, days)| days.map(|days| (file, days)))
+        .collect()
+    }
+}
+
+/// 删除状态存储和数据目录中超过保留期的记录，返回每类记录或文件删除的行数（只列出有删除的）
+pub async fn prune(data_dir: &Path, store: &dyn StateStore, config: &RetentionConfig) -> Result<Vec<(&'static str, usize)>> {
+    let now = Utc::now().timestamp();
+    let mut pruned = Vec::new();
+    for (records, days) in config.records() {
+        let cutoff = now - (days * 24 * 60 * 60) as i64;
+        let removed = store.prune(records, cutoff).await?;
+        if removed > 0 {
+            pruned.push((records.name(), removed));
+        }
+    }
+    let files: Vec<_> = config.files().into_iter().map(|(file, days)| (file, data_dir.join(file), days)).collect();
+    let removed = tokio::task::spawn_blocking(move || {
+        files
+            .into_iter()
+            .map(|(file, path, days)| Ok((file, prune_file(&path, now - (days * 24 * 60 * 60) as i64)?)))
+            .collect::<Result<Vec<_>>>()
+    })
+    .await??;
+    pruned.extend(removed.into_iter().filter(|(_, removed)| *removed > 0));
+    Ok(pruned)
+}
+
+/// 按每行的 `timestamp` 字段删除早于 `cutoff` 的记录，无法解析的行保留。
+///
+/// 先写入临时文件再替换，中途失败不会损坏原文件。读取和替换之间追加的行会丢失，
+/// 这段时间很短，只在定时任务中执行
+pub(crate) fn prune_file(path: &Path, cutoff: i64) -> Result<usize> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+    };
+
+    let mut kept = String::with_capacity(content.len());
+    let mut removed = 0;
+    for line in content.lines() {
+        let timestamp = serde_json::from_str::<serde_json::Value>(line)
+            .ok()
+            .and_then(|record| record["timestamp"].as_i64());
+        if timestamp.is_some_and(|timestamp| timestamp < cutoff) {
+            removed += 1;
+        } else {
+            kept.push_str(line);
+            kept.push('\n');
+        }
+    }
+    if removed == 0 {
+        return Ok(0);
+    }
+
+    let tmp = path.with_extension("jsonl.tmp");
+    fs::write(&tmp, kept).map_err(|e| anyhow!("Failed to write {}: {}", tmp.display(), e))?;
+    fs::rename(&tmp, path).map_err(|e| anyhow!("Failed to replace {}: {}", path.display(), e))?;
+    Ok(removed)
+}
@@ -0,0 +1,176 @@
+use crate::models::{AlertSink, AlertType, HoldingSnapshot, TradeEvent};
+use crate::{enrichment::escape_html, metadata::TokenMetadata, mints::to_tokens, wallet_monitor::SOL_DECIMALS};
+use serde::Deserialize;
+
+/// 配置文件中的一条声明式提醒规则
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRule {
+    pub name: String,
+    #[serde(default)]
+    pub when: RuleConditions,
+    #[serde(default)]
+    pub action: RuleAction,
+    #[serde(default = "default_once_per_mint")]
+    pub once_per_mint: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+/// 规则条件，所有已配置的条件都满足时规则才触发
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConditions {
+    pub side: Option<TradeSide>,
+    #[serde(default)]
+    pub target_only: bool,
+    #[serde(default)]
+    pub wallets: Vec<String>,
+    pub min_price_change: Option<f64>,
+    pub max_price_change: Option<f64>,
+    pub min_trade_sol: Option<f64>,
+    pub max_trade_sol: Option<f64>,
+    pub min_position_sol: Option<f64>,
+    pub max_position_sol: Option<f64>,
+    pub min_token_age_secs: Option<i64>,
+    pub max_token_age_secs: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleAction {
+    #[serde(default = "default_alert_type")]
+    pub alert_type: AlertType,
+    #[serde(default)]
+    pub sink: AlertSink,
+    pub template: Option<String>,
+    pub confidence: Option<f64>,  // 规则信号的置信度（0 到 1），默认 1
+}
+
+impl Default for RuleAction {
+    fn default() -> Self {
+        Self {
+            alert_type: default_alert_type(),
+            sink: AlertSink::default(),
+            template: None,
+            confidence: None,
+        }
+    }
+}
+
+fn default_once_per_mint() -> bool {
+    true
+}
+
+fn default_alert_type() -> AlertType {
+    AlertType::Custom
+}
+
+/// 规则求值时可用的交易和持仓数据
+pub struct RuleContext<'a> {
+    pub trade: &'a TradeEvent,
+    pub is_target: bool,
+    pub price: f64,
+    pub holding: Option<&'a HoldingSnapshot>,
+    pub token_age_secs: Option<i64>,
+    pub token: Option<&'a TokenMetadata>,  // 已缓存的代币元数据
+    pub wallet_label: Option<&'a str>,  // 地址簿中交易钱包的名称
+}
+
+impl RuleContext<'_> {
+    fn trade_sol(&self) -> f64 {
+        self.trade.sol_amount as f64 / 10f64.powi(SOL_DECIMALS as i32)
+    }
+
+    fn trade_tokens(&self) -> f64 {
+        to_tokens(self.trade.token_amount, self.trade.decimals)
+    }
+
+    fn position_sol(&self) -> Option<f64> {
+        self.holding.map(|h| h.amount * h.current_price)
+    }
+}
+
+impl AlertRule {
+    pub fn matches(&self, ctx: &RuleContext) -> bool {
+        let when = &self.when;
+
+        if when.target_only && !ctx.is_target {
+            return false;
+        }
+        if let Some(side) = when.side {
+            if (side == TradeSide::Buy) != ctx.trade.is_buy {
+                return false;
+            }
+        }
+        if !when.wallets.is_empty() && !when.wallets.contains(&ctx.trade.user) {
+            return false;
+        }
+
+        let price_change = ctx.holding.map(|h| h.price_change as f64);
+        let trade_sol = Some(ctx.trade_sol());
+        // 代币年龄未知时视为无穷大：满足最小年龄，不满足最大年龄
+        let token_age = Some(ctx.token_age_secs.map_or(f64::INFINITY, |age| age as f64));
+
+        within(price_change, when.min_price_change, when.max_price_change)
+            && within(trade_sol, when.min_trade_sol, when.max_trade_sol)
+            && within(ctx.position_sol(), when.min_position_sol, when.max_position_sol)
+            && within(
+                token_age,
+                when.min_token_age_secs.map(|age| age as f64),
+                when.max_token_age_secs.map(|age| age as f64),
+            )
+    }
+
+    /// 用交易数据填充模板中的 `{placeholder}`
+    pub fn render(&self, ctx: &RuleContext) -> String {
+        let template = self.action.template.as_deref().unwrap_or(
+            "📐 Rule <b>{rule}</b> triggered\n\n\
+            Token: <a href=\"https://gmgn.ai/sol/token/{mint}\">{token}</a>\n\
+            Wallet: {wallet_name}\n\
+            Side: {side} {trade_sol} SOL\n\
+            Price: {price} SOL",
+        );
+
+        let holding_value = |f: fn(&HoldingSnapshot) -> String| {
+            ctx.holding.map(f).unwrap_or_else(|| "-".to_string())
+        };
+        let token_age = ctx
+            .token_age_secs
+            .map(|age| format!("{}s", age))
+            .unwrap_or_else(|| "unknown".to_string());
+
+        template
+            .replace("{rule}", &self.name)
+            .replace(
+                "{token}",
+                &ctx.token.map_or_else(|| ctx.trade.mint.clone(), |token| escape_html(&token.label(&ctx.trade.mint))),
+            )
+            .replace("{mint}", &ctx.trade.mint)
+            .replace("{wallet_name}", &ctx.wallet_label.map_or_else(|| ctx.trade.user.clone(), escape_html))
+            .replace("{wallet}", &ctx.trade.user)
+            .replace("{side}", if ctx.trade.is_buy { "Buy" } else { "Sell" })
+            .replace("{trade_sol}", &format!("{:.3}", ctx.trade_sol()))
+            .replace("{trade_tokens}", &format!("{:.1}", ctx.trade_tokens()))
+            .replace("{price}", &format!("{:.9}", ctx.price))
+            .replace("{avg_price}", &holding_value(|h| format!("{:.9}", h.avg_price)))
+            .replace("{price_change}", &holding_value(|h| format!("{}%", h.price_change)))
+            .replace("{position_sol}", &holding_value(|h| format!("{:.3}", h.amount * h.current_price)))
+            .replace("{token_age}", &token_age)
+    }
+}
+
+fn within(value: Option<f64>, min: Option<f64>, max: Option<f64>) -> bool {
+    if min.is_none() && max.is_none() {
+        return true;
+    }
+    match value {
+        Some(value) => min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max),
+        None => false,
+    }
+}
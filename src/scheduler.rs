@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use cron::Schedule;
+use serde::Deserialize;
+use std::{fmt, str::FromStr, time::Duration};
+
+/// 可以被调度的任务
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Job {
+    Report,     // 持仓报告
+    Reconcile,  // 链上余额对账
+    Stress,     // 组合压力测试报告
+    Prune,      // 删除超过保留期的记录
+    Outcomes,   // 最近 7 天价格提醒的结果统计
+    Leaderboard,     // 最近 7 天目标钱包按盈亏的排行
+    Snapshot,        // 保存持仓状态
+    Activity,        // 检查目标钱包是否长时间没有交易
+    Counterparties,  // 比较持仓代币相邻两个窗口的新买家
+}
+
+impl fmt::Display for Job {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Job::Report => write!(f, "report"),
+            Job::Reconcile => write!(f, "reconcile"),
+            Job::Stress => write!(f, "stress"),
+            Job::Prune => write!(f, "prune"),
+            Job::Outcomes => write!(f, "outcomes"),
+            Job::Leaderboard => write!(f, "leaderboard"),
+            Job::Snapshot => write!(f, "snapshot"),
+            Job::Activity => write!(f, "activity"),
+            Job::Counterparties => write!(f, "counterparties"),
+        }
+    }
+}
+
+/// 任务的触发方式
+#[derive(Debug, Clone)]
+pub enum Trigger {
+    Cron(Box<Schedule>),  // 按本地时间解析的 cron 表达式
+    Every(Duration),      // 固定间隔，从启动时开始计算
+}
+
+impl Trigger {
+    /// 计算 `after` 之后的下一次触发时间
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            Trigger::Cron(schedule) => schedule.after(&after).next(),
+            Trigger::Every(period) => chrono::Duration::from_std(*period).ok().map(|period| after + period),
+        }
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Cron(schedule) => write!(f, "cron '{}'", schedule),
+            Trigger::Every(period) => write!(f, "every {}s", period.as_secs()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduledJob {
+    pub job: Job,
+    pub trigger: Trigger,
+}
+
+/// 配置文件中 `schedules` 的一项
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleEntry {
+    pub job: Job,
+    pub cron: String,
+}
+
+impl ScheduleEntry {
+    pub fn parse(&self) -> Result<ScheduledJob> {
+        let schedule = Schedule::from_str(&self.cron)
+            .map_err(|e| anyhow!("Invalid cron expression '{}' for job {}: {}", self.cron, self.job, e))?;
+        Ok(ScheduledJob {
+            job: self.job,
+            trigger: Trigger::Cron(Box::new(schedule)),
+        })
+    }
+}
+
+/// 记录每个任务的下一次触发时间
+pub struct Scheduler {
+    entries: Vec<(ScheduledJob, Option<DateTime<Local>>)>,
+}
+
+impl Scheduler {
+    pub fn new(jobs: Vec<ScheduledJob>, now: DateTime<Local>) -> Self {
+        let entries = jobs
+            .into_iter()
+            .map(|job| {
+                let next = job.trigger.next_after(now);
+                (job, next)
+            })
+            .collect();
+        Self { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(|(_, next)| next.is_none())
+    }
+
+    pub fn next_deadline(&self) -> Option<DateTime<Local>> {
+        self.entries.iter().filter_map(|(_, next)| *next).min()
+    }
+
+    /// 取出所有到期的任务，并安排它们的下一次触发
+    pub fn pop_due(&mut self, now: DateTime<Local>) -> Vec<Job> {
+        let mut due = Vec::new();
+        for (job, next) in &mut self.entries {
+            if next.is_some_and(|next| next <= now) {
+                due.push(job.job);
+                *next = job.trigger.next_after(now);
+            }
+        }
+        due
+    }
+}
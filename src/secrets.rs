@@ -0,0 +1,198 @@
+use aes_gcm_siv::{
+    aead::{Aead, NewAead},
+    Aes256GcmSiv, Key, Nonce,
+};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::Hmac;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{ErrorKind, IsTerminal},
+    path::PathBuf,
+};
+
+const DEFAULT_SECRETS_FILE: &str = "secrets.enc";
+const PASSPHRASE_VAR: &str = "MONITOR_SECRETS_PASSPHRASE";
+const KDF_ROUNDS: u32 = 600_000;  // PBKDF2-HMAC-SHA256 的迭代次数
+const FORMAT_VERSION: u32 = 1;
+
+/// 加密的密钥文件，明文是变量名到值的映射
+#[derive(Serialize, Deserialize)]
+struct SecretsFile {
+    version: u32,
+    kdf_rounds: u32,
+    salt: String,   // base64
+    nonce: String,  // base64
+    ciphertext: String,  // base64
+}
+
+/// 密钥文件的位置，`SECRETS_FILE` 未设置时为当前目录下的 `secrets.enc`
+pub fn path() -> PathBuf {
+    env::var("SECRETS_FILE").map_or_else(|_| PathBuf::from(DEFAULT_SECRETS_FILE), PathBuf::from)
+}
+
+/// 解密密钥文件，把其中的变量设置到环境变量中，已经设置的环境变量优先。
+/// 返回设置的变量数，没有密钥文件时返回 0。
+///
+/// 在启动时、读取配置和启动任何任务之前调用，与 `.env` 的加载方式相同
+pub fn load_into_env() -> Result<usize> {
+    let Some(file) = load()? else {
+        return Ok(0);
+    };
+    if env::var_os(PASSPHRASE_VAR).is_none() && !std::io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "{} is encrypted, set {} or start the monitor from a terminal",
+            path().display(),
+            PASSPHRASE_VAR
+        ));
+    }
+    let secrets = decrypt(&file, &passphrase(false)?)?;
+    let mut loaded = 0;
+    for (key, value) in secrets {
+        if env::var_os(&key).is_none() {
+            env::set_var(&key, value);
+            loaded += 1;
+        }
+    }
+    Ok(loaded)
+}
+
+/// `monitor secrets set|remove|list`
+pub fn run(args: &[String]) -> Result<()> {
+    match args {
+        [command, key] if command == "set" => {
+            let value = rpassword::prompt_password(format!("Value of {}: ", key))?;
+            update(|secrets| {
+                secrets.insert(key.clone(), value);
+            })?;
+            println!("Stored {} in {}", key, path().display());
+        }
+        [command, key] if command == "remove" => {
+            let mut removed = false;
+            update(|secrets| removed = secrets.remove(key).is_some())?;
+            if !removed {
+                return Err(anyhow!("{} is not stored in {}", key, path().display()));
+            }
+            println!("Removed {} from {}", key, path().display());
+        }
+        [command] if command == "list" => {
+            let secrets = match load()? {
+                Some(file) => decrypt(&file, &passphrase(false)?)?,
+                None => BTreeMap::new(),
+            };
+            if secrets.is_empty() {
+                println!("No secrets stored in {}", path().display());
+            }
+            secrets.keys().for_each(|key| println!("{}", key));
+        }
+        _ => {
+            return Err(anyhow!(
+                "Usage: monitor secrets set <NAME> | monitor secrets remove <NAME> | monitor secrets list"
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// 读取、修改并重新加密密钥文件，每次写入使用新的 salt 和 nonce
+fn update(change: impl FnOnce(&mut BTreeMap<String, String>)) -> Result<()> {
+    let existing = load()?;
+    let passphrase = passphrase(existing.is_none())?;
+    let mut secrets = match &existing {
+        Some(file) => decrypt(file, &passphrase)?,
+        None => BTreeMap::new(),
+    };
+    change(&mut secrets);
+
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let cipher = cipher(&passphrase, &salt, KDF_ROUNDS);
+    let plaintext = serde_json::to_vec(&secrets)?;
+    let ciphertext = cipher
+        .encrypt(&Nonce::from(nonce), plaintext.as_ref())
+        .map_err(|_| anyhow!("Failed to encrypt secrets"))?;
+    let file = SecretsFile {
+        version: FORMAT_VERSION,
+        kdf_rounds: KDF_ROUNDS,
+        salt: STANDARD.encode(salt),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    };
+
+    let path = path();
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(&file)?)
+        .map_err(|e| anyhow!("Failed to write {}: {}", tmp.display(), e))?;
+    restrict_permissions(&tmp)?;
+    fs::rename(&tmp, &path).map_err(|e| anyhow!("Failed to replace {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// 读取密钥文件，文件不存在时返回 None
+fn load() -> Result<Option<SecretsFile>> {
+    let path = path();
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+    };
+    let file: SecretsFile = serde_json::from_str(&content)
+        .map_err(|e| anyhow!("Invalid secrets file {}: {}", path.display(), e))?;
+    if file.version != FORMAT_VERSION {
+        return Err(anyhow!("Unsupported secrets file version {} in {}", file.version, path.display()));
+    }
+    Ok(Some(file))
+}
+
+fn decrypt(file: &SecretsFile, passphrase: &str) -> Result<BTreeMap<String, String>> {
+    let path = path();
+    let salt = STANDARD.decode(&file.salt)?;
+    let nonce = STANDARD.decode(&file.nonce)?;
+    let ciphertext = STANDARD.decode(&file.ciphertext)?;
+    let nonce: [u8; 12] = nonce.try_into().map_err(|_| anyhow!("Invalid nonce in {}", path.display()))?;
+    let plaintext = cipher(passphrase, &salt, file.kdf_rounds)
+        .decrypt(&Nonce::from(nonce), ciphertext.as_ref())
+        .map_err(|_| anyhow!("Failed to decrypt {}: wrong passphrase or corrupted file", path.display()))?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+/// 优先使用 `MONITOR_SECRETS_PASSPHRASE`，否则在终端中输入。新建文件时需要输入两次
+fn passphrase(confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_VAR) {
+        return Ok(passphrase);
+    }
+    let passphrase = rpassword::prompt_password("Secrets passphrase: ")?;
+    if confirm {
+        if passphrase.is_empty() {
+            return Err(anyhow!("The passphrase must not be empty"));
+        }
+        if rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+            return Err(anyhow!("Passphrases do not match"));
+        }
+    }
+    Ok(passphrase)
+}
+
+fn cipher(passphrase: &str, salt: &[u8], rounds: u32) -> Aes256GcmSiv {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, rounds, &mut key);
+    Aes256GcmSiv::new(&Key::from(key))
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+        .map_err(|e| anyhow!("Failed to restrict permissions of {}: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &std::path::Path) -> Result<()> {
+    Ok(())
+}
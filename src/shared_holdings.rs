@@ -0,0 +1,113 @@
+use crate::store::{SharedHolding, StateStore};
+use anyhow::Result;
+use chrono::Utc;
+use std::{
+    collections::{BTreeSet, HashMap},
+    future::Future,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+use tokio::{sync::Mutex, time::sleep};
+
+const LOCK_SECS: i64 = 10;  // 计入一笔交易时锁定代币行的时长，实例中途退出时锁到期后自动释放
+const LOCK_RETRY: Duration = Duration::from_millis(100);
+
+/// 多个实例共享的持仓。每个代币一行，带版本号：写入时版本必须仍是本实例最后读到或写入的版本，
+/// 否则说明其他实例修改过，放弃本地的修改并读取存储中的内容。
+///
+/// 内容按 JSON 字符串比较，只写入变化的代币。计入交易时先锁定代币行，读取最新内容后再计入，
+/// 其他实例在锁定期间不能写入，同一代币的交易不会互相覆盖
+pub struct SharedHoldings {
+    store: Arc<dyn StateStore>,
+    known: StdMutex<HashMap<String, Known>>,  // 代币 -> 本实例最后读到或写入的行
+    publishing: Mutex<()>,  // 本实例的保存任务和交易依次写入
+}
+
+struct Known {
+    revision: i64,
+    content: Option<String>,
+}
+
+impl SharedHoldings {
+    /// `rows` 为启动时读取的所有行
+    pub fn new(store: Arc<dyn StateStore>, rows: &[SharedHolding]) -> Self {
+        let known = rows
+            .iter()
+            .map(|row| (row.mint.clone(), Known { revision: row.revision, content: row.content.clone() }))
+            .collect();
+        Self {
+            store,
+            known: StdMutex::new(known),
+            publishing: Mutex::new(()),
+        }
+    }
+
+    /// 锁定代币行，其他实例持有锁时等待，最多等一个锁的时长。返回是否锁定成功
+    pub async fn lock(&self, mint: &str) -> Result<bool> {
+        let deadline = Utc::now().timestamp() + LOCK_SECS;
+        loop {
+            let now = Utc::now().timestamp();
+            if self.store.lock_holding(mint, now, now + LOCK_SECS).await? {
+                return Ok(true);
+            }
+            if now >= deadline {
+                return Ok(false);
+            }
+            sleep(LOCK_RETRY).await;
+        }
+    }
+
+    /// 读取存储中的行，`mint` 为 None 时读取所有代币。只返回比本实例已知版本新的行
+    pub async fn fetch(&self, mint: Option<&str>) -> Result<Vec<SharedHolding>> {
+        let rows = self.store.load_holdings(mint).await?;
+        let known = self.known.lock().unwrap();
+        Ok(rows
+            .into_iter()
+            .filter(|row| known.get(&row.mint).is_none_or(|known| row.revision > known.revision))
+            .collect())
+    }
+
+    /// 在持有持仓写锁时确认要使用读到的行。读取之后本实例又写入过时返回 false，行已经过时
+    pub fn accept(&self, row: &SharedHolding) -> bool {
+        let mut known = self.known.lock().unwrap();
+        if known.get(&row.mint).is_some_and(|known| row.revision <= known.revision) {
+            return false;
+        }
+        known.insert(row.mint.clone(), Known { revision: row.revision, content: row.content.clone() });
+        true
+    }
+
+    /// 写入内容变化的代币。`holdings` 读取代币当前的内容，在轮到本次写入后才读取，不会写入被更新的写入覆盖的旧内容；
+    /// 不在其中的已知代币视为已清仓。`only` 只写入这个代币，即使内容没有变化也写入以释放锁。
+    /// 返回写入失败（其他实例修改过或持有锁）的代币
+    pub async fn publish(&self, holdings: impl Future<Output = HashMap<String, String>>, only: Option<&str>) -> Result<Vec<String>> {
+        let _publishing = self.publishing.lock().await;
+        let holdings = holdings.await;
+        let changes: Vec<(String, i64, Option<String>)> = {
+            let known = self.known.lock().unwrap();
+            let mints: BTreeSet<&str> = match only {
+                Some(mint) => BTreeSet::from([mint]),
+                None => known.keys().chain(holdings.keys()).map(String::as_str).collect(),
+            };
+            mints
+                .into_iter()
+                .filter_map(|mint| {
+                    let content = holdings.get(mint);
+                    let (revision, published) = known.get(mint).map_or((0, None), |known| (known.revision, known.content.as_ref()));
+                    (only.is_some() || content != published).then(|| (mint.to_string(), revision, content.cloned()))
+                })
+                .collect()
+        };
+        let now = Utc::now().timestamp();
+        let mut conflicts = Vec::new();
+        for (mint, revision, content) in changes {
+            match self.store.save_holding(&mint, revision, content.as_deref(), now).await? {
+                Some(revision) => {
+                    self.known.lock().unwrap().insert(mint, Known { revision, content });
+                }
+                None => conflicts.push(mint),
+            }
+        }
+        Ok(conflicts)
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use tokio::process::Command;
+
+const MIN_INTERVAL: Duration = Duration::from_secs(2);  // 连续的严重提醒只响一次
+
+static SOUND: OnceLock<Sound> = OnceLock::new();
+static LAST_PLAYED: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// 严重提醒触发时的提示音
+#[derive(Debug)]
+enum Sound {
+    Bell,
+    File { path: PathBuf, player: Option<String> },
+}
+
+/// 读取 `ALERT_SOUND`：`bell` 输出终端响铃，其他值视为声音文件路径，
+/// 用 `ALERT_SOUND_PLAYER` 或系统自带的播放器播放。只在终端运行时生效
+pub fn init() -> Result<()> {
+    let spec = env::var("ALERT_SOUND").unwrap_or_default();
+    let sound = match spec.trim() {
+        "" | "off" => return Ok(()),
+        "bell" => Sound::Bell,
+        path => {
+            let path = PathBuf::from(path);
+            if !path.is_file() {
+                return Err(anyhow!("ALERT_SOUND file not found: {}", path.display()));
+            }
+            let player = env::var("ALERT_SOUND_PLAYER").ok().filter(|player| !player.trim().is_empty());
+            Sound::File { path, player }
+        }
+    };
+    if io::stdout().is_terminal() {
+        let _ = SOUND.set(sound);
+    }
+    Ok(())
+}
+
+/// 播放提示音，不等待播放结束
+pub fn play() {
+    let Some(sound) = SOUND.get() else {
+        return;
+    };
+    {
+        let mut last = LAST_PLAYED.lock().unwrap();
+        if last.is_some_and(|last| last.elapsed() < MIN_INTERVAL) {
+            return;
+        }
+        *last = Some(Instant::now());
+    }
+
+    match sound {
+        Sound::Bell => {
+            let mut stdout = io::stdout().lock();
+            let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+        }
+        Sound::File { path, player } => {
+            let mut command = match player {
+                Some(player) => Command::new(player),
+                None => default_player(),
+            };
+            command.arg(path);
+            if let Err(e) = command.spawn() {
+                debug!("Failed to play alert sound {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn default_player() -> Command {
+    Command::new("afplay")
+}
+
+#[cfg(windows)]
+fn default_player() -> Command {
+    // 声音文件路径作为最后一个参数传给脚本
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", "& { (New-Object Media.SoundPlayer $args[0]).PlaySync() }"]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn default_player() -> Command {
+    Command::new("paplay")
+}
@@ -0,0 +1,81 @@
+use crate::store::{SqlConnection, SqlValue};
+use anyhow::{anyhow, Result};
+use rusqlite::{
+    params_from_iter,
+    types::{ToSqlOutput, Value, ValueRef},
+    Connection, ToSql,
+};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);  // 其他进程写入时等待锁的时间
+
+/// SQLite 数据库的连接，使用 WAL 模式，`monitor report` 可以在监控运行时读取
+pub struct SqliteConnection {
+    connection: Connection,
+    path: PathBuf,
+}
+
+impl SqliteConnection {
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection = Connection::open(path).map_err(|e| anyhow!("Failed to open {}: {}", path.display(), e))?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        Ok(Self {
+            connection,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// 内存中的数据库，用于测试
+    #[cfg(test)]
+    pub fn memory() -> Result<Self> {
+        Ok(Self {
+            connection: Connection::open_in_memory()?,
+            path: PathBuf::from(":memory:"),
+        })
+    }
+}
+
+impl ToSql for SqlValue<'_> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(match self {
+            SqlValue::Text(text) => ToSqlOutput::Borrowed(ValueRef::Text(text.as_bytes())),
+            SqlValue::Int(value) => ToSqlOutput::Owned(Value::Integer(*value)),
+            SqlValue::Null => ToSqlOutput::Owned(Value::Null),
+        })
+    }
+}
+
+impl SqlConnection for SqliteConnection {
+    const SERIAL: &'static str = "INTEGER PRIMARY KEY AUTOINCREMENT";
+
+    fn execute(&mut self, sql: &str, params: &[SqlValue]) -> Result<u64> {
+        let changed = self.connection.execute(sql, params_from_iter(params))?;
+        Ok(changed as u64)
+    }
+
+    fn query(&mut self, sql: &str, params: &[SqlValue]) -> Result<Vec<Vec<Option<String>>>> {
+        let mut statement = self.connection.prepare(sql)?;
+        let columns = statement.column_count();
+        let rows = statement.query_map(params_from_iter(params), |row| {
+            (0..columns)
+                .map(|column| {
+                    Ok(match row.get_ref(column)? {
+                        ValueRef::Null => None,
+                        ValueRef::Integer(value) => Some(value.to_string()),
+                        ValueRef::Real(value) => Some(value.to_string()),
+                        ValueRef::Text(text) | ValueRef::Blob(text) => Some(String::from_utf8_lossy(text).into_owned()),
+                    })
+                })
+                .collect()
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn describe(&self) -> String {
+        format!("SQLite database {}", self.path.display())
+    }
+}
@@ -0,0 +1,702 @@
+use crate::{
+    alert_service::HISTORY_FILE,
+    journal::{self, JournalEntry, JOURNAL_FILE},
+    migrations, persistence, retention,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use log::info;
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tokio::task;
+
+pub(crate) const SQLITE_FILE: &str = "monitor.db";
+
+/// 状态的存储后端（`STATE_STORE`）
+#[derive(Clone, PartialEq)]
+pub enum StoreConfig {
+    Json,  // 数据目录中的 state.json 和 JSONL 文件
+    Sqlite(Option<PathBuf>),  // 数据库文件，None 时为数据目录中的 `monitor.db`
+    Postgres(String),  // 连接字符串，例如 `postgres://monitor:secret@db/monitor`
+}
+
+impl fmt::Debug for StoreConfig {
+    /// 连接字符串可能包含密码，不输出
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreConfig::Json => write!(f, "Json"),
+            StoreConfig::Sqlite(path) => f.debug_tuple("Sqlite").field(path).finish(),
+            StoreConfig::Postgres(_) => write!(f, "Postgres(..)"),
+        }
+    }
+}
+
+/// 存储中可以按时间清理的记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Records {
+    Trades,
+    Alerts,
+}
+
+impl Records {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Records::Trades => "trades",
+            Records::Alerts => "alert history",
+        }
+    }
+}
+
+/// 多个实例共享的持仓中一个代币的行
+#[derive(Debug, Clone)]
+pub struct SharedHolding {
+    pub mint: String,
+    pub revision: i64,  // 每次写入加一，0 表示只被锁定过、还没有写入
+    pub content: Option<String>,  // 持仓（JSON），None 表示没有持仓
+}
+
+/// 持仓状态、交易日志和提醒历史的存储。
+///
+/// 文件和数据库的读写都是阻塞的，实现在 `spawn_blocking` 的线程中执行，不占用 tokio 的 worker 线程。
+/// 实现需要自己处理并发调用。
+///
+/// 设置了 `INSTANCE_ID` 时多个实例共用一个数据库：持仓按代币分行保存，交易和提醒由先领取的实例处理。
+/// 后面几个共享状态的方法只在这时使用，默认实现对应只有一个实例的情况
+#[async_trait]
+pub trait StateStore: Send + Sync {
+    /// 启动日志中显示的位置，不含密码
+    fn describe(&self) -> String;
+
+    /// 保存的持仓状态（JSON），从未保存过时为 None。共享状态时每个实例各有一份
+    async fn load_state(&self) -> Result<Option<String>>;
+
+    /// 替换保存的持仓状态，写到一半失败时保留上一次的状态
+    async fn save_state(&self, content: &str) -> Result<()>;
+
+    /// 追加一笔目标钱包的交易。返回 false 表示另一个实例已经记录了这笔交易，由它计入持仓
+    async fn record_trade(&self, entry: &JournalEntry) -> Result<bool>;
+
+    /// 所有交易，按写入的顺序
+    async fn load_trades(&self) -> Result<Vec<JournalEntry>>;
+
+    /// 追加一条提醒的投递记录（JSON）
+    async fn record_alert(&self, timestamp: i64, entry: &str) -> Result<()>;
+
+    /// 删除 `timestamp` 早于 `cutoff` 的记录，返回删除的数量
+    async fn prune(&self, records: Records, cutoff: i64) -> Result<usize>;
+
+    /// 共享状态的实例名（`INSTANCE_ID`），只有一个实例时为 None
+    fn instance(&self) -> Option<&str> {
+        None
+    }
+
+    /// 领取提醒的幂等键。其他实例在 `since` 之后领取过时返回 false，提醒由它发送
+    async fn claim_alert(&self, _key: &str, _now: i64, _since: i64) -> Result<bool> {
+        Ok(true)
+    }
+
+    /// 共享持仓的行，`mint` 为 None 时读取所有代币
+    async fn load_holdings(&self, _mint: Option<&str>) -> Result<Vec<SharedHolding>> {
+        Ok(Vec::new())
+    }
+
+    /// 锁定代币的行到 `until`，其他实例的锁还没有到期时返回 false。锁定期间其他实例不能写入这一行
+    async fn lock_holding(&self, _mint: &str, _now: i64, _until: i64) -> Result<bool> {
+        Ok(true)
+    }
+
+    /// 行的版本仍是 `revision` 并且没有被其他实例锁定时写入并释放本实例的锁，返回新版本；
+    /// 否则不写入，返回 None
+    async fn save_holding(&self, _mint: &str, revision: i64, _content: Option<&str>, _now: i64) -> Result<Option<i64>> {
+        Ok(Some(revision + 1))
+    }
+}
+
+/// 在阻塞线程池中执行文件或数据库操作
+async fn blocking<T: Send + 'static>(run: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    task::spawn_blocking(run).await.map_err(|e| anyhow!("Store operation panicked: {}", e))?
+}
+
+/// 按配置打开存储。数据库还没有这个 profile（实例）的状态而数据目录中有 JSON 状态时，
+/// 先把状态和交易日志导入数据库，从文件切换到数据库时不丢失持仓。
+/// `instance` 为共享状态的实例名，只能和数据库一起使用。
+/// `replica` 为只读副本：不修改数据库的表，也不导入文件，数据库必须已经由监控实例升级到当前版本
+pub async fn open(profile: &str, instance: Option<&str>, config: &StoreConfig, data_dir: &Path, replica: bool) -> Result<Arc<dyn StateStore>> {
+    let (profile_name, instance_name) = (profile.to_string(), instance.map(str::to_string));
+    let store: Arc<dyn StateStore> = match config {
+        StoreConfig::Json if instance.is_some() => {
+            return Err(anyhow!("INSTANCE_ID needs STATE_STORE=sqlite or postgres, JSON files cannot be shared"))
+        }
+        StoreConfig::Json => return Ok(Arc::new(JsonStore::new(data_dir))),
+        StoreConfig::Sqlite(path) => {
+            let path = path.clone().unwrap_or_else(|| data_dir.join(SQLITE_FILE));
+            blocking(move || open_sqlite(&profile_name, instance_name.as_deref(), &path, replica)).await?
+        }
+        StoreConfig::Postgres(url) => {
+            let url = url.clone();
+            blocking(move || open_postgres(&profile_name, instance_name.as_deref(), &url, replica)).await?
+        }
+    };
+    if !replica {
+        import_json(profile, store.as_ref(), data_dir).await?;
+    }
+    Ok(store)
+}
+
+#[cfg(feature = "sqlite")]
+fn open_sqlite(profile: &str, instance: Option<&str>, path: &Path, replica: bool) -> Result<Arc<dyn StateStore>> {
+    Ok(Arc::new(SqlStore::open(profile, instance, crate::sqlite::SqliteConnection::open(path)?, replica)?))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_sqlite(_profile: &str, _instance: Option<&str>, _path: &Path, _replica: bool) -> Result<Arc<dyn StateStore>> {
+    Err(anyhow!("STATE_STORE=sqlite needs a build with `--features sqlite`"))
+}
+
+#[cfg(feature = "postgres")]
+fn open_postgres(profile: &str, instance: Option<&str>, url: &str, replica: bool) -> Result<Arc<dyn StateStore>> {
+    Ok(Arc::new(SqlStore::open(profile, instance, crate::postgres::PostgresConnection::connect(url)?, replica)?))
+}
+
+#[cfg(not(feature = "postgres"))]
+fn open_postgres(_profile: &str, _instance: Option<&str>, _url: &str, _replica: bool) -> Result<Arc<dyn StateStore>> {
+    Err(anyhow!("STATE_STORE=postgres needs a build with `--features postgres`"))
+}
+
+async fn import_json(profile: &str, store: &dyn StateStore, data_dir: &Path) -> Result<()> {
+    if store.load_state().await?.is_some() {
+        return Ok(());
+    }
+    let json = JsonStore::new(data_dir);
+    let Some(state) = json.load_state().await? else {
+        return Ok(());
+    };
+    let trades = if data_dir.join(JOURNAL_FILE).exists() { json.load_trades().await? } else { Vec::new() };
+    for entry in &trades {
+        store.record_trade(entry).await?;
+    }
+    // 状态最后写入，中途失败时下次启动重新导入
+    store.save_state(&state).await?;
+    info!("[{}] Imported the state and {} trade(s) from {} into {}", profile, trades.len(), data_dir.display(), store.describe());
+    Ok(())
+}
+
+/// 数据目录中的文件：`state.json`、`trades.jsonl` 和 `alert_history.jsonl`
+pub struct JsonStore {
+    data_dir: PathBuf,
+}
+
+impl JsonStore {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            data_dir: data_dir.to_path_buf(),
+        }
+    }
+
+    fn file(&self, records: Records) -> PathBuf {
+        self.data_dir.join(match records {
+            Records::Trades => JOURNAL_FILE,
+            Records::Alerts => HISTORY_FILE,
+        })
+    }
+}
+
+#[async_trait]
+impl StateStore for JsonStore {
+    fn describe(&self) -> String {
+        format!("JSON files in {}", self.data_dir.display())
+    }
+
+    async fn load_state(&self) -> Result<Option<String>> {
+        let data_dir = self.data_dir.clone();
+        blocking(move || persistence::read(&data_dir)).await
+    }
+
+    async fn save_state(&self, content: &str) -> Result<()> {
+        let (data_dir, content) = (self.data_dir.clone(), content.to_string());
+        blocking(move || persistence::save(&data_dir, &content)).await
+    }
+
+    async fn record_trade(&self, entry: &JournalEntry) -> Result<bool> {
+        let (path, line) = (self.file(Records::Trades), serde_json::to_string(entry)?);
+        blocking(move || append_line(&path, &line)).await?;
+        Ok(true)
+    }
+
+    async fn load_trades(&self) -> Result<Vec<JournalEntry>> {
+        let data_dir = self.data_dir.clone();
+        blocking(move || journal::load(&data_dir)).await
+    }
+
+    async fn record_alert(&self, _timestamp: i64, entry: &str) -> Result<()> {
+        let (path, line) = (self.file(Records::Alerts), entry.to_string());
+        blocking(move || append_line(&path, &line)).await
+    }
+
+    async fn prune(&self, records: Records, cutoff: i64) -> Result<usize> {
+        let path = self.file(records);
+        blocking(move || retention::prune_file(&path, cutoff)).await
+    }
+}
+
+fn append_line(path: &Path, line: &str) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// SQL 参数
+#[cfg_attr(not(any(feature = "sqlite", feature = "postgres")), allow(dead_code))]
+#[derive(Clone, Copy)]
+pub enum SqlValue<'a> {
+    Text(&'a str),
+    Int(i64),
+    Null,
+}
+
+/// SQL 数据库的连接，SQLite 和 PostgreSQL 各实现一个。参数使用 `?1`、`?2` 的写法，PostgreSQL 的连接转换为 `$1`
+#[cfg_attr(not(any(feature = "sqlite", feature = "postgres")), allow(dead_code))]
+pub trait SqlConnection: Send {
+    /// 自增主键列的类型
+    const SERIAL: &'static str;
+
+    /// 执行语句，返回修改的行数
+    fn execute(&mut self, sql: &str, params: &[SqlValue]) -> Result<u64>;
+
+    /// 执行查询，返回每行各列的文本，NULL 为 None
+    fn query(&mut self, sql: &str, params: &[SqlValue]) -> Result<Vec<Vec<Option<String>>>>;
+
+    fn describe(&self) -> String;
+}
+
+/// 保存在 SQL 数据库中的状态。多个 profile 可以共用一个数据库，每行都带 profile 名。
+/// 持仓状态整体保存为一行 JSON，交易和提醒历史各一张表，每条记录一行。
+///
+/// 共享状态时（有实例名）每个实例的状态保存在 `instance_state`，持仓另外按代币保存在 `holdings`，
+/// 用版本号做乐观并发控制；`trade_claims` 和 `alert_keys` 的主键保证每笔交易和每条提醒只被一个实例领取
+#[cfg_attr(not(any(feature = "sqlite", feature = "postgres")), allow(dead_code))]
+pub struct SqlStore<C> {
+    database: Arc<SqlDatabase<C>>,
+}
+
+/// `SqlStore` 的同步部分，在阻塞线程中使用
+struct SqlDatabase<C> {
+    profile: String,
+    instance: Option<String>,
+    connection: Mutex<C>,
+}
+
+#[cfg_attr(not(any(feature = "sqlite", feature = "postgres")), allow(dead_code))]
+impl<C: SqlConnection + 'static> SqlStore<C> {
+    /// 使用连接并把数据库升级到当前版本，只读副本只检查版本。会阻塞，在阻塞线程中调用
+    pub fn open(profile: &str, instance: Option<&str>, connection: C, replica: bool) -> Result<Self> {
+        let database = match replica {
+            true => SqlDatabase::attach(profile, instance, connection)?,
+            false => SqlDatabase::open(profile, instance, connection)?,
+        };
+        Ok(Self {
+            database: Arc::new(database),
+        })
+    }
+
+    /// 在阻塞线程中使用数据库
+    async fn run<T: Send + 'static>(&self, run: impl FnOnce(&SqlDatabase<C>) -> Result<T> + Send + 'static) -> Result<T> {
+        let database = self.database.clone();
+        blocking(move || run(&database)).await
+    }
+}
+
+#[cfg_attr(not(any(feature = "sqlite", feature = "postgres")), allow(dead_code))]
+impl<C: SqlConnection> SqlDatabase<C> {
+    fn open(profile: &str, instance: Option<&str>, mut connection: C) -> Result<Self> {
+        let applied = migrations::apply_sql(&mut connection)
+            .map_err(|e| anyhow!("Failed to migrate {}: {:#}", connection.describe(), e))?;
+        if applied > 0 {
+            info!("Applied {} schema migration(s) to {}", applied, connection.describe());
+        }
+        Ok(Self {
+            profile: profile.to_string(),
+            instance: instance.map(str::to_string),
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// 只读副本使用监控实例的数据库，不执行迁移。数据库的版本必须与程序一致，
+    /// 并且监控实例设置了 `INSTANCE_ID`，持仓按代币保存在共享的行中
+    fn attach(profile: &str, instance: Option<&str>, mut connection: C) -> Result<Self> {
+        let database = connection.describe();
+        let version = migrations::sql_version(&mut connection)
+            .map_err(|e| anyhow!("{} has no monitor tables, start the monitoring instance first: {}", database, e))?;
+        let latest = migrations::latest_sql_version();
+        if version != latest {
+            return Err(anyhow!(
+                "{} has schema version {} but this build needs version {}, upgrade the monitoring instance and the replica together",
+                database,
+                version,
+                latest
+            ));
+        }
+        let exists = |connection: &mut C, sql: &str| -> Result<bool> { Ok(!connection.query(sql, &[SqlValue::Text(profile)])?.is_empty()) };
+        let unshared = exists(&mut connection, "SELECT 1 FROM monitor_state WHERE profile = ?1 LIMIT 1")?;
+        let shared = exists(&mut connection, "SELECT 1 FROM instance_state WHERE profile = ?1 LIMIT 1")?;
+        if unshared && !shared {
+            return Err(anyhow!(
+                "Profile '{}' in {} is saved without INSTANCE_ID, set INSTANCE_ID on the monitoring instance so a replica can read its holdings",
+                profile,
+                database
+            ));
+        }
+        Ok(Self {
+            profile: profile.to_string(),
+            instance: instance.map(str::to_string),
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// 在一个事务中执行，出错时回滚
+    fn transaction<T>(&self, run: impl FnOnce(&mut C) -> Result<T>) -> Result<T> {
+        let mut connection = self.connection.lock().unwrap();
+        connection.execute("BEGIN", &[])?;
+        match run(&mut connection) {
+            Ok(value) => {
+                connection.execute("COMMIT", &[])?;
+                Ok(value)
+            }
+            Err(e) => {
+                let _ = connection.execute("ROLLBACK", &[]);
+                Err(e)
+            }
+        }
+    }
+
+    fn insert_trade(&self, connection: &mut C, entry: &JournalEntry) -> Result<()> {
+        let content = serde_json::to_string(entry)?;
+        connection.execute(
+            "INSERT INTO trades (profile, timestamp, signature, wallet, entry) VALUES (?1, ?2, ?3, ?4, ?5)",
+            &[
+                SqlValue::Text(&self.profile),
+                SqlValue::Int(entry.timestamp),
+                SqlValue::Text(&entry.signature),
+                SqlValue::Text(&entry.wallet),
+                SqlValue::Text(&content),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn load_state(&self) -> Result<Option<String>> {
+        let mut connection = self.connection.lock().unwrap();
+        let rows = match &self.instance {
+            Some(instance) => connection.query(
+                "SELECT content FROM instance_state WHERE profile = ?1 AND instance = ?2",
+                &[SqlValue::Text(&self.profile), SqlValue::Text(instance)],
+            )?,
+            None => connection.query("SELECT content FROM monitor_state WHERE profile = ?1", &[SqlValue::Text(&self.profile)])?,
+        };
+        Ok(rows.into_iter().next().and_then(|row| row.into_iter().next().flatten()))
+    }
+
+    fn save_state(&self, content: &str) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let mut connection = self.connection.lock().unwrap();
+        match &self.instance {
+            Some(instance) => connection.execute(
+                "INSERT INTO instance_state (profile, instance, saved_at, content) VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (profile, instance) DO UPDATE SET saved_at = excluded.saved_at, content = excluded.content",
+                &[SqlValue::Text(&self.profile), SqlValue::Text(instance), SqlValue::Int(now), SqlValue::Text(content)],
+            )?,
+            None => connection.execute(
+                "INSERT INTO monitor_state (profile, saved_at, content) VALUES (?1, ?2, ?3)
+                ON CONFLICT (profile) DO UPDATE SET saved_at = excluded.saved_at, content = excluded.content",
+                &[SqlValue::Text(&self.profile), SqlValue::Int(now), SqlValue::Text(content)],
+            )?,
+        };
+        Ok(())
+    }
+
+    /// 共享状态时先领取交易，领取和写入在同一个事务中。已经被本实例领取的交易（例如退出前领取了
+    /// 但还没有计入持仓，重启后补处理）仍返回 true，但不重复写入
+    fn record_trade(&self, entry: &JournalEntry) -> Result<bool> {
+        let Some(instance) = &self.instance else {
+            self.insert_trade(&mut self.connection.lock().unwrap(), entry)?;
+            return Ok(true);
+        };
+        let side = if entry.is_buy { "buy" } else { "sell" };
+        let key = [
+            SqlValue::Text(&self.profile),
+            SqlValue::Text(&entry.signature),
+            SqlValue::Text(&entry.wallet),
+            SqlValue::Text(&entry.mint),
+            SqlValue::Text(side),
+        ];
+        self.transaction(|connection| {
+            let claimed = connection.execute(
+                "INSERT INTO trade_claims (profile, signature, wallet, mint, side, instance, claimed_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) ON CONFLICT DO NOTHING",
+                &[&key[..], &[SqlValue::Text(instance), SqlValue::Int(entry.timestamp)]].concat(),
+            )?;
+            if claimed > 0 {
+                self.insert_trade(connection, entry)?;
+                return Ok(true);
+            }
+            let owner = connection.query(
+                "SELECT instance FROM trade_claims WHERE profile = ?1 AND signature = ?2 AND wallet = ?3 AND mint = ?4 AND side = ?5",
+                &key,
+            )?;
+            Ok(owner.into_iter().next().and_then(|row| row.into_iter().next().flatten()).as_deref() == Some(instance.as_str()))
+        })
+    }
+
+    /// 无法解析的记录跳过，与读取 JSONL 文件时一致
+    fn load_trades(&self) -> Result<Vec<JournalEntry>> {
+        let rows = self
+            .connection
+            .lock()
+            .unwrap()
+            .query("SELECT entry FROM trades WHERE profile = ?1 ORDER BY id", &[SqlValue::Text(&self.profile)])?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| row.into_iter().next().flatten())
+            .filter_map(|entry| serde_json::from_str(&entry).ok())
+            .collect())
+    }
+
+    fn record_alert(&self, timestamp: i64, entry: &str) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "INSERT INTO alert_history (profile, timestamp, entry) VALUES (?1, ?2, ?3)",
+            &[SqlValue::Text(&self.profile), SqlValue::Int(timestamp), SqlValue::Text(entry)],
+        )?;
+        Ok(())
+    }
+
+    /// 清理交易时同时清理交易的领取记录，这之前的交易不会再被补处理
+    fn prune(&self, records: Records, cutoff: i64) -> Result<usize> {
+        let sql = match records {
+            Records::Trades => "DELETE FROM trades WHERE profile = ?1 AND timestamp < ?2",
+            Records::Alerts => "DELETE FROM alert_history WHERE profile = ?1 AND timestamp < ?2",
+        };
+        let params = [SqlValue::Text(&self.profile), SqlValue::Int(cutoff)];
+        let mut connection = self.connection.lock().unwrap();
+        let removed = connection.execute(sql, &params)?;
+        if records == Records::Trades {
+            connection.execute("DELETE FROM trade_claims WHERE profile = ?1 AND claimed_at < ?2", &params)?;
+        }
+        Ok(removed as usize)
+    }
+
+    /// 过期的键在领取时删除。本实例领取过的键也返回 true，本地的队列会识别重复的提醒
+    fn claim_alert(&self, key: &str, now: i64, since: i64) -> Result<bool> {
+        let Some(instance) = &self.instance else {
+            return Ok(true);
+        };
+        let mut connection = self.connection.lock().unwrap();
+        connection.execute(
+            "DELETE FROM alert_keys WHERE profile = ?1 AND claimed_at < ?2",
+            &[SqlValue::Text(&self.profile), SqlValue::Int(since)],
+        )?;
+        let claimed = connection.execute(
+            "INSERT INTO alert_keys (profile, key, instance, claimed_at) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (profile, key) DO UPDATE SET instance = excluded.instance, claimed_at = excluded.claimed_at
+            WHERE alert_keys.claimed_at < ?5 OR alert_keys.instance = excluded.instance",
+            &[
+                SqlValue::Text(&self.profile),
+                SqlValue::Text(key),
+                SqlValue::Text(instance),
+                SqlValue::Int(now),
+                SqlValue::Int(since),
+            ],
+        )?;
+        Ok(claimed > 0)
+    }
+
+    fn load_holdings(&self, mint: Option<&str>) -> Result<Vec<SharedHolding>> {
+        let mut connection = self.connection.lock().unwrap();
+        let rows = match mint {
+            Some(mint) => connection.query(
+                "SELECT mint, revision, content FROM holdings WHERE profile = ?1 AND mint = ?2",
+                &[SqlValue::Text(&self.profile), SqlValue::Text(mint)],
+            )?,
+            None => connection.query("SELECT mint, revision, content FROM holdings WHERE profile = ?1", &[SqlValue::Text(&self.profile)])?,
+        };
+        rows.into_iter()
+            .map(|row| match <[Option<String>; 3]>::try_from(row) {
+                Ok([Some(mint), Some(revision), content]) => Ok(SharedHolding {
+                    mint,
+                    revision: revision.parse()?,
+                    content,
+                }),
+                _ => Err(anyhow!("Invalid row in holdings")),
+            })
+            .collect()
+    }
+
+    /// 行不存在时插入一个没有内容的行来持有锁
+    fn lock_holding(&self, mint: &str, now: i64, until: i64) -> Result<bool> {
+        let Some(instance) = &self.instance else {
+            return Ok(true);
+        };
+        let locked = self.connection.lock().unwrap().execute(
+            "INSERT INTO holdings (profile, mint, revision, content, updated_at, updated_by, locked_by, locked_until)
+            VALUES (?1, ?2, 0, NULL, ?3, ?4, ?4, ?5)
+            ON CONFLICT (profile, mint) DO UPDATE SET locked_by = excluded.locked_by, locked_until = excluded.locked_until
+            WHERE holdings.locked_until < ?3 OR holdings.locked_by = excluded.locked_by",
+            &[
+                SqlValue::Text(&self.profile),
+                SqlValue::Text(mint),
+                SqlValue::Int(now),
+                SqlValue::Text(instance),
+                SqlValue::Int(until),
+            ],
+        )?;
+        Ok(locked > 0)
+    }
+
+    fn save_holding(&self, mint: &str, revision: i64, content: Option<&str>, now: i64) -> Result<Option<i64>> {
+        let Some(instance) = &self.instance else {
+            return Ok(Some(revision + 1));
+        };
+        let saved = self.connection.lock().unwrap().execute(
+            "INSERT INTO holdings (profile, mint, revision, content, updated_at, updated_by, locked_by, locked_until)
+            VALUES (?1, ?2, CAST(?3 AS BIGINT) + 1, ?4, ?5, ?6, NULL, 0)
+            ON CONFLICT (profile, mint) DO UPDATE SET revision = excluded.revision, content = excluded.content,
+                updated_at = excluded.updated_at, updated_by = excluded.updated_by, locked_by = NULL, locked_until = 0
+            WHERE holdings.revision = ?3 AND (holdings.locked_until < ?5 OR holdings.locked_by = excluded.updated_by)",
+            &[
+                SqlValue::Text(&self.profile),
+                SqlValue::Text(mint),
+                SqlValue::Int(revision),
+                content.map_or(SqlValue::Null, SqlValue::Text),
+                SqlValue::Int(now),
+                SqlValue::Text(instance),
+            ],
+        )?;
+        Ok((saved > 0).then_some(revision + 1))
+    }
+}
+
+#[async_trait]
+impl<C: SqlConnection + 'static> StateStore for SqlStore<C> {
+    fn describe(&self) -> String {
+        self.database.connection.lock().unwrap().describe()
+    }
+
+    async fn load_state(&self) -> Result<Option<String>> {
+        self.run(|database| database.load_state()).await
+    }
+
+    async fn save_state(&self, content: &str) -> Result<()> {
+        let content = content.to_string();
+        self.run(move |database| database.save_state(&content)).await
+    }
+
+    async fn record_trade(&self, entry: &JournalEntry) -> Result<bool> {
+        let entry = entry.clone();
+        self.run(move |database| database.record_trade(&entry)).await
+    }
+
+    async fn load_trades(&self) -> Result<Vec<JournalEntry>> {
+        self.run(|database| database.load_trades()).await
+    }
+
+    async fn record_alert(&self, timestamp: i64, entry: &str) -> Result<()> {
+        let entry = entry.to_string();
+        self.run(move |database| database.record_alert(timestamp, &entry)).await
+    }
+
+    async fn prune(&self, records: Records, cutoff: i64) -> Result<usize> {
+        self.run(move |database| database.prune(records, cutoff)).await
+    }
+
+    fn instance(&self) -> Option<&str> {
+        self.database.instance.as_deref()
+    }
+
+    async fn claim_alert(&self, key: &str, now: i64, since: i64) -> Result<bool> {
+        let key = key.to_string();
+        self.run(move |database| database.claim_alert(&key, now, since)).await
+    }
+
+    async fn load_holdings(&self, mint: Option<&str>) -> Result<Vec<SharedHolding>> {
+        let mint = mint.map(str::to_string);
+        self.run(move |database| database.load_holdings(mint.as_deref())).await
+    }
+
+    async fn lock_holding(&self, mint: &str, now: i64, until: i64) -> Result<bool> {
+        let mint = mint.to_string();
+        self.run(move |database| database.lock_holding(&mint, now, until)).await
+    }
+
+    async fn save_holding(&self, mint: &str, revision: i64, content: Option<&str>, now: i64) -> Result<Option<i64>> {
+        let (mint, content) = (mint.to_string(), content.map(str::to_string));
+        self.run(move |database| database.save_holding(&mint, revision, content.as_deref(), now)).await
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::sqlite::SqliteConnection;
+
+    fn store(instance: Option<&str>) -> SqlStore<SqliteConnection> {
+        SqlStore::open("test", instance, SqliteConnection::memory().unwrap(), false).unwrap()
+    }
+
+    fn trade(timestamp: i64, signature: &str) -> JournalEntry {
+        JournalEntry {
+            timestamp,
+            signature: signature.to_string(),
+            wallet: "wallet".to_string(),
+            mint: "mint".to_string(),
+            is_buy: true,
+            sol_amount: 1_000_000_000,
+            token_amount: 5_000_000,
+            decimals: 6,
+            price: 0.0002,
+            token_age_secs: Some(30),
+        }
+    }
+
+    #[tokio::test]
+    async fn state_trades_and_alerts_round_trip() {
+        let store = store(None);
+        assert_eq!(store.load_state().await.unwrap(), None);
+        store.save_state("{\"holdings\":[]}").await.unwrap();
+        store.save_state("{\"holdings\":[1]}").await.unwrap();
+        assert_eq!(store.load_state().await.unwrap().as_deref(), Some("{\"holdings\":[1]}"));
+
+        assert!(store.record_trade(&trade(100, "a")).await.unwrap());
+        assert!(store.record_trade(&trade(200, "b")).await.unwrap());
+        let trades = store.load_trades().await.unwrap();
+        assert_eq!(trades.iter().map(|entry| entry.signature.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(trades[0].token_age_secs, Some(30));
+
+        store.record_alert(100, "{}").await.unwrap();
+        assert_eq!(store.prune(Records::Trades, 150).await.unwrap(), 1);
+        assert_eq!(store.prune(Records::Alerts, 150).await.unwrap(), 1);
+        assert_eq!(store.load_trades().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn shared_rows_use_claims_locks_and_revisions() {
+        let store = store(Some("a"));
+        assert!(store.record_trade(&trade(100, "a")).await.unwrap());
+        // 本实例领取过的交易仍返回 true，但不重复写入
+        assert!(store.record_trade(&trade(100, "a")).await.unwrap());
+        assert_eq!(store.load_trades().await.unwrap().len(), 1);
+
+        assert!(store.claim_alert("key", 100, 0).await.unwrap());
+        assert!(store.lock_holding("mint", 100, 110).await.unwrap());
+        assert_eq!(store.save_holding("mint", 0, Some("{}"), 101).await.unwrap(), Some(1));
+        // 版本已经变化，旧版本的写入被拒绝
+        assert_eq!(store.save_holding("mint", 0, Some("{}"), 102).await.unwrap(), None);
+        let rows = store.load_holdings(Some("mint")).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0].revision, rows[0].content.as_deref()), (1, Some("{}")));
+    }
+}
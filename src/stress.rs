@@ -0,0 +1,122 @@
+use crate::{
+    display::HoldingRow,
+    wallet_monitor::{format_f64, format_signed_sol, truncate_address},
+};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// 一种统一回撤情景下的组合价值
+#[derive(Debug, Serialize)]
+pub struct StressScenario {
+    pub drawdown: f64,    // 百分比，例如 50 表示所有代币下跌 50%
+    pub value: f64,       // 按回撤后价格计算的价值
+    pub exit_value: f64,  // 再扣除各代币的流动性折损后能实际卖出的价值
+    pub pnl: f64,         // 按卖出价值计算的盈亏
+}
+
+/// 单个持仓的流动性折损
+#[derive(Debug, Serialize)]
+pub struct PositionLiquidity {
+    pub mint: String,
+    pub value: f64,
+    pub exit_value: f64,
+    pub price_impact: Option<f64>,  // 没有流动性估算时为 None
+}
+
+/// 组合压力测试结果
+#[derive(Debug, Serialize)]
+pub struct StressReport {
+    pub value: f64,
+    pub exit_value: f64,
+    pub cost: f64,
+    pub scenarios: Vec<StressScenario>,
+    pub positions: Vec<PositionLiquidity>,
+}
+
+/// 对当前持仓模拟统一回撤。
+///
+/// 每个代币的流动性折损按当前的卖出价值与价值之比计算，并假设回撤后比例不变
+pub fn run(rows: &[HoldingRow], drawdowns: &[f64]) -> StressReport {
+    let value: f64 = rows.iter().map(|row| row.value).sum();
+    let exit_value: f64 = rows.iter().map(HoldingRow::exit_value).sum();
+    let cost: f64 = rows.iter().map(|row| row.cost).sum();
+
+    let scenarios = drawdowns
+        .iter()
+        .map(|drawdown| {
+            let remaining = 1.0 - drawdown / 100.0;
+            let stressed_exit = exit_value * remaining;
+            StressScenario {
+                drawdown: *drawdown,
+                value: value * remaining,
+                exit_value: stressed_exit,
+                pnl: stressed_exit - cost,
+            }
+        })
+        .collect();
+
+    let mut positions: Vec<PositionLiquidity> = rows
+        .iter()
+        .map(|row| PositionLiquidity {
+            mint: row.mint.clone(),
+            value: row.value,
+            exit_value: row.exit_value(),
+            price_impact: row.price_impact,
+        })
+        .collect();
+    positions.sort_by(|a, b| b.value.total_cmp(&a.value));
+
+    StressReport {
+        value,
+        exit_value,
+        cost,
+        scenarios,
+        positions,
+    }
+}
+
+impl StressReport {
+    /// Telegram 消息格式
+    pub fn to_message(&self) -> String {
+        let mut message = String::from("🧪 Portfolio Stress Test\n");
+        if self.positions.is_empty() {
+            message.push_str("\nNo open positions");
+            return message;
+        }
+
+        let _ = write!(
+            message,
+            "\nValue: {} SOL | Exit: {} SOL | Cost: {} SOL\n",
+            format_f64(self.value),
+            format_f64(self.exit_value),
+            format_f64(self.cost)
+        );
+        for scenario in &self.scenarios {
+            let _ = write!(
+                message,
+                "\n-{}%: value {} SOL, exit {} SOL, PnL {} SOL",
+                scenario.drawdown,
+                format_f64(scenario.value),
+                format_f64(scenario.exit_value),
+                format_signed_sol(scenario.pnl)
+            );
+        }
+
+        message.push_str("\n\nLiquidity impact per position:");
+        for position in &self.positions {
+            let impact = position
+                .price_impact
+                .map_or_else(|| "n/a".to_string(), |impact| format!("-{:.1}%", impact * 100.0));
+            let _ = write!(
+                message,
+                "\n<a href=\"https://gmgn.ai/sol/token/{}\">{}</a>: {} SOL → {} SOL ({})",
+                position.mint,
+                truncate_address(&position.mint, 16),
+                format_f64(position.value),
+                format_f64(position.exit_value),
+                impact
+            );
+        }
+        message
+    }
+}
@@ -0,0 +1,55 @@
+use crate::wallet_monitor::WalletMonitor;
+use chrono::Utc;
+use futures_util::future::join_all;
+use log::{info, warn};
+use sd_notify::NotifyState;
+use std::time::Duration;
+use tokio::time::interval;
+
+/// systemd 设置的看门狗超时（`WatchdogSec`），没有配置或不在 systemd 下运行时为 None
+fn watchdog_timeout() -> Option<Duration> {
+    let mut usec = 0;
+    sd_notify::watchdog_enabled(false, &mut usec).then(|| Duration::from_micros(usec))
+}
+
+/// 没有 `NOTIFY_SOCKET`（不在 systemd 下运行）时什么也不做
+fn notify(state: &[NotifyState]) {
+    if let Err(e) = sd_notify::notify(false, state) {
+        warn!("Failed to notify systemd: {}", e);
+    }
+}
+
+/// 所有 profile 的日志订阅都确认后通知 systemd 启动完成（`Type=notify`），
+/// 之后每隔半个看门狗超时检查各 profile 的主循环，全部在运行时才喂看门狗，卡住时由 systemd 重启
+pub async fn run(monitors: Vec<WalletMonitor>) {
+    join_all(monitors.iter().map(|monitor| monitor.wait_subscribed())).await;
+    notify(&[
+        NotifyState::Ready,
+        NotifyState::Status(&format!("Monitoring {} profile(s)", monitors.len())),
+    ]);
+
+    let Some(timeout) = watchdog_timeout() else {
+        return;
+    };
+    info!("Feeding the systemd watchdog every {}ms", (timeout / 2).as_millis());
+    let mut interval = interval(timeout / 2);
+    loop {
+        interval.tick().await;
+        let now = Utc::now().timestamp_millis();
+        let stalled: Vec<&str> = monitors
+            .iter()
+            .filter(|monitor| now - monitor.alive_at() > timeout.as_millis() as i64)
+            .map(|monitor| monitor.profile())
+            .collect();
+        if stalled.is_empty() {
+            notify(&[NotifyState::Watchdog]);
+        } else {
+            warn!("Main loop of {} stalled, not feeding the systemd watchdog", stalled.join(", "));
+        }
+    }
+}
+
+/// 开始关闭，systemd 不再把之后的退出当作异常
+pub fn notify_stopping() {
+    notify(&[NotifyState::Stopping]);
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+pub const UNTAGGED: &str = "untagged";
+
+/// 配置文件中的标签规则：代币名称或符号包含任一关键词（不区分大小写）时打上标签
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TagRule {
+    pub tag: String,
+    pub keywords: Vec<String>,
+}
+
+impl TagRule {
+    fn matches(&self, name: &str, symbol: &str) -> bool {
+        let (name, symbol) = (name.to_lowercase(), symbol.to_lowercase());
+        self.keywords.iter().any(|keyword| {
+            let keyword = keyword.to_lowercase();
+            name.contains(&keyword) || symbol.contains(&keyword)
+        })
+    }
+}
+
+/// 按规则匹配代币名称和符号得到的标签
+pub fn match_tags(rules: &[TagRule], name: &str, symbol: &str) -> BTreeSet<String> {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(name, symbol))
+        .map(|rule| rule.tag.clone())
+        .collect()
+}
+
+/// 各标签的已实现盈亏，卖出时按持仓当时的标签记账
+#[derive(Debug, Default)]
+pub struct TagLedger {
+    realized: HashMap<String, (f64, u32)>,  // 标签 -> (已实现盈亏, 已清仓数)
+}
+
+impl TagLedger {
+    pub fn record_sell(&mut self, tags: &BTreeSet<String>, realized_pnl: f64, closed: bool) {
+        for tag in tags_or_untagged(tags) {
+            let entry = self.realized.entry(tag.to_string()).or_default();
+            entry.0 += realized_pnl;
+            entry.1 += u32::from(closed);
+        }
+    }
+}
+
+/// 一个标签下的敞口和盈亏。带多个标签的持仓计入每个标签
+#[derive(Debug, Default, Serialize)]
+pub struct TagSummary {
+    pub tag: String,
+    pub open_positions: usize,
+    pub exposure: f64,        // 未平仓持仓的当前价值（SOL）
+    pub unrealized_pnl: f64,
+    pub realized_pnl: f64,
+    pub closed_positions: u32,
+}
+
+/// 持仓的标签、当前价值和成本
+pub struct TaggedPosition<'a> {
+    pub tags: &'a BTreeSet<String>,
+    pub value: f64,
+    pub cost: f64,
+}
+
+/// 按标签汇总未平仓持仓和已实现盈亏，按总盈亏从高到低排序
+pub fn breakdown(positions: &[TaggedPosition], ledger: &TagLedger) -> Vec<TagSummary> {
+    let mut summaries: BTreeMap<String, TagSummary> = BTreeMap::new();
+    for position in positions {
+        for tag in tags_or_untagged(position.tags) {
+            let entry = summary_entry(&mut summaries, tag);
+            entry.open_positions += 1;
+            entry.exposure += position.value;
+            entry.unrealized_pnl += position.value - position.cost;
+        }
+    }
+    for (tag, (realized_pnl, closed_positions)) in &ledger.realized {
+        let entry = summary_entry(&mut summaries, tag);
+        entry.realized_pnl += realized_pnl;
+        entry.closed_positions += closed_positions;
+    }
+
+    let mut summaries: Vec<TagSummary> = summaries.into_values().collect();
+    summaries.sort_by(|a, b| (b.realized_pnl + b.unrealized_pnl).total_cmp(&(a.realized_pnl + a.unrealized_pnl)));
+    summaries
+}
+
+fn summary_entry<'a>(summaries: &'a mut BTreeMap<String, TagSummary>, tag: &str) -> &'a mut TagSummary {
+    summaries.entry(tag.to_string()).or_insert_with(|| TagSummary {
+        tag: tag.to_string(),
+        ..TagSummary::default()
+    })
+}
+
+fn tags_or_untagged(tags: &BTreeSet<String>) -> Vec<&str> {
+    if tags.is_empty() {
+        vec![UNTAGGED]
+    } else {
+        tags.iter().map(String::as_str).collect()
+    }
+}
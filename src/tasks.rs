@@ -0,0 +1,54 @@
+use log::{debug, error, warn};
+use std::{future::Future, time::Duration};
+use tokio::{task::JoinSet, time::timeout};
+use tokio_util::sync::CancellationToken;
+
+/// 后台任务集合，关闭时等待所有任务结束，不会遗留在后台运行的任务
+#[derive(Default)]
+pub struct TaskSet {
+    tasks: JoinSet<()>,
+}
+
+impl TaskSet {
+    /// 启动自行处理取消的任务，例如需要优雅关闭的服务
+    pub fn spawn<F>(&mut self, name: &'static str, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(async move {
+            task.await;
+            debug!("Task {} finished", name);
+        });
+    }
+
+    /// 启动任务，令牌取消后任务在下一个等待点停止
+    pub fn spawn_cancellable<F>(&mut self, name: &'static str, token: CancellationToken, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(async move {
+            tokio::select! {
+                _ = token.cancelled() => debug!("Task {} cancelled", name),
+                _ = task => debug!("Task {} finished", name),
+            }
+        });
+    }
+
+    /// 等待所有任务结束，超过 `grace` 仍未结束的任务被中止
+    pub async fn join(mut self, grace: Duration) {
+        let drained = timeout(grace, async {
+            while let Some(result) = self.tasks.join_next().await {
+                if let Err(e) = result {
+                    if e.is_panic() {
+                        error!("Background task panicked: {}", e);
+                    }
+                }
+            }
+        })
+        .await;
+        if drained.is_err() {
+            warn!("{} background task(s) did not stop within {}s, aborting", self.tasks.len(), grace.as_secs());
+            self.tasks.shutdown().await;
+        }
+    }
+}
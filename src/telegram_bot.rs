@@ -0,0 +1,375 @@
+use crate::{
+    activity::{self, to_sol},
+    annotations::{TradeAnnotations, TAG_PREFIX},
+    config::ProfileConfig,
+    confirm::TradeConfirmations,
+    display::{self, SortKey},
+    enrichment::escape_html,
+    escalation::{Escalations, ACK_PREFIX},
+    logging,
+    wallet_monitor::{format_f64, truncate_address, WalletMonitor},
+};
+use chrono::{Local, TimeZone, Utc};
+use log::{info, warn};
+use std::{fmt::Write as _, str::FromStr, sync::Arc, time::Duration};
+use teloxide::{
+    prelude::*,
+    types::{AllowedUpdate, ChatId, ParseMode, UpdateKind},
+    utils::command::BotCommands,
+};
+use tokio::time::sleep;
+
+const POLL_TIMEOUT_SECS: u32 = 10;  // getUpdates 长轮询的时长，也是检查超时确认的间隔
+const POLL_RETRY: Duration = Duration::from_secs(5);
+const MAX_REPLY: usize = 4000;  // Telegram 消息上限 4096 字符，留出截断提示的位置
+const DEFAULT_RECENT: usize = 10;
+const MAX_RECENT: usize = 50;
+
+#[derive(BotCommands, Clone, Debug)]
+#[command(rename_rule = "lowercase", description = "Monitor commands:")]
+enum Command {
+    #[command(description = "list the commands")]
+    Help,
+    #[command(description = "open positions with their price change")]
+    Holdings,
+    #[command(description = "unrealized and realized PnL")]
+    Pnl,
+    #[command(description = "monitor status")]
+    Status,
+    #[command(description = "mute alerts for a token: /mute <mint>")]
+    Mute(String),
+    #[command(description = "unmute a token: /unmute <mint>")]
+    Unmute(String),
+    #[command(description = "attach a note to a position: /note <mint> [text]")]
+    Note(String),
+    #[command(description = "recent trades: /recent [count]")]
+    Recent(String),
+    #[command(description = "tokens watched after signals from other monitors")]
+    Watchlist,
+    #[command(description = "portfolio stress test")]
+    Stress,
+    #[command(description = "sort the holdings table: /sort <value|pnl|recency>")]
+    Sort(String),
+    #[command(description = "show or change the log filter: /loglevel [directives]")]
+    Loglevel(String),
+    #[command(description = "acknowledge a critical alert: /ack [id]")]
+    Ack(String),
+}
+
+impl Command {
+    /// 修改设置的命令，只接受 `TELEGRAM_ADMINS` 中的用户
+    fn changes_settings(&self) -> bool {
+        match self {
+            Command::Mute(_) | Command::Unmute(_) | Command::Note(_) | Command::Sort(_) => true,
+            // 不带参数时只显示当前的过滤规则
+            Command::Loglevel(directives) => !directives.trim().is_empty(),
+            _ => false,
+        }
+    }
+}
+
+/// Telegram 的命令（`TELEGRAM_COMMANDS`）、交易确认、交易标注和 critical 提醒的确认按钮。同一个 bot 只能有一个 getUpdates 轮询，
+/// 所以它们共用这里的轮询；只接受配置的聊天中的消息和按钮
+pub struct TelegramBot {
+    profile: String,
+    bot: Bot,
+    chat_id: i64,
+    commands: bool,
+    admins: Vec<u64>,  // 可以使用修改设置的命令的用户
+    confirmations: Option<Arc<TradeConfirmations>>,
+    escalations: Option<Arc<Escalations>>,
+    annotations: Option<Arc<TradeAnnotations>>,
+}
+
+impl TelegramBot {
+    pub fn new(
+        profile: &ProfileConfig,
+        confirmations: Option<Arc<TradeConfirmations>>,
+        escalations: Option<Arc<Escalations>>,
+        annotations: Option<Arc<TradeAnnotations>>,
+    ) -> Self {
+        Self {
+            profile: profile.name.clone(),
+            bot: Bot::new(&profile.telegram_bot_token),
+            chat_id: profile.telegram_chat_id,
+            commands: profile.telegram_commands,
+            admins: profile.telegram_admins.clone(),
+            confirmations,
+            escalations,
+            annotations,
+        }
+    }
+
+    pub async fn run(self: Arc<Self>, monitor: WalletMonitor) {
+        let mut allowed = Vec::new();
+        if self.commands || self.escalations.is_some() {
+            allowed.push(AllowedUpdate::Message);
+            if let Err(e) = self.bot.set_my_commands(Command::bot_commands()).await {
+                warn!("[{}] Failed to register Telegram commands: {}", self.profile, e);
+            }
+        }
+        if self.confirmations.is_some() || self.escalations.is_some() || self.annotations.is_some() {
+            allowed.push(AllowedUpdate::CallbackQuery);
+        }
+        // 命令解析需要 bot 的用户名（群组中的 /status@bot）
+        let username = loop {
+            match self.bot.get_me().await {
+                Ok(me) => break me.username.clone().unwrap_or_default(),
+                Err(e) => {
+                    warn!("[{}] Failed to reach Telegram, retrying: {}", self.profile, e);
+                    sleep(POLL_RETRY).await;
+                }
+            }
+        };
+        info!("[{}] Listening for Telegram updates as @{}", self.profile, username);
+
+        let mut offset = 0;
+        loop {
+            let updates = self
+                .bot
+                .get_updates()
+                .offset(offset)
+                .timeout(POLL_TIMEOUT_SECS)
+                .allowed_updates(allowed.clone())
+                .await;
+            match updates {
+                Ok(updates) => {
+                    for update in updates {
+                        offset = update.id + 1;
+                        match update.kind {
+                            UpdateKind::Message(message) if message.chat.id.0 == self.chat_id => {
+                                let Some(command) = message.text().and_then(|text| Command::parse(text, &username).ok()) else {
+                                    continue;
+                                };
+                                // 只开启了升级时只接受 /ack
+                                if !self.commands && !matches!(command, Command::Ack(_)) {
+                                    continue;
+                                }
+                                info!("[{}] Telegram command {:?}", self.profile, command);
+                                let admin = message.from().is_some_and(|user| self.admins.contains(&user.id.0));
+                                if command.changes_settings() && !admin {
+                                    warn!("[{}] Telegram command {:?} refused, the sender is not in TELEGRAM_ADMINS", self.profile, command);
+                                    self.reply(&message, "Only TELEGRAM_ADMINS can change settings").await;
+                                    continue;
+                                }
+                                let reply = execute(&self, &monitor, &message, command).await;
+                                self.reply(&message, &reply).await;
+                            }
+                            UpdateKind::CallbackQuery(query) => {
+                                let data = query.data.as_deref().unwrap_or_default();
+                                let (is_ack, is_tag) = (data.starts_with(ACK_PREFIX), data.starts_with(TAG_PREFIX));
+                                match (&self.escalations, &self.annotations, &self.confirmations) {
+                                    (Some(escalations), _, _) if is_ack => escalations.answer(query).await,
+                                    (_, Some(annotations), _) if is_tag => annotations.answer(query).await,
+                                    (_, _, Some(confirmations)) if !is_ack && !is_tag => confirmations.answer(query).await,
+                                    _ => {}
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("[{}] Failed to poll Telegram updates: {}", self.profile, e);
+                    sleep(POLL_RETRY).await;
+                }
+            }
+            if let Some(confirmations) = &self.confirmations {
+                confirmations.expire().await;
+            }
+            if let Some(escalations) = &self.escalations {
+                escalations.repeat_due().await;
+            }
+        }
+    }
+
+    /// `/ack <id>` 确认 critical 提醒，不带 ID 时列出等待确认的提醒
+    async fn acknowledge(&self, message: &Message, id: &str) -> String {
+        let Some(escalations) = &self.escalations else {
+            return "Escalation is not configured".to_string();
+        };
+        if id.is_empty() {
+            let pending = escalations.unacknowledged();
+            if pending.is_empty() {
+                return "No alerts waiting for acknowledgement".to_string();
+            }
+            let mut reply = format!("🚨 {} alert(s) waiting for acknowledgement", pending.len());
+            for alert in pending {
+                let _ = write!(
+                    reply,
+                    "\n<code>{}</code> {} · sent {} ago, repeated {}x",
+                    alert.id,
+                    alert.title,
+                    activity::format_duration(Utc::now().timestamp() - alert.sent_at),
+                    alert.repeats
+                );
+            }
+            return reply;
+        }
+        let Ok(id) = id.parse::<u64>() else {
+            return "Usage: /ack [id]".to_string();
+        };
+        let user = message
+            .from()
+            .map(|user| user.username.clone().unwrap_or_else(|| user.first_name.clone()))
+            .unwrap_or_default();
+        if escalations.acknowledge(id, &user).await {
+            format!("✅ Alert {} acknowledged", id)
+        } else {
+            format!("Alert {} is not waiting for acknowledgement", id)
+        }
+    }
+
+    /// 在命令所在的聊天和话题中回复，过长时按行截断
+    async fn reply(&self, message: &Message, text: &str) {
+        let mut request = self
+            .bot
+            .send_message(ChatId(self.chat_id), truncate_reply(text))
+            .parse_mode(ParseMode::Html);
+        if let Some(thread_id) = message.thread_id {
+            request = request.message_thread_id(thread_id);
+        }
+        if let Err(e) = request.await {
+            warn!("[{}] Failed to reply to Telegram command: {}", self.profile, e);
+        }
+    }
+}
+
+async fn execute(bot: &TelegramBot, monitor: &WalletMonitor, message: &Message, command: Command) -> String {
+    match command {
+        Command::Mute(_) | Command::Unmute(_) | Command::Note(_) if monitor.is_replica() => {
+            "This is a read-only replica, make changes on the monitoring instance".to_string()
+        }
+        Command::Help => escape_html(&Command::descriptions().to_string()),
+        Command::Holdings => monitor.holdings_report().await,
+        Command::Pnl => monitor.pnl_report().await,
+        Command::Status => monitor.status_report().await,
+        Command::Mute(mint) => match mint.trim() {
+            "" => "Usage: /mute &lt;mint&gt;".to_string(),
+            mint => {
+                monitor.mute_mint(mint).await;
+                format!("🔇 Muted alerts for <code>{}</code>", escape_html(mint))
+            }
+        },
+        Command::Unmute(mint) => match monitor.unmute_mint(mint.trim()).await {
+            Ok(()) => format!("🔔 Unmuted alerts for <code>{}</code>", escape_html(mint.trim())),
+            Err(e) => escape_html(&e.to_string()),
+        },
+        Command::Note(args) => {
+            let args = args.trim();
+            let (mint, text) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+            if mint.is_empty() {
+                return "Usage: /note &lt;mint&gt; [text]".to_string();
+            }
+            // 备注可以用引号括起来
+            let text = text.trim().trim_matches(|c| matches!(c, '"' | '“' | '”')).trim();
+            match monitor.set_note(mint, Some(text.to_string())).await {
+                Ok(()) if text.is_empty() => format!("📝 Note of <code>{}</code> cleared", escape_html(mint)),
+                Ok(()) => format!("📝 Note of <code>{}</code>: {}", escape_html(mint), escape_html(text)),
+                Err(e) => escape_html(&e.to_string()),
+            }
+        }
+        Command::Recent(count) => {
+            let limit = match count.trim() {
+                "" => DEFAULT_RECENT,
+                count => match count.parse::<usize>() {
+                    Ok(limit) => limit.clamp(1, MAX_RECENT),
+                    Err(_) => return "Usage: /recent [count]".to_string(),
+                },
+            };
+            recent_trades(monitor, limit).await
+        }
+        Command::Watchlist => monitor.watchlist_report().await,
+        Command::Stress => monitor.stress_report().await.to_message(),
+        Command::Sort(key) => match SortKey::from_str(key.trim()) {
+            Ok(sort) => {
+                display::set_sort(sort);
+                info!("Display sort changed to {:?}", sort);
+                format!("Holdings table sorted by {}", key.trim())
+            }
+            Err(e) => escape_html(&e.to_string()),
+        },
+        Command::Loglevel(directives) => {
+            let directives = directives.trim();
+            if directives.is_empty() {
+                return format!("Log filter: <code>{}</code>", escape_html(&logging::directives()));
+            }
+            match logging::set_directives(directives) {
+                Ok(()) => {
+                    info!("Log directives changed to {}", logging::directives());
+                    format!("Log filter changed to <code>{}</code>", escape_html(&logging::directives()))
+                }
+                Err(e) => escape_html(&e.to_string()),
+            }
+        }
+        Command::Ack(id) => bot.acknowledge(message, id.trim()).await,
+    }
+}
+
+async fn recent_trades(monitor: &WalletMonitor, limit: usize) -> String {
+    let trades = monitor.recent_trades(limit).await;
+    if trades.is_empty() {
+        return "No trades yet".to_string();
+    }
+    let mut message = format!("🕒 Last {} trade(s)", trades.len());
+    for trade in trades {
+        let _ = write!(
+            message,
+            "\n{} {} <code>{}</code> {} SOL @ {} by {}{}",
+            Local
+                .timestamp_opt(trade.timestamp, 0)
+                .single()
+                .map(|time| time.format("%H:%M:%S").to_string())
+                .unwrap_or_default(),
+            if trade.is_buy { "🟢 Buy" } else { "🔴 Sell" },
+            truncate_address(&trade.mint, 16),
+            format_f64(to_sol(trade.sol_amount)),
+            format_f64(trade.price),
+            escape_html(&monitor.address_book().short(&trade.user, 12)),
+            if trade.is_target { " 🎯" } else { "" }
+        );
+    }
+    message
+}
+
+fn truncate_reply(text: &str) -> String {
+    if text.len() <= MAX_REPLY {
+        return text.to_string();
+    }
+    // 在行尾截断，不拆开行内的 HTML 标签
+    let line_end = text
+        .match_indices('\n')
+        .map(|(index, _)| index)
+        .take_while(|index| *index <= MAX_REPLY)
+        .last()
+        .filter(|index| *index > 0);
+    if let Some(end) = line_end {
+        return format!("{}\n… (truncated)", &text[..end]);
+    }
+    // 第一行就超长时在字符边界截断，避开截断处的标签和实体，并补上没有闭合的标签
+    let mut end = MAX_REPLY;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let head = &text[..end];
+    let end = match (head.rfind('<'), head.rfind('&')) {
+        (Some(open), _) if !head[open..].contains('>') => open,
+        (_, Some(amp)) if !head[amp..].contains(';') => amp,
+        _ => end,
+    };
+    let head = &text[..end];
+    let mut open_tags = Vec::new();
+    for (index, _) in head.match_indices('<') {
+        let tag = &head[index + 1..];
+        let name: String = tag.trim_start_matches('/').chars().take_while(char::is_ascii_alphanumeric).collect();
+        if tag.starts_with('/') {
+            if let Some(position) = open_tags.iter().rposition(|open| *open == name) {
+                open_tags.truncate(position);
+            }
+        } else {
+            open_tags.push(name);
+        }
+    }
+    let closing: String = open_tags.iter().rev().map(|tag| format!("</{}>", tag)).collect();
+    format!("{}{}\n… (truncated)", head, closing)
+}
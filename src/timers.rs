@@ -0,0 +1,43 @@
+use std::collections::{BTreeSet, HashMap};
+
+/// 按截止时间排序的一次性定时器，每个 key 最多一个定时器
+#[derive(Debug, Default)]
+pub struct Timers {
+    queue: BTreeSet<(i64, String)>,
+    deadlines: HashMap<String, i64>,
+}
+
+impl Timers {
+    /// 设置或替换 key 的定时器
+    pub fn set(&mut self, key: &str, deadline: i64) {
+        self.cancel(key);
+        self.queue.insert((deadline, key.to_string()));
+        self.deadlines.insert(key.to_string(), deadline);
+    }
+
+    pub fn cancel(&mut self, key: &str) -> bool {
+        match self.deadlines.remove(key) {
+            Some(deadline) => self.queue.remove(&(deadline, key.to_string())),
+            None => false,
+        }
+    }
+
+    pub fn next_deadline(&self) -> Option<i64> {
+        self.queue.first().map(|(deadline, _)| *deadline)
+    }
+
+    /// 取出所有已到期的定时器
+    pub fn pop_due(&mut self, now: i64) -> Vec<String> {
+        let mut due = Vec::new();
+        while let Some((deadline, _)) = self.queue.first() {
+            if *deadline > now {
+                break;
+            }
+            if let Some((_, key)) = self.queue.pop_first() {
+                self.deadlines.remove(&key);
+                due.push(key);
+            }
+        }
+        due
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{activity::format_duration, recording::Frame};
+use chrono::{Local, TimeZone};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+/// 一个持仓：profile、代币、开仓时间，按钱包显示时加上钱包
+type PositionKey = (String, String, i64, Option<String>);
+
+/// 模拟的一次提醒，价格从提醒时开始跟踪到持仓消失或录制结束
+#[derive(Debug, Clone, Copy)]
+struct SimulatedAlert {
+    price: f64,  // 提醒时的价格
+    peak: f64,   // 提醒之后的最高价格
+    last: f64,   // 平仓前或录制结束时的价格
+}
+
+impl SimulatedAlert {
+    fn track(&mut self, price: f64) {
+        self.peak = self.peak.max(price);
+        self.last = price;
+    }
+
+    fn peak_change(&self) -> f64 {
+        (self.peak / self.price - 1.0) * 100.0
+    }
+
+    fn close_change(&self) -> f64 {
+        (self.last / self.price - 1.0) * 100.0
+    }
+}
+
+/// 一个阈值在录制中的模拟结果
+#[derive(Debug)]
+pub struct ThresholdResult {
+    pub threshold: f64,
+    alerts: Vec<SimulatedAlert>,
+}
+
+/// `monitor tune` 的报告
+#[derive(Debug)]
+pub struct TuneReport {
+    pub frames: usize,
+    pub positions: usize,
+    pub started_at: Option<i64>,
+    pub ended_at: Option<i64>,
+    pub results: Vec<ThresholdResult>,
+}
+
+/// 按 `DISPLAY_RECORD` 录制的持仓表模拟每个阈值：涨幅第一次超过阈值时提醒一次（每个持仓每个阈值一次，
+/// 与实时提醒相同），之后跟踪价格直到持仓从持仓表中消失。`profile` 为 None 时包含所有 profile
+pub fn simulate(frames: impl IntoIterator<Item = Frame>, profile: Option<&str>, thresholds: &[f64]) -> TuneReport {
+    let mut open: HashMap<PositionKey, Vec<Option<SimulatedAlert>>> = HashMap::new();
+    let mut closed: Vec<Vec<SimulatedAlert>> = vec![Vec::new(); thresholds.len()];
+    let mut positions = 0;
+    let mut report = TuneReport {
+        frames: 0,
+        positions: 0,
+        started_at: None,
+        ended_at: None,
+        results: Vec::new(),
+    };
+
+    for frame in frames {
+        report.frames += 1;
+        report.started_at.get_or_insert(frame.timestamp);
+        report.ended_at = Some(frame.timestamp);
+
+        let mut seen = HashSet::new();
+        for profile_frame in frame.profiles.iter().filter(|frame| profile.is_none_or(|name| frame.profile == name)) {
+            for row in &profile_frame.rows {
+                // 按钱包显示时同一代币有多行，用钱包区分
+                let by_wallet = profile_frame.rows.iter().filter(|other| other.mint == row.mint).count() > 1;
+                let key = (
+                    profile_frame.profile.clone(),
+                    row.mint.clone(),
+                    row.opened_at,
+                    by_wallet.then(|| row.wallet.clone()).flatten(),
+                );
+                let alerts = open.entry(key.clone()).or_insert_with(|| {
+                    positions += 1;
+                    vec![None; thresholds.len()]
+                });
+                for (alert, threshold) in alerts.iter_mut().zip(thresholds) {
+                    match alert {
+                        Some(alert) => alert.track(row.price),
+                        None if row.avg_price > 0.0 && row.change as f64 > *threshold => {
+                            *alert = Some(SimulatedAlert {
+                                price: row.price,
+                                peak: row.price,
+                                last: row.price,
+                            })
+                        }
+                        None => {}
+                    }
+                }
+                seen.insert(key);
+            }
+        }
+
+        // 不在这一帧中的持仓已经平仓
+        let gone: Vec<PositionKey> = open.keys().filter(|key| !seen.contains(key)).cloned().collect();
+        for key in gone {
+            close(&mut closed, open.remove(&key).unwrap_or_default());
+        }
+    }
+    for (_, alerts) in open.drain() {
+        close(&mut closed, alerts);
+    }
+
+    report.positions = positions;
+    report.results = thresholds
+        .iter()
+        .zip(closed)
+        .map(|(threshold, alerts)| ThresholdResult {
+            threshold: *threshold,
+            alerts,
+        })
+        .collect();
+    report
+}
+
+fn close(closed: &mut [Vec<SimulatedAlert>], alerts: Vec<Option<SimulatedAlert>>) {
+    for (results, alert) in closed.iter_mut().zip(alerts) {
+        results.extend(alert);
+    }
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    values.sort_by(f64::total_cmp);
+    values.get(values.len() / 2).copied()
+}
+
+fn format_change(change: Option<f64>) -> String {
+    change.map_or_else(|| "-".to_string(), |change| format!("{:+.1}%", change))
+}
+
+impl TuneReport {
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+        let (Some(started_at), Some(ended_at)) = (self.started_at, self.ended_at) else {
+            let _ = writeln!(output, "The recording contains no frames");
+            return output;
+        };
+        let time = |timestamp: i64| {
+            Local
+                .timestamp_opt(timestamp, 0)
+                .single()
+                .map_or_else(|| timestamp.to_string(), |time| time.format("%Y-%m-%d %H:%M").to_string())
+        };
+        let span = ended_at - started_at;
+        let _ = writeln!(
+            output,
+            "Replayed {} frames from {} to {} ({}), {} positions",
+            self.frames,
+            time(started_at),
+            time(ended_at),
+            format_duration(span),
+            self.positions
+        );
+        let _ = writeln!(
+            output,
+            "\n{:>10} {:>7} {:>8} {:>12} {:>13} {:>20}",
+            "Threshold", "Alerts", "Per day", "Median peak", "Median close", "Closed above alert"
+        );
+        let days = (span as f64 / 86400.0).max(1.0 / 24.0);
+        for result in &self.results {
+            let alerts = result.alerts.len();
+            let above = result.alerts.iter().filter(|alert| alert.last >= alert.price).count();
+            let share = if alerts == 0 { 0.0 } else { above as f64 / alerts as f64 * 100.0 };
+            let _ = writeln!(
+                output,
+                "{:>10} {:>7} {:>8.1} {:>12} {:>13} {:>20}",
+                format!("+{}%", result.threshold),
+                alerts,
+                alerts as f64 / days,
+                format_change(median(result.alerts.iter().map(SimulatedAlert::peak_change).collect())),
+                format_change(median(result.alerts.iter().map(SimulatedAlert::close_change).collect())),
+                format!("{} ({:.0}%)", above, share)
+            );
+        }
+        let _ = writeln!(
+            output,
+            "\nPeak and close are measured from the price at the alert. Positions still open at the end of the recording close at their last price."
+        );
+        output
+    }
+}
@@ -0,0 +1,49 @@
+use crate::models::TradeEvent;
+use crate::{mints::{to_tokens, PUMP_TOKEN_SUPPLY}, wallet_monitor::SOL_DECIMALS};
+use anyhow::{anyhow, Result};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+// 合理价格区间（SOL/代币）。bonding curve 上的价格大约在 1e-8 到 1e-6 之间，这里留足余量
+const MIN_PLAUSIBLE_PRICE: f64 = 1e-11;
+const MAX_PLAUSIBLE_PRICE: f64 = 1e-3;
+const MAX_PLAUSIBLE_TRADE_SOL: f64 = 1_000.0; // 单笔交易的最大 SOL 数量
+
+/// 检查解码出的交易数值是否合理，拒绝会污染均价或触发错误提醒的异常数据
+pub fn validate_trade(trade: &TradeEvent, price: f64) -> Result<()> {
+    if trade.sol_amount == 0 || trade.token_amount == 0 {
+        return Err(anyhow!(
+            "Implausible trade amounts: sol_amount={}, token_amount={}",
+            trade.sol_amount, trade.token_amount
+        ));
+    }
+
+    let sol = trade.sol_amount as f64 / 10f64.powi(SOL_DECIMALS as i32);
+    if sol > MAX_PLAUSIBLE_TRADE_SOL {
+        return Err(anyhow!("Implausible trade size: {} SOL", sol));
+    }
+
+    let tokens = to_tokens(trade.token_amount, trade.decimals);
+    if tokens > PUMP_TOKEN_SUPPLY {
+        return Err(anyhow!("Token amount exceeds total supply: {}", tokens));
+    }
+
+    validate_price(price)?;
+
+    // 交易者应该是普通钱包（在 ed25519 曲线上）
+    let user = Pubkey::from_str(&trade.user)
+        .map_err(|e| anyhow!("Invalid user address {}: {}", trade.user, e))?;
+    if !user.is_on_curve() {
+        return Err(anyhow!("User {} is not on the ed25519 curve", trade.user));
+    }
+
+    Ok(())
+}
+
+/// 检查价格是否在合理区间内，用于没有交易者信息的价格来源（例如 Raydium 的交换日志）
+pub fn validate_price(price: f64) -> Result<()> {
+    if !price.is_finite() || !(MIN_PLAUSIBLE_PRICE..=MAX_PLAUSIBLE_PRICE).contains(&price) {
+        return Err(anyhow!("Implausible price: {} SOL", price));
+    }
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use crate::{
+    activity::to_sol,
+    journal::JournalEntry,
+    store::StateStore,
+};
+use log::{error, info, warn};
+use std::{collections::HashMap, sync::Arc};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+
+/// 一个目标钱包从开始监控以来的持仓和盈亏，按平均成本计算
+#[derive(Debug, Clone, Default)]
+pub struct WalletLedger {
+    pub trades: usize,
+    pub invested: f64,      // 买入花费的 SOL
+    pub realized_pnl: f64,  // 卖出部分按平均成本计算的盈亏
+    pub positions: HashMap<String, (u64, f64)>,  // 代币 -> (数量, 成本 SOL)
+}
+
+impl WalletLedger {
+    fn apply(&mut self, entry: &JournalEntry) {
+        self.trades += 1;
+        let sol = to_sol(entry.sol_amount);
+        let (amount, cost) = self.positions.entry(entry.mint.clone()).or_default();
+        if entry.is_buy {
+            self.invested += sol;
+            *amount += entry.token_amount;
+            *cost += sol;
+        } else if *amount > 0 && entry.token_amount > 0 {
+            // 只计算监控期间买入的部分，之前买入的数量没有成本
+            let sold = entry.token_amount.min(*amount);
+            let sold_cost = *cost * sold as f64 / *amount as f64;
+            self.realized_pnl += sol * sold as f64 / entry.token_amount as f64 - sold_cost;
+            *amount -= sold;
+            *cost -= sold_cost;
+        }
+        if *amount == 0 {
+            self.positions.remove(&entry.mint);
+        }
+    }
+}
+
+enum Command {
+    Trade(JournalEntry),
+    Finish(oneshot::Sender<WalletLedger>),
+}
+
+struct ActorHandle {
+    sender: mpsc::UnboundedSender<Command>,
+    task: JoinHandle<()>,
+    token: CancellationToken,
+    started_at: i64,
+}
+
+/// 每个目标钱包一个 actor，按顺序记录它的交易。
+///
+/// actor 在钱包第一次交易时启动；异常退出后从交易日志重建状态并重启。
+/// 移除钱包时先处理完邮箱中剩余的交易，再返回最终的持仓和盈亏
+pub struct WalletActors {
+    profile: String,
+    store: Arc<dyn StateStore>,
+    parent: CancellationToken,
+    actors: HashMap<String, ActorHandle>,
+}
+
+impl WalletActors {
+    pub fn new(profile: &str, store: Arc<dyn StateStore>, parent: CancellationToken) -> Self {
+        Self {
+            profile: profile.to_string(),
+            store,
+            parent,
+            actors: HashMap::new(),
+        }
+    }
+
+    /// 把目标钱包的交易交给它的 actor，交易应已写入交易日志
+    pub async fn record(&mut self, entry: JournalEntry) {
+        let failed = match self.actors.get(&entry.wallet) {
+            Some(actor) => actor.sender.send(Command::Trade(entry.clone())).is_err(),
+            None => {
+                let actor = self.spawn(&entry.wallet, WalletLedger::default(), entry.timestamp);
+                let _ = actor.sender.send(Command::Trade(entry));
+                return;
+            }
+        };
+        if failed {
+            // 交易已在日志中，重建状态时会包含它
+            warn!("[{}] Actor of wallet {} stopped unexpectedly, restarting", self.profile, entry.wallet);
+            self.restart(&entry.wallet).await;
+        }
+    }
+
+    /// 停止钱包的 actor，处理完剩余的交易后返回最终状态。钱包没有交易过时返回 None
+    pub async fn stop(&mut self, wallet: &str) -> Option<WalletLedger> {
+        let actor = self.actors.remove(wallet)?;
+        let (reply, ledger) = oneshot::channel();
+        let ledger = match actor.sender.send(Command::Finish(reply)) {
+            Ok(()) => ledger.await.ok(),
+            Err(_) => None,
+        };
+        actor.token.cancel();
+        let _ = actor.task.await;
+        match ledger {
+            Some(ledger) => Some(ledger),
+            None => {
+                warn!("[{}] Actor of wallet {} stopped unexpectedly, rebuilding its ledger from the journal", self.profile, wallet);
+                Some(self.replay(wallet, actor.started_at).await)
+            }
+        }
+    }
+
+    fn spawn(&mut self, wallet: &str, ledger: WalletLedger, started_at: i64) -> &ActorHandle {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let token = self.parent.child_token();
+        let task = tokio::spawn(run(ledger, receiver, token.clone()));
+        info!("[{}] Started actor for wallet {}", self.profile, wallet);
+        self.actors.insert(wallet.to_string(), ActorHandle {
+            sender,
+            task,
+            token,
+            started_at,
+        });
+        &self.actors[wallet]
+    }
+
+    async fn restart(&mut self, wallet: &str) {
+        let Some(actor) = self.actors.remove(wallet) else {
+            return;
+        };
+        actor.token.cancel();
+        let ledger = self.replay(wallet, actor.started_at).await;
+        self.spawn(wallet, ledger, actor.started_at);
+    }
+
+    /// 从交易日志重放钱包自 actor 启动以来的交易
+    async fn replay(&self, wallet: &str, since: i64) -> WalletLedger {
+        let mut ledger = WalletLedger::default();
+        match self.store.load_trades().await {
+            Ok(entries) => entries
+                .iter()
+                .filter(|entry| entry.wallet == wallet && entry.timestamp >= since)
+                .for_each(|entry| ledger.apply(entry)),
+            Err(e) => error!("[{}] Failed to rebuild ledger of wallet {}: {:?}", self.profile, wallet, e),
+        }
+        ledger
+    }
+}
+
+async fn run(mut ledger: WalletLedger, mut receiver: mpsc::UnboundedReceiver<Command>, token: CancellationToken) {
+    loop {
+        let command = tokio::select! {
+            _ = token.cancelled() => return,
+            command = receiver.recv() => command,
+        };
+        match command {
+            Some(Command::Trade(entry)) => ledger.apply(&entry),
+            Some(Command::Finish(reply)) => {
+                let _ = reply.send(ledger);
+                return;
+            }
+            None => return,
+        }
+    }
+}
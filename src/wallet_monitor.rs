@@ -1,34 +1,109 @@
 use anyhow::{Result, anyhow};
 use futures_util::{SinkExt, StreamExt};
-use log::{error, info, debug};
-use solana_sdk::{pubkey::Pubkey};
-use std::{env, str::FromStr, collections::{HashMap, HashSet}, time::Duration, fmt, fmt::Write};
-use tokio::{sync::{RwLock, Mutex}, time::interval};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use log::{error, info, debug, warn};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{str::FromStr, collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque}, time::Duration, fmt, fmt::Write, path::PathBuf};
+use tokio::{sync::{mpsc, watch, RwLock, RwLockReadGuard, RwLockWriteGuard, Mutex, Notify}, task::JoinSet, time::{interval, interval_at, sleep, Instant}};
+use tokio_util::sync::CancellationToken;
+use tokio_tungstenite::{connect_async, tungstenite::{self, protocol::Message}};
 use url::Url;
 use base64::{Engine as _, engine::general_purpose};
-use serde_json::Value;
-use bs58;
-use std::sync::Arc;
-use chrono::Local;
-use crate::{alert_service::AlertService, models::AlertType};
+use std::sync::{atomic::{AtomicI64, Ordering}, Arc, Mutex as StdMutex};
+use chrono::{Local, Utc};
+use serde::{Deserialize, Serialize};
+use crate::{
+    activity::{self, to_sol, ActivityEvent, ActivityTracker},
+    address_book::AddressBook,
+    amm::{self, AmmPools, PoolSubscriptions, RaydiumPool, PUMP_AMM_PROGRAM_ID, RAYDIUM_AMM_PROGRAM_ID},
+    alert_service::{AlertService, ChannelHealth},
+    audit::{AuditAction, AuditEvent, AuditLog, HoldingState},
+    backfill,
+    blacklist::{self, Blacklist},
+    checkpoint::{self, Checkpoint, Progress, MAX_CATCH_UP},
+    counterparties::CounterpartyTracker,
+    dedup::SeenEvents,
+    enrichment::{escape_html, EnrichmentPipeline, EnrichmentSources},
+    flow::SolFlow,
+    endpoints::{EndpointDiag, Endpoints, RpcEndpoint},
+    heartbeat::{self, HeartbeatConfig},
+    helius::{HeliusKeys, KeyUsage, Limit},
+    holders::{self, HolderTracker},
+    error::MonitorError,
+    impersonation::{self, KnownToken, KnownTokens},
+    journal::{JournalEntry, SandwichRecord, TradeJournal},
+    ledger::{Realized, TradeLedger},
+    mev::{Sandwich, SandwichDetector, SlotTrade},
+    migrations,
+    metadata::{self, MetadataResolver, TokenMetadata},
+    mints::{self, default_decimals, market_cap, to_tokens, MintDecimals, DEFAULT_TOKEN_DECIMALS},
+    wallet_actor::{WalletActors, WalletLedger},
+    backoff::Backoff,
+    chaos,
+    config::{self, ProfileConfig},
+    display::{self, HoldingRow},
+    exit_quotes::ExitQuoter,
+    federation::{self, InboundAction, InboundSource, WatchEntry, Watchlist},
+    models::{AlertType, HoldingSnapshot, Notification, NotificationValue, RecordedTrade, SignalProvenance, TradeEvent, TradeRef, TradeSignal},
+    metrics::Metrics,
+    oracle::{self, BaseCurrency, BondingCurve, OracleKind, PriceOracles},
+    outcomes::{self, AlertOutcome, OutcomeLog},
+    plugins::ScriptPlugin,
+    price_updates::{PriceUpdate, PriceUpdates},
+    pump_events::{self, PumpEvent},
+    quarantine::Quarantine,
+    reconcile::{self, Discrepancy, TOKEN_PROGRAM_IDS},
+    retention::{self, RetentionConfig},
+    recording::{ProfileFrame, Recorder},
+    replay::{self, MessageRecorder},
+    rules::{AlertRule, RuleContext},
+    scheduler::{Job, ScheduledJob, Scheduler},
+    shared_holdings::SharedHoldings,
+    stress::{self, StressReport},
+    store::StateStore,
+    telegram_bot::TelegramBot,
+    tags::{self, TagLedger, TagRule, TagSummary, TaggedPosition},
+    timers::Timers,
+    validation::{validate_price, validate_trade},
+    wallet_report,
+};
 
-const PUMP_PROGRAM_ID: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P"; // PUMP 程序
+pub(crate) const PUMP_PROGRAM_ID: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P"; // PUMP 程序
 const MIN_HOLDING_AMOUNT: u64 = 10000; // 最小持仓数量
-const SOL_DECIMALS: u32 = 9;  // SOL 的小数位数
-const TOKEN_DECIMALS: u32 = 6; // SPL 代币的小数位数（大多数是6位）
+pub(crate) const SOL_DECIMALS: u32 = 9;  // SOL 的小数位数
+const UNKNOWN_EVENT_ALERT_THRESHOLD: u64 = 20; // 未知事件出现多少次后发送警告
+
+/// Program data 的解码结果
+enum DecodedEvent {
+    Trade(TradeEvent),
+    Create(pump_events::CreateEvent),  // 新代币创建
+    Complete(pump_events::CompleteEvent),  // 代币毕业
+    Ignored(&'static str),  // 已知但不需要处理的事件
+    Unknown([u8; 8]),
+}
+
+const WORKER_QUEUE_CAPACITY: usize = 1024; // 每个 worker 的消息队列长度
+const INBOUND_QUEUE_CAPACITY: usize = 256; // 接收的信号等待处理的队列长度
+const LOGS_SUBSCRIPTION_ID: u64 = 1; // logsSubscribe 请求的 id
+const AMM_LOGS_SUBSCRIPTION_ID: u64 = 2; // PumpSwap 日志订阅的 id，代币账户和 Raydium 池订阅的 id 从它之后开始
+const DECODE_BUFFER_LEN: usize = 512; // Program data 解码缓冲区，足够容纳 TradeEvent 和常见的 CreateEvent
+const MAX_TRACKED_TOKENS: usize = 100_000; // 记录创建或首次出现时间的代币数，超过后淘汰最早记录的
+const MAX_FIRED_RULES: usize = 10_000; // 记录的已触发 (规则, 代币) 数，超过后淘汰最早触发的
+const KNOWN_TOKENS_REFRESH: Duration = Duration::from_secs(6 * 60 * 60); // 知名代币列表的刷新间隔
+const STATE_SAVE_INTERVAL: Duration = Duration::from_secs(1); // 两次保存持仓状态之间的最短间隔
+const STATE_SYNC_INTERVAL: Duration = Duration::from_secs(2); // 共享持仓时读取其他实例修改的间隔
+const SHARED_APPLY_ATTEMPTS: usize = 5; // 共享持仓时写入一次最多尝试的次数；计入交易失败这么多次后发出警告，继续重试
+const SHARED_APPLY_RETRY: Duration = Duration::from_millis(500); // 存储出错后第一次重试的间隔，之后每次加倍
+const SHARED_APPLY_RETRY_MAX: Duration = Duration::from_secs(30); // 重试间隔的上限
+const CATCH_UP_RETRY: Duration = Duration::from_secs(30); // 补处理有查询失败时第一次重试的间隔，之后每次加倍
+const CATCH_UP_RETRY_MAX: Duration = Duration::from_secs(10 * 60); // 补处理重试间隔的上限
+const OUTCOME_SAMPLE_INTERVAL: Duration = Duration::from_secs(10); // 检查提醒结果采样是否到期的间隔
+const OUTCOME_REPORT_DAYS: u64 = 7; // 提醒结果报告统计的天数
+const LEADERBOARD_DAYS: u64 = 7; // 钱包排行统计的天数
+const BALANCE_MISMATCH_GRACE: Duration = Duration::from_secs(15); // 余额通知可能早于交易日志到达，不一致持续这么久才提醒
 
 // ANSI 转义序列
-const CLEAR_SCREEN: &str = "\x1B[2J\x1B[1;1H";  // 清屏并移动光标到顶部
-const BOLD: &str = "\x1B[1m";
-const RESET: &str = "\x1B[0m";
-const GREEN: &str = "\x1B[32m";
-const RED: &str = "\x1B[31m";
-const YELLOW: &str = "\x1B[33m";
-const BLUE: &str = "\x1B[34m";
-const CYAN: &str = "\x1B[36m";
-
-fn format_f64(value: f64) -> String {
+
+pub(crate) fn format_f64(value: f64) -> String {
     if value < 0.000001 {
         format!("{:.9}", value)
     } else if value < 0.001 {
@@ -38,22 +113,20 @@ fn format_f64(value: f64) -> String {
     }
 }
 
-fn format_token_amount(raw_amount: u64) -> String {
+pub(crate) fn format_token_amount(raw_amount: u64, decimals: u8) -> String {
     // 将原始数量转换为实际数量（考虑小数位）
-    let actual_amount = (raw_amount as f64) / 10f64.powi(TOKEN_DECIMALS as i32);
+    let actual_amount = to_tokens(raw_amount, decimals);
     
     // 格式化数字，添加千位分隔符
     let amount_str = format!("{:.1}", actual_amount);
     let parts: Vec<&str> = amount_str.split('.').collect();
     
     let mut int_part = String::new();
-    let mut count = 0;
-    for c in parts[0].chars().rev() {
-        if count > 0 && count % 3 == 0 {
+    for (count, c) in parts[0].chars().rev().enumerate() {
+        if count > 0 && count.is_multiple_of(3) {
             int_part.insert(0, ',');
         }
         int_part.insert(0, c);
-        count += 1;
     }
     
     if parts.len() > 1 {
@@ -63,21 +136,38 @@ fn format_token_amount(raw_amount: u64) -> String {
     }
 }
 
-fn format_price_change(change: i32) -> String {
-    if change > 0 {
-        format!("{}+{}%{}", GREEN, change, RESET)
-    } else if change < 0 {
-        format!("{}{}%{}", RED, change, RESET)
+/// pump 代币按价格计算的市值，例如 "68.4 SOL"
+pub(crate) fn format_market_cap(price: f64) -> String {
+    format!("{:.1} SOL", market_cap(price))
+}
+
+pub(crate) fn format_signed_sol(value: f64) -> String {
+    if value < 0.0 {
+        format!("-{}", format_f64(-value))
+    } else {
+        format!("+{}", format_f64(value))
+    }
+}
+
+/// 提醒档位，例如 "50%, 100%, 200%"
+pub(crate) fn format_thresholds(thresholds: &[f64]) -> String {
+    thresholds.iter().map(|threshold| format!("{}%", threshold)).collect::<Vec<_>>().join(", ")
+}
+
+pub(crate) fn format_usd(value: f64) -> String {
+    if value < 0.0 {
+        format!("-${:.2}", -value)
     } else {
-        format!("{}%", change)
+        format!("+${:.2}", value)
     }
 }
 
-fn truncate_address(address: &str, length: usize) -> String {
-    if address.len() <= length {
+pub(crate) fn truncate_address(address: &str, length: usize) -> String {
+    if address.chars().count() <= length {
         address.to_string()
     } else {
-        format!("{}...", &address[..length - 3])
+        // 按字符截断，避免切在多字节字符中间
+        format!("{}...", address.chars().take(length - 3).collect::<String>())
     }
 }
 
@@ -90,7 +180,7 @@ fn format_number_with_commas(num: f64) -> String {
     let len = int_str.len();
     
     for (i, c) in int_str.chars().enumerate() {
-        if i > 0 && (len - i) % 3 == 0 {
+        if i > 0 && (len - i).is_multiple_of(3) {
             result.push(',');
         }
         result.push(c);
@@ -107,45 +197,311 @@ fn format_number_with_commas(num: f64) -> String {
     result
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 struct TokenHolding {
     amount: u64,
     mint: String,
     total_cost: f64,    // 总花费的 SOL
+    cost_usd: Option<f64>,  // 按每笔买入时的 SOL/USD 汇率计的成本，有买入缺少汇率时为 None
     current_price: f64,  // 当前价格
+    opened_at: i64,  // 建仓时间
+    hold_plan: Option<HoldPlan>,  // 计划持仓时间，超过后提醒
+    last_trade_at: i64,  // 目标钱包最近一次交易的时间
+    last_wallet: Option<String>,  // 最近一次交易的目标钱包
+    tags: BTreeSet<String>,  // 叙事标签，用于按标签统计盈亏
+    impersonates: Option<KnownToken>,  // 名称或符号与之相同的知名代币
+    wallets: BTreeMap<String, WalletPosition>,  // 各目标钱包在监控期间建立的仓位
+    #[serde(default)]
+    ledger: TradeLedger,  // 按先进先出计算成本和已实现盈亏的交易账本
+    #[serde(default = "default_decimals")]
+    decimals: u8,  // 代币的小数位数
+    #[serde(default)]
+    cost_unknown: bool,  // 启动时从链上余额同步的持仓，成本按同步时（或之后第一次看到）的价格估算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,  // 持仓的备注（买入理由、计划），附在之后的提醒和报告中
+}
+
+/// 单个目标钱包在某个代币上的仓位，按它自己的买入计算均价
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct WalletPosition {
+    amount: u64,
+    total_cost: f64,  // SOL
+}
+
+impl WalletPosition {
+    fn avg_price(&self, decimals: u8) -> f64 {
+        if self.amount == 0 {
+            return 0.0;
+        }
+        self.total_cost / to_tokens(self.amount, decimals)
+    }
+}
+
+/// 已发送价格提醒的 (钱包, 代币) 和已提醒的最高档位。没有钱包明细的持仓（例如对账时发现的）钱包为空字符串
+type AlertedPositions = HashMap<(String, String), f64>;
+
+/// 已触发的 `once_per_mint` 规则 (规则, 代币)，最多 `MAX_FIRED_RULES` 个，超出时淘汰最早触发的
+#[derive(Debug, Default)]
+struct FiredRules {
+    fired: HashSet<(String, String)>,
+    order: VecDeque<(String, String)>,  // 按触发的顺序
+}
+
+impl FiredRules {
+    fn contains(&self, key: &(String, String)) -> bool {
+        self.fired.contains(key)
+    }
+
+    fn insert(&mut self, key: (String, String)) {
+        if self.fired.insert(key.clone()) {
+            self.order.push_back(key);
+        }
+        while self.order.len() > MAX_FIRED_RULES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.fired.remove(&oldest);
+            }
+        }
+    }
+
+    /// 清仓后同一代币的规则可以再次触发
+    fn forget(&mut self, mint: &str) {
+        self.fired.retain(|(_, fired_mint)| fired_mint != mint);
+        self.order.retain(|(_, fired_mint)| fired_mint != mint);
+    }
+}
+
+/// 代币账户 -> (所有者, mint, 链上数量)
+type TokenAccounts = HashMap<String, (String, String, u64)>;
+
+/// 保存在数据目录中的持仓和提醒状态，重启时恢复，成本和已发送的提醒不会丢失
+#[derive(Serialize, Deserialize)]
+struct SavedState<H> {
+    saved_at: i64,
+    holdings: Vec<H>,
+    #[serde(default)]
+    alert_tiers: Vec<(String, String, f64)>,  // (钱包, 代币, 已提醒的最高档位)
+    #[serde(default)]
+    pending_outcomes: Vec<AlertOutcome>,  // 还在采样之后价格的提醒
+    #[serde(default)]
+    checkpoint: Option<Checkpoint>,  // 日志订阅处理到的位置，重启后从这里补处理目标钱包的交易
+    #[serde(default)]
+    held: Vec<(String, String)>,  // (钱包, 代币) 持有过的代币
+}
+
+/// 交易日志中 pump 程序和 PumpSwap 自己输出的 (程序, Program data)，按程序调用栈排除其他程序的输出
+fn pump_program_data(logs: &[String]) -> Vec<(usize, &str, &str)> {
+    let mut program_stack: Vec<&str> = Vec::new();
+    let mut data = Vec::new();
+    for (index, log_str) in logs.iter().enumerate() {
+        if let Some(rest) = log_str.strip_prefix("Program ") {
+            let mut parts = rest.split_whitespace();
+            if let (Some(program), Some(action)) = (parts.next(), parts.next()) {
+                if action == "invoke" {
+                    program_stack.push(program);
+                } else if action == "success" || action.starts_with("failed") {
+                    program_stack.pop();
+                }
+            }
+        }
+        if let Some(&program) = program_stack.last().filter(|program| [PUMP_PROGRAM_ID, PUMP_AMM_PROGRAM_ID].contains(program)) {
+            if let Some(data_str) = log_str.strip_prefix("Program data: ") {
+                data.push((index, program, data_str));
+            }
+        }
+    }
+    data
+}
+
+/// 目标钱包的一笔卖出，用于卖出和清仓提醒
+struct Sale {
+    trade: TradeEvent,
+    signature: String,
+    price: f64,
+    realized: Realized,  // 这笔卖出的已实现盈亏
+    held_secs: i64,  // 从建仓到卖出的时长
+    position_pnl: Option<f64>,  // 卖出后清仓时整个仓位的已实现盈亏
+    note: String,  // 持仓备注的提醒行，清仓后持仓已移除，所以随卖出一起传递
+}
+
+/// 代币清仓后清除它所有钱包的提醒状态
+fn forget_alerts(alerted: &mut AlertedPositions, mint: &str) {
+    alerted.retain(|(_, alerted_mint), _| alerted_mint != mint);
+}
+
+/// 单个持仓的计划持仓时间
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HoldPlan {
+    max_hold: Duration,
+    label: Option<String>,  // 例如 "scalp"
 }
 
 impl TokenHolding {
-    fn new(mint: String, amount: u64, price: f64) -> Self {
-        let actual_amount = (amount as f64) / 10f64.powi(TOKEN_DECIMALS as i32);
+    fn new(mint: String, amount: u64, price: f64, decimals: u8) -> Self {
+        let actual_amount = to_tokens(amount, decimals);
         Self {
             amount,
             mint,
             total_cost: actual_amount * price,
+            cost_usd: Some(0.0).filter(|_| amount == 0),
             current_price: price,
+            opened_at: Utc::now().timestamp(),
+            hold_plan: None,
+            last_trade_at: Utc::now().timestamp(),
+            last_wallet: None,
+            tags: BTreeSet::new(),
+            impersonates: None,
+            wallets: BTreeMap::new(),
+            ledger: TradeLedger::default(),
+            decimals,
+            cost_unknown: false,
+            note: None,
+        }
+    }
+
+    /// 按小数位数换算的持仓数量
+    fn tokens(&self) -> f64 {
+        to_tokens(self.amount, self.decimals)
+    }
+
+    /// 账本没有覆盖的数量（没有账本的旧状态）按当前成本补为最早的一批
+    fn sync_ledger(&mut self) {
+        let missing = self.amount.saturating_sub(self.ledger.amount());
+        if missing > 0 {
+            let cost = (self.total_cost - self.ledger.cost()).max(0.0);
+            let cost_usd = self.cost_usd.zip(self.ledger.cost_usd()).map(|(total, ledger)| (total - ledger).max(0.0));
+            self.ledger.open_with(missing, cost, cost_usd, self.opened_at);
         }
     }
 
+    fn buy(&mut self, amount: u64, price: f64, sol_usd: Option<f64>) {
+        self.sync_ledger();
+        self.ledger.buy(amount, self.decimals, price, sol_usd, Utc::now().timestamp());
+        self.amount = self.amount.saturating_add(amount);
+        self.total_cost = self.ledger.cost();
+        self.cost_usd = self.ledger.cost_usd();
+    }
+
+    /// 卖出按先进先出扣除最早买入的批次，剩余仓位的成本是剩余批次的成本
+    fn sell(&mut self, amount: u64, price: f64, sol_usd: Option<f64>) -> Realized {
+        self.sync_ledger();
+        let realized = self.ledger.sell(amount, self.decimals, price, sol_usd, Utc::now().timestamp());
+        self.amount = self.amount.saturating_sub(amount);
+        self.total_cost = self.ledger.cost();
+        self.cost_usd = self.ledger.cost_usd();
+        realized
+    }
+
+    /// 按比例调整数量和成本（对账修正、移除钱包），均价不变，不计入已实现盈亏
+    fn scale_to(&mut self, amount: u64) {
+        self.sync_ledger();
+        self.ledger.scale_to(amount);
+        self.amount = amount;
+        self.total_cost = self.ledger.cost();
+        self.cost_usd = self.ledger.cost_usd();
+    }
+
     fn avg_price(&self) -> f64 {
         if self.amount == 0 {
             0.0
         } else {
-            let actual_amount = (self.amount as f64) / 10f64.powi(TOKEN_DECIMALS as i32);
-            self.total_cost / actual_amount
+            self.total_cost / self.tokens()
         }
     }
 
     fn price_change_percentage(&self) -> i32 {
+        self.price_change_at(self.current_price)
+    }
+
+    /// 按给定价格计算相对买入均价的涨跌幅
+    fn price_change_at(&self, price: f64) -> i32 {
         if self.avg_price() == 0.0 {
             0
         } else {
-            ((self.current_price - self.avg_price()) / self.avg_price() * 100.0) as i32
+            ((price - self.avg_price()) / self.avg_price() * 100.0) as i32
         }
     }
 
     fn total_value(&self) -> f64 {
-        let actual_amount = (self.amount as f64) / 10f64.powi(TOKEN_DECIMALS as i32);
-        actual_amount * self.current_price
+        self.tokens() * self.current_price
+    }
+
+    fn state(&self) -> HoldingState {
+        HoldingState {
+            amount: self.amount,
+            total_cost: self.total_cost,
+            current_price: self.current_price,
+            total_cost_usd: self.cost_usd,
+        }
+    }
+
+    fn row(&self) -> HoldingRow {
+        HoldingRow {
+            mint: self.mint.clone(),
+            wallet: self.last_wallet.clone(),
+            amount: self.amount,
+            decimals: self.decimals,
+            avg_price: self.avg_price(),
+            price: self.current_price,
+            change: self.price_change_percentage(),
+            value: self.total_value(),
+            cost: self.total_cost,
+            opened_at: self.opened_at,
+            last_trade_at: self.last_trade_at,
+            exit_quote: None,
+            price_impact: None,
+            cost_usd: None,
+            realized: self.ledger.realized_pnl(),
+            symbol: None,
+            cost_unknown: self.cost_unknown,
+        }
+    }
+
+    /// 每个目标钱包一行，价格和时间取自整个持仓。没有钱包明细时与 `row` 相同
+    fn wallet_rows(&self) -> Vec<HoldingRow> {
+        if self.wallets.is_empty() {
+            return vec![self.row()];
+        }
+        self.wallets
+            .iter()
+            .enumerate()
+            .map(|(index, (wallet, position))| {
+                let avg_price = position.avg_price(self.decimals);
+                HoldingRow {
+                    wallet: Some(wallet.clone()),
+                    realized: if index == 0 { self.ledger.realized_pnl() } else { 0.0 },
+                    amount: position.amount,
+                    avg_price,
+                    change: if avg_price == 0.0 { 0 } else { ((self.current_price - avg_price) / avg_price * 100.0) as i32 },
+                    value: to_tokens(position.amount, self.decimals) * self.current_price,
+                    cost: position.total_cost,
+                    ..self.row()
+                }
+            })
+            .collect()
+    }
+
+    /// 提醒中列出持有该代币的目标钱包，没有钱包明细时为空
+    fn wallets_line(&self, book: &AddressBook) -> String {
+        if self.wallets.is_empty() {
+            return String::new();
+        }
+        let wallets: Vec<String> = self.wallets.keys().map(|wallet| book.display(wallet)).collect();
+        format!("\nWallets: {}", wallets.join(", "))
+    }
+
+    fn note_line(&self) -> String {
+        self.note.as_ref().map_or_else(String::new, |note| format!("\n📝 {}", escape_html(note)))
+    }
+
+    fn snapshot(&self) -> HoldingSnapshot {
+        HoldingSnapshot {
+            mint: self.mint.clone(),
+            amount: self.tokens(),
+            avg_price: self.avg_price(),
+            current_price: self.current_price,
+            total_cost: self.total_cost,
+            price_change: self.price_change_percentage(),
+        }
     }
 }
 
@@ -156,468 +512,3909 @@ impl fmt::Display for TokenHolding {
     }
 }
 
+/// 代币的创建时间，不知道时为首次出现的时间
+#[derive(Debug, Clone)]
+pub(crate) struct TokenSeen {
+    pub(crate) at: i64,
+    pub(crate) created: bool,  // true 表示来自 CreateEvent 或链上查询的创建时间，否则只是首次观察到交易
+    pub(crate) names: Option<(String, String)>,  // CreateEvent 中的 (名称, 符号)
+    looked_up: bool,  // 已经从链上查询过创建时间
+}
+
+/// 最近出现的代币的 `TokenSeen`，最多 `MAX_TRACKED_TOKENS` 个，超出时淘汰最早记录的
+#[derive(Debug, Default)]
+pub(crate) struct SeenTokens {
+    tokens: HashMap<String, TokenSeen>,
+    order: VecDeque<String>,  // 按记录的顺序
+}
+
+impl SeenTokens {
+    pub(crate) fn get(&self, mint: &str) -> Option<&TokenSeen> {
+        self.tokens.get(mint)
+    }
+
+    fn entry(&mut self, mint: &str, at: i64) -> &mut TokenSeen {
+        if !self.tokens.contains_key(mint) {
+            self.order.push_back(mint.to_string());
+            while self.order.len() > MAX_TRACKED_TOKENS {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.tokens.remove(&oldest);
+                }
+            }
+        }
+        self.tokens.entry(mint.to_string()).or_insert(TokenSeen { at, created: false, names: None, looked_up: false })
+    }
+
+    /// 观察到代币的交易，第一次出现时记录时间
+    fn observe(&mut self, mint: &str, at: i64) {
+        self.entry(mint, at);
+    }
+
+    /// 记录代币的创建时间，`names` 为 None 时保留已知的名称
+    fn create(&mut self, mint: &str, at: i64, names: Option<(String, String)>) {
+        let seen = self.entry(mint, at);
+        seen.at = at;
+        seen.created = true;
+        if names.is_some() {
+            seen.names = names;
+        }
+    }
+
+    /// 代币还不知道创建时间并且没有查询过时标记为已查询并返回 true
+    fn begin_lookup(&mut self, mint: &str) -> bool {
+        match self.tokens.get_mut(mint) {
+            Some(seen) if !seen.created && !seen.looked_up => {
+                seen.looked_up = true;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 一轮正在运行的补处理，结束或取消时把 `catching_up` 减一
+struct CatchUpGuard(Arc<watch::Sender<usize>>);
+
+impl CatchUpGuard {
+    fn start(running: &Arc<watch::Sender<usize>>) -> Self {
+        running.send_modify(|count| *count += 1);
+        Self(running.clone())
+    }
+}
+
+impl Drop for CatchUpGuard {
+    fn drop(&mut self) {
+        self.0.send_modify(|count| *count -= 1);
+    }
+}
+
+/// 从上次全部清仓以来的交易统计
+#[derive(Debug, Default)]
+struct TradingSession {
+    realized_pnl: f64,  // 已实现盈亏（SOL）
+    realized_pnl_usd: Option<f64>,  // 已实现盈亏（USD），缺少汇率的卖出不计入
+    closed_positions: u32,
+    exit_started: Option<i64>,  // 最后一次买入之后第一次清仓的时间
+}
+
+#[derive(Clone)]
 pub struct WalletMonitor {
-    target_wallet: Pubkey,
+    profile: String,
+    target_wallets: Arc<RwLock<HashSet<String>>>,  // 监控的钱包地址
     holdings: Arc<RwLock<HashMap<String, TokenHolding>>>,
-    alerted_mints: Arc<Mutex<HashSet<String>>>,  // 记录已发送通知的代币
-    alert_service: AlertService,
-    price_change_threshold: f64,
+    alerted: Arc<Mutex<AlertedPositions>>,  // 已发送价格提醒的仓位
+    held: Arc<Mutex<HashSet<(String, String)>>>,  // (钱包, 代币) 目标钱包持有过的代币，第一次买入时发送新仓位提醒
+    alert_service: Arc<AlertService>,
+    price_change_thresholds: Arc<RwLock<Vec<f64>>>,
+    script: Option<Arc<ScriptPlugin>>,  // 用户自定义规则脚本
+    rules: Arc<Vec<AlertRule>>,  // 配置文件中的声明式规则
+    fired_rules: Arc<Mutex<FiredRules>>,
+    token_first_seen: Arc<Mutex<SeenTokens>>,  // 代币创建或首次出现的时间
+    recent_trades: Arc<Mutex<VecDeque<RecordedTrade>>>,  // 最近处理的交易（环形缓冲区）
+    recent_trades_capacity: usize,
+    metrics: Arc<Metrics>,
+    quarantine: Arc<Quarantine>,  // 解码失败的原始数据
+    audit: Arc<AuditLog>,  // 持仓变动审计日志
+    journal: Arc<TradeJournal>,  // 三明治攻击记录
+    store: Arc<dyn StateStore>,  // 持仓状态和目标钱包的交易日志
+    shared: Option<Arc<SharedHoldings>>,  // 与其他实例共享的持仓，没有设置 `INSTANCE_ID` 时为 None
+    replica: bool,  // 只读副本，持仓只从共享存储读取
+    sandwiches: Option<Arc<Mutex<SandwichDetector>>>,  // 未开启 MEV 检测时为 None
+    blacklist: Arc<RwLock<Blacklist>>,
+    blacklist_urls: Arc<Vec<String>>,
+    blacklist_refresh: Duration,
+    known_tokens: Arc<RwLock<KnownTokens>>,
+    known_tokens_url: Option<Arc<String>>,
+    rpc: Option<RpcEndpoint>,
+    endpoints: Option<Arc<Endpoints>>,  // 配置的 RPC_URL 和 WS_URL，没有配置时使用 Helius key
+    endpoint_probe: Option<Duration>,
+    helius_keys: Option<Arc<HeliusKeys>>,
+    ws_reconnect_max: Duration,
+    ws_reconnect_attempts: Option<u32>,
+    commitment: CommitmentConfig,  // 订阅和补处理使用的确认级别
+    cancel: CancellationToken,  // 关闭监控时停止所有相关任务
+    subscribed: Arc<watch::Sender<bool>>,  // 日志订阅当前是否已确认
+    catching_up: Arc<watch::Sender<usize>>,  // 正在补处理错过交易的任务数，不为 0 时目标钱包的实时交易等待
+    resumed: Arc<watch::Sender<bool>>,  // 第一次连接之前错过的交易已经补处理完
+    alive_at: Arc<AtomicI64>,  // 主循环最近一次运行的时间（毫秒），systemd 看门狗据此判断是否卡住
+    progress: Arc<StdMutex<Progress>>,  // 日志订阅处理到的位置，与持仓一起保存
+    wallet_actors: Arc<Mutex<WalletActors>>,  // 每个目标钱包的交易记录
+    schedules: Vec<ScheduledJob>,  // 定时任务
+    data_dir: PathBuf,
+    retention: RetentionConfig,  // 清理任务使用的保留期
+    reconcile_tolerance: f64,  // 允许的偏差百分比
+    startup_sync: bool,  // 启动时把链上有余额但没有跟踪的 pump 代币加入持仓
+    backfill_txs: usize,  // 启动时回放每个钱包最近多少笔交易，0 表示不回放
+    track_token_accounts: bool,  // 是否订阅目标钱包的代币账户
+    ws_record: Option<Arc<MessageRecorder>>,  // 录制收到的 WebSocket 消息
+    token_accounts: Arc<Mutex<TokenAccounts>>,
+    balance_mismatches: Arc<Mutex<HashMap<String, bool>>>,  // 链上余额与持仓不一致的代币 -> 是否已经提醒
+    wallets_changed: Arc<Notify>,  // 目标钱包有增减，连接上需要订阅新钱包的代币账户
+    session: Arc<Mutex<TradingSession>>,
+    flat_alert_window: Option<Duration>,  // 在此时间内清空所有持仓时发送清仓提醒
+    activity: Arc<Mutex<ActivityTracker>>,  // 目标钱包的交易活跃度
+    max_hold: Option<Duration>,  // 默认的最长持仓时间
+    hold_timers: Arc<Mutex<Timers>>,  // 每个持仓的持仓时间提醒
+    hold_timers_changed: Arc<Notify>,
+    state_changed: Arc<Notify>,  // 持仓或提醒状态可能有变化，需要保存
+    pending_outcomes: Arc<Mutex<Vec<AlertOutcome>>>,  // 价格提醒之后 5m/30m/2h 的价格采样
+    outcome_log: Arc<OutcomeLog>,  // 采样完成的提醒结果
+    decode_workers: usize,  // 解码和处理消息的 worker 数量
+    seen_events: Arc<StdMutex<SeenEvents>>,  // 最近处理过的事件，跳过重复的通知
+    price_updates: Arc<Mutex<PriceUpdates>>,  // 按代币合并的待处理价格更新
+    price_updates_changed: Arc<Notify>,
+    unknown_events: Arc<Mutex<HashMap<[u8; 8], u64>>>,  // 未知事件标识符出现次数
+    oracles: PriceOracles,  // 终端显示和价格提醒使用的价格来源
+    exit_quoter: Option<Arc<ExitQuoter>>,  // 按当前流动性估算卖出价值
+    holder_tracker: Option<Arc<Mutex<HolderTracker>>>,  // 持仓代币的持有人数量采样
+    counterparty_tracker: Option<Arc<Mutex<CounterpartyTracker>>>,  // 持仓代币的买家和卖家
+    stress_drawdowns: Vec<f64>,  // 压力测试模拟的回撤百分比
+    base_currency: BaseCurrency,
+    tag_rules: Arc<Vec<TagRule>>,
+    tag_ledger: Arc<Mutex<TagLedger>>,  // 各标签的已实现盈亏
+    enrichment: Arc<EnrichmentPipeline>,  // 提醒投递前补充的信息
+    flow: Arc<SolFlow>,  // 持仓代币和目标钱包交易的 SOL 买卖流量
+    flow_window: Option<Duration>,
+    address_book: Arc<AddressBook>,  // 提醒和交易信号中的钱包名称
+    heartbeat: Option<HeartbeatConfig>,
+    inbound_sources: Vec<Arc<InboundSource>>,  // 订阅的其他监控的信号
+    watchlist: Arc<Mutex<Watchlist>>,  // 由接收的信号加入的代币
+    mint_decimals: Arc<MintDecimals>,  // 代币的小数位数
+    amm_pools: Arc<AmmPools>,  // 毕业代币的 AMM 池
+    pool_subscriptions: Arc<StdMutex<PoolSubscriptions>>,  // 当前连接中 Raydium 池的日志订阅
+    metadata: Arc<MetadataResolver>,  // 代币的名称、符号和图片
+    telegram_bot: Option<Arc<TelegramBot>>,  // 聊天命令、交易确认和提醒确认按钮，都没有开启时为 None
 }
 
 impl WalletMonitor {
-    pub fn new(alert_service: AlertService) -> Result<Self> {
-        let price_change_threshold: f64 = env::var("PRICE_CHANGE_THRESHOLD")
-            .unwrap_or_else(|_| "5.0".to_string())
-            .parse()
-            .unwrap_or(5.0);
-        
-        let wallet_address = env::var("MONITOR_WALLET")?;
-        
-        info!("Attempting to parse target wallet address: {}", wallet_address);
-        
-        let wallet_pubkey = match Pubkey::from_str(&wallet_address) {
-            Ok(pubkey) => {
-                info!("Successfully parsed target wallet address");
-                pubkey
-            },
-            Err(e) => {
-                error!("Failed to parse wallet address '{}': {}", wallet_address, e);
-                return Err(anyhow!("Invalid wallet address: {}", e));
+    /// 按配置构造，与 `WalletMonitorBuilder::from_config` 相同
+    pub async fn new(profile: &ProfileConfig, alert_service: AlertService) -> Result<Self, MonitorError> {
+        WalletMonitorBuilder::from_config(profile.clone()).alert_service(alert_service).build().await
+    }
+
+    async fn with_config(profile: &ProfileConfig, alert_service: AlertService) -> Result<Self, MonitorError> {
+        let mut target_wallets = HashSet::new();
+        for wallet_address in &profile.wallets {
+            info!("[{}] Attempting to parse target wallet address: {}", profile.name, wallet_address);
+
+            let wallet_pubkey = match Pubkey::from_str(wallet_address) {
+                Ok(pubkey) => {
+                    info!("[{}] Successfully parsed target wallet address", profile.name);
+                    pubkey
+                },
+                Err(e) => {
+                    error!("[{}] Failed to parse wallet address '{}': {}", profile.name, wallet_address, e);
+                    return Err(MonitorError::config(format!("Invalid wallet address: {}", e)));
+                }
+            };
+            target_wallets.insert(wallet_pubkey.to_string());
+        }
+
+        let script = match &profile.rules_script {
+            Some(path) => {
+                let plugin = ScriptPlugin::load(path).map_err(MonitorError::config)?;
+                info!("[{}] Loaded rules script {}", profile.name, path.display());
+                Some(Arc::new(plugin))
             }
+            None => None,
         };
-        
+        if !profile.rules.is_empty() {
+            info!("[{}] Loaded {} alert rule(s)", profile.name, profile.rules.len());
+        }
+
+        let ws_record = match &profile.ws_record {
+            Some(path) => {
+                info!("[{}] Recording WebSocket messages to {}", profile.name, path.display());
+                Some(Arc::new(MessageRecorder::open(path).await.map_err(MonitorError::config)?))
+            }
+            None => None,
+        };
+
+        let endpoints = (!profile.rpc_urls.is_empty() || !profile.ws_urls.is_empty())
+            .then(|| Arc::new(Endpoints::new(&profile.name, profile.rpc_urls.clone(), profile.ws_urls.clone())));
+        let rpc = match &endpoints {
+            Some(endpoints) if endpoints.has_rpc() => Some(RpcEndpoint::Probed(endpoints.clone())),
+            _ => profile.helius_keys.clone().map(RpcEndpoint::Helius),
+        };
+        let mint_decimals = Arc::new(MintDecimals::new(rpc.clone()));
+        let amm_pools = Arc::new(AmmPools::new(rpc.clone()));
+        let metadata = Arc::new(MetadataResolver::new(rpc.clone()));
+        let oracles = PriceOracles::new(
+            profile.display_oracle,
+            profile.alert_oracle,
+            rpc.as_ref(),
+            profile.oracle_cache_ttl,
+            profile.base_currency,
+        )
+        .map_err(MonitorError::config)?;
+        if profile.display_oracle != OracleKind::Stream || profile.alert_oracle != OracleKind::Stream {
+            info!(
+                "[{}] Price oracles: {} for display, {} for alerts",
+                profile.name, profile.display_oracle, profile.alert_oracle
+            );
+        }
+
+        let exit_quoter = match (profile.exit_quotes, rpc.as_ref()) {
+            (false, _) => None,
+            (true, Some(rpc)) => Some(Arc::new(ExitQuoter::new(rpc, profile.oracle_cache_ttl))),
+            (true, None) => return Err(MonitorError::config("EXIT_QUOTES requires RPC_URL or HELIUS_API_KEY")),
+        };
+        if profile.holder_alert.is_some() && rpc.is_none() {
+            return Err(MonitorError::config("HOLDER_SAMPLE_MINS requires RPC_URL or HELIUS_API_KEY"));
+        }
+        let holder_tracker = profile.holder_alert.map(|rule| Arc::new(Mutex::new(HolderTracker::new(rule))));
+        let counterparty_tracker = profile.counterparty_alert.map(|rule| Arc::new(Mutex::new(CounterpartyTracker::new(rule))));
+
+        let token_first_seen = Arc::new(Mutex::new(SeenTokens::default()));
+        let blacklist = Arc::new(RwLock::new(Blacklist::default()));
+        let known_tokens = Arc::new(RwLock::new(KnownTokens::default()));
+        // volume enricher 查询最近 5 分钟，价格提醒查询 FLOW_WINDOW_MINS
+        let flow_retention = profile.flow_window.unwrap_or_default().max(Duration::from_secs(5 * 60));
+        let flow = Arc::new(SolFlow::new(flow_retention));
+        let enrichment = match &profile.enrichment {
+            Some(config) => {
+                let sources = EnrichmentSources {
+                    token_first_seen: token_first_seen.clone(),
+                    blacklist: blacklist.clone(),
+                    known_tokens: known_tokens.clone(),
+                    flow: flow.clone(),
+                    rpc: rpc.clone(),
+                    metadata: metadata.clone(),
+                };
+                info!("[{}] Alert enrichment: {} enricher(s), {}ms budget", profile.name, config.enrichers.len(), config.budget_ms);
+                EnrichmentPipeline::new(config, &sources).map_err(MonitorError::config)?
+            }
+            None => EnrichmentPipeline::default(),
+        };
+
+        let cancel = CancellationToken::new();
+
+        let mut activity = ActivityTracker::new(
+            profile.inactivity_alert,
+            profile.burst_alert,
+            profile.size_anomaly_multiplier,
+        );
+        for wallet in &target_wallets {
+            activity.track_wallet(wallet, Utc::now().timestamp());
+        }
+
+        // 恢复上次保存的持仓和提醒状态，已经过了截止时间的持仓时间提醒不再重复
+        let store = alert_service.store();
+        info!("[{}] State store: {}", profile.name, store.describe());
+        let saved = match store.load_state().await.map_err(MonitorError::config)? {
+            Some(content) => Some(
+                serde_json::from_str::<SavedState<TokenHolding>>(&content)
+                    .map_err(|e| MonitorError::config(format!("Invalid saved state in {}: {}", store.describe(), e)))?,
+            ),
+            None => None,
+        };
+        let mut holdings = HashMap::new();
+        let mut alerted = HashMap::new();
+        let mut held = HashSet::new();
+        let mut hold_timers = Timers::default();
+        let mut pending_outcomes = Vec::new();
+        let mut progress = Progress::default();
+        if let Some(saved) = saved {
+            info!(
+                "[{}] Restored {} holding(s) saved at {}",
+                profile.name,
+                saved.holdings.len(),
+                chrono::DateTime::from_timestamp(saved.saved_at, 0).map_or_else(String::new, |at| at.to_rfc3339())
+            );
+            holdings.extend(saved.holdings.into_iter().map(|holding| (holding.mint.clone(), holding)));
+            alerted.extend(saved.alert_tiers.into_iter().map(|(wallet, mint, tier)| ((wallet, mint), tier)));
+            pending_outcomes = saved.pending_outcomes;
+            held.extend(saved.held);
+            match saved.checkpoint {
+                Some(checkpoint) if checkpoint.slot > 0 => {
+                    info!("[{}] Resuming after slot {}", profile.name, checkpoint.slot);
+                    progress.restore(checkpoint);
+                }
+                // 没有位置的状态只知道保存的时间，回放时跳过之前的交易，避免重复计入持仓
+                checkpoint => progress.restore(Checkpoint {
+                    time: checkpoint.map_or(saved.saved_at, |checkpoint| checkpoint.time),
+                    ..Checkpoint::default()
+                }),
+            }
+        }
+        // 共享状态时存储中的持仓行优先于本实例保存的状态，还没有行的持仓（例如刚从文件导入的）之后写入
+        let shared = match store.instance() {
+            Some(instance) => {
+                let rows = store.load_holdings(None).await.map_err(MonitorError::config)?;
+                for row in &rows {
+                    match row.content.as_deref().map(serde_json::from_str::<TokenHolding>) {
+                        Some(Ok(holding)) => {
+                            holdings.insert(row.mint.clone(), holding);
+                        }
+                        Some(Err(e)) => warn!("[{}] Invalid shared holding {}: {}", profile.name, row.mint, e),
+                        None => {
+                            holdings.remove(&row.mint);
+                        }
+                    }
+                }
+                info!("[{}] Sharing holdings with other instances as '{}', {} holding(s) in the store", profile.name, instance, holdings.len());
+                Some(Arc::new(SharedHoldings::new(store.clone(), &rows)))
+            }
+            None => None,
+        };
+        let now = Utc::now().timestamp();
+        for holding in holdings.values() {
+            if let Some(plan) = &holding.hold_plan {
+                let deadline = holding.opened_at + plan.max_hold.as_secs() as i64;
+                if deadline > now {
+                    hold_timers.set(&holding.mint, deadline);
+                }
+            }
+            mint_decimals.insert(&holding.mint, holding.decimals);
+            // 没有记录持有过的代币的旧状态至少包含当前的持仓
+            held.extend(holding.wallets.keys().map(|wallet| (wallet.clone(), holding.mint.clone())));
+        }
+
+        // 副本的 bot 只回答命令，提醒上的按钮由发送提醒的监控实例处理
+        let (confirmations, escalations, annotations) = match profile.replica {
+            true => (None, None, None),
+            false => (alert_service.trade_confirmations(), alert_service.escalations(), alert_service.trade_annotations()),
+        };
+        let telegram_bot = (profile.telegram_commands || confirmations.is_some() || escalations.is_some() || annotations.is_some()).then(|| {
+            Arc::new(TelegramBot::new(profile, confirmations, escalations, annotations))
+        });
+
         Ok(Self {
-            target_wallet: wallet_pubkey,
-            holdings: Arc::new(RwLock::new(HashMap::new())),
-            alerted_mints: Arc::new(Mutex::new(HashSet::new())),
-            alert_service,
-            price_change_threshold,
+            profile: profile.name.clone(),
+            target_wallets: Arc::new(RwLock::new(target_wallets)),
+            holdings: Arc::new(RwLock::new(holdings)),
+            alerted: Arc::new(Mutex::new(alerted)),
+            held: Arc::new(Mutex::new(held)),
+            alert_service: Arc::new(alert_service),
+            price_change_thresholds: Arc::new(RwLock::new(profile.price_change_thresholds.clone())),
+            script,
+            rules: Arc::new(profile.rules.clone()),
+            fired_rules: Arc::new(Mutex::new(FiredRules::default())),
+            token_first_seen,
+            recent_trades: Arc::new(Mutex::new(VecDeque::with_capacity(profile.recent_trades_capacity))),
+            recent_trades_capacity: profile.recent_trades_capacity,
+            metrics: Arc::new(Metrics::default()),
+            quarantine: Arc::new(Quarantine::new(&profile.data_dir)),
+            audit: Arc::new(AuditLog::new(&profile.name, &profile.data_dir)),
+            journal: Arc::new(TradeJournal::new(&profile.data_dir)),
+            store: store.clone(),
+            shared,
+            replica: profile.replica,
+            blacklist,
+            blacklist_urls: Arc::new(profile.blacklist_urls.clone()),
+            blacklist_refresh: profile.blacklist_refresh,
+            known_tokens,
+            known_tokens_url: profile.known_tokens_url.clone().map(Arc::new),
+            sandwiches: profile.mev_detection.then(|| Arc::new(Mutex::new(SandwichDetector::default()))),
+            rpc,
+            endpoints,
+            endpoint_probe: profile.endpoint_probe,
+            helius_keys: profile.helius_keys.clone(),
+            ws_reconnect_max: profile.ws_reconnect_max,
+            ws_reconnect_attempts: profile.ws_reconnect_attempts,
+            commitment: profile.commitment,
+            wallet_actors: Arc::new(Mutex::new(WalletActors::new(&profile.name, store, cancel.clone()))),
+            cancel,
+            subscribed: Arc::new(watch::channel(false).0),
+            catching_up: Arc::new(watch::channel(0).0),
+            resumed: Arc::new(watch::channel(false).0),
+            alive_at: Arc::new(AtomicI64::new(Utc::now().timestamp_millis())),
+            progress: Arc::new(StdMutex::new(progress)),
+            schedules: profile.schedules.clone(),
+            data_dir: profile.data_dir.clone(),
+            retention: profile.retention.clone(),
+            reconcile_tolerance: profile.reconcile_tolerance,
+            startup_sync: profile.startup_sync,
+            backfill_txs: profile.backfill_txs,
+            track_token_accounts: profile.track_token_accounts,
+            ws_record,
+            token_accounts: Arc::new(Mutex::new(HashMap::new())),
+            balance_mismatches: Arc::new(Mutex::new(HashMap::new())),
+            wallets_changed: Arc::new(Notify::new()),
+            session: Arc::new(Mutex::new(TradingSession::default())),
+            flat_alert_window: profile.flat_alert_window,
+            activity: Arc::new(Mutex::new(activity)),
+            max_hold: profile.max_hold,
+            hold_timers: Arc::new(Mutex::new(hold_timers)),
+            hold_timers_changed: Arc::new(Notify::new()),
+            state_changed: Arc::new(Notify::new()),
+            pending_outcomes: Arc::new(Mutex::new(pending_outcomes)),
+            outcome_log: Arc::new(OutcomeLog::new(&profile.data_dir)),
+            decode_workers: profile.decode_workers,
+            seen_events: Arc::new(StdMutex::new(SeenEvents::new(profile.dedup_capacity))),
+            price_updates: Arc::new(Mutex::new(PriceUpdates::default())),
+            price_updates_changed: Arc::new(Notify::new()),
+            unknown_events: Arc::new(Mutex::new(HashMap::new())),
+            oracles,
+            exit_quoter,
+            holder_tracker,
+            counterparty_tracker,
+            stress_drawdowns: profile.stress_drawdowns.clone(),
+            base_currency: profile.base_currency,
+            tag_rules: Arc::new(profile.tag_rules.clone()),
+            tag_ledger: Arc::new(Mutex::new(TagLedger::default())),
+            enrichment: Arc::new(enrichment),
+            flow,
+            flow_window: profile.flow_window,
+            address_book: Arc::new(AddressBook::new(&profile.wallet_labels)),
+            heartbeat: profile.heartbeat.clone(),
+            inbound_sources: profile.inbound_signals.iter().cloned().map(Arc::new).collect(),
+            watchlist: Arc::new(Mutex::new(Watchlist::default())),
+            mint_decimals,
+            amm_pools,
+            pool_subscriptions: Arc::new(StdMutex::new(PoolSubscriptions::default())),
+            metadata,
+            telegram_bot,
         })
     }
 
-    fn decode_program_data(&self, data_str: &str) -> Option<(String, String, bool, u64, u64)> {
-        if let Ok(decoded_data) = general_purpose::STANDARD.decode(data_str) {
-            if decoded_data.len() < 129 {
-                return None;
-            }
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    pub fn address_book(&self) -> &AddressBook {
+        &self.address_book
+    }
 
-            // 跳过前8个字节的事件标识符
-            let event_type = &decoded_data[..8];
-            debug!("Event Type: {:02X?}", event_type);
+    /// 是否为只读副本（`REPLICA`）
+    pub fn is_replica(&self) -> bool {
+        self.replica
+    }
 
-            // 从第8个字节开始是mint地址 (32 bytes)
-            let mint_bytes = &decoded_data[8..40];
-            let mint = bs58::encode(mint_bytes).into_string();
-            debug!("Mint: {}", mint);
+    /// 监控的取消令牌，后台任务在它取消时停止
+    pub fn cancellation(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
 
-            let mut pos = 40;
+    /// 等到日志订阅被确认
+    pub async fn wait_subscribed(&self) {
+        let _ = self.subscribed.subscribe().wait_for(|subscribed| *subscribed).await;
+    }
 
-            // 读取 sol_amount (8 bytes)
-            let sol_amount = {
-                let mut bytes = [0u8; 8];
-                bytes.copy_from_slice(&decoded_data[pos..pos + 8]);
-                u64::from_le_bytes(bytes)
-            };
-            pos += 8;
+    pub fn alive_at(&self) -> i64 {
+        self.alive_at.load(Ordering::Relaxed)
+    }
 
-            // 读取 token_amount (8 bytes)
-            let token_amount = {
-                let mut bytes = [0u8; 8];
-                bytes.copy_from_slice(&decoded_data[pos..pos + 8]);
-                u64::from_le_bytes(bytes)
-            };
-            pos += 8;
+    /// 停止日志订阅和所有后台任务
+    pub fn shutdown(&self) {
+        if !self.cancel.is_cancelled() {
+            info!("[{}] Shutting down", self.profile);
+            self.cancel.cancel();
+        }
+    }
 
-            // 读取 is_buy (1 byte)
-            let is_buy = decoded_data[pos] != 0;
-            pos += 1;
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// 每个 Helius key 的使用情况，没有配置 key 时为空
+    pub fn key_usage(&self) -> Vec<KeyUsage> {
+        self.helius_keys.as_ref().map_or_else(Vec::new, |keys| keys.usage())
+    }
 
-            // 读取 user 地址 (32 bytes)
-            let user_bytes = &decoded_data[pos..pos + 32];
-            let user = bs58::encode(user_bytes).into_string();
-            debug!("User: {}", user);
+    /// 检查每个提醒通道是否可用
+    pub async fn channel_health(&self) -> Vec<ChannelHealth> {
+        self.alert_service.channel_health().await
+    }
 
-            return Some((mint, user, is_buy, sol_amount, token_amount));
+    /// 启动时检查提醒通道，不可用的通道只记录警告，投递时由投递链转到下一个通道
+    pub async fn check_channels(&self) {
+        for health in self.channel_health().await {
+            match health.error {
+                Some(error) => warn!("[{}] Alert channel {} is unavailable: {}", self.profile, health.name, error),
+                None => debug!("[{}] Alert channel {} is available", self.profile, health.name),
+            }
         }
-        None
     }
 
-    fn calculate_price(sol_amount: u64, token_amount: u64) -> f64 {
-        if token_amount == 0 {
-            return 0.0;
+    /// 配置的端点最近一次探测的延迟，没有配置 RPC_URL 和 WS_URL 时为 None
+    pub fn endpoint_diag(&self) -> Option<EndpointDiag> {
+        self.endpoints.as_ref().map(|endpoints| endpoints.diag())
+    }
+
+    /// 探测配置的端点并选择最快的，启动时在订阅日志之前调用
+    pub async fn probe_endpoints(&self) {
+        if let Some(endpoints) = &self.endpoints {
+            endpoints.probe().await;
         }
-        
-        // 将 SOL 从 lamports 转换为实际的 SOL 数量
-        let sol = (sol_amount as f64) / 10f64.powi(SOL_DECIMALS as i32);
-        // 将代币数量转换为实际数量
-        let tokens = (token_amount as f64) / 10f64.powi(TOKEN_DECIMALS as i32);
-        
-        // 计算每个代币的价格（SOL）
-        sol / tokens
     }
 
-    async fn check_and_send_alert(&self, mint: &str, holding: &TokenHolding, alerted_mints: &mut HashSet<String>) -> Result<()> {
-        let price_change = holding.price_change_percentage();
-        info!("Checking alert for {}: price change {}%", mint, price_change);
-        
-        if price_change as f64 > self.price_change_threshold {
+    /// 定期重新探测端点。WebSocket 端点的切换在下次重连时生效，不主动断开正常的连接
+    pub async fn run_endpoint_probe(self) {
+        let (Some(endpoints), Some(period)) = (self.endpoints.clone(), self.endpoint_probe) else {
+            return;
+        };
+        let mut interval = interval_at(Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            endpoints.probe().await;
+        }
+    }
 
-            if !alerted_mints.contains(mint) {
-                info!("Sending alert for {}: price change {}%", mint, price_change);
-                
-                // 构造通知消息
-                let message = format!(
-                    "🚀 Token Pump Alert!\n\n\
-                    Token: <a href=\"https://gmgn.ai/sol/token/{}\">{}</a>\n\
-                    Current Price: {:.9} SOL\n\
-                    Avg Buy Price: {:.9} SOL",
-                    mint, mint,
-                    holding.current_price,
-                    holding.avg_price()
-                );
+    /// 更新需要在抓取时从提醒队列采样的指标
+    pub fn sample_metrics(&self) {
+        let stats = self.alert_service.queue_stats();
+        self.metrics.alert_queue_depth.store(stats.depth as u64, Ordering::Relaxed);
+        self.metrics.alerts_dropped.store(stats.dropped, Ordering::Relaxed);
+        self.metrics.alerts_coalesced.store(stats.coalesced, Ordering::Relaxed);
+    }
 
-                // 发送通知
-                match self.alert_service.send_alert(&message, AlertType::PriceAlert, Some(mint.to_string())).await {
-                    Ok(_) => {
-                        info!("Successfully sent alert for {}", mint);
-                        // 记录已发送通知
-                        alerted_mints.insert(mint.to_string());
-                    },
-                    Err(e) => {
-                        error!("Failed to send alert for {}: {:?}", mint, e);
-                        return Err(anyhow::anyhow!("Failed to send alert: {}", e));
-                    }
-                }
-            } else {
-                debug!("Alert already sent for {}", mint);
+    /// 获取持仓读锁并记录等待时间
+    async fn read_holdings(&self) -> RwLockReadGuard<'_, HashMap<String, TokenHolding>> {
+        let started = Instant::now();
+        let guard = self.holdings.read().await;
+        self.metrics.holdings_read_wait.observe(started.elapsed());
+        guard
+    }
+
+    /// 获取持仓写锁并记录等待时间。持仓和提醒状态只在持有写锁时修改，
+    /// 保存任务要等写锁释放后才能读取，因此保存的是修改后的状态
+    async fn write_holdings(&self) -> RwLockWriteGuard<'_, HashMap<String, TokenHolding>> {
+        let started = Instant::now();
+        let guard = self.holdings.write().await;
+        self.metrics.holdings_write_wait.observe(started.elapsed());
+        self.state_changed.notify_one();
+        guard
+    }
+
+    /// 持仓或提醒状态变化后保存到数据目录，最多每秒一次。退出前由 main 再保存一次
+    pub async fn run_state_saver(self) {
+        loop {
+            self.state_changed.notified().await;
+            if let Err(e) = self.save_state().await {
+                error!("[{}] Failed to save holdings state: {:?}", self.profile, e);
             }
+            sleep(STATE_SAVE_INTERVAL).await;
         }
-        Ok(())
     }
 
-    async fn update_holdings(&self, mint: String, is_buy: bool, token_amount: u64, price: f64) {
-        // 获取所有需要的锁
-        let mut holdings = self.holdings.write().await;
-        let mut alerted_mints = self.alerted_mints.lock().await;
-        
-        if is_buy {
-            // 买入，增加持仓
-            let holding = holdings.entry(mint.clone()).or_insert_with(|| TokenHolding::new(mint.clone(), 0, price));
-            
-            // 更新总成本和数量
-            let actual_amount = (token_amount as f64) / 10f64.powi(TOKEN_DECIMALS as i32);
-            holding.total_cost += actual_amount * price;
-            holding.amount = holding.amount.saturating_add(token_amount);
-            holding.current_price = price;
-            
-            // 检查是否需要发送通知
-            if let Err(e) = self.check_and_send_alert(&mint, holding, &mut alerted_mints).await {
-                error!("Failed to send alert: {:?}", e);
-            }
-            
-            info!("\n=== 🛍️  Buy Transaction ===");
-            info!("{}", holding);
-            info!("====================");
-        } else {
-            // 卖出，减少持仓
-            if let Some(holding) = holdings.get_mut(&mint) {
-                // 按比例减少总成本
-                let sell_ratio = token_amount as f64 / holding.amount as f64;
-                holding.total_cost *= (1.0 - sell_ratio);
-                holding.amount = holding.amount.saturating_sub(token_amount);
-                holding.current_price = price;
-                
-                // 检查是否需要发送通知
-                if let Err(e) = self.check_and_send_alert(&mint, holding, &mut alerted_mints).await {
-                    error!("Failed to send alert: {:?}", e);
-                }
-                
-                info!("\n=== 💰 Sell Transaction ===");
-                info!("{}", holding);
-                info!("====================");
-                
-                // 检查是否清仓
-                if holding.amount < MIN_HOLDING_AMOUNT {
-                    info!("\n🔔 Position Closed 🔔");
-                    info!("{}", holding);
-                    info!("====================");
-                    holdings.remove(&mint);
-                    alerted_mints.remove(&mint);
+    pub async fn save_state(&self) -> Result<()> {
+        // 副本不写入存储，持仓由监控实例保存
+        if self.replica {
+            return Ok(());
+        }
+        // 在锁内只做序列化，写入存储时不阻塞交易处理
+        let content = {
+            let holdings = self.holdings.read().await;
+            let alerted = self.alerted.lock().await;
+            let held = self.held.lock().await;
+            let pending_outcomes = self.pending_outcomes.lock().await;
+            serde_json::to_string(&SavedState {
+                saved_at: Utc::now().timestamp(),
+                holdings: holdings.values().collect(),
+                alert_tiers: alerted.iter().map(|((wallet, mint), tier)| (wallet.clone(), mint.clone(), *tier)).collect(),
+                pending_outcomes: pending_outcomes.clone(),
+                checkpoint: self.progress.lock().unwrap().checkpoint(),
+                held: held.iter().cloned().collect(),
+            })?
+        };
+        self.store.save_state(&content).await?;
+        if let Some(shared) = &self.shared {
+            let conflicts = shared.publish(self.shared_contents(None), None).await?;
+            if !conflicts.is_empty() {
+                debug!("[{}] {} holding(s) were changed or locked by another instance", self.profile, conflicts.len());
+                for mint in &conflicts {
+                    self.refresh_shared(shared, Some(mint)).await?;
                 }
+                // 被其他实例锁定的代币稍后再写
+                self.state_changed.notify_one();
             }
         }
+        Ok(())
     }
 
-    async fn update_price(&self, mint: &str, price: f64) {
-        // 获取所有需要的锁
-        let mut holdings = self.holdings.write().await;
-        let mut alerted_mints = self.alerted_mints.lock().await;
-        
-        // 如果价格为 0，跳过更新
-        if price == 0.0 {
-            debug!("Skipping price update with zero price for {}", mint);
+    /// 共享持仓时每隔 `STATE_SYNC_INTERVAL` 读取其他实例修改的持仓
+    pub async fn run_state_sync(self) {
+        let Some(shared) = self.shared.clone() else {
             return;
+        };
+        let mut ticker = interval(STATE_SYNC_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.refresh_shared(&shared, None).await {
+                error!("[{}] Failed to read shared holdings: {:?}", self.profile, e);
+            }
         }
-        
-        // 如果持仓数量为 0，直接移除
-        if let Some(holding) = holdings.get(mint) {
-            let real_amount = holding.amount as f64 / 1e6;
-            if real_amount < MIN_HOLDING_AMOUNT as f64 {
-                info!("Removing token {} from holdings during price update (real_amount: {})", mint, format_number_with_commas(real_amount));
+    }
+
+    /// 读取其他实例修改过的持仓行替换本地的持仓，`mint` 为 None 时读取所有代币
+    async fn refresh_shared(&self, shared: &SharedHoldings, mint: Option<&str>) -> Result<()> {
+        let rows = shared.fetch(mint).await?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+        // 新出现或计划持仓时间变化的持仓需要重新安排提醒
+        let deadline = |holding: &TokenHolding| holding.hold_plan.as_ref().map(|plan| holding.opened_at + plan.max_hold.as_secs() as i64);
+        let mut rescheduled = Vec::new();
+        let mut holdings = self.write_holdings().await;
+        let mut held = self.held.lock().await;
+        for row in rows {
+            if !shared.accept(&row) {
+                continue;
+            }
+            match row.content.as_deref().map(serde_json::from_str::<TokenHolding>) {
+                Some(Ok(holding)) => {
+                    debug!("[{}] {} updated by another instance (revision {})", self.profile, row.mint, row.revision);
+                    self.mint_decimals.insert(&holding.mint, holding.decimals);
+                    held.extend(holding.wallets.keys().map(|wallet| (wallet.clone(), holding.mint.clone())));
+                    let reminder = deadline(&holding);
+                    self.amm_pools.derive(&holding.mint);
+                    if holdings.insert(row.mint.clone(), holding).as_ref().map(deadline) != Some(reminder) {
+                        rescheduled.push((row.mint, reminder));
+                    }
+                }
+                Some(Err(e)) => warn!("[{}] Invalid shared holding {}: {}", self.profile, row.mint, e),
+                None => {
+                    if holdings.remove(&row.mint).is_some() {
+                        debug!("[{}] {} closed by another instance", self.profile, row.mint);
+                        self.hold_timers.lock().await.cancel(&row.mint);
+                    }
+                }
+            }
+        }
+        drop(held);
+        drop(holdings);
+        if rescheduled.is_empty() {
+            return Ok(());
+        }
+        let mut timers = self.hold_timers.lock().await;
+        for (mint, reminder) in &rescheduled {
+            match reminder {
+                Some(deadline) => timers.set(mint, *deadline),
+                None => {
+                    timers.cancel(mint);
+                }
+            }
+        }
+        drop(timers);
+        self.hold_timers_changed.notify_one();
+        for (mint, _) in &rescheduled {
+            self.prefetch_metadata(mint);
+        }
+        Ok(())
+    }
+
+    /// 共享持仓中每个代币的内容。按 `serde_json::Value` 序列化，字段顺序固定，内容相同时字符串相同
+    async fn shared_contents(&self, mint: Option<&str>) -> HashMap<String, String> {
+        let holdings = self.read_holdings().await;
+        holdings
+            .values()
+            .filter(|holding| mint.is_none_or(|mint| holding.mint == mint))
+            .filter_map(|holding| match serde_json::to_value(holding).and_then(|value| serde_json::to_string(&value)) {
+                Ok(content) => Some((holding.mint.clone(), content)),
+                Err(e) => {
+                    error!("[{}] Failed to serialize holding {}: {:?}", self.profile, holding.mint, e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// 计入目标钱包的交易，`notify` 为 false 时不发送提醒（回放历史）。
+    ///
+    /// 共享持仓时只在持有代币行的锁时计入：锁定、读取其他实例的修改、计入、写入并释放锁。
+    /// 写入时发现其他实例修改过（例如锁已经过期），本地的修改被存储中的内容替换，重新执行整个过程。
+    /// 重试时不再发送提醒，提醒已经在第一次计入时发出。
+    /// 交易已经由本实例领取，其他实例不会计入，所以失败时按指数退避一直重试到监控关闭
+    async fn apply_trade(&self, trade: &TradeEvent, price: f64, signature: &str, notify: bool) {
+        let Some(shared) = &self.shared else {
+            self.update_holdings(trade, price, signature, notify).await;
+            return;
+        };
+        let mut notify = notify;
+        let mut retry = SHARED_APPLY_RETRY;
+        for attempt in 0.. {
+            if attempt > 0 {
+                if attempt == SHARED_APPLY_ATTEMPTS {
+                    warn!(
+                        "[{}] {} could not be applied to shared holding {} after {} attempts, still retrying",
+                        self.profile, signature, trade.mint, attempt
+                    );
+                }
+                tokio::select! {
+                    _ = self.cancel.cancelled() => break,
+                    _ = sleep(retry) => {}
+                }
+                retry = (retry * 2).min(SHARED_APPLY_RETRY_MAX);
+            }
+            match shared.lock(&trade.mint).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    warn!("[{}] {} is still locked by another instance, retrying {}", self.profile, trade.mint, signature);
+                    continue;
+                }
+                Err(e) => {
+                    error!("[{}] Failed to lock shared holding {}: {:?}", self.profile, trade.mint, e);
+                    continue;
+                }
+            }
+            if let Err(e) = self.refresh_shared(shared, Some(&trade.mint)).await {
+                error!("[{}] Failed to read shared holding {}: {:?}", self.profile, trade.mint, e);
+                continue;
+            }
+            self.update_holdings(trade, price, signature, notify).await;
+            notify = false;
+            // 写入出错时还持有锁，只重试写入；重新计入会在本地重复计算这笔交易
+            let mut published = shared.publish(self.shared_contents(Some(&trade.mint)), Some(&trade.mint)).await;
+            for _ in 1..SHARED_APPLY_ATTEMPTS {
+                let Err(e) = &published else {
+                    break;
+                };
+                error!("[{}] Failed to write shared holding {}: {:?}", self.profile, trade.mint, e);
+                sleep(SHARED_APPLY_RETRY).await;
+                published = shared.publish(self.shared_contents(Some(&trade.mint)), Some(&trade.mint)).await;
+            }
+            match published {
+                Ok(conflicts) if conflicts.is_empty() => return,
+                Ok(_) => warn!("[{}] {} was changed by another instance while applying {}, retrying", self.profile, trade.mint, signature),
+                // 本地的持仓包含这笔交易，由保存任务继续写入
+                Err(e) => {
+                    error!("[{}] Failed to write shared holding {}: {:?}", self.profile, trade.mint, e);
+                    return;
+                }
+            }
+        }
+        error!(
+            "[{}] Shutting down before {} was applied to shared holding {}, run a reconciliation to correct it",
+            self.profile, signature, trade.mint
+        );
+    }
+
+    pub async fn add_wallet(&self, wallet: &str) -> Result<(), MonitorError> {
+        let pubkey = Pubkey::from_str(wallet.trim())
+            .map_err(|e| MonitorError::config(format!("Invalid wallet address: {}", e)))?;
+        self.target_wallets.write().await.insert(pubkey.to_string());
+        self.activity.lock().await.track_wallet(&pubkey.to_string(), Utc::now().timestamp());
+        self.wallets_changed.notify_one();
+        info!("[{}] Added target wallet {}", self.profile, pubkey);
+        Ok(())
+    }
+
+    /// 停止监控钱包：处理完它已收到的交易，从持仓中扣除它剩余的仓位，并发送最终盈亏报告
+    pub async fn remove_wallet(&self, wallet: &str) -> Result<(), MonitorError> {
+        // 与 add_wallet 一样解析并规范化地址，带空白的输入也能找到钱包
+        let wallet = Pubkey::from_str(wallet.trim())
+            .map_err(|e| MonitorError::config(format!("Invalid wallet address: {}", e)))?
+            .to_string();
+        if !self.target_wallets.write().await.remove(&wallet) {
+            return Err(MonitorError::config(format!("Wallet {} is not being monitored", wallet)));
+        }
+        self.activity.lock().await.untrack_wallet(&wallet);
+        let ledger = self.wallet_actors.lock().await.stop(&wallet).await.unwrap_or_default();
+        let message = self.release_positions(&wallet, &ledger).await;
+        info!("[{}] Removed target wallet {}", self.profile, wallet);
+        self.alert_service.send_alert(&message, AlertType::Report, None).await
+    }
+
+    /// 从持仓中扣除被移除钱包的剩余仓位（按当前价格估值），返回最终报告
+    async fn release_positions(&self, wallet: &str, ledger: &WalletLedger) -> String {
+        let mut message = format!(
+            "📕 Stopped monitoring <code>{}</code>\nTrades: {} | Invested: {} SOL | Realized PnL: {} SOL",
+            wallet,
+            ledger.trades,
+            format_f64(ledger.invested),
+            format_signed_sol(ledger.realized_pnl)
+        );
+        if ledger.positions.is_empty() {
+            return message;
+        }
+
+        let mut holdings = self.write_holdings().await;
+        let mut open_value = 0.0;
+        let mut open_cost = 0.0;
+        for (mint, &(amount, cost)) in &ledger.positions {
+            open_cost += cost;
+            let Some(holding) = holdings.get_mut(mint) else {
+                continue;
+            };
+            let released = amount.min(holding.amount);
+            let tokens = to_tokens(released, holding.decimals);
+            open_value += tokens * holding.current_price;
+
+            let before = holding.state();
+            holding.wallets.remove(wallet);
+            self.alerted.lock().await.remove(&(wallet.to_string(), mint.clone()));
+            holding.scale_to(holding.amount - released);
+            let after = if holding.amount < MIN_HOLDING_AMOUNT {
+                holdings.remove(mint);
+                forget_alerts(&mut *self.alerted.lock().await, mint);
+                self.hold_timers.lock().await.cancel(mint);
+                None
+            } else {
+                Some(holding.state())
+            };
+            self.audit(AuditEvent {
+                action: AuditAction::Release,
+                mint,
+                signature: None,
+                token_amount: Some(released),
+                price: None,
+                before: Some(before),
+                after,
+                sol_usd: None,
+            });
+        }
+        drop(holdings);
+
+        let _ = write!(
+            message,
+            "\nOpen: {} position(s) worth {} SOL (cost {} SOL, unrealized {} SOL), no longer tracked",
+            ledger.positions.len(),
+            format_f64(open_value),
+            format_f64(open_cost),
+            format_signed_sol(open_value - open_cost)
+        );
+        let _ = write!(message, "\nTotal PnL: {} SOL", format_signed_sol(ledger.realized_pnl + open_value - open_cost));
+        message
+    }
+
+    pub async fn mute_mint(&self, mint: &str) {
+        self.alert_service.mute(mint);
+        info!("[{}] Muted alerts for {}", self.profile, mint);
+    }
+
+    pub async fn unmute_mint(&self, mint: &str) -> Result<(), MonitorError> {
+        if !self.alert_service.unmute(mint) {
+            return Err(MonitorError::config(format!("Token {} is not muted", mint)));
+        }
+        info!("[{}] Unmuted alerts for {}", self.profile, mint);
+        Ok(())
+    }
+
+    /// 替换提醒档位。已经提醒过的档位保持不变，新增的更高档位仍会提醒
+    pub async fn set_price_change_thresholds(&self, mut thresholds: Vec<f64>) -> Result<(), MonitorError> {
+        if let Some(threshold) = thresholds.iter().find(|threshold| !threshold.is_finite() || **threshold < 0.0) {
+            return Err(MonitorError::config(format!("Invalid price change threshold: {}", threshold)));
+        }
+        config::normalize_thresholds(&mut thresholds).map_err(MonitorError::config)?;
+        info!("[{}] Price change thresholds set to {}", self.profile, format_thresholds(&thresholds));
+        *self.price_change_thresholds.write().await = thresholds;
+        Ok(())
+    }
+
+    /// 生成持仓报告并发送到 Telegram
+    pub async fn send_report(&self) -> Result<(), MonitorError> {
+        let message = self.holdings_report().await;
+        self.alert_service.send_alert(&message, AlertType::Report, None).await
+    }
+
+    /// 持仓报告：每个持仓的数量、均价和涨跌幅，以及总价值、成本和盈亏
+    pub async fn holdings_report(&self) -> String {
+        let holdings = self.read_holdings().await;
+
+        let mut message = String::from("📋 Holdings Report\n");
+        if holdings.is_empty() {
+            message.push_str("\nNo open positions");
+        }
+        for holding in holdings.values() {
+            let _ = write!(
+                message,
+                "\n<a href=\"https://gmgn.ai/sol/token/{}\">{}</a>\n\
+                Amount: {}\n\
+                Avg Price: {} SOL | Price: {} SOL | Change: {}%\n",
+                holding.mint,
+                self.token_label(&holding.mint).await.unwrap_or_else(|| truncate_address(&holding.mint, 16)),
+                format_token_amount(holding.amount, holding.decimals),
+                format_f64(holding.avg_price()),
+                format_f64(holding.current_price),
+                holding.price_change_percentage()
+            );
+            if let Some(note) = &holding.note {
+                let _ = writeln!(message, "📝 {}", escape_html(note));
+            }
+            if holding.ledger.realized_pnl() != 0.0 {
+                let _ = writeln!(
+                    message,
+                    "Realized: {} SOL | Unrealized: {} SOL ({} trades)",
+                    format_signed_sol(holding.ledger.realized_pnl()),
+                    format_signed_sol(holding.total_value() - holding.total_cost),
+                    holding.ledger.trades()
+                );
+            }
+        }
+
+        let total_value: f64 = holdings.values().map(|h| h.total_value()).sum();
+        let total_cost: f64 = holdings.values().map(|h| h.total_cost).sum();
+        let _ = write!(
+            message,
+            "\nTotal Value: {} SOL\nTotal Cost: {} SOL\nTotal PnL: {} SOL",
+            format_f64(total_value),
+            format_f64(total_cost),
+            format_f64(total_value - total_cost)
+        );
+        // 上次全部清仓以来卖出的已实现盈亏，包括已平仓的代币
+        let realized = self.session.lock().await.realized_pnl;
+        if realized != 0.0 {
+            let _ = write!(
+                message,
+                "\nRealized PnL: {} SOL (since last flat)\nNet PnL: {} SOL",
+                format_signed_sol(realized),
+                format_signed_sol(realized + total_value - total_cost)
+            );
+        }
+        if self.base_currency == BaseCurrency::Usd {
+            let total_cost_usd: Option<f64> = holdings.values().map(|h| h.cost_usd).sum();
+            match (self.oracles.sol_usd().await, total_cost_usd) {
+                (Some(sol_usd), Some(total_cost_usd)) => {
+                    let _ = write!(
+                        message,
+                        "\nTotal Value: ${:.2} (at ${:.2}/SOL)\nTotal Cost: ${:.2} (at trade time)\nTotal PnL: {}",
+                        total_value * sol_usd,
+                        sol_usd,
+                        total_cost_usd,
+                        format_usd(total_value * sol_usd - total_cost_usd)
+                    );
+                }
+                _ => message.push_str("\nUSD PnL unavailable: missing SOL/USD rate"),
+            }
+        }
+        drop(holdings);
+
+        // 配置了标签规则或有持仓被打过标签时，附上按标签的盈亏
+        let breakdown = self.tag_breakdown().await;
+        if breakdown.iter().any(|summary| summary.tag != tags::UNTAGGED) {
+            message.push_str("\n\n🏷️ By tag:");
+            for summary in &breakdown {
+                let _ = write!(
+                    message,
+                    "\n{}: exposure {} SOL ({} open) | unrealized {} SOL | realized {} SOL ({} closed)",
+                    summary.tag,
+                    format_f64(summary.exposure),
+                    summary.open_positions,
+                    format_signed_sol(summary.unrealized_pnl),
+                    format_signed_sol(summary.realized_pnl),
+                    summary.closed_positions
+                );
+            }
+        }
+        message
+    }
+
+    /// 未实现盈亏，以及上次全部清仓以来的已实现盈亏
+    pub async fn pnl_report(&self) -> String {
+        let (open, value, cost) = {
+            let holdings = self.read_holdings().await;
+            (
+                holdings.len(),
+                holdings.values().map(|h| h.total_value()).sum::<f64>(),
+                holdings.values().map(|h| h.total_cost).sum::<f64>(),
+            )
+        };
+        let session = self.session.lock().await;
+        let mut message = format!(
+            "💰 PnL\n\
+            Open: {} position(s), value {} SOL, cost {} SOL\n\
+            Unrealized: {} SOL\n\
+            Realized: {} SOL ({} closed since last flat)\n\
+            Net: {} SOL",
+            open,
+            format_f64(value),
+            format_f64(cost),
+            format_signed_sol(value - cost),
+            format_signed_sol(session.realized_pnl),
+            session.closed_positions,
+            format_signed_sol(session.realized_pnl + value - cost)
+        );
+        if let Some(pnl) = session.realized_pnl_usd {
+            let _ = write!(message, "\nRealized USD: {}", format_usd(pnl));
+        }
+        message
+    }
+
+    /// 监控状态：钱包、持仓、提醒档位、静音的代币、提醒队列和最近一次交易
+    pub async fn status_report(&self) -> String {
+        let queue = self.alert_service.queue_stats();
+        let last_trade = match self.recent_trades(1).await.first() {
+            Some(trade) => format!("{} ago", activity::format_duration(Utc::now().timestamp() - trade.timestamp)),
+            None => "none yet".to_string(),
+        };
+        format!(
+            "📡 Status of {}{}\n\
+            Wallets: {}\n\
+            Open positions: {}\n\
+            Alert tiers: {}\n\
+            Muted tokens: {}\n\
+            Alert queue: {} pending, {} dropped\n\
+            WebSocket reconnects: {}\n\
+            Last trade: {}",
+            self.profile,
+            if self.replica { " (read-only replica)" } else { "" },
+            self.target_wallets.read().await.len(),
+            self.read_holdings().await.len(),
+            format_thresholds(&self.price_change_thresholds.read().await),
+            self.alert_service.muted_count(),
+            queue.depth,
+            queue.dropped,
+            self.metrics.ws_reconnects.load(Ordering::Relaxed),
+            last_trade
+        )
+    }
+
+    /// 对当前持仓做回撤压力测试，包含各代币的流动性折损
+    pub async fn stress_report(&self) -> StressReport {
+        stress::run(&self.holding_rows().await, &self.stress_drawdowns)
+    }
+
+    pub async fn send_stress_report(&self) -> Result<(), MonitorError> {
+        let message = self.stress_report().await.to_message();
+        self.alert_service.send_alert(&message, AlertType::Report, None).await
+    }
+
+    /// 将内部持仓与链上代币余额对比，返回不一致的代币。
+    ///
+    /// `fix` 为 true 时按链上数量修正已跟踪的持仓，并保持平均成本不变；
+    /// 链上有余额但未跟踪的 pump 代币只报告，因为无法得知其成本
+    pub async fn reconcile(&self, fix: bool) -> Result<Vec<Discrepancy>, MonitorError> {
+        let rpc = self.rpc.as_ref()
+            .ok_or_else(|| MonitorError::config("RPC_URL or HELIUS_API_KEY must be set for reconciliation"))?;
+        let owners: Vec<String> = self.target_wallets.read().await.iter().cloned().collect();
+        let balances = reconcile::fetch_token_balances(rpc, &owners).await?;
+
+        // 只关心 pump 代币，忽略钱包里的其他代币。bonding curve 在锁外查询
+        let client = rpc.client();
+        let mut pump_mints = HashSet::new();
+        let candidates: Vec<String> = {
+            let holdings = self.read_holdings().await;
+            balances
+                .iter()
+                .filter(|(mint, amount)| **amount >= MIN_HOLDING_AMOUNT && !holdings.contains_key(*mint))
+                .map(|(mint, _)| mint.clone())
+                .collect()
+        };
+        for mint in candidates {
+            match oracle::find_bonding_curve(&client, &mint).await {
+                Ok(Some(_)) => {
+                    pump_mints.insert(mint);
+                }
+                Ok(None) => {}
+                Err(e) => warn!("[{}] Failed to check whether {} is a pump token: {}", self.profile, mint, e),
+            }
+        }
+
+        let mut holdings = self.write_holdings().await;
+        let mut discrepancies = Vec::new();
+
+        for (mint, holding) in holdings.iter() {
+            let on_chain = balances.get(mint).copied().unwrap_or(0);
+            if on_chain != holding.amount {
+                discrepancies.push(Discrepancy {
+                    mint: mint.clone(),
+                    tracked: Some(holding.amount),
+                    on_chain,
+                    decimals: holding.decimals,
+                });
+            }
+        }
+        for (mint, &on_chain) in &balances {
+            if pump_mints.contains(mint) && !holdings.contains_key(mint) {
+                discrepancies.push(Discrepancy {
+                    mint: mint.clone(),
+                    tracked: None,
+                    on_chain,
+                    decimals: self.mint_decimals.cached(mint),
+                });
+            }
+        }
+        discrepancies.sort_by(|a, b| a.mint.cmp(&b.mint));
+
+        if fix {
+            for discrepancy in discrepancies.iter().filter(|d| d.tracked.is_some()) {
+                let mint = &discrepancy.mint;
+                let Some(holding) = holdings.get_mut(mint) else {
+                    continue;
+                };
+                let before = holding.state();
+                let after = if discrepancy.on_chain < MIN_HOLDING_AMOUNT {
+                    holdings.remove(mint);
+                    forget_alerts(&mut *self.alerted.lock().await, mint);
+                    None
+                } else {
+                    holding.scale_to(discrepancy.on_chain);
+                    Some(holding.state())
+                };
+                self.audit(AuditEvent {
+                    action: AuditAction::Reconcile,
+                    mint,
+                    signature: None,
+                    token_amount: Some(discrepancy.on_chain),
+                    price: None,
+                    before: Some(before),
+                    after,
+                    sol_usd: None,
+                });
+            }
+        }
+
+        info!(
+            "[{}] Reconciliation found {} discrepancy(ies){}",
+            self.profile,
+            discrepancies.len(),
+            if fix { ", tracked amounts corrected" } else { "" }
+        );
+        Ok(discrepancies)
+    }
+
+    /// 把链上有余额但没有跟踪的 pump 代币加入持仓，返回加入的数量。成本无从得知，
+    /// 按 bonding curve 的现价估算并标记为未知，价格提醒从现价开始计算涨幅。
+    /// 没有 bonding curve 账户的代币不是 pump 发行的，跳过
+    pub async fn sync_holdings(&self) -> Result<usize, MonitorError> {
+        let rpc = self.rpc.as_ref()
+            .ok_or_else(|| MonitorError::config("RPC_URL or HELIUS_API_KEY must be set to sync holdings"))?;
+        let owners: Vec<String> = self.target_wallets.read().await.iter().cloned().collect();
+        let balances = reconcile::fetch_token_balances(rpc, &owners).await?;
+        let untracked: Vec<(String, u64)> = {
+            let holdings = self.read_holdings().await;
+            balances
+                .into_iter()
+                .filter(|(mint, amount)| *amount >= MIN_HOLDING_AMOUNT && !holdings.contains_key(mint))
+                .collect()
+        };
+
+        let client = rpc.client();
+        let mut synced = 0;
+        for (mint, amount) in untracked {
+            // 已毕业时价格为 0，等第一次价格更新时再估算成本
+            let price = match oracle::find_bonding_curve(&client, &mint).await {
+                Ok(Some(curve)) => curve.spot_price().unwrap_or(0.0),
+                Ok(None) => {
+                    debug!("[{}] {} has no bonding curve, not a pump token", self.profile, mint);
+                    continue;
+                }
+                Err(e) => {
+                    warn!("[{}] Failed to check whether {} is a pump token, not synced: {}", self.profile, mint, e);
+                    continue;
+                }
+            };
+            let decimals = self.mint_decimals.get(&mint).await;
+            let mut holdings = self.write_holdings().await;
+            if holdings.contains_key(&mint) {
+                continue;  // 同步期间已经有了交易
+            }
+            let mut holding = TokenHolding::new(mint.clone(), amount, price, decimals);
+            holding.cost_unknown = true;
+            self.audit(AuditEvent {
+                action: AuditAction::Sync,
+                mint: &mint,
+                signature: None,
+                token_amount: Some(amount),
+                price: Some(price),
+                before: None,
+                after: Some(holding.state()),
+                sol_usd: None,
+            });
+            info!("[{}] Synced {} {} from chain, cost basis unknown", self.profile, format_token_amount(amount, decimals), mint);
+            holdings.insert(mint.clone(), holding);
+            drop(holdings);
+            self.prefetch_metadata(&mint);
+            self.amm_pools.derive(&mint);
+            synced += 1;
+        }
+        Ok(synced)
+    }
+
+    /// 从 Helius 的解析交易历史读取每个钱包最近 `backfill_txs` 笔交易，把其中的 pump 交易按时间顺序
+    /// 计入持仓并写入交易日志，重建成本。已经包含在恢复的持仓中的交易（保存的位置之前或已计入的签名，
+    /// 没有位置的旧状态按保存的时间）跳过，回放不发送提醒。返回回放的笔数
+    pub async fn backfill(&self) -> Result<usize, MonitorError> {
+        // 共享持仓时无法区分其他实例已经计入的交易，只在存储中还没有持仓时回放
+        if self.shared.is_some() && !self.store.load_holdings(None).await.map_err(MonitorError::config)?.is_empty() {
+            info!("[{}] Skipping backfill, the shared store already has holdings", self.profile);
+            return Ok(0);
+        }
+        let keys = self.helius_keys.as_ref()
+            .ok_or_else(|| MonitorError::config("HELIUS_API_KEY must be set to backfill trade history"))?;
+        let client = reqwest::Client::new();
+        let wallets: Vec<String> = self.target_wallets.read().await.iter().cloned().collect();
+        let mut history = Vec::new();
+        for wallet in &wallets {
+            match backfill::fetch_pump_trades(&client, keys, wallet, self.backfill_txs).await {
+                Ok(trades) => {
+                    debug!("[{}] Found {} pump trade(s) in the last {} transactions of {}", self.profile, trades.len(), self.backfill_txs, wallet);
+                    history.extend(trades.into_iter().map(|trade| (wallet.clone(), trade)));
+                }
+                Err(e) => error!("[{}] Failed to fetch transaction history of {}: {:?}", self.profile, wallet, e),
+            }
+        }
+        // 多个钱包的交易合并后按 slot 排列，同一钱包的顺序保持不变
+        history.sort_by_key(|(_, trade)| trade.slot);
+
+        let (restored, restored_at) = {
+            let progress = self.progress.lock().unwrap();
+            (progress.watermark(), progress.restored_at())
+        };
+        let mut replayed = 0;
+        for (wallet, historical) in history {
+            let signature = historical.signature.as_str();
+            let included = match restored {
+                Some(slot) => historical.slot <= slot,
+                None => restored_at.is_some_and(|at| historical.timestamp <= at),
+            };
+            if included || self.progress.lock().unwrap().is_applied(signature) {
+                continue;
+            }
+            self.mint_decimals.insert(&historical.mint, historical.decimals);
+            let trade = TradeEvent {
+                mint: historical.mint.clone(),
+                user: wallet,
+                is_buy: historical.is_buy,
+                sol_amount: historical.sol_amount,
+                token_amount: historical.token_amount,
+                slot: historical.slot,
+                decimals: historical.decimals,
+                layout: "helius",
+                reserves: None,
+            };
+            let price = Self::calculate_price(trade.sol_amount, trade.token_amount, trade.decimals);
+            if let Err(e) = validate_trade(&trade, price) {
+                debug!("Rejected historical trade in {}: {}", signature, e);
+                continue;
+            }
+            debug!(
+                "[{}] Backfilling {} of {} by {} in {}",
+                self.profile, if trade.is_buy { "buy" } else { "sell" }, trade.mint, trade.user, signature
+            );
+            if !self.journal_trade(&trade, price, signature, historical.timestamp).await {
+                continue;
+            }
+            self.apply_trade(&trade, price, signature, false).await;
+            replayed += 1;
+        }
+        Ok(replayed)
+    }
+
+    /// 按配置的 cron 表达式或固定间隔运行定时任务
+    pub async fn run_scheduler(self) {
+        for scheduled in &self.schedules {
+            info!("[{}] Scheduled {} job: {}", self.profile, scheduled.job, scheduled.trigger);
+        }
+        let mut scheduler = Scheduler::new(self.schedules.clone(), Local::now());
+        if scheduler.is_empty() {
+            return;
+        }
+
+        let mut drift_alerted: HashSet<String> = HashSet::new();
+        while let Some(deadline) = scheduler.next_deadline() {
+            let wait = (deadline - Local::now()).to_std().unwrap_or_default();
+            sleep(wait).await;
+
+            for job in scheduler.pop_due(Local::now()) {
+                debug!("[{}] Running scheduled {} job", self.profile, job);
+                let result = match job {
+                    Job::Report => self.send_report().await,
+                    Job::Reconcile => self.check_drift(&mut drift_alerted).await,
+                    Job::Stress => self.send_stress_report().await,
+                    Job::Prune => self.prune_data().await,
+                    Job::Outcomes => self.send_outcome_report().await,
+                    Job::Leaderboard => self.send_leaderboard().await,
+                    Job::Snapshot => self.save_state().await.map_err(MonitorError::config),
+                    Job::Activity => {
+                        self.check_activity().await;
+                        Ok(())
+                    }
+                    Job::Counterparties => {
+                        self.check_counterparties().await;
+                        Ok(())
+                    }
+                };
+                if let Err(e) = result {
+                    error!("[{}] Scheduled {} job failed: {:?}", self.profile, job, e);
+                }
+            }
+        }
+    }
+
+    /// 最近 7 天价格提醒之后的走势：各时间点价格高于提醒价格的比例
+    pub async fn send_outcome_report(&self) -> Result<(), MonitorError> {
+        let outcomes = outcomes::load(&self.data_dir).map_err(MonitorError::config)?;
+        let since = Utc::now().timestamp() - (OUTCOME_REPORT_DAYS * 24 * 60 * 60) as i64;
+        let message = outcomes::summarize(&outcomes, since, OUTCOME_REPORT_DAYS);
+        self.alert_service.send_alert(&message, AlertType::Report, None).await
+    }
+
+    /// 最近 7 天目标钱包按盈亏的排行
+    pub async fn send_leaderboard(&self) -> Result<(), MonitorError> {
+        let entries = self.store.load_trades().await.map_err(MonitorError::config)?;
+        let wallets: Vec<String> = self.target_wallets.read().await.iter().cloned().collect();
+        let since = Utc::now().timestamp() - (LEADERBOARD_DAYS * 24 * 60 * 60) as i64;
+        let message = wallet_report::leaderboard(&entries, &wallets, since, LEADERBOARD_DAYS, &self.address_book);
+        self.alert_service.send_alert(&message, AlertType::Report, None).await
+    }
+
+    /// 价格提醒之后按时间点采样代币价格，全部采样后写入 `alert_outcomes.jsonl`
+    pub async fn run_outcome_tracker(self) {
+        let mut interval = interval(OUTCOME_SAMPLE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let now = Utc::now().timestamp();
+            let due: HashSet<String> = self.pending_outcomes.lock().await
+                .iter()
+                .filter(|outcome| outcome.next_due().is_some_and(|due| due <= now))
+                .map(|outcome| outcome.mint.clone())
+                .collect();
+            if due.is_empty() {
+                continue;
+            }
+
+            let mut prices = HashMap::new();
+            for mint in due {
+                let price = self.outcome_price(&mint).await;
+                prices.insert(mint, price);
+            }
+            let mut pending = self.pending_outcomes.lock().await;
+            for outcome in pending.iter_mut() {
+                if let Some(price) = prices.get(&outcome.mint).filter(|_| outcome.next_due().is_some_and(|due| due <= now)) {
+                    outcome.sample(now, *price);
+                }
+            }
+            for outcome in pending.iter().filter(|outcome| outcome.next_due().is_none()) {
+                if let Err(e) = self.outcome_log.append(outcome) {
+                    error!("[{}] Failed to write alert outcome: {:?}", self.profile, e);
+                }
+            }
+            pending.retain(|outcome| outcome.next_due().is_some());
+            self.state_changed.notify_one();
+        }
+    }
+
+    /// 采样用的代币价格：按价格提醒的 oracle 查询，没有报价时使用仍持有的持仓的当前价格
+    async fn outcome_price(&self, mint: &str) -> Option<f64> {
+        match self.oracles.alerts.get_token_price(mint).await {
+            Ok(Some(price)) => return Some(price),
+            Ok(None) => {}
+            Err(e) => warn!("[{}] {} price oracle failed for {}: {:?}", self.profile, self.oracles.alerts.name(), mint, e),
+        }
+        self.read_holdings().await.get(mint).map(|holding| holding.current_price)
+    }
+
+    /// 删除数据目录和状态存储中超过保留期的记录
+    async fn prune_data(&self) -> Result<(), MonitorError> {
+        let pruned = retention::prune(&self.data_dir, self.store.as_ref(), &self.retention).await.map_err(MonitorError::config)?;
+        for (file, removed) in pruned {
+            info!("[{}] Pruned {} expired record(s) from {}", self.profile, removed, file);
+        }
+        Ok(())
+    }
+
+    /// 对账并在持仓偏差超过容忍度时发送警告。同一批偏差只提醒一次
+    async fn check_drift(&self, alerted: &mut HashSet<String>) -> Result<(), MonitorError> {
+        let discrepancies = self.reconcile(false).await?;
+        let drifted: Vec<&Discrepancy> = discrepancies.iter()
+            .filter(|d| d.drift_percent() > self.reconcile_tolerance)
+            .collect();
+
+        let current: HashSet<String> = drifted.iter().map(|d| d.mint.clone()).collect();
+        let has_new = current.iter().any(|mint| !alerted.contains(mint));
+        *alerted = current;
+        if !has_new {
+            return Ok(());
+        }
+
+        warn!("[{}] Holdings drifted from on-chain balances for {} token(s)", self.profile, drifted.len());
+        let mut message = String::from("⚠️ Holdings drifted from on-chain balances\n");
+        for discrepancy in &drifted {
+            let decimals = discrepancy.decimals;
+            let _ = write!(
+                message,
+                "\n<a href=\"https://gmgn.ai/sol/token/{}\">{}</a>\n\
+                Tracked: {} | On-chain: {} ({:.1}%)\n",
+                discrepancy.mint,
+                self.token_label(&discrepancy.mint).await.unwrap_or_else(|| truncate_address(&discrepancy.mint, 16)),
+                discrepancy.tracked.map_or_else(|| "untracked".to_string(), |tracked| format_token_amount(tracked, decimals)),
+                format_token_amount(discrepancy.on_chain, decimals),
+                discrepancy.drift_percent()
+            );
+        }
+        message.push_str("\nRun <code>monitor reconcile --fix</code> to correct tracked amounts.");
+        self.alert_service.send_alert(&message, AlertType::Warning, None).await
+    }
+
+    /// 投递提醒队列
+    pub async fn run_alert_queue(self) {
+        self.alert_service.clone().run_queue(self.enrichment.clone(), self.metadata.clone()).await;
+    }
+
+    /// 接收 Telegram 聊天中的命令和交易确认的按钮
+    pub async fn run_telegram_bot(self) {
+        if let Some(bot) = self.telegram_bot.clone() {
+            bot.run(self).await;
+        }
+    }
+
+    /// 每个间隔内处理过 WebSocket 消息时请求心跳地址。连接断开、订阅卡住或 worker 停止时不再发送，
+    /// 外部服务因此报警
+    pub async fn run_heartbeat(self) {
+        let Some(config) = self.heartbeat.clone() else {
+            return;
+        };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        let mut handled = self.metrics.message_handling.count();
+        let mut interval = interval_at(Instant::now() + config.interval, config.interval);
+        loop {
+            interval.tick().await;
+            let now_handled = self.metrics.message_handling.count();
+            if now_handled == handled {
+                warn!("[{}] No messages processed in the last {}s, skipping heartbeat", self.profile, config.interval.as_secs());
+                continue;
+            }
+            handled = now_handled;
+            match heartbeat::ping(&client, &config.url).await {
+                Ok(()) => debug!("[{}] Heartbeat sent", self.profile),
+                Err(e) => warn!("[{}] Failed to send heartbeat: {:?}", self.profile, e),
+            }
+        }
+    }
+
+    /// 订阅配置的信号来源，把通过筛选的信号加入观察列表，按来源的设置发送提醒
+    pub async fn run_inbound_signals(self) {
+        if self.inbound_sources.is_empty() {
+            return;
+        }
+        let (sender, mut receiver) = mpsc::channel(INBOUND_QUEUE_CAPACITY);
+        // 任务被取消时 JoinSet 随之丢弃，所有连接一起关闭
+        let mut readers = JoinSet::new();
+        for source in &self.inbound_sources {
+            readers.spawn(federation::subscribe(self.profile.clone(), source.clone(), self.ws_reconnect_max, sender.clone()));
+        }
+        drop(sender);
+        while let Some((source, signal)) = receiver.recv().await {
+            self.accept_signal(&source, signal).await;
+        }
+    }
+
+    async fn accept_signal(&self, source: &InboundSource, signal: TradeSignal) {
+        let now = Utc::now().timestamp();
+        if let Err(reason) = source.accepts(&signal, now) {
+            debug!("[{}] Ignoring signal {} for {} from {}: {}", self.profile, signal.signal, signal.mint, source.name, reason);
+            return;
+        }
+        // 来自本监控的目标钱包的信号已经由本地的提醒覆盖，例如同一个信号服务器上自己发出的信号
+        let wallet = signal.provenance.as_ref().and_then(|provenance| provenance.wallet.clone());
+        if let Some(wallet) = &wallet {
+            if self.target_wallets.read().await.contains(wallet) {
+                debug!("[{}] Ignoring signal from {} about our own wallet {}", self.profile, source.name, wallet);
+                return;
+            }
+        }
+
+        let entry = WatchEntry::new(source, &signal, now);
+        if !self.watchlist.lock().await.add(entry) {
+            debug!("[{}] {} is already on the watchlist", self.profile, signal.mint);
+            return;
+        }
+        info!("[{}] Watching {} after signal {} from {}", self.profile, signal.mint, signal.signal, source.name);
+        if source.action != InboundAction::Alert {
+            return;
+        }
+
+        let mut message = format!(
+            "📡 Signal <b>{}</b> from {}\n\n\
+            Token: <a href=\"https://gmgn.ai/sol/token/{}\">{}</a>",
+            escape_html(&signal.signal),
+            escape_html(&source.name),
+            escape_html(&signal.mint),
+            self.token_label(&signal.mint).await.unwrap_or_else(|| escape_html(&signal.mint))
+        );
+        if let Some(provenance) = &signal.provenance {
+            let wallet = provenance.wallet_label.as_deref().or(provenance.wallet.as_deref()).unwrap_or("-");
+            let _ = write!(
+                message,
+                "\nRule: {}\nWallet: <code>{}</code>\nConfidence: {:.0}%\nPrice: {:.9} SOL",
+                escape_html(&provenance.rule),
+                escape_html(wallet),
+                provenance.confidence * 100.0,
+                provenance.price
+            );
+        }
+        // 不附带信号，接收的信号不会再转发出去
+        if let Err(e) = self.alert_service.send_alert(&message, AlertType::Signal, Some(signal.mint.clone())).await {
+            error!("Failed to send inbound signal alert: {:?}", e);
+        }
+    }
+
+    /// 观察列表中的代币和信号之后的价格变化
+    pub async fn watchlist_report(&self) -> String {
+        let entries = {
+            let mut watchlist = self.watchlist.lock().await;
+            watchlist.expire(Utc::now().timestamp());
+            watchlist.entries()
+        };
+        if entries.is_empty() {
+            return "👀 Watchlist is empty".to_string();
+        }
+        let now = Utc::now().timestamp();
+        let mut message = format!("👀 Watchlist ({})", entries.len());
+        for entry in entries {
+            let _ = write!(
+                message,
+                "\n<code>{}</code> {} via {}, {} ago{}",
+                truncate_address(&entry.mint, 16),
+                escape_html(&entry.rule),
+                escape_html(&entry.source),
+                activity::format_duration(now - entry.added_at),
+                entry.price_change().map_or_else(String::new, |change| format!(", {:+.1}% since", change))
+            );
+        }
+        message
+    }
+
+    /// 价格提醒中的观察列表信息：代币之前由其他监控的信号加入
+    async fn format_watch(&self, mint: &str) -> String {
+        let watchlist = self.watchlist.lock().await;
+        let Some(entry) = watchlist.get(mint) else {
+            return String::new();
+        };
+        format!(
+            "\n📡 Watched: {} signal from {} {} ago",
+            escape_html(&entry.rule),
+            escape_html(&entry.source),
+            activity::format_duration(Utc::now().timestamp() - entry.added_at)
+        )
+    }
+
+    /// 定期拉取外部代币黑名单，新上榜的代币已在持仓中时发出警告
+    pub async fn run_blacklist_sync(self) {
+        if self.blacklist_urls.is_empty() {
+            return;
+        }
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap_or_default();
+        let mut interval = interval(self.blacklist_refresh);
+        loop {
+            interval.tick().await;
+            let mut added = Vec::new();
+            for url in self.blacklist_urls.iter() {
+                match blacklist::fetch(&client, url).await {
+                    Ok(mints) => {
+                        debug!("[{}] Blacklist {} lists {} token(s)", self.profile, url, mints.len());
+                        added.extend(self.blacklist.write().await.update(url, mints));
+                    }
+                    Err(e) => warn!("[{}] Failed to fetch blacklist {}: {:?}", self.profile, url, e),
+                }
+            }
+            info!("[{}] Blacklists synced, {} token(s) listed", self.profile, self.blacklist.read().await.len());
+
+            let held: Vec<String> = {
+                let holdings = self.read_holdings().await;
+                added.into_iter().filter(|mint| holdings.contains_key(mint)).collect()
+            };
+            for mint in held {
+                self.send_blacklist_warning(&mint, "Held token was added to a blacklist").await;
+            }
+        }
+    }
+
+    /// 定期拉取知名代币列表，失败时 5 分钟后重试
+    pub async fn run_known_tokens_sync(self) {
+        let Some(url) = self.known_tokens_url.clone() else {
+            return;
+        };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .unwrap_or_default();
+        loop {
+            let delay = match impersonation::fetch(&client, &url).await {
+                Ok(tokens) => {
+                    info!("[{}] Loaded {} known token(s) for impersonation checks", self.profile, tokens.len());
+                    *self.known_tokens.write().await = tokens;
+                    KNOWN_TOKENS_REFRESH
+                }
+                Err(e) => {
+                    warn!("[{}] Failed to fetch known tokens from {}: {:?}", self.profile, url, e);
+                    Duration::from_secs(5 * 60)
+                }
+            };
+            sleep(delay).await;
+        }
+    }
+
+    /// 代币的元数据，查不到时使用 CreateEvent 中的名称和符号（没有图片）
+    async fn token_metadata(&self, mint: &str) -> Option<TokenMetadata> {
+        if let Some(metadata) = self.metadata.resolve(mint).await {
+            return Some(metadata);
+        }
+        let (name, symbol) = self.token_first_seen.lock().await.get(mint)?.names.clone()?;
+        let metadata = TokenMetadata { name, symbol, image: None };
+        self.metadata.insert(mint, metadata.clone());
+        Some(metadata)
+    }
+
+    /// 提醒中的代币名，例如 `PEPE2 (6EF8…)`，已转义 HTML。没有元数据时为 None，由调用方显示 mint。
+    /// 只读取缓存和 CreateEvent，不等待查询；没有缓存时在后台查询，之后的提醒使用查到的结果
+    async fn token_label(&self, mint: &str) -> Option<String> {
+        let metadata = match self.metadata.cached(mint) {
+            Some(metadata) => Some(metadata),
+            None => {
+                self.prefetch_metadata(mint);
+                let (name, symbol) = self.token_first_seen.lock().await.get(mint)?.names.clone()?;
+                Some(TokenMetadata { name, symbol, image: None })
+            }
+        };
+        metadata.map(|metadata| escape_html(&metadata.label(mint)))
+    }
+
+    /// 在后台查询代币的元数据，持仓表和之后的提醒从缓存中读取
+    fn prefetch_metadata(&self, mint: &str) {
+        if !self.metadata.begin_lookup(mint) {
+            return;
+        }
+        let monitor = self.clone();
+        let mint = mint.to_string();
+        tokio::spawn(async move {
+            monitor.token_metadata(&mint).await;
+        });
+    }
+
+    /// 按 CreateEvent 中的名称和符号查找被冒充的知名代币，没有看到 CreateEvent 时无法判断
+    async fn impersonated(&self, mint: &str) -> Option<KnownToken> {
+        self.known_tokens_url.as_ref()?;
+        let (name, symbol) = self.token_first_seen.lock().await.get(mint)?.names.clone()?;
+        self.known_tokens.read().await.impersonated(mint, &name, &symbol).cloned()
+    }
+
+    /// 目标钱包第一次买入一个代币，和涨幅提醒无关
+    async fn send_new_position_alert(&self, trade: &TradeEvent, price: f64, signature: String) {
+        info!("[{}] Target wallet {} opened a new position in {}", self.profile, trade.user, trade.mint);
+        let message = format!(
+            "🆕 New Position\n\n\
+            Token: <a href=\"https://gmgn.ai/sol/token/{}\">{}</a>\n\
+            Wallet: {}\n\
+            Entry Price: {:.9} SOL\n\
+            Market Cap: {}\n\
+            SOL Spent: {}\n\
+            Token Age: {}",
+            trade.mint,
+            self.token_label(&trade.mint).await.unwrap_or_else(|| trade.mint.clone()),
+            self.address_book.display(&trade.user),
+            price,
+            format_market_cap(trade.spot_price().unwrap_or(price)),
+            format_f64(to_sol(trade.sol_amount)),
+            self.format_token_age(&trade.mint).await
+        );
+        let trade_ref = TradeRef { signature, wallet: trade.user.clone(), mint: trade.mint.clone() };
+        if let Err(e) = self.alert_service.send_trade_alert(&message, AlertType::NewPosition, trade_ref).await {
+            error!("Failed to send new position alert: {:?}", e);
+        }
+    }
+
+    async fn send_impersonation_warning(&self, mint: &str, wallet: &str, known: &KnownToken) {
+        warn!("[{}] Target wallet {} bought {}, which looks like an impersonation of {}", self.profile, wallet, mint, known);
+        let message = format!(
+            "🎭 Likely impersonation\n\n\
+            Token: <a href=\"https://gmgn.ai/sol/token/{}\">{}</a>\n\
+            Wallet: {}\n\
+            Same name or symbol as: {} ({})\n\
+            Real mint: {}",
+            mint,
+            mint,
+            self.address_book.display(wallet),
+            escape_html(&known.name),
+            escape_html(&known.symbol),
+            escape_html(&known.mint)
+        );
+        if let Err(e) = self.alert_service.send_alert(&message, AlertType::Warning, Some(mint.to_string())).await {
+            error!("Failed to send impersonation warning: {:?}", e);
+        }
+    }
+
+    /// 持仓的代币毕业时发送 `Graduation` 提醒，并推导迁移池以便继续跟踪 PumpSwap 上的交易
+    async fn handle_graduation(&self, curve: pump_events::CompleteEvent, signature: &str) {
+        let mint = curve.mint.to_string();
+        debug!("Bonding curve of {} completed in {}", mint, signature);
+        let (message, snapshot) = {
+            let holdings = self.read_holdings().await;
+            let Some(holding) = holdings.get(&mint) else {
+                return;
+            };
+            let message = format!(
+                "🎓 Graduated\n\n\
+                Token: <a href=\"https://gmgn.ai/sol/token/{}\">{}</a>\n\
+                Bonding curve completed by: {}\n\
+                Price: {:.9} SOL\n\
+                Change: {}%{}{}",
+                mint,
+                self.metadata.cached(&mint).map_or_else(|| mint.clone(), |metadata| escape_html(&metadata.label(&mint))),
+                self.address_book.display(&curve.user.to_string()),
+                holding.current_price,
+                holding.price_change_percentage(),
+                holding.wallets_line(&self.address_book),
+                holding.note_line()
+            );
+            (message, holding.snapshot())
+        };
+        info!("[{}] Held token {} graduated from its bonding curve", self.profile, mint);
+        self.amm_pools.derive(&mint);
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            let event = format!("graduation:{}", mint);
+            if let Err(e) = monitor.alert_service.send_holding_alert(&message, AlertType::Graduation, snapshot, None, Some(&event)).await {
+                error!("Failed to send graduation alert: {:?}", e);
+            }
+        });
+    }
+
+    /// 持有该代币的目标钱包，用于提醒消息
+    async fn wallets_line(&self, mint: &str) -> String {
+        self.read_holdings().await.get(mint).map_or_else(String::new, |holding| holding.wallets_line(&self.address_book))
+    }
+
+    async fn note_line(&self, mint: &str) -> String {
+        self.read_holdings().await.get(mint).map_or_else(String::new, TokenHolding::note_line)
+    }
+
+    /// 目标钱包买入的代币在黑名单上时发出警告
+    async fn check_blacklist(&self, mint: &str) {
+        if self.blacklist_urls.is_empty() || self.blacklist.read().await.sources_of(mint).is_empty() {
+            return;
+        }
+        self.send_blacklist_warning(mint, "Target wallet bought a blacklisted token").await;
+    }
+
+    async fn send_blacklist_warning(&self, mint: &str, title: &str) {
+        let sources = self.blacklist.read().await.sources_of(mint).join("\n");
+        warn!("[{}] {}: {}", self.profile, title, mint);
+        let message = format!(
+            "🚨🚨 {}\n\n\
+            Token: <a href=\"https://gmgn.ai/sol/token/{}\">{}</a>{}{}\n\
+            Listed by:\n{}",
+            title,
+            mint,
+            self.token_label(mint).await.unwrap_or_else(|| mint.to_string()),
+            self.wallets_line(mint).await,
+            self.note_line(mint).await,
+            sources
+        );
+        if let Err(e) = self.alert_service.send_alert(&message, AlertType::Warning, Some(mint.to_string())).await {
+            error!("Failed to send blacklist warning: {:?}", e);
+        }
+    }
+
+    /// 检查目标钱包是否长时间没有交易，由 `activity` 定时任务调用
+    async fn check_activity(&self) {
+        let events = self.activity.lock().await.check_inactive(Utc::now().timestamp());
+        self.send_activity_alerts(events).await;
+    }
+
+    /// 定期采样持仓代币的持有人数量，短时间内明显增加或减少时提醒
+    pub async fn run_holder_watch(self) {
+        let (Some(tracker), Some(rpc)) = (self.holder_tracker.clone(), self.rpc.as_ref()) else {
+            return;
+        };
+        let client = rpc.client();
+        let rule = tracker.lock().await.rule();
+        let mut interval = interval(rule.interval);
+        loop {
+            interval.tick().await;
+            let held: Vec<String> = self.read_holdings().await.keys().cloned().collect();
+            tracker.lock().await.retain(|mint| held.iter().any(|held| held == mint));
+            for mint in held {
+                let holders = match holders::count_holders(&client, &mint).await {
+                    Ok(holders) => holders,
+                    Err(e) => {
+                        warn!("[{}] Failed to count holders of {}: {}", self.profile, mint, e);
+                        continue;
+                    }
+                };
+                debug!("[{}] {} has {} holder(s)", self.profile, mint, holders);
+                let change = tracker.lock().await.record(&mint, holders, Utc::now().timestamp());
+                if let Some(change) = change {
+                    info!("[{}] Holders of {} changed by {:.1}%", self.profile, mint, change.percent());
+                    let message = format!("{}{}{}", change.message(), self.wallets_line(&mint).await, self.note_line(&mint).await);
+                    if let Err(e) = self.alert_service.send_alert(&message, AlertType::Holders, Some(mint)).await {
+                        error!("Failed to send holder alert: {:?}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// 比较持仓代币相邻两个窗口的新买家，明显减少时提醒，由 `counterparties` 定时任务调用
+    async fn check_counterparties(&self) {
+        let Some(tracker) = &self.counterparty_tracker else {
+            return;
+        };
+        let held: Vec<String> = self.read_holdings().await.keys().cloned().collect();
+        let drying = {
+            let mut tracker = tracker.lock().await;
+            tracker.retain(|mint| held.iter().any(|held| held == mint));
+            tracker.check(Utc::now().timestamp())
+        };
+        for change in drying {
+            info!("[{}] New buyers of {} dropped by {:.0}%", self.profile, change.mint, change.drop_percent());
+            let message = format!("{}{}{}", change.message(), self.wallets_line(&change.mint).await, self.note_line(&change.mint).await);
+            if let Err(e) = self.alert_service.send_alert(&message, AlertType::Buyers, Some(change.mint)).await {
+                error!("Failed to send buyers alert: {:?}", e);
+            }
+        }
+    }
+
+    /// 记录持仓代币和目标钱包交易的交易对手
+    async fn record_counterparty(&self, trade: &TradeEvent) {
+        if let Some(tracker) = &self.counterparty_tracker {
+            tracker.lock().await.record(&trade.mint, &trade.user, trade.is_buy, Utc::now().timestamp());
+        }
+    }
+
+    async fn send_activity_alerts(&self, events: Vec<ActivityEvent>) {
+        for event in events {
+            info!("[{}] Wallet activity changed: {:?}", self.profile, event);
+            if let Err(e) = self.alert_service.send_alert(&event.message(&self.address_book), AlertType::Activity, None).await {
+                error!("Failed to send activity alert: {:?}", e);
+            }
+        }
+    }
+
+    /// 设置某个持仓的计划持仓时间，从建仓时开始计算
+    pub async fn set_hold_plan(&self, mint: &str, max_hold: Duration, label: Option<String>) -> Result<(), MonitorError> {
+        let mut holdings = self.write_holdings().await;
+        let holding = holdings.get_mut(mint)
+            .ok_or_else(|| MonitorError::config(format!("Token {} is not held", mint)))?;
+        holding.hold_plan = Some(HoldPlan { max_hold, label });
+        self.schedule_hold_reminder(holding).await;
+        info!("[{}] Max hold time of {} set to {} minute(s)", self.profile, mint, max_hold.as_secs() / 60);
+        Ok(())
+    }
+
+    async fn schedule_hold_reminder(&self, holding: &TokenHolding) {
+        let mut timers = self.hold_timers.lock().await;
+        match &holding.hold_plan {
+            Some(plan) => timers.set(&holding.mint, holding.opened_at + plan.max_hold.as_secs() as i64),
+            None => {
+                timers.cancel(&holding.mint);
+            }
+        }
+        drop(timers);
+        self.hold_timers_changed.notify_one();
+    }
+
+    /// 持仓时间提醒的定时任务，睡眠到最近的截止时间或定时器变化
+    pub async fn run_hold_reminders(self) {
+        loop {
+            let next_deadline = self.hold_timers.lock().await.next_deadline();
+            let wait = next_deadline.map_or(Duration::from_secs(3600), |deadline| {
+                Duration::from_secs((deadline - Utc::now().timestamp()).max(0) as u64)
+            });
+            tokio::select! {
+                _ = sleep(wait) => {}
+                _ = self.hold_timers_changed.notified() => continue,
+            }
+
+            let due = self.hold_timers.lock().await.pop_due(Utc::now().timestamp());
+            for mint in due {
+                let (message, snapshot, event) = {
+                    let holdings = self.read_holdings().await;
+                    let Some(holding) = holdings.get(&mint) else {
+                        continue;
+                    };
+                    let Some(plan) = &holding.hold_plan else {
+                        continue;
+                    };
+                    let plan_label = plan.label.as_ref()
+                        .map(|label| format!(" (plan was {})", label))
+                        .unwrap_or_default();
+                    let message = format!(
+                        "⏰ Hold time exceeded\n\n\
+                        Token: <a href=\"https://gmgn.ai/sol/token/{}\">{}</a>\n\
+                        Held for {}, planned at most {}{}\n\
+                        Change: {}%{}{}",
+                        mint,
+                        self.metadata.cached(&mint).map_or_else(|| mint.clone(), |metadata| escape_html(&metadata.label(&mint))),
+                        activity::format_duration(Utc::now().timestamp() - holding.opened_at),
+                        activity::format_duration(plan.max_hold.as_secs() as i64),
+                        plan_label,
+                        holding.price_change_percentage(),
+                        holding.wallets_line(&self.address_book),
+                        holding.note_line()
+                    );
+                    (message, holding.snapshot(), format!("reminder:{}:{}", mint, holding.opened_at))
+                };
+                info!("[{}] Position {} exceeded its max hold time", self.profile, mint);
+                if let Err(e) = self.alert_service.send_holding_alert(&message, AlertType::Reminder, snapshot, None, Some(&event)).await {
+                    error!("Failed to send hold time reminder: {:?}", e);
+                }
+            }
+        }
+    }
+
+    /// 返回最近处理的交易，最新的在前
+    pub async fn recent_trades(&self, limit: usize) -> Vec<RecordedTrade> {
+        self.recent_trades.lock().await.iter().rev().take(limit).cloned().collect()
+    }
+
+    async fn record_trade(&self, trade: RecordedTrade) {
+        if self.recent_trades_capacity == 0 {
+            return;
+        }
+        let mut recent_trades = self.recent_trades.lock().await;
+        if recent_trades.len() >= self.recent_trades_capacity {
+            recent_trades.pop_front();
+        }
+        recent_trades.push_back(trade);
+    }
+
+    fn decode_program_data(&self, data_str: &str) -> Result<DecodedEvent, MonitorError> {
+        Self::decode_payload(data_str).map_err(MonitorError::decode)
+    }
+
+    /// 解码 PumpSwap 的 Program data。事件中只有池地址：持仓代币的迁移池已知，
+    /// 目标钱包在其他池的交易查询池账户，其余的池不处理
+    async fn decode_amm_data(&self, data_str: &str) -> Result<DecodedEvent, MonitorError> {
+        let data = general_purpose::STANDARD.decode(data_str)
+            .map_err(|e| MonitorError::decode(format!("Invalid base64 payload: {}", e)))?;
+        let Some(swap) = amm::decode_swap_event(&data).map_err(MonitorError::decode)? else {
+            return Ok(DecodedEvent::Ignored("PumpSwap event"));
+        };
+        let mint = match self.amm_pools.pump_mint(&swap.pool) {
+            Some(mint) => mint,
+            None if self.target_wallets.read().await.contains(&swap.user) => self.amm_pools.resolve_pump_pool(&swap.pool).await,
+            // 持仓代币的迁移池在建仓时已经推导
+            None => None,
+        };
+        let Some(mint) = mint else {
+            return Ok(DecodedEvent::Ignored("PumpSwap trade in an untracked pool"));
+        };
+        Ok(DecodedEvent::Trade(TradeEvent {
+            mint,
+            user: swap.user,
+            is_buy: swap.is_buy,
+            sol_amount: swap.quote_amount,
+            token_amount: swap.base_amount,
+            slot: 0,
+            decimals: DEFAULT_TOKEN_DECIMALS,
+            layout: "pump_amm",
+            reserves: None,
+        }))
+    }
+
+    fn decode_payload(data_str: &str) -> Result<DecodedEvent> {
+        // 常见事件解码到栈上的缓冲区，只有特别长的事件才分配
+        let mut buffer = [0u8; DECODE_BUFFER_LEN];
+        let heap_buffer;
+        let decoded_data: &[u8] = if base64::decoded_len_estimate(data_str.len()) <= DECODE_BUFFER_LEN {
+            let len = general_purpose::STANDARD.decode_slice(data_str, &mut buffer)
+                .map_err(|e| anyhow!("Invalid base64 payload: {}", e))?;
+            &buffer[..len]
+        } else {
+            heap_buffer = general_purpose::STANDARD.decode(data_str)
+                .map_err(|e| anyhow!("Invalid base64 payload: {}", e))?;
+            &heap_buffer
+        };
+
+        Ok(match pump_events::decode(decoded_data)? {
+            PumpEvent::Trade(trade) => {
+                debug!("TradeEvent layout {} ({} bytes)", trade.layout(), decoded_data.len());
+                DecodedEvent::Trade(TradeEvent {
+                    mint: trade.mint.to_string(),
+                    user: trade.user.to_string(),
+                    is_buy: trade.is_buy,
+                    sol_amount: trade.sol_amount,
+                    token_amount: trade.token_amount,
+                    slot: 0,
+                    decimals: DEFAULT_TOKEN_DECIMALS,
+                    layout: trade.layout(),
+                    reserves: Some(BondingCurve {
+                        virtual_token_reserves: trade.virtual_token_reserves,
+                        virtual_sol_reserves: trade.virtual_sol_reserves,
+                        complete: false,
+                    }),
+                })
+            }
+            PumpEvent::Create(token) => DecodedEvent::Create(token),
+            PumpEvent::Complete(curve) => DecodedEvent::Complete(curve),
+            PumpEvent::Ignored(name) => DecodedEvent::Ignored(name),
+            PumpEvent::Unknown(discriminator) => DecodedEvent::Unknown(discriminator),
+        })
+    }
+
+    /// 统计未知事件，同一标识符频繁出现时发送警告（可能是 pump 更新了事件格式）
+    async fn track_unknown_event(&self, discriminator: [u8; 8]) {
+        Metrics::inc(&self.metrics.unknown_events);
+        let count = {
+            let mut unknown_events = self.unknown_events.lock().await;
+            let count = unknown_events.entry(discriminator).or_insert(0);
+            *count += 1;
+            *count
+        };
+
+        if count == UNKNOWN_EVENT_ALERT_THRESHOLD {
+            warn!("[{}] Unknown pump event {:02X?} seen {} times", self.profile, discriminator, count);
+            let message = format!(
+                "⚠️ Unknown pump event discriminator {:02X?} seen {} times.\n\
+                The event layout may have changed, check the quarantine file.",
+                discriminator, count
+            );
+            if let Err(e) = self.alert_service.send_alert(&message, AlertType::Warning, None).await {
+                error!("Failed to send unknown event warning: {:?}", e);
+            }
+        }
+    }
+
+    fn calculate_price(sol_amount: u64, token_amount: u64, decimals: u8) -> f64 {
+        if token_amount == 0 {
+            return 0.0;
+        }
+        
+        // 将 SOL 从 lamports 转换为实际的 SOL 数量
+        let sol = (sol_amount as f64) / 10f64.powi(SOL_DECIMALS as i32);
+        // 将代币数量转换为实际数量
+        let tokens = to_tokens(token_amount, decimals);
+        
+        // 计算每个代币的价格（SOL）
+        sol / tokens
+    }
+
+    /// 用于价格提醒的价格：按配置的 oracle 查询，没有报价或查询失败时使用交易价格
+    async fn alert_price(&self, mint: &str, trade_price: f64) -> f64 {
+        let oracle = &self.oracles.alerts;
+        match oracle.get_token_price(mint).await {
+            Ok(Some(price)) => price,
+            Ok(None) => trade_price,
+            Err(e) => {
+                warn!("[{}] {} price oracle failed for {}: {:?}", self.profile, oracle.name(), mint, e);
+                trade_price
+            }
+        }
+    }
+
+    /// 按每个目标钱包自己的均价检查涨幅，同一钱包的同一仓位只提醒一次。
+    /// 同时越过阈值的钱包合并为一条提醒
+    async fn check_and_send_alert(&self, mint: &str, holding: &TokenHolding, price: f64, slot: u64, alerted: &mut AlertedPositions) -> Result<()> {
+        info!("Checking alert for {}: price change {}%", mint, holding.price_change_at(price));
+
+        let positions: Vec<(String, f64)> = if holding.wallets.is_empty() {
+            vec![(String::new(), holding.avg_price())]
+        } else {
+            holding.wallets.iter().map(|(wallet, position)| (wallet.clone(), position.avg_price(holding.decimals))).collect()
+        };
+        // 每个仓位取越过的最高一档，只有比已提醒的档位更高时才提醒。
+        // 一次越过多档时只发一条提醒，较低的档位视为已提醒
+        let thresholds = self.price_change_thresholds.read().await.clone();
+        let crossed: Vec<(String, f64, i32, f64)> = positions
+            .into_iter()
+            .filter(|(_, avg_price)| *avg_price > 0.0)
+            .filter_map(|(wallet, avg_price)| {
+                let change = ((price - avg_price) / avg_price * 100.0) as i32;
+                let tier = thresholds.iter().rev().find(|threshold| change as f64 > **threshold).copied()?;
+                let fired = alerted.get(&(wallet.clone(), mint.to_string())).copied();
+                fired.is_none_or(|fired| tier > fired).then_some((wallet, avg_price, change, tier))
+            })
+            .collect();
+
+        let Some(top_tier) = crossed.iter().map(|(_, _, _, tier)| *tier).reduce(f64::max) else {
+            return Ok(());
+        };
+        // 置信度为越过阈值的仓位占该代币所有目标钱包仓位的比例，多个钱包同时盈利时更高
+        let wallet = crossed
+            .iter()
+            .find(|(wallet, _, _, tier)| !wallet.is_empty() && *tier == top_tier)
+            .map(|(wallet, _, _, _)| wallet.clone())
+            .or_else(|| holding.last_wallet.clone());
+        let provenance = SignalProvenance {
+            rule: "price_alert".to_string(),
+            wallet_label: wallet.as_deref().and_then(|wallet| self.address_book.label(wallet)).map(str::to_string),
+            wallet,
+            confidence: crossed.len() as f64 / holding.wallets.len().max(1) as f64,
+            price,
+            slot,
+        };
+
+        info!("Sending alert for {}: {} position(s) crossed the +{}% threshold", mint, crossed.len(), top_tier);
+        let positions: String = crossed
+            .iter()
+            .map(|(wallet, avg_price, change, _)| {
+                if wallet.is_empty() {
+                    format!("Avg Buy Price: {:.9} SOL ({:+}%)\n", avg_price, change)
+                } else {
+                    format!("Wallet: {}\nAvg Buy Price: {:.9} SOL ({:+}%)\n", self.address_book.display(wallet), avg_price, change)
+                }
+            })
+            .collect();
+
+        // 构造通知消息
+        let message = format!(
+            "🚀 Token Pump Alert! Above +{}%\n\n\
+            Token: <a href=\"https://gmgn.ai/sol/token/{}\">{}</a>\n\
+            Current Price: {:.9} SOL\n\
+            Market Cap: {}\n\
+            {}\
+            Token Age: {}{}{}{}{}{}",
+            top_tier,
+            mint,
+            self.token_label(mint).await.unwrap_or_else(|| mint.to_string()),
+            price,
+            format_market_cap(price),
+            positions,
+            self.format_token_age(mint).await,
+            self.format_flow(mint),
+            self.format_counterparties(mint).await,
+            self.format_watch(mint).await,
+            holding.impersonates.as_ref().map_or_else(String::new, |known| {
+                format!("\n⚠️ Likely impersonation of {} ({})", escape_html(&known.name), escape_html(&known.symbol))
+            }),
+            holding.note_line()
+        );
+
+        let snapshot = HoldingSnapshot {
+            current_price: price,
+            price_change: holding.price_change_at(price),
+            ..holding.snapshot()
+        };
+        // 发送通知，同一仓位的同一档位在多个实例间只提醒一次
+        let event = format!("price:{}:{}:{}", mint, holding.opened_at, top_tier);
+        match self.alert_service.send_holding_alert(&message, AlertType::PriceAlert, snapshot, Some(provenance), Some(&event)).await {
+            Ok(_) => {
+                info!("Successfully sent alert for {}", mint);
+                // 记录已发送通知
+                alerted.extend(crossed.into_iter().map(|(wallet, _, _, tier)| ((wallet, mint.to_string()), tier)));
+                self.pending_outcomes.lock().await.push(AlertOutcome::new(Utc::now().timestamp(), mint, top_tier, price));
+                self.state_changed.notify_one();
+            },
+            Err(e) => {
+                error!("Failed to send alert for {}: {:?}", mint, e);
+                return Err(anyhow::anyhow!("Failed to send alert: {}", e));
+            }
+        }
+        Ok(())
+    }
+
+    /// 价格提醒中的净买卖流量，例如 "net +320 SOL last 10m"。窗口内没有交易时为空
+    fn format_flow(&self, mint: &str) -> String {
+        let Some(window) = self.flow_window else {
+            return String::new();
+        };
+        let flow = self.flow.summary(mint, window);
+        if flow.is_empty() {
+            return String::new();
+        }
+        format!(
+            "\n💧 Flow: net {} SOL last {} ({} buys / {} sells)",
+            format_signed_sol(flow.net_sol()),
+            activity::format_duration(window.as_secs() as i64),
+            flow.buys,
+            flow.sells
+        )
+    }
+
+    /// 价格提醒中最近一个窗口的买家和卖家，例如 "Buyers: 42 (30 new / 12 returning) · Sellers: 15 last 5m"。窗口内没有交易时为空
+    async fn format_counterparties(&self, mint: &str) -> String {
+        let Some(tracker) = &self.counterparty_tracker else {
+            return String::new();
+        };
+        let tracker = tracker.lock().await;
+        let Some(summary) = tracker.summary(mint, Utc::now().timestamp()).filter(|summary| summary.buyers + summary.sellers > 0) else {
+            return String::new();
+        };
+        format!(
+            "\n👥 Buyers: {} ({} new / {} returning) · Sellers: {} last {}",
+            summary.buyers,
+            summary.new_buyers,
+            summary.returning_buyers(),
+            summary.sellers,
+            activity::format_duration(tracker.rule().window.as_secs() as i64)
+        )
+    }
+
+    /// 执行用户规则脚本，并把脚本产生的提醒发送出去
+    async fn run_script(&self, trade: &TradeEvent, is_target: bool, price: f64) {
+        let Some(script) = &self.script else {
+            return;
+        };
+
+        let holding = self.read_holdings().await.get(&trade.mint).map(|h| h.snapshot());
+        let messages = match script.on_trade(trade, is_target, price, holding.as_ref()) {
+            Ok(messages) => messages,
+            Err(e) => {
+                error!("[{}] {:?}", self.profile, e);
+                return;
+            }
+        };
+
+        for message in messages {
+            info!("[{}] Rules script {} emitted alert for {}", self.profile, script.path().display(), trade.mint);
+            if let Err(e) = self.alert_service.send_alert(&message, AlertType::Custom, Some(trade.mint.clone())).await {
+                error!("Failed to send script alert: {:?}", e);
+            }
+        }
+    }
+
+    /// 记录代币的创建时间，用于计算代币年龄。`created` 为 CreateEvent 中的代币信息，
+    /// 旧版本的 CreateEvent 没有创建时间，使用收到的时间；没有 CreateEvent 时只记录首次出现的时间
+    async fn record_token_seen(&self, mint: &str, created: Option<pump_events::CreateEvent>) {
+        let now = Utc::now().timestamp();
+        let mut seen = self.token_first_seen.lock().await;
+        match created {
+            Some(token) => {
+                let at = token.creation.as_ref().map_or(now, |creation| creation.timestamp);
+                seen.create(mint, at, Some((metadata::strip_control(&token.name), metadata::strip_control(&token.symbol))));
+            }
+            None => seen.observe(mint, now),
+        }
+    }
+
+    /// 监控启动前创建的代币在目标钱包交易时在后台从链上查询创建时间，每个代币只查询一次。
+    /// 交易不等待查询，查到之前代币年龄显示为首次出现以来的时间（≥）
+    async fn resolve_token_creation(&self, mint: &str) {
+        let Some(rpc) = self.rpc.clone() else {
+            return;
+        };
+        if !self.token_first_seen.lock().await.begin_lookup(mint) {
+            return;
+        }
+        let monitor = self.clone();
+        let mint = mint.to_string();
+        tokio::spawn(async move {
+            match mints::fetch_creation_time(&rpc.client(), &mint).await {
+                Ok(Some(at)) => {
+                    debug!("[{}] {} was created at {}", monitor.profile, mint, at);
+                    monitor.token_first_seen.lock().await.create(&mint, at, None);
+                }
+                Ok(None) => debug!("[{}] Creation of {} is beyond the looked up history", monitor.profile, mint),
+                Err(e) => warn!("[{}] Failed to look up creation time of {}: {:?}", monitor.profile, mint, e),
+            }
+        });
+    }
+
+    /// 按标签规则匹配代币名称和符号，没有看到 CreateEvent 的代币没有自动标签
+    async fn auto_tags(&self, mint: &str) -> BTreeSet<String> {
+        if self.tag_rules.is_empty() {
+            return BTreeSet::new();
+        }
+        match self.token_first_seen.lock().await.get(mint).and_then(|seen| seen.names.as_ref()) {
+            Some((name, symbol)) => tags::match_tags(&self.tag_rules, name, symbol),
+            None => BTreeSet::new(),
+        }
+    }
+
+    /// 替换持仓的标签
+    pub async fn set_tags(&self, mint: &str, tags: BTreeSet<String>) -> Result<(), MonitorError> {
+        let mut holdings = self.write_holdings().await;
+        let holding = holdings.get_mut(mint)
+            .ok_or_else(|| MonitorError::config(format!("Token {} is not held", mint)))?;
+        info!("[{}] Tags of {} set to {:?}", self.profile, mint, tags);
+        holding.tags = tags;
+        Ok(())
+    }
+
+    /// 设置持仓的备注，None 或空字符串时清除
+    pub async fn set_note(&self, mint: &str, note: Option<String>) -> Result<(), MonitorError> {
+        let mut holdings = self.write_holdings().await;
+        let holding = holdings.get_mut(mint)
+            .ok_or_else(|| MonitorError::config(format!("Token {} is not held", mint)))?;
+        holding.note = note.map(|note| note.trim().to_string()).filter(|note| !note.is_empty());
+        match &holding.note {
+            Some(note) => info!("[{}] Note of {} set to {:?}", self.profile, mint, note),
+            None => info!("[{}] Note of {} cleared", self.profile, mint),
+        }
+        Ok(())
+    }
+
+    /// 按标签汇总敞口和盈亏
+    pub async fn tag_breakdown(&self) -> Vec<TagSummary> {
+        let holdings = self.read_holdings().await;
+        let positions: Vec<TaggedPosition> = holdings
+            .values()
+            .map(|holding| TaggedPosition {
+                tags: &holding.tags,
+                value: holding.total_value(),
+                cost: holding.total_cost,
+            })
+            .collect();
+        tags::breakdown(&positions, &*self.tag_ledger.lock().await)
+    }
+
+    /// 代币年龄（秒），以及是否是创建以来的时间
+    async fn token_age(&self, mint: &str) -> Option<(i64, bool)> {
+        self.token_first_seen.lock().await
+            .get(mint)
+            .map(|seen| (Utc::now().timestamp() - seen.at, seen.created))
+    }
+
+    /// 用于提醒消息的代币年龄，只观察到交易时年龄至少为该值
+    async fn format_token_age(&self, mint: &str) -> String {
+        match self.token_age(mint).await {
+            Some((age, true)) => activity::format_duration(age),
+            Some((age, false)) => format!("≥{}", activity::format_duration(age)),
+            None => "unknown".to_string(),
+        }
+    }
+
+    /// 评估配置文件中的声明式规则
+    async fn run_rules(&self, trade: &TradeEvent, is_target: bool, price: f64) {
+        if self.rules.is_empty() {
+            return;
+        }
+
+        let holding = self.read_holdings().await.get(&trade.mint).map(|h| h.snapshot());
+        let token_age_secs = self.token_age(&trade.mint).await.map(|(age, _)| age);
+        let token = self.metadata.cached(&trade.mint);
+        let ctx = RuleContext {
+            trade,
+            is_target,
+            price,
+            holding: holding.as_ref(),
+            token_age_secs,
+            token: token.as_ref(),
+            wallet_label: self.address_book.label(&trade.user),
+        };
+
+        for rule in self.rules.iter() {
+            if !rule.matches(&ctx) {
+                continue;
+            }
+
+            let key = (rule.name.clone(), trade.mint.clone());
+            if rule.once_per_mint && self.fired_rules.lock().await.contains(&key) {
+                debug!("Rule {} already fired for {}", rule.name, trade.mint);
+                continue;
+            }
+
+            info!("[{}] Rule {} triggered for {}", self.profile, rule.name, trade.mint);
+            let message = rule.render(&ctx);
+            let provenance = SignalProvenance {
+                rule: rule.name.clone(),
+                wallet: Some(trade.user.clone()),
+                wallet_label: self.address_book.label(&trade.user).map(str::to_string),
+                confidence: rule.action.confidence.unwrap_or(1.0).clamp(0.0, 1.0),
+                price,
+                slot: trade.slot,
+            };
+            match self.alert_service.send_rule_alert(&message, rule.action.alert_type.clone(), &trade.mint, rule.action.sink, provenance).await {
+                Ok(_) => {
+                    self.fired_rules.lock().await.insert(key);
+                }
+                Err(e) => error!("Failed to send alert for rule {}: {:?}", rule.name, e),
+            }
+        }
+    }
+
+    /// 把一笔目标钱包的交易计入持仓。`notify` 为 false 时（回放历史交易）只更新持仓，不发送提醒
+    async fn update_holdings(&self, trade: &TradeEvent, price: f64, signature: &str, notify: bool) {
+        let (mint, is_buy, token_amount) = (trade.mint.clone(), trade.is_buy, trade.token_amount);
+        // 成本按成交价计算，现价使用成交后 bonding curve 的价格
+        let spot_price = trade.spot_price().unwrap_or(price);
+        self.oracles.observe_trade(&mint, spot_price);
+        // 在加锁前查询提醒价格，远程 oracle 的请求不阻塞持仓
+        let alert_price = self.alert_price(&mint, spot_price).await;
+        let auto_tags = if is_buy { self.auto_tags(&mint).await } else { BTreeSet::new() };
+        let sol_usd = self.oracles.sol_usd().await;
+        let impersonated = if is_buy { self.impersonated(&mint).await } else { None };
+        if is_buy {
+            self.prefetch_metadata(&mint);
+        }
+        // 获取所有需要的锁
+        let mut holdings = self.write_holdings().await;
+        let mut alerted = self.alerted.lock().await;
+        self.progress.lock().unwrap().apply(signature, trade.slot);
+        
+        if is_buy {
+            // 买入，增加持仓
+            self.session.lock().await.exit_started = None;
+            let before = holdings.get(&mint).map(TokenHolding::state);
+            let holding = holdings.entry(mint.clone()).or_insert_with(|| TokenHolding::new(mint.clone(), 0, price, trade.decimals));
+            if before.is_none() {
+                self.amm_pools.derive(&mint);
+                // 新建仓位，按默认持仓时间设置提醒
+                holding.hold_plan = self.max_hold.map(|max_hold| HoldPlan { max_hold, label: None });
+                holding.tags = auto_tags;
+                if let Some(known) = &impersonated {
+                    holding.impersonates = Some(known.clone());
+                    if notify {
+                        self.send_impersonation_warning(&mint, &trade.user, known).await;
+                    }
+                }
+                self.schedule_hold_reminder(holding).await;
+            }
+            
+            // 更新总成本和数量
+            let actual_amount = to_tokens(token_amount, trade.decimals);
+            holding.buy(token_amount, price, sol_usd);
+            let position = holding.wallets.entry(trade.user.clone()).or_default();
+            position.amount = position.amount.saturating_add(token_amount);
+            position.total_cost += actual_amount * price;
+            if self.held.lock().await.insert((trade.user.clone(), mint.clone())) && notify {
+                // 查询代币名可能需要请求 RPC，不在持仓的锁内等待
+                let monitor = self.clone();
+                let trade = trade.clone();
+                let signature = signature.to_string();
+                tokio::spawn(async move { monitor.send_new_position_alert(&trade, price, signature).await });
+            }
+            holding.current_price = spot_price;
+            holding.last_trade_at = Utc::now().timestamp();
+            holding.last_wallet = Some(trade.user.clone());
+            self.audit(AuditEvent {
+                action: AuditAction::Buy,
+                mint: &mint,
+                signature: Some(signature),
+                token_amount: Some(token_amount),
+                price: Some(price),
+                before,
+                after: Some(holding.state()),
+                sol_usd,
+            });
+            
+            // 检查是否需要发送通知，回放的历史价格不触发提醒
+            if notify {
+                if let Err(e) = self.check_and_send_alert(&mint, holding, alert_price, trade.slot, &mut alerted).await {
+                    error!("Failed to send alert: {:?}", e);
+                }
+            }
+            
+            info!("\n=== 🛍️  Buy Transaction ===");
+            info!("{}", holding);
+            info!("====================");
+        } else {
+            // 卖出，减少持仓
+            if let Some(holding) = holdings.get_mut(&mint) {
+                let before = holding.state();
+
+                let Realized { pnl: realized_pnl, pnl_usd: realized_pnl_usd } = holding.sell(token_amount, price, sol_usd);
+                {
+                    let mut session = self.session.lock().await;
+                    session.realized_pnl += realized_pnl;
+                    if let Some(pnl) = realized_pnl_usd {
+                        *session.realized_pnl_usd.get_or_insert(0.0) += pnl;
+                    }
+                }
+                if let Some(position) = holding.wallets.get_mut(&trade.user) {
+                    // 卖出超过监控期间买入的部分时，多出的是之前持有的代币，不影响仓位成本
+                    let ratio = (token_amount as f64 / position.amount as f64).min(1.0);
+                    position.total_cost *= 1.0 - ratio;
+                    position.amount = position.amount.saturating_sub(token_amount);
+                    if position.amount < MIN_HOLDING_AMOUNT {
+                        holding.wallets.remove(&trade.user);
+                        alerted.remove(&(trade.user.clone(), mint.clone()));
+                    }
+                }
+                holding.current_price = spot_price;
+                holding.last_trade_at = Utc::now().timestamp();
+                holding.last_wallet = Some(trade.user.clone());
+                self.audit(AuditEvent {
+                    action: AuditAction::Sell,
+                    mint: &mint,
+                    signature: Some(signature),
+                    token_amount: Some(token_amount),
+                    price: Some(price),
+                    before: Some(before),
+                    after: Some(holding.state()),
+                    sol_usd,
+                });
+                
+                // 检查是否需要发送通知，回放的历史价格不触发提醒
+                if notify {
+                    if let Err(e) = self.check_and_send_alert(&mint, holding, alert_price, trade.slot, &mut alerted).await {
+                        error!("Failed to send alert: {:?}", e);
+                    }
+                }
+                
+                info!("\n=== 💰 Sell Transaction ===");
+                info!("{}", holding);
+                info!("====================");
+                
+                let closed = holding.amount < MIN_HOLDING_AMOUNT;
+                self.tag_ledger.lock().await.record_sell(&holding.tags, realized_pnl, closed);
+                // 查询代币名可能需要请求 RPC，不在持仓的锁内等待
+                let monitor = self.clone();
+                let sale = Sale {
+                    trade: trade.clone(),
+                    price,
+                    realized: Realized { pnl: realized_pnl, pnl_usd: realized_pnl_usd },
+                    held_secs: Utc::now().timestamp() - holding.opened_at,
+                    position_pnl: closed.then(|| holding.ledger.realized_pnl()),
+                    note: holding.note_line(),
+                    signature: signature.to_string(),
+                };
+                if notify {
+                    tokio::spawn(async move { monitor.send_sell_alert(sale).await });
+                }
+
+                // 检查是否清仓
+                if closed {
+                    info!("\n🔔 Position Closed 🔔");
+                    info!("{}", holding);
+                    info!("====================");
+                    self.audit(AuditEvent {
+                        action: AuditAction::Close,
+                        mint: &mint,
+                        signature: Some(signature),
+                        token_amount: None,
+                        price: Some(price),
+                        before: Some(holding.state()),
+                        after: None,
+                        sol_usd,
+                    });
+                    holdings.remove(&mint);
+                    forget_alerts(&mut alerted, &mint);
+                    self.fired_rules.lock().await.forget(&mint);
+                    self.hold_timers.lock().await.cancel(&mint);
+
+                    let is_flat = holdings.is_empty();
+                    drop(holdings);
+                    drop(alerted);
+                    if notify {
+                        self.record_position_closed(is_flat).await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 目标钱包卖出时发送 `Sell` 提醒，卖出后清仓时发送 `PositionClosed` 提醒
+    async fn send_sell_alert(&self, sale: Sale) {
+        let Sale { trade, signature, price, realized, held_secs, position_pnl, note } = sale;
+        let with_usd = |pnl: f64, pnl_usd: Option<f64>| match pnl_usd {
+            Some(pnl_usd) if self.base_currency == BaseCurrency::Usd => format!("{} SOL ({})", format_signed_sol(pnl), format_usd(pnl_usd)),
+            _ => format!("{} SOL", format_signed_sol(pnl)),
+        };
+        let (title, alert_type) = match position_pnl {
+            Some(_) => ("🔒 Position Closed", AlertType::PositionClosed),
+            None => ("🔴 Sell", AlertType::Sell),
+        };
+        let message = format!(
+            "{}\n\n\
+            Token: <a href=\"https://gmgn.ai/sol/token/{}\">{}</a>\n\
+            Wallet: {}\n\
+            Sold: {} tokens for {} SOL\n\
+            Price: {:.9} SOL\n\
+            Market Cap: {}\n\
+            Realized PnL: {}{}\n\
+            Held: {}{}",
+            title,
+            trade.mint,
+            self.token_label(&trade.mint).await.unwrap_or_else(|| trade.mint.clone()),
+            self.address_book.display(&trade.user),
+            format_token_amount(trade.token_amount, trade.decimals),
+            format_f64(to_sol(trade.sol_amount)),
+            price,
+            format_market_cap(trade.spot_price().unwrap_or(price)),
+            with_usd(realized.pnl, realized.pnl_usd),
+            position_pnl.map_or_else(String::new, |pnl| format!("\nPosition realized PnL: {} SOL", format_signed_sol(pnl))),
+            activity::format_duration(held_secs),
+            note
+        );
+        let trade_ref = TradeRef { signature, wallet: trade.user.clone(), mint: trade.mint.clone() };
+        if let Err(e) = self.alert_service.send_trade_alert(&message, alert_type, trade_ref).await {
+            error!("Failed to send sell alert: {:?}", e);
+        }
+    }
+
+    /// 记录一次清仓；如果所有持仓在清仓窗口内被清空，发送钱包清仓提醒并开始新的统计
+    async fn record_position_closed(&self, is_flat: bool) {
+        let now = Utc::now().timestamp();
+        let message = {
+            let mut session = self.session.lock().await;
+            session.closed_positions += 1;
+            let exit_started = *session.exit_started.get_or_insert(now);
+            if !is_flat {
+                return;
+            }
+
+            let finished = std::mem::take(&mut *session);
+            let Some(window) = self.flat_alert_window else {
+                return;
+            };
+            if now - exit_started > window.as_secs() as i64 {
+                debug!("[{}] Wallet went flat, but exit took longer than the alert window", self.profile);
+                return;
+            }
+            format!(
+                "🏁 Wallet flat: all positions closed\n\n\
+                Closed positions: {}\n\
+                Exit duration: {}s\n\
+                Session realized PnL: {} SOL{}",
+                finished.closed_positions,
+                now - exit_started,
+                format_signed_sol(finished.realized_pnl),
+                match finished.realized_pnl_usd {
+                    Some(pnl) if self.base_currency == BaseCurrency::Usd => format!(" ({})", format_usd(pnl)),
+                    _ => String::new(),
+                }
+            )
+        };
+
+        info!("[{}] All positions closed, wallet is flat", self.profile);
+        if let Err(e) = self.alert_service.send_alert(&message, AlertType::WalletFlat, None).await {
+            error!("Failed to send wallet flat alert: {:?}", e);
+        }
+    }
+
+    async fn update_price(&self, mint: &str, price: f64, slot: u64, signature: &str) {
+        if price != 0.0 {
+            self.oracles.observe_trade(mint, price);
+        }
+        let alert_price = self.alert_price(mint, price).await;
+        // 获取所有需要的锁
+        let mut holdings = self.write_holdings().await;
+        let mut alerted = self.alerted.lock().await;
+        
+        // 如果价格为 0，跳过更新
+        if price == 0.0 {
+            debug!("Skipping price update with zero price for {}", mint);
+            return;
+        }
+        
+        // 如果持仓数量为 0，直接移除
+        if let Some(holding) = holdings.get(mint) {
+            let real_amount = holding.tokens();
+            if real_amount < MIN_HOLDING_AMOUNT as f64 {
+                info!("Removing token {} from holdings during price update (real_amount: {})", mint, format_number_with_commas(real_amount));
+                self.audit(AuditEvent {
+                    action: AuditAction::Remove,
+                    mint,
+                    signature: Some(signature),
+                    token_amount: None,
+                    price: Some(price),
+                    before: Some(holding.state()),
+                    after: None,
+                    sol_usd: None,
+                });
+                holdings.remove(mint);
+                forget_alerts(&mut alerted, mint);
+                return;
+            }
+        }
+        
+        if let Some(holding) = holdings.get_mut(mint) {
+            // 先克隆需要的数据
+            let real_amount = holding.tokens();
+            let holding_info = holding.to_string();
+            
+            holding.current_price = price;
+            if holding.cost_unknown && holding.total_cost == 0.0 {
+                // 同步时没有现价的持仓，以第一次看到的价格作为参考
+                holding.total_cost = real_amount * price;
+            }
+            
+            // 检查是否需要发送通知
+            if let Err(e) = self.check_and_send_alert(mint, holding, alert_price, slot, &mut alerted).await {
+                error!("Failed to send alert: {:?}", e);
+            }
+            
+            // 如果数量小于最小持仓量，从列表中移除
+            if real_amount < MIN_HOLDING_AMOUNT as f64 {
                 holdings.remove(mint);
-                alerted_mints.remove(mint);
+                forget_alerts(&mut alerted, mint);
+                
+                info!("\n🔔 Position Closed (Price Update) 🔔");
+                info!("{}", holding_info);
+                info!("====================");
+            } else {
+                debug!("\n=== 📊 Price Update ===");
+                debug!("{}", holding_info);
+                debug!("====================");
+            }
+        }
+    }
+
+    /// 其他钱包的交易：价格更新交给价格任务处理，积压时同一代币只处理最新一笔；
+    /// 规则和脚本对每笔交易都运行，不合并
+    async fn observe_price(&self, trade: &TradeEvent, price: f64, signature: &str) {
+        let update = PriceUpdate {
+            mint: trade.mint.clone(),
+            price,
+            slot: trade.slot,
+            signature: signature.to_string(),
+        };
+        if self.price_updates.lock().await.push(update) {
+            Metrics::inc(&self.metrics.price_updates_coalesced);
+        }
+        self.price_updates_changed.notify_one();
+        self.run_script(trade, false, price).await;
+        self.run_rules(trade, false, price).await;
+    }
+
+    /// 依次应用合并后的价格更新
+    pub async fn run_price_updates(self) {
+        loop {
+            let next = self.price_updates.lock().await.pop();
+            match next {
+                Some(update) => self.apply_price_update(update).await,
+                None => self.price_updates_changed.notified().await,
+            }
+        }
+    }
+
+    async fn apply_price_update(&self, update: PriceUpdate) {
+        self.update_price(&update.mint, update.price, update.slot, &update.signature).await;
+    }
+
+    /// 清理数量过小的持仓，返回剩余的持仓数量
+    async fn prune_holdings(&self) -> usize {
+        let holdings = self.read_holdings().await;
+        
+        // 打印所有持仓的详细信息
+        info!("\n=== Current Holdings Debug ===");
+        for (mint, holding) in holdings.iter() {
+            let real_amount = holding.tokens();
+            info!("Token {}: real_amount = {}, min_amount = {}", 
+                  mint, format_number_with_commas(real_amount), MIN_HOLDING_AMOUNT);
+        }
+        info!("============================\n");
+        
+        // 清理数量为 0 的持仓，考虑小数位
+        let to_remove: Vec<_> = holdings.iter()
+            .filter(|(_, holding)| {
+                let real_amount = holding.tokens();
+                real_amount < MIN_HOLDING_AMOUNT as f64
+            })
+            .map(|(mint, holding)| {
+                let real_amount = holding.tokens();
+                info!("Will remove token {} from holdings (real_amount: {})", 
+                     mint, format_number_with_commas(real_amount));
+                mint.clone()
+            })
+            .collect();
+        
+        // 释放读锁，获取写锁来删除
+        drop(holdings);
+        let mut holdings = self.write_holdings().await;
+        for mint in to_remove {
+            if let Some(holding) = holdings.remove(&mint) {
+                self.audit(AuditEvent {
+                    action: AuditAction::Remove,
+                    mint: &mint,
+                    signature: None,
+                    token_amount: None,
+                    price: None,
+                    before: Some(holding.state()),
+                    after: None,
+                    sol_usd: None,
+                });
+            }
+        }
+        holdings.len()
+    }
+
+    /// 持仓表的行，价格按显示用的 oracle 重新计算，并附带按当前流动性估算的卖出价值
+    async fn holding_rows(&self) -> Vec<HoldingRow> {
+        let by_wallet = display::settings().by_wallet;
+        let mut rows: Vec<HoldingRow> = self.read_holdings().await
+            .values()
+            .flat_map(|holding| {
+                if by_wallet && !holding.wallets.is_empty() {
+                    // 美元成本只按整个持仓记录，无法分到各个钱包
+                    holding.wallet_rows()
+                } else {
+                    vec![HoldingRow {
+                        cost_usd: holding.cost_usd.filter(|_| self.base_currency == BaseCurrency::Usd),
+                        ..holding.row()
+                    }]
+                }
+            })
+            .collect();
+        let oracle = &self.oracles.display;
+        for row in &mut rows {
+            row.symbol = self.metadata.cached(&row.mint).map(|metadata| metadata.symbol);
+            match oracle.get_token_price(&row.mint).await {
+                Ok(Some(price)) => row.reprice(price),
+                Ok(None) => {}
+                Err(e) => debug!("[{}] {} price oracle failed for {}: {:?}", self.profile, oracle.name(), row.mint, e),
+            }
+            if let Some(quoter) = &self.exit_quoter {
+                match quoter.estimate(&row.mint, row.amount).await {
+                    Ok(Some(estimate)) => {
+                        row.exit_quote = Some(estimate.out_sol);
+                        row.price_impact = Some(estimate.price_impact);
+                    }
+                    Ok(None) => {}
+                    Err(e) => debug!("[{}] Failed to estimate exit for {}: {:?}", self.profile, row.mint, e),
+                }
+            }
+        }
+        rows
+    }
+
+    /// 显示用的 SOL/USD 汇率，oracle 不提供时为 None；USD 记账时回退到记账用的汇率
+    async fn display_sol_usd(&self) -> Option<f64> {
+        let oracle = &self.oracles.display;
+        let sol_usd = oracle
+            .get_sol_usd()
+            .await
+            .inspect_err(|e| debug!("[{}] {} price oracle failed for SOL/USD: {:?}", self.profile, oracle.name(), e))
+            .ok()
+            .flatten();
+        match sol_usd {
+            None if self.base_currency == BaseCurrency::Usd => self.oracles.sol_usd().await,
+            sol_usd => sol_usd,
+        }
+    }
+
+    /// 定时在终端刷新所有 profile 的持仓表，设置了录制时同时写入录制文件
+    pub async fn run_display(monitors: Vec<WalletMonitor>, mut recorder: Option<Recorder>) {
+        let show_profile = monitors.len() > 1;
+        let mut interval = interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+
+            let mut total_holdings = 0;
+            for monitor in &monitors {
+                total_holdings += monitor.prune_holdings().await;
+            }
+
+            let now = Local::now();
+            let mut frames = Vec::with_capacity(monitors.len());
+            for monitor in &monitors {
+                frames.push(ProfileFrame {
+                    profile: monitor.profile.clone(),
+                    rows: monitor.holding_rows().await,
+                    sol_usd: monitor.display_sol_usd().await,
+                });
+            }
+            // 清仓后的空帧也要录制，回放时才能看到仓位关闭
+            if let Some(recorder) = recorder.as_mut() {
+                if let Err(e) = recorder.record(now.timestamp(), &frames) {
+                    error!("Failed to record display frame: {:?}", e);
+                }
+            }
+            if total_holdings == 0 {
+                continue;
+            }
+
+            let tables: Vec<String> = frames
+                .into_iter()
+                .map(|frame| {
+                    let profile = show_profile.then_some(frame.profile.as_str());
+                    display::render_holdings(profile, frame.rows, frame.sol_usd, now.timestamp())
+                })
+                .collect();
+            if let Err(e) = display::draw_frame(&tables, now) {
+                debug!("Failed to draw holdings display: {}", e);
+            }
+        }
+    }
+
+    /// 处理一条 logsSubscribe 通知：解码 pump 事件并更新持仓和价格
+    async fn handle_logs_notification(&self, slot: u64, value: &NotificationValue<'_>) {
+        // 获取交易签名
+        let signature = value.signature.as_deref().unwrap_or("unknown");
+
+        let mut instruction_type = "Unknown";
+        let mut target_trades: Vec<(TradeEvent, f64)> = Vec::new();  // 本实例计入持仓的目标钱包交易，按日志顺序
+        let mut program_stack: Vec<&str> = Vec::new();  // 当前正在执行的程序调用栈
+
+        // 检查日志
+        for (index, log_str) in value.logs.iter().map(|line| &**line).enumerate() {
+            debug!("Log: {}", log_str);
+
+            // 记录程序调用栈，用于判断 Program data 是哪个程序输出的
+            if let Some(rest) = log_str.strip_prefix("Program ") {
+                let mut parts = rest.split_whitespace();
+                if let (Some(program), Some(action)) = (parts.next(), parts.next()) {
+                    if action == "invoke" {
+                        program_stack.push(program);
+                    } else if action == "success" || action.starts_with("failed") {
+                        program_stack.pop();
+                    }
+                }
+            }
+
+            // 检查指令类型
+            if log_str.contains("Instruction: ") {
+                instruction_type = log_str.split("Instruction: ").nth(1).unwrap_or("Unknown");
+            }
+
+            // 只解析 pump 程序和 PumpSwap 输出的 Program data
+            let program = program_stack.last().copied();
+            if program != Some(PUMP_PROGRAM_ID) && program != Some(PUMP_AMM_PROGRAM_ID) {
+                continue;
+            }
+            let Some(data_str) = log_str.strip_prefix("Program data: ") else {
+                continue;
+            };
+            if self.is_duplicate(value.signature.as_deref(), index) {
+                continue;
+            }
+            let decoded = if program == Some(PUMP_AMM_PROGRAM_ID) {
+                self.decode_amm_data(data_str).await
+            } else {
+                self.decode_program_data(data_str)
+            };
+
+            let trade = match decoded {
+                Ok(DecodedEvent::Trade(trade)) => TradeEvent { slot, ..trade },
+                Ok(DecodedEvent::Create(token)) => {
+                    debug!("Token {} ({}) created in {}", token.mint, metadata::strip_control(&token.symbol), signature);
+                    self.record_token_seen(&token.mint.to_string(), Some(token)).await;
+                    continue;
+                }
+                Ok(DecodedEvent::Complete(curve)) => {
+                    self.handle_graduation(curve, signature).await;
+                    continue;
+                }
+                Ok(DecodedEvent::Ignored(name)) => {
+                    debug!("Skipping {} in {}", name, signature);
+                    continue;
+                }
+                Ok(DecodedEvent::Unknown(discriminator)) => {
+                    let reason = format!("Unknown event discriminator {:02X?}", discriminator);
+                    self.quarantine_payload(slot, signature, data_str, reason);
+                    self.track_unknown_event(discriminator).await;
+                    continue;
+                }
+                Err(e) => {
+                    debug!("Failed to decode program data in {}: {}", signature, e);
+                    self.quarantine_payload(slot, signature, data_str, e.to_string());
+                    continue;
+                }
+            };
+            debug!("Decoded user: {}, is_buy: {}, layout: {}", trade.user, trade.is_buy, trade.layout);
+
+            // 目标钱包交易的代币查询 mint 账户的小数位数，其他交易使用已缓存的值：
+            // 持仓的代币在建仓时已经查询过，其余的代币不值得逐一查询
+            let is_target = self.target_wallets.read().await.contains(&trade.user);
+            let decimals = if is_target {
+                self.mint_decimals.get(&trade.mint).await
+            } else {
+                self.mint_decimals.cached(&trade.mint)
+            };
+            let trade = TradeEvent { decimals, ..trade };
+
+            // 计算价格
+            let trade_price = Self::calculate_price(trade.sol_amount, trade.token_amount, trade.decimals);
+
+            // 拒绝数值异常的交易
+            if let Err(e) = validate_trade(&trade, trade_price) {
+                debug!("Rejected trade in {}: {}", signature, e);
+                Metrics::inc(&self.metrics.rejected_trades);
+                self.quarantine_payload(slot, signature, data_str, e.to_string());
+                continue;
+            }
+            self.record_token_seen(&trade.mint, None).await;
+            if is_target {
+                self.resolve_token_creation(&trade.mint).await;
+            }
+            self.watchlist.lock().await.observe(&trade.mint, trade.spot_price().unwrap_or(trade_price));
+            if let Some(detector) = &self.sandwiches {
+                let slot_trade = SlotTrade {
+                    slot,
+                    signature: signature.to_string(),
+                    user: trade.user.clone(),
+                    is_buy: trade.is_buy,
+                    token_amount: trade.token_amount,
+                    decimals: trade.decimals,
+                    price: trade_price,
+                    is_target,
+                };
+                let sandwiches = detector.lock().await.observe(&trade.mint, slot_trade);
+                for sandwich in sandwiches {
+                    self.record_sandwich(sandwich);
+                }
+            }
+
+            let recorded = |is_target: bool| RecordedTrade {
+                timestamp: Utc::now().timestamp(),
+                slot,
+                signature: signature.to_string(),
+                mint: trade.mint.clone(),
+                user: trade.user.clone(),
+                is_buy: trade.is_buy,
+                is_target,
+                sol_amount: trade.sol_amount,
+                token_amount: trade.token_amount,
+                price: trade_price,
+                raw: data_str.to_string(),
+            };
+
+            // 如果是目标钱包的交易
+            if is_target {
+                self.flow.record(&trade.mint, trade.is_buy, trade.sol_amount);
+                self.record_counterparty(&trade).await;
+                self.record_trade(recorded(true)).await;
+                // 补处理的交易都早于这笔交易，先计入持仓
+                self.wait_caught_up().await;
+                if self.journal_trade(&trade, trade_price, signature, Utc::now().timestamp()).await {
+                    target_trades.push((trade, trade_price));
+                } else {
+                    debug!("[{}] {} of {} in {} is handled by another instance", self.profile, if trade.is_buy { "Buy" } else { "Sell" }, trade.mint, signature);
+                }
+            } else {
+                // 如果不是目标钱包的交易，检查是否需要更新价格
+                let holdings = self.read_holdings().await;
+                if holdings.contains_key(&trade.mint) {
+                    drop(holdings); // 释放锁
+                    self.flow.record(&trade.mint, trade.is_buy, trade.sol_amount);
+                    self.record_counterparty(&trade).await;
+                    self.record_trade(recorded(false)).await;
+                    // 持仓的现价使用成交后的价格，不是这笔交易的成交均价
+                    let price = trade.spot_price().unwrap_or(trade_price);
+                    self.observe_price(&trade, price, signature).await;
+                }
+            }
+        }
+
+        // 一笔交易中可能有目标钱包的多个事件（例如机器人的多次买卖），都按顺序计入持仓
+        for (trade, price) in target_trades {
+            // 先应用该代币之前的价格更新，保持顺序
+            let pending = self.price_updates.lock().await.take(&trade.mint);
+            if let Some(update) = pending {
+                self.apply_price_update(update).await;
+            }
+            // 更新持仓信息
+            self.apply_trade(&trade, price, signature, true).await;
+            if trade.is_buy {
+                self.check_blacklist(&trade.mint).await;
+            }
+            let events = self.activity.lock().await.record_trade(&trade, Utc::now().timestamp());
+            self.send_activity_alerts(events).await;
+            self.run_script(&trade, true, price).await;
+            self.run_rules(&trade, true, price).await;
+
+            debug!("Found interaction with target wallet!");
+            debug!("Transaction: https://solscan.io/tx/{}", signature);
+            debug!("Instruction Type: {}", instruction_type);
+            debug!("Mint: {}", trade.mint);
+            debug!("Action: {}", if trade.is_buy { "Buy" } else { "Sell" });
+            debug!("Amount: {} tokens", trade.token_amount);
+            debug!("Price: {} SOL/token", price);
+            debug!("-----------------------------------");
+        }
+    }
+
+    /// 处理 Raydium 池的日志通知。交换日志中没有交易者，所以只用于更新持仓代币的价格
+    async fn handle_raydium_notification(&self, slot: u64, pool: &RaydiumPool, value: &NotificationValue<'_>) {
+        let signature = value.signature.as_deref().unwrap_or("unknown");
+        let mut program_stack: Vec<&str> = Vec::new();
+        for (index, log_str) in value.logs.iter().map(|line| &**line).enumerate() {
+            if let Some(rest) = log_str.strip_prefix("Program ") {
+                let mut parts = rest.split_whitespace();
+                if let (Some(program), Some(action)) = (parts.next(), parts.next()) {
+                    if action == "invoke" {
+                        program_stack.push(program);
+                    } else if action == "success" || action.starts_with("failed") {
+                        program_stack.pop();
+                    }
+                }
+            }
+            if program_stack.last() != Some(&RAYDIUM_AMM_PROGRAM_ID) {
+                continue;
+            }
+            let Some(swap) = log_str.strip_prefix("Program log: ray_log: ").and_then(amm::decode_ray_log) else {
+                continue;
+            };
+            if self.is_duplicate(value.signature.as_deref(), index) {
+                continue;
+            }
+            let (is_buy, token_amount, sol_amount) = pool.trade_of(swap);
+            let trade = TradeEvent {
+                mint: pool.mint.clone(),
+                user: String::new(),
+                is_buy,
+                sol_amount,
+                token_amount,
+                slot,
+                decimals: self.mint_decimals.cached(&pool.mint),
+                layout: "raydium",
+                reserves: None,
+            };
+            let price = Self::calculate_price(trade.sol_amount, trade.token_amount, trade.decimals);
+            if let Err(e) = validate_price(price) {
+                debug!("Rejected Raydium swap in {}: {}", signature, e);
+                continue;
+            }
+            if !self.read_holdings().await.contains_key(&pool.mint) {
+                continue;
+            }
+            self.watchlist.lock().await.observe(&trade.mint, price);
+            self.flow.record(&trade.mint, trade.is_buy, trade.sol_amount);
+            self.observe_price(&trade, price, signature).await;
+        }
+    }
+
+    /// 把目标钱包的交易写入交易日志和钱包的交易记录，返回是否由本实例处理。
+    /// 共享状态时另一个实例已经记录的交易由它计入持仓和发送提醒，存储写入失败时仍然处理
+    async fn journal_trade(&self, trade: &TradeEvent, price: f64, signature: &str, timestamp: i64) -> bool {
+        let entry = JournalEntry {
+            timestamp,
+            signature: signature.to_string(),
+            wallet: trade.user.clone(),
+            mint: trade.mint.clone(),
+            is_buy: trade.is_buy,
+            sol_amount: trade.sol_amount,
+            token_amount: trade.token_amount,
+            decimals: trade.decimals,
+            price,
+            token_age_secs: self.token_age(&trade.mint).await
+                .and_then(|(age, created)| created.then_some(age)),
+        };
+        let claimed = self.store.record_trade(&entry).await.unwrap_or_else(|e| {
+            error!("[{}] Failed to write trade journal: {:?}", self.profile, e);
+            true
+        });
+        if claimed {
+            self.wallet_actors.lock().await.record(entry).await;
+        }
+        claimed
+    }
+
+    /// 等到没有正在运行的补处理
+    async fn wait_caught_up(&self) {
+        let _ = self.catching_up.subscribe().wait_for(|running| *running == 0).await;
+    }
+
+    /// 补处理目标钱包在 (`after`, `before`) 两个 slot 之间错过的交易，已经计入持仓的交易跳过。
+    /// 调用前在 `after + 1` 分配一条占位消息并把 `catching_up` 加一，直到补处理全部完成（包括重试之间的等待）
+    /// 目标钱包的实时交易都等待，错过的交易总是先于之后的交易计入持仓。
+    /// 有查询失败时占位消息保留，保存的位置不超过 `after`，等待后重试到全部完成
+    async fn catch_up(self, after: u64, before: u64, _running: CatchUpGuard) {
+        let Some(rpc) = &self.rpc else {
+            warn!("[{}] Trades after slot {} are not replayed, RPC_URL or HELIUS_API_KEY is not set", self.profile, after);
+            self.progress.lock().unwrap().complete(after + 1);
+            self.resumed.send_replace(true);
+            return;
+        };
+        let mut retry = CATCH_UP_RETRY;
+        loop {
+            if self.catch_up_once(rpc, after, before).await {
+                self.progress.lock().unwrap().complete(after + 1);
+                self.resumed.send_replace(true);
                 return;
             }
+            warn!(
+                "[{}] Some trades between slots {} and {} could not be replayed, retrying in {}s",
+                self.profile, after, before, retry.as_secs()
+            );
+            tokio::time::sleep(retry).await;
+            retry = (retry * 2).min(CATCH_UP_RETRY_MAX);
+        }
+    }
+
+    /// 补处理一轮，所有查询都成功时返回 true
+    async fn catch_up_once(&self, rpc: &RpcEndpoint, after: u64, before: u64) -> bool {
+        let client = rpc.client();
+        let wallets: Vec<String> = self.target_wallets.read().await.iter().cloned().collect();
+        let mut missed = Vec::new();
+        let mut complete = true;
+        for wallet in &wallets {
+            match checkpoint::missed_signatures(&client, wallet, after, before, self.commitment).await {
+                Ok((signatures, truncated)) => {
+                    if truncated {
+                        warn!("[{}] {} made more than {} transactions since slot {}, older ones are not replayed", self.profile, wallet, MAX_CATCH_UP, after);
+                    }
+                    missed.extend(signatures);
+                }
+                Err(e) => {
+                    error!("[{}] Failed to list transactions of {} since slot {}: {:?}", self.profile, wallet, after, e);
+                    complete = false;
+                }
+            }
+        }
+        missed.sort();
+        missed.dedup();
+
+        let mut replayed = 0;
+        for (slot, signature) in &missed {
+            if self.progress.lock().unwrap().is_applied(signature) {
+                continue;
+            }
+            match checkpoint::transaction_logs(&client, signature, self.commitment).await {
+                Ok(Some(logs)) => replayed += self.replay_transaction(*slot, signature, &logs).await,
+                Ok(None) => debug!("[{}] Transaction {} not found", self.profile, signature),
+                Err(e) => {
+                    error!("[{}] Failed to fetch transaction {}: {:?}", self.profile, signature, e);
+                    complete = false;
+                }
+            }
+        }
+        info!(
+            "[{}] Caught up on slots {} to {}: {} transaction(s) of target wallets, {} trade(s) replayed",
+            self.profile, after + 1, before - 1, missed.len(), replayed
+        );
+        complete
+    }
+
+    /// 把一笔错过的交易中目标钱包的 pump 交易计入持仓，返回计入的笔数。不运行规则和脚本
+    async fn replay_transaction(&self, slot: u64, signature: &str, logs: &[String]) -> usize {
+        let mut replayed = 0;
+        for (index, program, data_str) in pump_program_data(logs) {
+            // 补处理的交易之后仍可能收到实时通知，同样记为处理过
+            if self.is_duplicate(Some(signature), index) {
+                continue;
+            }
+            let decoded = if program == PUMP_AMM_PROGRAM_ID {
+                self.decode_amm_data(data_str).await
+            } else {
+                self.decode_program_data(data_str)
+            };
+            let Ok(DecodedEvent::Trade(trade)) = decoded else {
+                continue;
+            };
+            if !self.target_wallets.read().await.contains(&trade.user) {
+                continue;
+            }
+            let decimals = self.mint_decimals.get(&trade.mint).await;
+            let trade = TradeEvent { slot, decimals, ..trade };
+            let price = Self::calculate_price(trade.sol_amount, trade.token_amount, trade.decimals);
+            if let Err(e) = validate_trade(&trade, price) {
+                debug!("Rejected replayed trade in {}: {}", signature, e);
+                continue;
+            }
+            info!(
+                "[{}] Replaying missed {} of {} by {} in {}",
+                self.profile, if trade.is_buy { "buy" } else { "sell" }, trade.mint, trade.user, signature
+            );
+            if !self.journal_trade(&trade, price, signature, Utc::now().timestamp()).await {
+                continue;
+            }
+            self.apply_trade(&trade, price, signature, true).await;
+            replayed += 1;
+        }
+        replayed
+    }
+
+    fn record_sandwich(&self, sandwich: Sandwich) {
+        warn!(
+            "[{}] Trade {} of {} on {} was sandwiched by {}, estimated loss {} SOL",
+            self.profile, sandwich.victim.signature, sandwich.victim.user, sandwich.mint, sandwich.attacker, format_f64(sandwich.loss)
+        );
+        let record = SandwichRecord {
+            timestamp: Utc::now().timestamp(),
+            slot: sandwich.slot,
+            signature: sandwich.victim.signature,
+            wallet: sandwich.victim.user,
+            mint: sandwich.mint,
+            attacker: sandwich.attacker,
+            front_signature: sandwich.front_signature,
+            back_signature: sandwich.back_signature,
+            loss: sandwich.loss,
+        };
+        if let Err(e) = self.journal.record_sandwich(&record) {
+            error!("[{}] Failed to write sandwich record: {:?}", self.profile, e);
+        }
+    }
+
+    /// 处理代币账户变动通知，链上余额与跟踪的持仓不一致时记录并计数，持续不一致时提醒
+    async fn handle_token_account_notification(&self, value: &NotificationValue<'_>) {
+        let Some(account_data) = &value.account else {
+            return;
+        };
+        let info = &account_data["data"]["parsed"]["info"];
+        let (Some(account), Some(owner), Some(mint), Some(amount)) = (
+            value.pubkey.as_deref(),
+            info["owner"].as_str(),
+            info["mint"].as_str(),
+            info["tokenAmount"]["amount"].as_str().and_then(|a| a.parse::<u64>().ok()),
+        ) else {
+            debug!("[{}] Ignoring unparsable token account notification", self.profile);
+            return;
+        };
+        // 已经移除的钱包在这个连接上仍然订阅着，它的账户不再计入
+        if !self.target_wallets.read().await.contains(owner) {
+            self.token_accounts.lock().await.remove(account);
+            return;
+        }
+        let decimals = match info["tokenAmount"]["decimals"].as_u64() {
+            Some(decimals) => {
+                self.mint_decimals.insert(mint, decimals as u8);
+                decimals as u8
+            }
+            None => self.mint_decimals.cached(mint),
+        };
+
+        self.token_accounts.lock().await.insert(account.to_string(), (owner.to_string(), mint.to_string(), amount));
+        debug!("[{}] Token account {} of {} now holds {}", self.profile, account, mint, amount);
+
+        let Some((on_chain, tracked)) = self.balance_mismatch(mint).await else {
+            self.balance_mismatches.lock().await.remove(mint);
+            return;
+        };
+        Metrics::inc(&self.metrics.balance_mismatches);
+        info!(
+            "[{}] On-chain balance of {} is {} but tracked amount is {}",
+            self.profile,
+            mint,
+            format_token_amount(on_chain, decimals),
+            tracked.map_or_else(|| "untracked".to_string(), |tracked| format_token_amount(tracked, decimals))
+        );
+        if self.balance_mismatches.lock().await.insert(mint.to_string(), false).is_none() {
+            let (monitor, mint) = (self.clone(), mint.to_string());
+            tokio::spawn(async move {
+                sleep(BALANCE_MISMATCH_GRACE).await;
+                monitor.confirm_balance_mismatch(&mint, decimals).await;
+            });
         }
-        
-        if let Some(holding) = holdings.get_mut(mint) {
-            // 先克隆需要的数据
-            let real_amount = holding.amount as f64 / 1e6;
-            let holding_info = holding.to_string();
-            
-            holding.current_price = price;
-            
-            // 检查是否需要发送通知
-            if let Err(e) = self.check_and_send_alert(mint, holding, &mut alerted_mints).await {
-                error!("Failed to send alert: {:?}", e);
+    }
+
+    /// 目标钱包在代币账户中的余额合计与跟踪的持仓数量不一致时返回 (链上数量, 跟踪的数量)。
+    /// 同一代币可能分布在多个钱包的账户中，按 mint 汇总
+    async fn balance_mismatch(&self, mint: &str) -> Option<(u64, Option<u64>)> {
+        let on_chain: u64 = {
+            let wallets = self.target_wallets.read().await;
+            self.token_accounts.lock().await.values()
+                .filter(|(owner, account_mint, _)| account_mint == mint && wallets.contains(owner))
+                .map(|(_, _, amount)| amount)
+                .sum()
+        };
+        let tracked = self.read_holdings().await.get(mint).map(|holding| holding.amount);
+        (tracked.unwrap_or(0) != on_chain && (tracked.is_some() || on_chain >= MIN_HOLDING_AMOUNT)).then_some((on_chain, tracked))
+    }
+
+    /// 不一致持续了 `BALANCE_MISMATCH_GRACE` 后发送警告，通常是钱包通过没有解码的渠道交易了这个代币。
+    /// 同一次不一致只提醒一次，余额重新一致后再次不一致时会再提醒
+    async fn confirm_balance_mismatch(&self, mint: &str, decimals: u8) {
+        let Some((on_chain, tracked)) = self.balance_mismatch(mint).await else {
+            self.balance_mismatches.lock().await.remove(mint);
+            return;
+        };
+        match self.balance_mismatches.lock().await.get_mut(mint) {
+            Some(alerted) if !*alerted => *alerted = true,
+            _ => return,
+        }
+        warn!("[{}] Balance of {} still differs from the tracked amount", self.profile, mint);
+        let message = format!(
+            "⚠️ Balance Mismatch\n\n\
+            Token: <a href=\"https://gmgn.ai/sol/token/{}\">{}</a>\n\
+            On-chain: {}\n\
+            Tracked: {}\n\n\
+            The target wallets may have traded it through a venue that is not decoded. \
+            Run <code>monitor reconcile --fix</code> to correct the holding.",
+            mint,
+            self.token_label(mint).await.unwrap_or_else(|| mint.to_string()),
+            format_token_amount(on_chain, decimals),
+            tracked.map_or_else(|| "untracked".to_string(), |tracked| format_token_amount(tracked, decimals))
+        );
+        if let Err(e) = self.alert_service.send_alert(&message, AlertType::Warning, Some(mint.to_string())).await {
+            error!("Failed to send balance mismatch warning: {:?}", e);
+        }
+    }
+
+    /// 解码 worker，按接收顺序处理分配给它的消息，连接断开后处理完剩余消息再退出
+    async fn run_worker(self, mut receiver: mpsc::Receiver<String>) {
+        while let Some(text) = receiver.recv().await {
+            self.metrics.worker_queue_depth.fetch_sub(1, Ordering::Relaxed);
+            self.handle_message(&text).await;
+            if let Some(slot) = Self::message_slot(&text) {
+                self.progress.lock().unwrap().complete(slot);
             }
-            
-            // 如果数量小于最小持仓量，从列表中移除
-            if real_amount < MIN_HOLDING_AMOUNT as f64 {
-                holdings.remove(mint);
-                alerted_mints.remove(mint);
-                
-                info!("\n🔔 Position Closed (Price Update) 🔔");
-                info!("{}", holding_info);
-                info!("====================");
+        }
+    }
+
+    async fn handle_message(&self, text: &str) {
+        let notification = match serde_json::from_str::<Notification>(text) {
+            Ok(notification) => notification,
+            Err(e) => {
+                debug!("Ignoring unparsable message: {}", e);
+                return;
+            }
+        };
+        // 订阅请求的回复，日志订阅确认后监控才算就绪
+        if let Some(id) = notification.id {
+            if notification.error.is_some() {
+                error!("[{}] Subscription request {} failed: {}", self.profile, id, text);
+            } else if id == LOGS_SUBSCRIPTION_ID {
+                info!("[{}] Log subscription confirmed", self.profile);
+                self.subscribed.send_replace(true);
+            } else if notification.result.is_some_and(|subscription| self.pool_subscriptions.lock().unwrap().confirm(id, subscription)) {
+                debug!("[{}] Raydium pool subscription {} confirmed", self.profile, id);
             } else {
-                debug!("\n=== 📊 Price Update ===");
-                debug!("{}", holding_info);
-                debug!("====================");
+                debug!("Received subscription confirmation");
+            }
+            return;
+        }
+        let Some(params) = notification.params else {
+            return;
+        };
+
+        let started = Instant::now();
+        let slot = params.result.context.map_or(0, |context| context.slot);
+        let pool = params.subscription.and_then(|subscription| self.pool_subscriptions.lock().unwrap().pool(subscription));
+        let value = &params.result.value;
+        match (notification.method.as_deref(), pool, &value.err) {
+            (Some("programNotification"), _, _) => self.handle_token_account_notification(value).await,
+            // 失败的交易整体回滚，日志中的事件没有生效，不能用来更新持仓和价格
+            (_, _, Some(err)) => {
+                Metrics::inc(&self.metrics.failed_transactions);
+                debug!("[{}] Skipping failed transaction {}: {}", self.profile, value.signature.as_deref().unwrap_or("unknown"), err);
             }
+            (_, Some(pool), None) => self.handle_raydium_notification(slot, &pool, value).await,
+            (_, None, None) => self.handle_logs_notification(slot, value).await,
         }
+        self.metrics.message_handling.observe(started.elapsed());
     }
 
-    async fn print_holdings(&self) {
-        let holdings = self.holdings.read().await;
-        let alerted_mints = self.alerted_mints.lock().await;
-        
-        // 打印所有持仓的详细信息
-        info!("\n=== Current Holdings Debug ===");
-        for (mint, holding) in holdings.iter() {
-            // SPL代币是6位小数
-            let real_amount = holding.amount as f64 / 1e6;
-            info!("Token {}: real_amount = {}, min_amount = {}", 
-                  mint, format_number_with_commas(real_amount), MIN_HOLDING_AMOUNT);
+    /// 消息所在的 slot，只查找第一个 `"slot":`（通知的 context），不解析 JSON
+    fn message_slot(text: &str) -> Option<u64> {
+        Self::json_number(text, "slot")
+    }
+
+    /// 分配 worker 用的键，同一代币的消息总是得到同一个键：Program data 中第一个 pump 事件的 mint，
+    /// 或第一个 PumpSwap 买卖事件的池（同一个池总是同一个代币）；Raydium 池订阅的通知使用池的代币，
+    /// 代币账户通知使用账户的 mint。不解析 JSON
+    fn route_key(&self, text: &str) -> Option<u64> {
+        if let Some(key) = Self::program_data_key(text) {
+            return Some(key);
         }
-        info!("============================\n");
-        
-        // 清理数量为 0 的持仓，考虑小数位
-        let to_remove: Vec<_> = holdings.iter()
-            .filter(|(_, holding)| {
-                let real_amount = holding.amount as f64 / 1e6;
-                real_amount < MIN_HOLDING_AMOUNT as f64
-            })
-            .map(|(mint, holding)| {
-                let real_amount = holding.amount as f64 / 1e6;
-                info!("Will remove token {} from holdings (real_amount: {})", 
-                     mint, format_number_with_commas(real_amount));
-                mint.clone()
-            })
-            .collect();
-        
-        // 释放读锁，获取写锁来删除
-        drop(holdings);
-        let mut holdings = self.holdings.write().await;
-        for mint in to_remove {
-            holdings.remove(&mint);
+        if text.contains("\"programNotification\"") {
+            return Self::json_string(text, "mint").and_then(Self::mint_key);
         }
-        
-        if !holdings.is_empty() {
-            print!("{}", CLEAR_SCREEN);  // 清屏
-            
-            // 打印标题和时间
-            let now = Local::now();
-            println!("\n{}📊 Sol Pump Monitor Holdings{}", BOLD, RESET);
-            println!("{}Last Update: {}{}\n", CYAN, now.format("%Y-%m-%d %H:%M:%S"), RESET);
-            
-            // 打印表头
-            println!("╔══════════════════╦════════════════╦════════════════╦════════════════╦════════════╗");
-            println!("║ {}{:^16}║ {:^14}║ {:^14}║ {:^14}║ {:^10}║{}",
-                    BOLD, "Token", "Amount", "Avg Price", "Price", "Change", RESET);
-            println!("╠══════════════════╬════════════════╬════════════════╬════════════════╬════════════╣");
-            
-            // 打印每个代币的信息
-            for holding in holdings.values() {
-                let price_change = holding.price_change_percentage();
-                println!("║ {:16}║ {:>14}║ {:>14}║ {:>14}║ {:>10}║",
-                    format!("{}{:16}{}", YELLOW, truncate_address(&holding.mint, 16), RESET),
-                    format_token_amount(holding.amount),
-                    format!("{} SOL", format_f64(holding.avg_price())),
-                    format!("{} SOL", format_f64(holding.current_price)),
-                    format_price_change(price_change)
-                );
+        let subscription = Self::json_number(text, "subscription")?;
+        let pool = self.pool_subscriptions.lock().unwrap().pool(subscription)?;
+        Self::mint_key(&pool.mint)
+    }
+
+    /// mint 的前 8 字节，和 TradeEvent 的键相同
+    fn mint_key(mint: &str) -> Option<u64> {
+        let mint = Pubkey::from_str(mint).ok()?.to_bytes();
+        Some(u64::from_le_bytes(mint[..8].try_into().unwrap()))
+    }
+
+    /// 消息中第一个 `"<field>":"..."` 的值
+    fn json_string<'a>(text: &'a str, field: &str) -> Option<&'a str> {
+        let pattern = format!("\"{}\":\"", field);
+        let start = text.find(&pattern)? + pattern.len();
+        let end = text[start..].find('"')?;
+        Some(&text[start..start + end])
+    }
+
+    /// 消息中第一个 `"<field>":<数字>` 的值
+    fn json_number(text: &str, field: &str) -> Option<u64> {
+        let pattern = format!("\"{}\":", field);
+        let start = text.find(&pattern)? + pattern.len();
+        let digits = text[start..].trim_start();
+        let end = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+        digits[..end].parse().ok()
+    }
+
+    /// Program data 中第一个 pump 事件或 PumpSwap 买卖事件的键。交易只解码开头，
+    /// 较少出现的创建和毕业事件完整解码
+    fn program_data_key(text: &str) -> Option<u64> {
+        const PREFIX: &str = "Program data: ";
+        const HEAD_CHARS: usize = 56;  // 42 字节（标识符和 mint）对应的 base64 长度
+        const SWAP_HEAD_CHARS: usize = 172;  // 129 字节，包含 PumpSwap 事件中池地址的前 8 字节
+        for (index, _) in text.match_indices(PREFIX) {
+            let start = index + PREFIX.len();
+            let Some(head) = text.get(start..start + HEAD_CHARS) else {
+                continue;
+            };
+            let mut bytes = [0u8; 42];
+            if general_purpose::STANDARD.decode_slice(head, &mut bytes).ok() != Some(bytes.len()) {
+                continue;
             }
-            println!("╚══════════════════╩════════════════╩════════════════╩════════════════╩════════════╝");
-            
-            // 打印总计
-            let total_value: f64 = holdings.values().map(|h| h.total_value()).sum();
-            let total_cost: f64 = holdings.values().map(|h| h.total_cost).sum();
-            let total_pnl = total_value - total_cost;
-            let total_pnl_percentage = if total_cost > 0.0 { (total_pnl / total_cost * 100.0) as i32 } else { 0 };
-            
-            println!("\n{}Portfolio Summary:{}", BOLD, RESET);
-            println!("Total Value: {} SOL", format_f64(total_value));
-            println!("Total Cost:  {} SOL", format_f64(total_cost));
-            println!("Total PnL:   {} SOL ({})", 
-                    format_f64(total_pnl),
-                    format_price_change(total_pnl_percentage));
+            if bytes[..8] == pump_events::TRADE_EVENT_DISCRIMINATOR {
+                return Some(u64::from_le_bytes(bytes[8..16].try_into().unwrap()));
+            }
+            if bytes[..8] == pump_events::CREATE_EVENT_DISCRIMINATOR || bytes[..8] == pump_events::COMPLETE_EVENT_DISCRIMINATOR {
+                let data = &text[start..];
+                let data = &data[..data.find('"').unwrap_or(data.len())];
+                let mint = match general_purpose::STANDARD.decode(data).ok().map(|data| pump_events::decode(&data)) {
+                    Some(Ok(PumpEvent::Create(token))) => token.mint,
+                    Some(Ok(PumpEvent::Complete(curve))) => curve.mint,
+                    _ => continue,
+                };
+                return Some(u64::from_le_bytes(mint.to_bytes()[..8].try_into().unwrap()));
+            }
+            if bytes[..8] == amm::BUY_EVENT_DISCRIMINATOR || bytes[..8] == amm::SELL_EVENT_DISCRIMINATOR {
+                let mut bytes = [0u8; 129];
+                let Some(head) = text.get(start..start + SWAP_HEAD_CHARS) else {
+                    continue;
+                };
+                if general_purpose::STANDARD.decode_slice(head, &mut bytes).ok() == Some(bytes.len()) {
+                    let pool = &bytes[amm::SWAP_POOL_OFFSET..amm::SWAP_POOL_OFFSET + 8];
+                    return Some(u64::from_le_bytes(pool.try_into().unwrap()));
+                }
+            }
+        }
+        None
+    }
+
+    /// 交易中的事件是否已经处理过，没有处理过时记为处理过。没有签名的通知无法判断，总是处理
+    fn is_duplicate(&self, signature: Option<&str>, index: usize) -> bool {
+        let Some(signature) = signature else {
+            return false;
+        };
+        if self.seen_events.lock().unwrap().insert(signature, index) {
+            return false;
         }
+        Metrics::inc(&self.metrics.duplicate_events);
+        debug!("[{}] Skipping already processed event {} of {}", self.profile, index, signature);
+        true
     }
 
-    pub async fn start_monitoring(&mut self) -> Result<()> {
-        // 启动持仓打印任务
-        let holdings_clone = self.holdings.clone();
-        tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(1));
-            loop {
-                interval.tick().await;
-                let monitor = WalletMonitor {
-                    target_wallet: Pubkey::from_str("ZDLFG5UNPzeNsEkacw9TdKHT1fBZCACfAQymjWnpcvg").unwrap(),
-                    holdings: holdings_clone.clone(),
-                    alerted_mints: Arc::new(Mutex::new(HashSet::new())),
-                    alert_service: AlertService::new(
-                        &env::var("TELEGRAM_BOT_TOKEN").expect("TELEGRAM_BOT_TOKEN must be set"),
-                        env::var("TELEGRAM_CHAT_ID")
-                            .expect("TELEGRAM_CHAT_ID must be set")
-                            .parse()
-                            .expect("TELEGRAM_CHAT_ID must be a valid integer"),
-                        env::var("TELEGRAM_TOPIC_ID")
-                            .ok()
-                            .and_then(|id| id.parse::<i32>().ok()),
-                        env::var("WS_ALERT_URL").ok()
-                    ),
-                    price_change_threshold: 5.0,
-                };
-                monitor.print_holdings().await;
+    /// 保存无法解码的原始数据并计数
+    fn quarantine_payload(&self, slot: u64, signature: &str, raw: &str, reason: String) {
+        Metrics::inc(&self.metrics.decode_failures);
+        if let Err(e) = self.quarantine.record(slot, signature, raw, reason) {
+            error!("[{}] Failed to quarantine payload: {:?}", self.profile, e);
+        }
+    }
+
+    /// 写入一条持仓审计记录
+    fn audit(&self, event: AuditEvent) {
+        if let Err(e) = self.audit.record(&event) {
+            error!("[{}] Failed to write audit log: {:?}", self.profile, e);
+        }
+    }
+
+    /// 订阅 pump 程序日志直到监控被关闭。连接断开时按指数退避重连并重新订阅，
+    /// 连续失败超过 `WS_RECONNECT_ATTEMPTS` 次时返回错误
+    pub async fn start_monitoring(&mut self) -> Result<(), MonitorError> {
+        if self.replica {
+            return self.serve_replica().await;
+        }
+        // 混沌测试回放录制的消息时连接本地的回放服务
+        let replay = match chaos::replay_file() {
+            Some(path) => Some(replay::serve(path, self.cancel.clone()).await.map_err(MonitorError::config)?),
+            None => None,
+        };
+        let ws_configured = self.endpoints.as_ref().is_some_and(|endpoints| endpoints.ws_url().is_some());
+        if replay.is_none() && !ws_configured && self.helius_keys.is_none() {
+            return Err(MonitorError::config("WS_URL or HELIUS_API_KEY must be set to stream pump program logs"));
+        }
+
+        // 消息分发到多个 worker 解码和处理，同一代币的交易总是进入同一个 worker，保证按顺序处理。
+        // worker 在重连期间保留，断线前已分配的消息不会丢失
+        let mut workers = Vec::with_capacity(self.decode_workers);
+        let mut worker_tasks = JoinSet::new();
+        for _ in 0..self.decode_workers {
+            let (sender, receiver) = mpsc::channel::<String>(WORKER_QUEUE_CAPACITY);
+            worker_tasks.spawn(self.clone().run_worker(receiver));
+            workers.push(sender);
+        }
+
+        if self.backfill_txs > 0 {
+            match self.backfill().await {
+                Ok(replayed) => info!("[{}] Backfill replayed {} historical trade(s)", self.profile, replayed),
+                Err(e) => warn!("[{}] Failed to backfill trade history: {}", self.profile, e),
+            }
+        }
+
+        // 链上余额已经包含错过的交易，补处理之后再同步，避免同一笔交易计入两次
+        if self.startup_sync && self.rpc.is_some() {
+            let monitor = self.clone();
+            tokio::spawn(async move {
+                let mut resumed = monitor.resumed.subscribe();
+                tokio::select! {
+                    _ = monitor.cancel.cancelled() => return,
+                    _ = resumed.wait_for(|resumed| *resumed) => {}
+                }
+                match monitor.sync_holdings().await {
+                    Ok(synced) => info!("[{}] Startup sync added {} untracked holding(s)", monitor.profile, synced),
+                    Err(e) => warn!("[{}] Failed to sync holdings from chain: {}", monitor.profile, e),
+                }
+            });
+        }
+
+        // 恢复的持仓在后台查询元数据，并推导迁移池
+        for mint in self.read_holdings().await.keys() {
+            self.prefetch_metadata(mint);
+            self.amm_pools.derive(mint);
+        }
+
+        let mut backoff = Backoff::new(Duration::from_secs(1), self.ws_reconnect_max);
+        let mut failures = 0;
+        let result = loop {
+            // 每次连接都重新选择端点：回放服务，最快的 WS_URL，或者没有被限流的 Helius key
+            let configured = replay
+                .as_ref()
+                .map(Url::to_string)
+                .or_else(|| self.endpoints.as_ref().and_then(|endpoints| endpoints.ws_url()));
+            let (key, ws_url) = match (configured, &self.helius_keys) {
+                (Some(ws_url), _) => (None, ws_url),
+                (None, Some(keys)) => {
+                    let (index, ws_url) = keys.ws_url();
+                    (Some(index), ws_url)
+                }
+                (None, None) => {
+                    break Err(MonitorError::config("WS_URL or HELIUS_API_KEY must be set to stream pump program logs"))
+                }
+            };
+            let url = match Url::parse(&ws_url) {
+                Ok(url) => url,
+                Err(e) => break Err(MonitorError::config(e)),
+            };
+            let session = self.stream_logs(&url, &workers).await;
+            self.subscribed.send_replace(false);
+            let reason = match session {
+                Ok(SessionEnd::Cancelled) => break Ok(()),
+                Ok(SessionEnd::WorkersStopped) => break Err(MonitorError::ws("Decode worker stopped")),
+                Ok(SessionEnd::Disconnected { received }) => {
+                    // 收到过消息说明连接曾经正常，重新计算退避
+                    if received {
+                        backoff.reset();
+                        failures = 0;
+                    }
+                    "connection closed".to_string()
+                }
+                Err(e) => {
+                    // 握手被限流或拒绝时换下一个 key，新 key 不需要按旧 key 的失败次数退避
+                    let limit = match e.downcast_ref::<tungstenite::Error>() {
+                        Some(tungstenite::Error::Http(response)) => Limit::from_status(response.status().as_u16()),
+                        _ => None,
+                    };
+                    if let (Some(index), Some(keys), Some(limit)) = (key, &self.helius_keys, limit) {
+                        if keys.report(index, limit) {
+                            backoff.reset();
+                        }
+                    }
+                    format!("{:?}", e)
+                }
+            };
+            if self.cancel.is_cancelled() {
+                break Ok(());
+            }
+
+            failures += 1;
+            if self.ws_reconnect_attempts.is_some_and(|attempts| failures > attempts) {
+                break Err(MonitorError::ws(format!("WebSocket failed {} times in a row, last error: {}", failures, reason)));
             }
+            let delay = backoff.next_delay();
+            warn!("[{}] WebSocket disconnected ({}), reconnecting in {}ms", self.profile, reason, delay.as_millis());
+            // 等待重连时主循环没有卡住，记为等待结束的时间
+            self.alive_at.store(Utc::now().timestamp_millis() + delay.as_millis() as i64, Ordering::Relaxed);
+            Metrics::inc(&self.metrics.ws_reconnects);
+            tokio::select! {
+                _ = self.cancel.cancelled() => break Ok(()),
+                _ = sleep(delay) => {}
+            }
+        };
+
+        // 关闭 worker 的队列，等待它们处理完已分配的消息
+        drop(workers);
+        while worker_tasks.join_next().await.is_some() {}
+        result
+    }
+
+    /// 只读副本不订阅日志，持仓由 `run_state_sync` 从共享存储读取。运行到监控被关闭，期间更新看门狗使用的时间
+    async fn serve_replica(&self) -> Result<(), MonitorError> {
+        info!("[{}] Running as a read-only replica, holdings are read from {}", self.profile, self.store.describe());
+        self.subscribed.send_replace(true);
+        let mut ticker = interval(STATE_SYNC_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = self.cancel.cancelled() => return Ok(()),
+                _ = ticker.tick() => self.alive_at.store(Utc::now().timestamp_millis(), Ordering::Relaxed),
+            }
+        }
+    }
+
+    /// Raydium 池的日志订阅请求，记录请求 ID 以便确认后按订阅 ID 找到池
+    fn subscribe_pool(&self, request_id: u64, pool: RaydiumPool) -> String {
+        info!("[{}] Subscribing to Raydium pool {} of {}", self.profile, pool.address, pool.mint);
+        let subscribe_msg = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": "logsSubscribe",
+            "params": [
+                {
+                    "mentions": [pool.address]
+                },
+                {
+                    "commitment": self.commitment.commitment.to_string()
+                }
+            ]
+        });
+        self.pool_subscriptions.lock().unwrap().request(request_id, pool);
+        subscribe_msg.to_string()
+    }
+
+    /// 订阅钱包在一个代币程序下的代币账户（owner 位于账户数据偏移 32 处）
+    fn subscribe_token_accounts(&self, request_id: u64, program_id: &str, wallet: &str) -> String {
+        let subscribe_msg = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": "programSubscribe",
+            "params": [
+                program_id,
+                {
+                    "commitment": self.commitment.commitment.to_string(),
+                    "encoding": "jsonParsed",
+                    "filters": [{ "memcmp": { "offset": 32, "bytes": wallet } }]
+                }
+            ]
         });
+        subscribe_msg.to_string()
+    }
 
+    /// 建立一次 WebSocket 连接并订阅，读取消息直到连接断开或监控被关闭
+    async fn stream_logs(&mut self, url: &Url, workers: &[mpsc::Sender<String>]) -> Result<SessionEnd> {
         // 连接 Helius WebSocket
-        let ws_url = format!(
-            "wss://mainnet.helius-rpc.com/?api-key={}",
-            env::var("HELIUS_API_KEY")?
-        );
-        let url = Url::parse(&ws_url)?;
-        let (ws_stream, _) = connect_async(url).await?;
+        let (ws_stream, _) = connect_async(url.clone()).await?;
         let (mut write, mut read) = ws_stream.split();
+        let commitment = self.commitment.commitment.to_string();
 
         // 订阅 pump 程序的日志
         let subscribe_msg = serde_json::json!({
             "jsonrpc": "2.0",
-            "id": 1,
+            "id": LOGS_SUBSCRIPTION_ID,
             "method": "logsSubscribe",
             "params": [
                 {
                     "mentions": [PUMP_PROGRAM_ID]
                 },
                 {
-                    "commitment": "confirmed",
+                    "commitment": commitment,
+                    "encoding": "jsonParsed"
+                }
+            ]
+        });
+        write.send(Message::Text(subscribe_msg.to_string())).await?;
+
+        // 毕业到 PumpSwap 的代币在这里继续交易
+        let subscribe_msg = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": AMM_LOGS_SUBSCRIPTION_ID,
+            "method": "logsSubscribe",
+            "params": [
+                {
+                    "mentions": [PUMP_AMM_PROGRAM_ID]
+                },
+                {
+                    "commitment": commitment,
                     "encoding": "jsonParsed"
                 }
             ]
         });
         write.send(Message::Text(subscribe_msg.to_string())).await?;
+        let mut request_id = AMM_LOGS_SUBSCRIPTION_ID + 1;
+
+        // 订阅目标钱包的代币账户，用于发现未解码渠道的余额变动。之后添加的钱包在收到通知时在这个连接上订阅
+        let mut account_wallets: HashSet<String> = HashSet::new();
+        if self.track_token_accounts {
+            let wallets: Vec<String> = self.target_wallets.read().await.iter().cloned().collect();
+            for wallet in wallets {
+                for program_id in TOKEN_PROGRAM_IDS {
+                    write.send(Message::Text(self.subscribe_token_accounts(request_id, program_id, &wallet))).await?;
+                    request_id += 1;
+                }
+                account_wallets.insert(wallet);
+            }
+            info!("[{}] Subscribed to token accounts of {} wallet(s)", self.profile, account_wallets.len());
+        }
+
+        // 迁移到 Raydium 的持仓代币：交换日志中没有代币地址，按池分别订阅。已知的池立即订阅，
+        // 其余的代币在后台查找，找到后在这个连接上订阅，不拖慢连接
+        self.pool_subscriptions.lock().unwrap().clear();
+        let held: Vec<String> = self.read_holdings().await.keys().cloned().collect();
+        let mut unknown = Vec::new();
+        for mint in held {
+            match self.amm_pools.known_raydium_pool(&mint) {
+                Some(Some(pool)) => {
+                    write.send(Message::Text(self.subscribe_pool(request_id, pool))).await?;
+                    request_id += 1;
+                }
+                Some(None) => {}
+                None => unknown.push(mint),
+            }
+        }
+        let (pool_sender, mut pool_receiver) = mpsc::unbounded_channel();
+        if !unknown.is_empty() {
+            let amm_pools = self.amm_pools.clone();
+            let cancel = self.cancel.clone();
+            tokio::spawn(async move {
+                for mint in unknown {
+                    let pool = tokio::select! {
+                        _ = cancel.cancelled() => return,
+                        pool = amm_pools.raydium_pool(&mint) => pool,
+                    };
+                    // 连接已经断开时停止，下次连接重新查找
+                    if pool.is_some_and(|pool| pool_sender.send(pool).is_err()) {
+                        return;
+                    }
+                }
+            });
+        }
+
+        info!("[{}] Started monitoring PUMP program ({} commitment)", self.profile, commitment);
+
+        let mut next_worker = 0;
+        let mut received = false;
+        // 上次处理到的位置（重启前保存的或者断线前的），收到第一条通知后补处理之间错过的交易
+        let mut resume_after = self.progress.lock().unwrap().watermark();
+        if resume_after.is_none() {
+            self.resumed.send_replace(true);
+        }
 
-        info!("Started monitoring PUMP program");
+        // 处理 WebSocket 消息，监控被关闭时主动关闭连接
+        loop {
+            let msg = tokio::select! {
+                _ = self.cancel.cancelled() => {
+                    info!("[{}] Stopping PUMP program monitor", self.profile);
+                    if let Err(e) = write.send(Message::Close(None)).await {
+                        debug!("[{}] Failed to close WebSocket: {}", self.profile, e);
+                    }
+                    return Ok(SessionEnd::Cancelled);
+                }
+                Some(pool) = pool_receiver.recv() => {
+                    write.send(Message::Text(self.subscribe_pool(request_id, pool))).await?;
+                    request_id += 1;
+                    continue;
+                }
+                _ = self.wallets_changed.notified(), if self.track_token_accounts => {
+                    let added: Vec<String> = self.target_wallets.read().await.difference(&account_wallets).cloned().collect();
+                    for wallet in added {
+                        for program_id in TOKEN_PROGRAM_IDS {
+                            write.send(Message::Text(self.subscribe_token_accounts(request_id, program_id, &wallet))).await?;
+                            request_id += 1;
+                        }
+                        info!("[{}] Subscribed to token accounts of added wallet {}", self.profile, wallet);
+                        account_wallets.insert(wallet);
+                    }
+                    continue;
+                }
+                msg = read.next() => msg,
+            };
+            let Some(msg) = msg else {
+                break;
+            };
+            self.alive_at.store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+            received = true;
+            // 注入的断线和真实的断线一样关闭连接，走同样的重连、补处理和去重
+            if chaos::should_disconnect() {
+                warn!("[{}] Chaos: injecting WebSocket disconnect", self.profile);
+                if let Err(e) = write.send(Message::Close(None)).await {
+                    debug!("[{}] Failed to close WebSocket: {}", self.profile, e);
+                }
+                break;
+            }
+            chaos::delay().await;
 
-        // 处理 WebSocket 消息
-        while let Some(msg) = read.next().await {
             match msg {
                 Ok(Message::Text(text)) => {
                     debug!("Received message: {}", text);
-                    
-                    if let Ok(json) = serde_json::from_str::<Value>(&text) {
-                        // 跳过订阅确认消息
-                        if json.get("id").is_some() {
-                            debug!("Received subscription confirmation");
-                            continue;
-                        }
-
-                        // 解析交易详情
-                        if let Some(value) = json.get("params")
-                            .and_then(|p| p.get("result"))
-                            .and_then(|r| r.get("value")) 
-                        {
-                            // 获取交易签名
-                            let signature = value.get("signature")
-                                .and_then(|s| s.as_str())
-                                .unwrap_or("unknown");
-                            
-                            let mut instruction_type = "Unknown";
-                            let mut found_target_wallet = false;
-                            let mut mint_address = String::new();
-                            let mut price = 0.0;
-                            let mut is_buy = false;
-                            let mut token_amount = 0;
-                            
-                            // 检查日志
-                            if let Some(logs) = value.get("logs").and_then(|l| l.as_array()) {
-                                for log in logs {
-                                    if let Some(log_str) = log.as_str() {
-                                        debug!("Log: {}", log_str);
-                                        
-                                        // 检查指令类型
-                                        if log_str.contains("Instruction: ") {
-                                            instruction_type = log_str.split("Instruction: ").nth(1).unwrap_or("Unknown");
-                                        }
-                                        
-                                        // 解析 Program data
-                                        if log_str.contains("Program data: ") {
-                                            if let Some(data_str) = log_str.split("Program data: ").nth(1) {
-                                                if let Some((mint, user, trade_is_buy, sol_amount, trade_token_amount)) = self.decode_program_data(data_str) {
-                                                    debug!("Decoded user: {}, is_buy: {}", user, trade_is_buy);
-                                                    
-                                                    // 计算价格
-                                                    let trade_price = Self::calculate_price(sol_amount, trade_token_amount);
-                                                    
-                                                    // 如果是目标钱包的交易
-                                                    if user == self.target_wallet.to_string() {
-                                                        found_target_wallet = true;
-                                                        mint_address = mint;
-                                                        is_buy = trade_is_buy;
-                                                        token_amount = trade_token_amount;
-                                                        price = trade_price;
-                                                    } else {
-                                                        // 如果不是目标钱包的交易，检查是否需要更新价格
-                                                        let holdings = self.holdings.read().await;
-                                                        if holdings.contains_key(&mint) {
-                                                            drop(holdings); // 释放锁
-                                                            self.update_price(&mint, trade_price).await;
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
+                    if let Some(recorder) = &self.ws_record {
+                        recorder.record(&text);
+                    }
+                    let slot = Self::message_slot(&text);
+                    if let Some(slot) = slot {
+                        match resume_after.take() {
+                            Some(after) if slot > after + 1 => {
+                                self.progress.lock().unwrap().dispatch(after + 1);
+                                // 在分配这条消息之前加一，之后的实时交易都等待补处理
+                                let running = CatchUpGuard::start(&self.catching_up);
+                                let monitor = self.clone();
+                                let cancel = self.cancel.clone();
+                                tokio::spawn(async move {
+                                    tokio::select! {
+                                        _ = cancel.cancelled() => {}
+                                        _ = monitor.catch_up(after, slot, running) => {}
                                     }
-                                }
+                                });
                             }
-                            
-                            // 只有在找到目标钱包时才更新和打印信息
-                            if found_target_wallet {
-                                // 更新持仓信息
-                                self.update_holdings(mint_address.clone(), is_buy, token_amount, price).await;
-
-                                debug!("Found interaction with target wallet!");
-                                debug!("Transaction: https://solscan.io/tx/{}", signature);
-                                debug!("Instruction Type: {}", instruction_type);
-                                debug!("Mint: {}", mint_address);
-                                debug!("Action: {}", if is_buy { "Buy" } else { "Sell" });
-                                debug!("Amount: {} tokens", token_amount);
-                                debug!("Price: {} SOL/token", price);
-                                debug!("-----------------------------------");
+                            Some(_) => {
+                                self.resumed.send_replace(true);
                             }
+                            None => {}
+                        }
+                        self.progress.lock().unwrap().dispatch(slot);
+                    }
+                    
+                    // 消息按代币分配，保证同一代币按顺序处理；找不到代币的消息（例如订阅确认）轮流分配。
+                    // worker 队列满时都等待，不丢弃任何消息
+                    let index = match self.route_key(&text) {
+                        Some(key) => (key % workers.len() as u64) as usize,
+                        None => {
+                            next_worker = (next_worker + 1) % workers.len();
+                            next_worker
                         }
+                    };
+                    self.metrics.worker_queue_depth.fetch_add(1, Ordering::Relaxed);
+                    let sent = workers[index].send(text).await.is_ok();
+                    if !sent {
+                        error!("[{}] Decode worker stopped", self.profile);
+                        return Ok(SessionEnd::WorkersStopped);
                     }
                 }
                 Ok(Message::Binary(data)) => {
@@ -644,6 +4441,112 @@ impl WalletMonitor {
             }
         }
 
-        Ok(())
+        Ok(SessionEnd::Disconnected { received })
+    }
+}
+
+/// 一次 WebSocket 连接结束的原因
+enum SessionEnd {
+    Cancelled,  // 监控被关闭
+    Disconnected { received: bool },  // 连接断开，`received` 表示断开前是否收到过消息
+    WorkersStopped,  // 解码 worker 异常退出，重连也无法恢复
+}
+
+
+/// 以代码方式构造 WalletMonitor，未设置的项使用与环境变量相同的默认值。`WalletMonitor::new` 也通过它构造
+pub struct WalletMonitorBuilder {
+    config: ProfileConfig,
+    alert_service: Option<AlertService>,
+}
+
+#[allow(dead_code)]  // 设置各项的方法供嵌入监控的调用方使用，命令行程序通过环境变量构造
+impl WalletMonitorBuilder {
+    pub fn new(profile: &str) -> Self {
+        Self::from_config(ProfileConfig::with_defaults(profile))
+    }
+
+    /// 在已有配置（例如 `ProfileConfig::from_env`）的基础上修改
+    pub fn from_config(config: ProfileConfig) -> Self {
+        Self {
+            config,
+            alert_service: None,
+        }
+    }
+
+    pub fn wallets<I, S>(mut self, wallets: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.wallets = wallets.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn price_change_threshold(mut self, threshold: f64) -> Self {
+        self.config.price_change_thresholds = vec![threshold];
+        self
+    }
+
+    /// 多个提醒档位，每档对每个仓位提醒一次
+    pub fn price_change_thresholds(mut self, thresholds: Vec<f64>) -> Self {
+        self.config.price_change_thresholds = thresholds;
+        self
+    }
+
+    /// 日志订阅的 WebSocket 端点
+    pub fn ws_url(mut self, url: &str) -> Self {
+        self.config.ws_urls = vec![url.to_string()];
+        self
+    }
+
+    /// 对账、价格来源和 enricher 使用的 HTTP RPC 端点
+    pub fn rpc_url(mut self, url: &str) -> Self {
+        self.config.rpc_urls = vec![url.to_string()];
+        self
+    }
+
+    pub fn data_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.data_dir = dir.into();
+        self
+    }
+
+    pub fn rules(mut self, rules: Vec<AlertRule>) -> Self {
+        self.config.rules = rules;
+        self
+    }
+
+    pub fn tag_rules(mut self, rules: Vec<TagRule>) -> Self {
+        self.config.tag_rules = rules;
+        self
+    }
+
+    pub fn oracles(mut self, display: OracleKind, alerts: OracleKind) -> Self {
+        self.config.display_oracle = display;
+        self.config.alert_oracle = alerts;
+        self
+    }
+
+    pub fn base_currency(mut self, currency: BaseCurrency) -> Self {
+        self.config.base_currency = currency;
+        self
+    }
+
+    pub fn decode_workers(mut self, workers: usize) -> Self {
+        self.config.decode_workers = workers.max(1);
+        self
+    }
+
+    pub fn alert_service(mut self, alert_service: AlertService) -> Self {
+        self.alert_service = Some(alert_service);
+        self
+    }
+
+    /// 创建数据目录并升级到当前格式后构造，已经是当前格式时不做修改
+    pub async fn build(self) -> Result<WalletMonitor, MonitorError> {
+        let alert_service = self.alert_service
+            .ok_or_else(|| MonitorError::config("An alert service is required, see AlertServiceBuilder"))?;
+        tokio::fs::create_dir_all(&self.config.data_dir).await.map_err(MonitorError::config)?;
+        migrations::apply(&self.config.data_dir).map_err(MonitorError::config)?;
+        WalletMonitor::with_config(&self.config, alert_service).await
     }
 }
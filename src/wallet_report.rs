@@ -0,0 +1,491 @@
+use crate::{
+    activity::{format_duration, to_sol},
+    address_book::AddressBook,
+    journal::{JournalEntry, SandwichRecord, TradeAnnotation},
+    mints::to_tokens,
+    wallet_monitor::{format_f64, format_signed_sol, truncate_address},
+};
+use chrono::{Local, TimeZone};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+};
+
+// 入场时间分布的区间上限（秒），最后一个区间没有上限
+const TIMING_BUCKETS: [(i64, &str); 5] = [
+    (10, "< 10s"),
+    (60, "10s - 1m"),
+    (300, "1m - 5m"),
+    (1800, "5m - 30m"),
+    (7200, "30m - 2h"),
+];
+const SNIPER_WINDOW_SECS: i64 = 60;
+const SNIPER_SHARE: f64 = 0.5;  // 超过一半的买入在代币创建一分钟内视为狙击
+const WASH_MAX_GAP_SECS: i64 = 30;  // 相邻两笔交易的最大间隔
+const WASH_MIN_TRADES: usize = 4;   // 至少两个来回
+const WASH_MAX_NET_SHARE: f64 = 0.2;  // 买卖数量之差不超过成交量的 20%，即基本没有建仓或出货
+
+/// 单个钱包在交易日志中的盈亏
+#[derive(Debug, Default)]
+pub struct WalletPnl {
+    pub trades: usize,
+    pub mints: BTreeSet<String>,
+    pub invested: f64,      // 买入花费的 SOL
+    pub realized_pnl: f64,  // 卖出部分按平均成本计算的盈亏
+    pub open_value: f64,    // 未卖出部分按日志中最后成交价计算的价值
+    pub open_cost: f64,
+}
+
+impl WalletPnl {
+    pub fn total_pnl(&self) -> f64 {
+        self.realized_pnl + self.open_value - self.open_cost
+    }
+}
+
+/// 两个钱包的对比
+#[derive(Debug)]
+pub struct Comparison {
+    pub wallet_a: String,
+    pub wallet_b: String,
+    pub pnl_a: WalletPnl,
+    pub pnl_b: WalletPnl,
+    pub shared_mints: usize,
+    pub bought_by_both: usize,
+    pub a_first: usize,  // 两者都买过的代币中 A 先买入的数量
+    pub b_first: usize,
+    pub median_lead_secs: Option<i64>,  // A 相对 B 的首次买入提前量中位数，负数表示 B 更早
+}
+
+/// 按平均成本法计算钱包的盈亏。日志中没有对应买入的卖出没有成本，不计入盈亏
+pub fn wallet_pnl(entries: &[JournalEntry], wallet: &str) -> WalletPnl {
+    let mut last_prices: HashMap<&str, (f64, u8)> = HashMap::new();  // mint -> (最新价格, 小数位数)
+    let mut positions: HashMap<&str, (u64, f64)> = HashMap::new();  // mint -> (数量, 成本)
+    let mut pnl = WalletPnl::default();
+
+    for entry in entries {
+        last_prices.insert(&entry.mint, (entry.price, entry.decimals));
+        if entry.wallet != wallet {
+            continue;
+        }
+        pnl.trades += 1;
+        pnl.mints.insert(entry.mint.clone());
+        let sol = to_sol(entry.sol_amount);
+        let (amount, cost) = positions.entry(&entry.mint).or_default();
+        if entry.is_buy {
+            pnl.invested += sol;
+            *amount = amount.saturating_add(entry.token_amount);
+            *cost += sol;
+        } else if *amount > 0 {
+            let ratio = (entry.token_amount as f64 / *amount as f64).min(1.0);
+            pnl.realized_pnl += sol - *cost * ratio;
+            *cost *= 1.0 - ratio;
+            *amount = amount.saturating_sub(entry.token_amount);
+        }
+    }
+
+    for (mint, (amount, cost)) in positions {
+        if amount == 0 {
+            continue;
+        }
+        if let Some((price, decimals)) = last_prices.get(mint) {
+            pnl.open_value += to_tokens(amount, *decimals) * price;
+        }
+        pnl.open_cost += cost;
+    }
+    pnl
+}
+
+/// 每个代币的首次买入时间
+fn first_buys<'a>(entries: &'a [JournalEntry], wallet: &str) -> HashMap<&'a str, i64> {
+    let mut first_buys = HashMap::new();
+    for entry in entries.iter().filter(|entry| entry.is_buy && entry.wallet == wallet) {
+        first_buys.entry(entry.mint.as_str()).or_insert(entry.timestamp);
+    }
+    first_buys
+}
+
+/// 对比两个钱包交易的代币、买入先后和盈亏，用于决定跟哪个钱包
+pub fn compare(entries: &[JournalEntry], wallet_a: &str, wallet_b: &str) -> Comparison {
+    let (buys_a, buys_b) = (first_buys(entries, wallet_a), first_buys(entries, wallet_b));
+    let mut leads: Vec<i64> = buys_a
+        .iter()
+        .filter_map(|(mint, at_a)| buys_b.get(mint).map(|at_b| at_b - at_a))
+        .collect();
+    leads.sort_unstable();
+
+    let pnl_a = wallet_pnl(entries, wallet_a);
+    let pnl_b = wallet_pnl(entries, wallet_b);
+    Comparison {
+        wallet_a: wallet_a.to_string(),
+        wallet_b: wallet_b.to_string(),
+        shared_mints: pnl_a.mints.intersection(&pnl_b.mints).count(),
+        bought_by_both: leads.len(),
+        a_first: leads.iter().filter(|lead| **lead > 0).count(),
+        b_first: leads.iter().filter(|lead| **lead < 0).count(),
+        median_lead_secs: leads.get(leads.len() / 2).copied(),
+        pnl_a,
+        pnl_b,
+    }
+}
+
+impl Comparison {
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+        let _ = writeln!(output, "A: {}\nB: {}\n", self.wallet_a, self.wallet_b);
+        let _ = writeln!(output, "{:<24} {:>16} {:>16}", "", "A", "B");
+        let mut row = |label: &str, value: fn(&WalletPnl) -> String| {
+            let _ = writeln!(output, "{:<24} {:>16} {:>16}", label, value(&self.pnl_a), value(&self.pnl_b));
+        };
+        row("Trades", |pnl| pnl.trades.to_string());
+        row("Tokens traded", |pnl| pnl.mints.len().to_string());
+        row("Invested (SOL)", |pnl| format_f64(pnl.invested));
+        row("Realized PnL (SOL)", |pnl| format_signed_sol(pnl.realized_pnl));
+        row("Open value (SOL)", |pnl| format_f64(pnl.open_value));
+        row("Unrealized PnL (SOL)", |pnl| format_signed_sol(pnl.open_value - pnl.open_cost));
+        row("Total PnL (SOL)", |pnl| format_signed_sol(pnl.total_pnl()));
+
+        let _ = writeln!(
+            output,
+            "\nShared tokens: {} ({} only A, {} only B)",
+            self.shared_mints,
+            self.pnl_a.mints.len() - self.shared_mints,
+            self.pnl_b.mints.len() - self.shared_mints
+        );
+        match self.median_lead_secs {
+            Some(lead) => {
+                let _ = writeln!(
+                    output,
+                    "Bought by both: {} (A first in {}, B first in {}, median lead of A: {}s)",
+                    self.bought_by_both, self.a_first, self.b_first, lead
+                );
+            }
+            None => {
+                let _ = writeln!(output, "No token was bought by both wallets");
+            }
+        }
+        output
+    }
+}
+
+/// 钱包买入时距代币创建的时间分布
+#[derive(Debug)]
+pub struct EntryTiming {
+    pub wallet: String,
+    pub buys: usize,
+    pub unknown: usize,  // 代币在监控启动前创建，无法得知入场时间
+    pub buckets: Vec<(&'static str, usize)>,
+    pub median_secs: Option<i64>,
+    pub within_first_minute: usize,
+}
+
+impl EntryTiming {
+    /// 已知入场时间的买入中超过一半在代币创建一分钟内
+    pub fn is_sniper(&self) -> bool {
+        let known = self.buys - self.unknown;
+        known > 0 && self.within_first_minute as f64 / known as f64 > SNIPER_SHARE
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut output = String::new();
+        let known = self.buys - self.unknown;
+        let _ = writeln!(output, "{}", self.wallet);
+        let _ = writeln!(output, "Buys: {} ({} with unknown launch time)", self.buys, self.unknown);
+        if known == 0 {
+            return output;
+        }
+        for (label, count) in &self.buckets {
+            let share = *count as f64 / known as f64 * 100.0;
+            let _ = writeln!(output, "  {:<10} {:>6} {:>5.1}% {}", label, count, share, "#".repeat((share / 2.0).round() as usize));
+        }
+        if let Some(median) = self.median_secs {
+            let median = if median < 60 { format!("{}s", median) } else { format_duration(median) };
+            let _ = writeln!(output, "Median entry: {} after launch", median);
+        }
+        let _ = writeln!(
+            output,
+            "Within the first minute: {} of {}{}",
+            self.within_first_minute,
+            known,
+            if self.is_sniper() { " (sniper)" } else { "" }
+        );
+        output
+    }
+}
+
+/// 统计钱包每笔买入距代币创建的时间
+pub fn entry_timing(entries: &[JournalEntry], wallet: &str) -> EntryTiming {
+    let buys: Vec<&JournalEntry> = entries.iter().filter(|entry| entry.is_buy && entry.wallet == wallet).collect();
+    let mut ages: Vec<i64> = buys.iter().filter_map(|entry| entry.token_age_secs).collect();
+    ages.sort_unstable();
+
+    let mut buckets: Vec<(&'static str, usize)> = TIMING_BUCKETS.iter().map(|(_, label)| (*label, 0)).collect();
+    buckets.push(("> 2h", 0));
+    for age in &ages {
+        let index = TIMING_BUCKETS.iter().position(|(bound, _)| age < bound).unwrap_or(TIMING_BUCKETS.len());
+        buckets[index].1 += 1;
+    }
+
+    EntryTiming {
+        wallet: wallet.to_string(),
+        buys: buys.len(),
+        unknown: buys.len() - ages.len(),
+        buckets,
+        median_secs: ages.get(ages.len() / 2).copied(),
+        within_first_minute: ages.iter().filter(|age| **age < SNIPER_WINDOW_SECS).count(),
+    }
+}
+
+/// 交易日志中出现过的钱包，按首次出现的顺序
+pub fn wallets(entries: &[JournalEntry]) -> Vec<String> {
+    let mut wallets: Vec<String> = Vec::new();
+    for entry in entries {
+        if !wallets.contains(&entry.wallet) {
+            wallets.push(entry.wallet.clone());
+        }
+    }
+    wallets
+}
+
+/// `since` 之后各目标钱包按总盈亏的排行（HTML），只统计这段时间内的交易：之前买入的代币卖出时没有成本，不计入盈亏
+pub fn leaderboard(entries: &[JournalEntry], wallets: &[String], since: i64, days: u64, address_book: &AddressBook) -> String {
+    let recent: Vec<JournalEntry> = entries.iter().filter(|entry| entry.timestamp >= since).cloned().collect();
+    let mut ranking: Vec<(&String, WalletPnl)> = wallets
+        .iter()
+        .map(|wallet| (wallet, wallet_pnl(&recent, wallet)))
+        .filter(|(_, pnl)| pnl.trades > 0)
+        .collect();
+    ranking.sort_by(|(_, a), (_, b)| b.total_pnl().total_cmp(&a.total_pnl()));
+
+    let mut output = format!("🏆 Wallet Leaderboard ({} days)\n", days);
+    if ranking.is_empty() {
+        output.push_str("\nNo trades by the target wallets");
+    }
+    for (rank, (wallet, pnl)) in ranking.iter().enumerate() {
+        let _ = write!(
+            output,
+            "\n{}. {}\nPnL: {} SOL (realized {} SOL) · {} trade(s) in {} token(s)",
+            rank + 1,
+            address_book.display(wallet),
+            format_signed_sol(pnl.total_pnl()),
+            format_signed_sol(pnl.realized_pnl),
+            pnl.trades,
+            pnl.mints.len()
+        );
+    }
+    output
+}
+
+/// 一段疑似对敲：同一钱包或关联钱包组在同一代币上短时间内交替买卖
+#[derive(Debug)]
+pub struct WashEpisode {
+    pub mint: String,
+    pub wallets: BTreeSet<String>,
+    pub trades: usize,
+    pub duration_secs: i64,
+    pub volume: f64,  // 买卖总额（SOL）
+}
+
+/// 钱包所在的关联钱包组，不在任何组中时只有钱包自己
+pub fn cluster_of<'a>(clusters: &'a BTreeMap<String, Vec<String>>, wallet: &str) -> Option<(&'a str, &'a [String])> {
+    clusters
+        .iter()
+        .find(|(_, members)| members.iter().any(|member| member == wallet))
+        .map(|(name, members)| (name.as_str(), members.as_slice()))
+}
+
+/// 检测一组钱包的对敲：相邻交易间隔不超过 30 秒、买卖交替至少 4 笔，且买卖数量基本抵消
+pub fn wash_trades(entries: &[JournalEntry], wallets: &[String]) -> Vec<WashEpisode> {
+    let mut by_mint: BTreeMap<&str, Vec<&JournalEntry>> = BTreeMap::new();
+    for entry in entries.iter().filter(|entry| wallets.contains(&entry.wallet)) {
+        by_mint.entry(&entry.mint).or_default().push(entry);
+    }
+
+    let mut episodes = Vec::new();
+    for (mint, mut trades) in by_mint {
+        trades.sort_by_key(|entry| entry.timestamp);
+        let mut start = 0;
+        for end in 1..=trades.len() {
+            let continues = end < trades.len()
+                && trades[end].is_buy != trades[end - 1].is_buy
+                && trades[end].timestamp - trades[end - 1].timestamp <= WASH_MAX_GAP_SECS;
+            if continues {
+                continue;
+            }
+            if let Some(episode) = wash_episode(mint, &trades[start..end]) {
+                episodes.push(episode);
+            }
+            start = end;
+        }
+    }
+    episodes
+}
+
+fn wash_episode(mint: &str, run: &[&JournalEntry]) -> Option<WashEpisode> {
+    if run.len() < WASH_MIN_TRADES {
+        return None;
+    }
+    let (bought, sold) = run.iter().fold((0u64, 0u64), |(bought, sold), entry| {
+        if entry.is_buy {
+            (bought + entry.token_amount, sold)
+        } else {
+            (bought, sold + entry.token_amount)
+        }
+    });
+    if bought.abs_diff(sold) as f64 > (bought + sold) as f64 * WASH_MAX_NET_SHARE {
+        return None;
+    }
+    Some(WashEpisode {
+        mint: mint.to_string(),
+        wallets: run.iter().map(|entry| entry.wallet.clone()).collect(),
+        trades: run.len(),
+        duration_secs: run[run.len() - 1].timestamp - run[0].timestamp,
+        volume: run.iter().map(|entry| to_sol(entry.sol_amount)).sum(),
+    })
+}
+
+/// 钱包的汇总统计：盈亏、入场时间、被夹损失和对敲标记。`sandwiches` 为 None 表示没有检测记录
+pub fn wallet_stats(
+    entries: &[JournalEntry],
+    sandwiches: Option<&[SandwichRecord]>,
+    clusters: &BTreeMap<String, Vec<String>>,
+    wallet: &str,
+) -> String {
+    let mut output = String::new();
+    let cluster = cluster_of(clusters, wallet);
+    let group = match cluster {
+        Some((_, members)) => members.to_vec(),
+        None => vec![wallet.to_string()],
+    };
+    let _ = writeln!(
+        output,
+        "{}{}",
+        wallet,
+        cluster.map_or_else(String::new, |(name, _)| format!(" (cluster {})", name))
+    );
+
+    let pnl = wallet_pnl(entries, wallet);
+    let _ = writeln!(
+        output,
+        "Trades: {} | Tokens: {} | Invested: {} SOL | Total PnL: {} SOL",
+        pnl.trades,
+        pnl.mints.len(),
+        format_f64(pnl.invested),
+        format_signed_sol(pnl.total_pnl())
+    );
+
+    let timing = entry_timing(entries, wallet);
+    let known = timing.buys - timing.unknown;
+    if known > 0 {
+        let _ = writeln!(
+            output,
+            "Entries within the first minute: {} of {}{}",
+            timing.within_first_minute,
+            known,
+            if timing.is_sniper() { " (sniper)" } else { "" }
+        );
+    }
+
+    if let Some(sandwiches) = sandwiches {
+        let sandwiched: Vec<&SandwichRecord> = sandwiches.iter().filter(|record| record.wallet == wallet).collect();
+        let _ = writeln!(
+            output,
+            "MEV: sandwiched in {} of {} trades, estimated loss {} SOL",
+            sandwiched.len(),
+            pnl.trades,
+            format_f64(sandwiched.iter().map(|record| record.loss).sum())
+        );
+    }
+
+    // 只报告涉及该钱包的对敲
+    let episodes: Vec<WashEpisode> = wash_trades(entries, &group)
+        .into_iter()
+        .filter(|episode| episode.wallets.contains(wallet))
+        .collect();
+    if episodes.is_empty() {
+        let _ = writeln!(output, "Wash trading: none detected");
+    } else {
+        let _ = writeln!(output, "⚠️ Wash trading: {} episode(s)", episodes.len());
+        for episode in &episodes {
+            let _ = writeln!(
+                output,
+                "  {} {} alternating trades in {}s, {} SOL volume ({})",
+                episode.mint,
+                episode.trades,
+                episode.duration_secs,
+                format_f64(episode.volume),
+                episode.wallets.iter().cloned().collect::<Vec<_>>().join(", ")
+            );
+        }
+    }
+    output
+}
+
+/// 按标签列出在 Telegram 中标注过的交易，用于复盘。`wallets` 为空时包含所有钱包。
+/// 每笔交易显示之后的价格变化（日志中该代币最后的成交价相对交易价格），同一笔交易的同一个标签只计一次
+pub fn annotation_review(entries: &[JournalEntry], annotations: &[TradeAnnotation], wallets: &[String]) -> String {
+    let trades: HashMap<&str, &JournalEntry> = entries.iter().map(|entry| (entry.signature.as_str(), entry)).collect();
+    let mut last_prices: HashMap<&str, f64> = HashMap::new();
+    for entry in entries {
+        last_prices.insert(&entry.mint, entry.price);
+    }
+    let mut by_tag: BTreeMap<&str, Vec<&TradeAnnotation>> = BTreeMap::new();
+    let mut seen = BTreeSet::new();
+    for annotation in annotations {
+        if !wallets.is_empty() && !wallets.contains(&annotation.wallet) {
+            continue;
+        }
+        if seen.insert((annotation.signature.as_str(), annotation.tag.as_str())) {
+            by_tag.entry(&annotation.tag).or_default().push(annotation);
+        }
+    }
+    if by_tag.is_empty() {
+        return "No annotated trades\n".to_string();
+    }
+
+    let mut output = String::new();
+    for (tag, annotations) in by_tag {
+        let _ = writeln!(output, "{} ({} trade(s))", tag, annotations.len());
+        let mut moves = Vec::new();
+        for annotation in annotations {
+            let Some(entry) = trades.get(annotation.signature.as_str()) else {
+                let _ = writeln!(
+                    output,
+                    "  {} {} (not in the trade journal)",
+                    truncate_address(&annotation.wallet, 12),
+                    truncate_address(&annotation.mint, 16)
+                );
+                continue;
+            };
+            let price_move = last_prices
+                .get(entry.mint.as_str())
+                .filter(|_| entry.price > 0.0)
+                .map(|last| (last - entry.price) / entry.price * 100.0);
+            if entry.is_buy {
+                moves.extend(price_move);
+            }
+            let _ = writeln!(
+                output,
+                "  {} {} {} {} {} SOL @ {}{}",
+                Local
+                    .timestamp_opt(entry.timestamp, 0)
+                    .single()
+                    .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default(),
+                if entry.is_buy { "Buy " } else { "Sell" },
+                truncate_address(&entry.wallet, 12),
+                truncate_address(&entry.mint, 16),
+                format_f64(to_sol(entry.sol_amount)),
+                format_f64(entry.price),
+                price_move.map_or_else(String::new, |change| format!(", price since {:+.0}%", change))
+            );
+        }
+        if !moves.is_empty() {
+            let _ = writeln!(
+                output,
+                "  Price since tagged buys: {:+.0}% on average",
+                moves.iter().sum::<f64>() / moves.len() as f64
+            );
+        }
+    }
+    output
+}